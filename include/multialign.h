@@ -0,0 +1,54 @@
+/* C interface of multialign, built with the multialign-ffi feature. */
+
+#ifndef MULTIALIGN_H
+#define MULTIALIGN_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The alphabet of the sequences. The IUPAC alphabets are used, such that ambiguous residues are accepted. */
+typedef enum MultialignAlphabet {
+    MULTIALIGN_ALPHABET_DNA = 0,
+    MULTIALIGN_ALPHABET_RNA = 1,
+    MULTIALIGN_ALPHABET_AMINO_ACID = 2,
+} MultialignAlphabet;
+
+typedef enum MultialignStatus {
+    MULTIALIGN_OK = 0,
+    /* A pointer was null, the alphabet is unknown, or less than two sequences were given. */
+    MULTIALIGN_INVALID_ARGUMENT = 1,
+    /* A sequence contains a character that is not part of the alphabet. */
+    MULTIALIGN_INVALID_CHARACTER = 2,
+    /* More sequences were given than supported. */
+    MULTIALIGN_TOO_MANY_SEQUENCES = 3,
+    /* The memory required by the aligner could not be allocated. */
+    MULTIALIGN_MEMORY_LIMIT = 4,
+    /* Any other error. */
+    MULTIALIGN_ERROR = 5,
+} MultialignStatus;
+
+/* An alignment, which must be freed with multialign_alignment_free. */
+typedef struct MultialignAlignment {
+    /* The gapped rows of the alignment, in the order of the input sequences. */
+    char **rows;
+    size_t row_amount;
+    int64_t cost;
+} MultialignAlignment;
+
+/* Align the null-terminated sequences with the default metric. On error, result is left unchanged.
+   The alphabet is one of the values of MultialignAlphabet. */
+MultialignStatus multialign_align(const char *const *sequences, size_t sequence_amount,
+                                  uint32_t alphabet, MultialignAlignment *result);
+
+/* Free the rows of an alignment returned by multialign_align. */
+void multialign_alignment_free(MultialignAlignment *alignment);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,128 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use compact_genome::interface::alphabet::Alphabet;
+use log::info;
+
+use super::error::{MultialignError, Result};
+
+/// A record of an aligned fasta file.
+pub struct AlignedFastaRecord<CharacterType> {
+    /// The id of the fasta record.
+    pub id: String,
+    /// The aligned sequence of the fasta record, where gaps are `None`.
+    pub row: Vec<Option<CharacterType>>,
+    /// True for each residue of the row that is soft-masked, i.e. written in lower case.
+    pub soft_mask: Vec<bool>,
+}
+
+/// Read an aligned fasta file.
+///
+/// Dash (`-`) and dot (`.`) characters are interpreted as gaps.
+/// Lower-case characters are parsed as upper-case, and recorded as soft-masked.
+/// If an ASCII index in `skip_characters` contains true, then that character will always be skipped (after capitalisation).
+///
+/// All records are required to have the same length.
+pub fn read_aligned_fasta_file<AlphabetType: Alphabet>(
+    path: impl AsRef<Path>,
+    skip_characters: &[bool],
+) -> Result<Vec<AlignedFastaRecord<AlphabetType::CharacterType>>> {
+    let path = path.as_ref();
+    info!("Loading aligned fasta file {path:?}");
+
+    let io_error = |source| MultialignError::Io {
+        path: path.to_owned(),
+        source,
+    };
+    let file = File::open(path).map_err(io_error)?;
+    let mut records: Vec<AlignedFastaRecord<AlphabetType::CharacterType>> = Vec::new();
+
+    for (line_index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(io_error)?;
+        let line = line.trim();
+
+        if let Some(header) = line.strip_prefix('>') {
+            let id = header.split_whitespace().next().unwrap_or("").to_string();
+            records.push(AlignedFastaRecord {
+                id,
+                row: Vec::new(),
+                soft_mask: Vec::new(),
+            });
+            continue;
+        }
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let Some(record) = records.last_mut() else {
+            return Err(MultialignError::InvalidAlignment(format!(
+                "Line {} contains sequence data before the first fasta header",
+                line_index + 1
+            )));
+        };
+
+        for raw_character in line.bytes() {
+            let character = raw_character.to_ascii_uppercase();
+            if skip_characters
+                .get(usize::from(character))
+                .copied()
+                .unwrap_or(false)
+            {
+                continue;
+            }
+
+            if character == b'-' || character == b'.' {
+                record.row.push(None);
+            } else {
+                record.soft_mask.push(raw_character.is_ascii_lowercase());
+                record
+                    .row
+                    .push(Some(AlphabetType::ascii_to_character(character).map_err(
+                        |_| {
+                            MultialignError::InvalidAlignment(format!(
+                            "Line {} contains a character that is not part of the alphabet: {:?}",
+                            line_index + 1,
+                            char::from(character),
+                        ))
+                        },
+                    )?));
+            }
+        }
+    }
+
+    if let Some(first_record) = records.first() {
+        for record in &records {
+            if record.row.len() != first_record.row.len() {
+                return Err(MultialignError::InvalidAlignment(format!(
+                    "Aligned fasta records have different lengths: {} has length {}, but {} has length {}",
+                    first_record.id,
+                    first_record.row.len(),
+                    record.id,
+                    record.row.len(),
+                )));
+            }
+        }
+    }
+
+    Ok(records)
+}
+
+/// Transpose the rows of the given records into alignment columns.
+pub fn records_to_columns<CharacterType: Clone>(
+    records: &[AlignedFastaRecord<CharacterType>],
+) -> Vec<Vec<Option<CharacterType>>> {
+    let length = records.first().map(|record| record.row.len()).unwrap_or(0);
+
+    (0..length)
+        .map(|column| {
+            records
+                .iter()
+                .map(|record| record.row[column].clone())
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,317 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use log::info;
+
+use super::{
+    aligned_fasta::AlignedFastaRecord,
+    error::{MultialignError, Result},
+};
+
+/// Extract anchor columns from a seed alignment.
+///
+/// The records of the seed are matched to the sequences by their ids, and the residues of each record must be equal to its sequence.
+/// A column is an anchor if it contains no gaps, and if its most frequent residue makes up at least `threshold` of the column.
+///
+/// Returns one vector of sequence offsets per anchor, in the order of the sequences.
+pub fn anchors_from_seed<'ids, CharacterType: Ord>(
+    seed: &[AlignedFastaRecord<CharacterType>],
+    ids: impl IntoIterator<Item = &'ids str>,
+    sequences: &[Vec<CharacterType>],
+    threshold: f64,
+) -> Result<Vec<Vec<usize>>> {
+    if !(0.0..=1.0).contains(&threshold) {
+        return Err(MultialignError::InvalidAnchors(format!(
+            "Anchor threshold must be between zero and one, but is {threshold}"
+        )));
+    }
+
+    let seed_records: BTreeMap<_, _> = seed
+        .iter()
+        .map(|record| (record.id.as_str(), record))
+        .collect();
+    let rows = ids
+        .into_iter()
+        .zip(sequences)
+        .map(|(id, sequence)| {
+            let record = seed_records.get(id).ok_or_else(|| {
+                MultialignError::InvalidAnchors(format!(
+                    "Seed alignment contains no record with id {id}"
+                ))
+            })?;
+            if !record.row.iter().flatten().eq(sequence.iter()) {
+                return Err(MultialignError::InvalidAnchors(format!(
+                    "Seed alignment record {id} does not match its input sequence"
+                )));
+            }
+            Ok(&record.row)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let length = rows.first().map(|row| row.len()).unwrap_or(0);
+    let mut offsets = vec![0; rows.len()];
+    let mut anchors = Vec::new();
+
+    for column in 0..length {
+        let characters: Option<Vec<_>> = rows.iter().map(|row| row[column].as_ref()).collect();
+
+        if let Some(characters) = characters {
+            let mut character_counts = BTreeMap::new();
+            for character in &characters {
+                *character_counts.entry(*character).or_insert(0usize) += 1;
+            }
+            let most_frequent = character_counts.values().copied().max().unwrap_or(0);
+
+            if most_frequent as f64 >= threshold * characters.len() as f64 {
+                anchors.push(offsets.clone());
+            }
+        }
+
+        for (offset, row) in offsets.iter_mut().zip(&rows) {
+            if row[column].is_some() {
+                *offset += 1;
+            }
+        }
+    }
+
+    info!(
+        "Found {} anchor columns out of {} seed alignment columns",
+        anchors.len(),
+        length
+    );
+
+    Ok(anchors)
+}
+
+/// Read anchors from a text file with one anchor per line, which contains one zero-based residue offset per sequence, in the order of the sequences.
+///
+/// The offsets are separated by whitespace or commas. Empty lines and lines starting with `#` are ignored.
+/// The residues of an anchor are aligned in the same column, and the anchors must be strictly increasing in every sequence,
+/// which is checked when aligning with them.
+pub fn read_anchors_file(
+    path: impl AsRef<Path>,
+    sequence_amount: usize,
+) -> Result<Vec<Vec<usize>>> {
+    let path = path.as_ref();
+    info!("Loading anchors from {path:?}");
+
+    let io_error = |source| MultialignError::Io {
+        path: path.to_owned(),
+        source,
+    };
+    let reader = BufReader::new(File::open(path).map_err(io_error)?);
+
+    let mut anchors = Vec::new();
+    for (line_index, line) in reader.lines().enumerate() {
+        let line = line.map_err(io_error)?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let anchor = line
+            .split(|character: char| character.is_whitespace() || character == ',')
+            .filter(|offset| !offset.is_empty())
+            .map(|offset| {
+                offset.parse().map_err(|_| {
+                    MultialignError::InvalidAnchors(format!(
+                        "Invalid offset {offset:?} in line {}",
+                        line_index + 1
+                    ))
+                })
+            })
+            .collect::<Result<Vec<usize>>>()?;
+        if anchor.len() != sequence_amount {
+            return Err(MultialignError::InvalidAnchors(format!(
+                "Line {} contains {} offsets, but there are {sequence_amount} sequences",
+                line_index + 1,
+                anchor.len()
+            )));
+        }
+        anchors.push(anchor);
+    }
+
+    info!("Loaded {} anchors", anchors.len());
+    Ok(anchors)
+}
+
+/// Find anchors by chaining the exact matches shared by all sequences.
+///
+/// The seeds are the k-mers of the given length that occur exactly once in each sequence.
+/// Seeds that continue each other in all sequences are merged into maximal exact matches,
+/// and of the chains of matches that are in the same order in all sequences, the one covering the most residues is selected.
+/// Each column of the chained matches becomes an anchor.
+///
+/// The best predecessor of each match in the first two sequences is found in logarithmic time.
+/// With more than two sequences, if that predecessor is out of order in another sequence,
+/// then only the last [`MAX_SCANNED_PREDECESSORS`] matches ending before the match in the first sequence are considered,
+/// so the selected chain may not be the one covering the most residues.
+///
+/// Returns one vector of sequence offsets per anchor, in the order of the sequences.
+pub fn anchors_from_matches<CharacterType: Ord>(
+    sequences: &[Vec<CharacterType>],
+    k: usize,
+) -> Result<Vec<Vec<usize>>> {
+    if k == 0 {
+        return Err(MultialignError::InvalidAnchors(
+            "The length of the k-mers must be at least one".to_owned(),
+        ));
+    }
+
+    // The offsets of each k-mer that is unique in all sequences.
+    let mut seeds: BTreeMap<&[CharacterType], Vec<usize>> = BTreeMap::new();
+    for (index, sequence) in sequences.iter().enumerate() {
+        // The first offset and the amount of occurrences of each k-mer.
+        let mut occurrences = BTreeMap::new();
+        for (offset, kmer) in sequence.windows(k).enumerate() {
+            occurrences.entry(kmer).or_insert((offset, 0)).1 += 1;
+        }
+
+        if index == 0 {
+            seeds = occurrences
+                .into_iter()
+                .filter(|(_, (_, count))| *count == 1)
+                .map(|(kmer, (offset, _))| (kmer, vec![offset]))
+                .collect();
+        } else {
+            seeds.retain(|kmer, offsets| match occurrences.get(kmer) {
+                Some(&(offset, 1)) => {
+                    offsets.push(offset);
+                    true
+                }
+                _ => false,
+            });
+        }
+    }
+
+    let mut seeds: Vec<_> = seeds.into_values().collect();
+    seeds.sort_unstable();
+    // The offsets at which each match starts, and its length.
+    let mut matches: Vec<(Vec<usize>, usize)> = Vec::new();
+    for offsets in seeds {
+        if let Some((starts, length)) = matches.last_mut() {
+            if starts
+                .iter()
+                .zip(&offsets)
+                .all(|(start, offset)| start + *length + 1 - k == *offset)
+            {
+                *length += 1;
+                continue;
+            }
+        }
+        matches.push((offsets, k));
+    }
+
+    // The matches are ordered by their start in the first sequence, and a match can precede another only if it ends before in all sequences.
+    // The matches ending before the current one in the first sequence are added to a range-max structure over their ends in the second sequence.
+    matches.sort_unstable_by_key(|(starts, _)| starts[0]);
+    let precedes = |previous: usize, next: usize| {
+        let (previous_starts, previous_length) = &matches[previous];
+        previous_starts
+            .iter()
+            .zip(&matches[next].0)
+            .all(|(previous_start, start)| previous_start + previous_length <= *start)
+    };
+    let key_sequence = sequences.len().min(2).saturating_sub(1);
+    let mut by_end: Vec<_> = (0..matches.len()).collect();
+    by_end.sort_unstable_by_key(|&index| matches[index].0[0] + matches[index].1);
+    let mut by_end = by_end.into_iter().peekable();
+    let mut ended = Vec::new();
+    let mut best_ended = MaxFenwickTree::new(sequences.get(key_sequence).map_or(0, Vec::len) + 1);
+
+    // The amount of residues covered by the best chain ending in each match, and the previous match of that chain.
+    let mut chains: Vec<(usize, Option<usize>)> = Vec::with_capacity(matches.len());
+    for (index, (starts, length)) in matches.iter().enumerate() {
+        while let Some(previous) =
+            by_end.next_if(|&previous| matches[previous].0[0] + matches[previous].1 <= starts[0])
+        {
+            let (previous_starts, previous_length) = &matches[previous];
+            best_ended.insert(
+                previous_starts[key_sequence] + previous_length,
+                (chains[previous].0, previous),
+            );
+            ended.push(previous);
+        }
+
+        let previous = match best_ended.max_up_to(starts[key_sequence]) {
+            Some((_, previous)) if precedes(previous, index) => Some(previous),
+            Some(_) => ended
+                .iter()
+                .rev()
+                .take(MAX_SCANNED_PREDECESSORS)
+                .copied()
+                .filter(|&previous| precedes(previous, index))
+                .max_by_key(|&previous| chains[previous].0),
+            None => None,
+        };
+        chains.push((
+            length + previous.map_or(0, |previous| chains[previous].0),
+            previous,
+        ));
+    }
+
+    let mut chain = Vec::new();
+    let mut current = (0..chains.len()).max_by_key(|&index| chains[index].0);
+    while let Some(index) = current {
+        chain.push(index);
+        current = chains[index].1;
+    }
+    chain.reverse();
+
+    let anchors: Vec<_> = chain
+        .iter()
+        .flat_map(|&index| {
+            let (starts, length) = &matches[index];
+            (0..*length).map(move |column| starts.iter().map(|start| start + column).collect())
+        })
+        .collect();
+    info!(
+        "Chained {} of {} exact matches of at least {k} residues into {} anchor columns",
+        chain.len(),
+        matches.len(),
+        anchors.len()
+    );
+
+    Ok(anchors)
+}
+
+/// The amount of previous matches that are scanned for the best predecessor of a match
+/// if the best predecessor in the first two sequences is out of order in another sequence, see [`anchors_from_matches`].
+pub const MAX_SCANNED_PREDECESSORS: usize = 1000;
+
+/// A Fenwick tree that returns the maximum value inserted at or below a position.
+struct MaxFenwickTree<Value> {
+    tree: Vec<Option<Value>>,
+}
+
+impl<Value: Ord + Copy> MaxFenwickTree<Value> {
+    /// Create a tree for the positions below `length`.
+    fn new(length: usize) -> Self {
+        Self {
+            tree: vec![None; length],
+        }
+    }
+
+    fn insert(&mut self, position: usize, value: Value) {
+        let mut index = position + 1;
+        while index <= self.tree.len() {
+            let entry = &mut self.tree[index - 1];
+            *entry = (*entry).max(Some(value));
+            index += index & index.wrapping_neg();
+        }
+    }
+
+    fn max_up_to(&self, position: usize) -> Option<Value> {
+        let mut result = None;
+        let mut index = (position + 1).min(self.tree.len());
+        while index > 0 {
+            result = result.max(self.tree[index - 1]);
+            index -= index & index.wrapping_neg();
+        }
+        result
+    }
+}
@@ -0,0 +1,290 @@
+use std::collections::{HashMap, HashSet};
+
+use compact_genome::interface::{alphabet::Alphabet, sequence::GenomeSequence};
+use generic_a_star::{cost::AStarCost, AStarContext, AStarPerformanceCounters};
+use log::debug;
+
+use super::{
+    cancellation::CancellationToken,
+    error::{MultialignError, Result},
+    metric::{self, MultialignMetric},
+    open_list::OpenList,
+    reconstruct_path, Context, Improvements, Node, NodeIdentifier, OpenListKind,
+};
+
+/// The weight is set to one once it is closer to one than this, since the last searches barely differ otherwise.
+const MIN_WEIGHT_DECREASE: f64 = 0.05;
+
+/// An anytime repairing A* search (ARA*), which finds a first alignment quickly and then improves it until it is optimal.
+///
+/// Each iteration is a weighted search, see [`MultialignOptions::weight`](crate::MultialignOptions::weight),
+/// whose alignment costs at most the weight times the optimal cost.
+/// After each iteration, the distance of the weight to one is halved.
+/// The costs of the nodes are kept between iterations, and only the nodes whose cost decreased after they were closed are searched again.
+pub struct AnytimeSearch<Identifier, Cost> {
+    /// The cheapest known cost of each generated node, and the predecessor it was reached from.
+    costs: HashMap<Identifier, (Cost, Option<Identifier>)>,
+    /// The open nodes with their costs, ordered by their priorities.
+    ///
+    /// Nodes whose cost decreased after they were pushed remain in the list with their old cost, and are skipped when popped.
+    open_list: OpenList<Cost, (Cost, Identifier)>,
+    /// The nodes closed in the current iteration.
+    closed_list: HashSet<Identifier>,
+    /// The nodes whose cost decreased after they were closed in the current iteration, which are opened again in the next one.
+    inconsistent: Vec<Identifier>,
+    performance_counters: AStarPerformanceCounters,
+}
+
+/// A path from the root to a target, with its cost and a lower bound of the optimal cost.
+pub struct AnytimeAlignment<Identifier: NodeIdentifier, Cost> {
+    /// The edges of the path, ordered from the target to the root like [`generic_a_star::AStar::backtrack`].
+    pub edges: Vec<Node<Identifier, Cost>>,
+    pub cost: Cost,
+    pub cost_lower_bound: Cost,
+}
+
+impl<Identifier: NodeIdentifier, Cost: AStarCost> AnytimeSearch<Identifier, Cost>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    pub fn new(open_list_kind: OpenListKind) -> Self {
+        Self {
+            costs: Default::default(),
+            open_list: OpenList::new(open_list_kind),
+            closed_list: Default::default(),
+            inconsistent: Default::default(),
+            performance_counters: Default::default(),
+        }
+    }
+
+    pub fn into_performance_counters(self) -> AStarPerformanceCounters {
+        self.performance_counters
+    }
+
+    /// Search with decreasing weights, starting at the weight of the context, and pass each improved alignment to `on_improvement`.
+    ///
+    /// Returns the last improved alignment once it is known to be optimal.
+    /// If the cancellation token is set, the last improved alignment is returned instead,
+    /// or [`MultialignError::Cancelled`] if none was found yet.
+    pub fn search<
+        AlphabetType: Alphabet,
+        SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized,
+        Metric: MultialignMetric<AlphabetType>,
+    >(
+        &mut self,
+        context: &mut Context<'_, AlphabetType, Cost, SequenceType, Identifier, Metric>,
+        cancellation: &CancellationToken,
+        on_improvement: Improvements<'_, Cost>,
+    ) -> Result<AnytimeAlignment<Identifier, Cost>> {
+        // Without lower bounds, the weight has no effect, so a single search is optimal.
+        if context.heuristic.is_none() {
+            context.weight = 1.0;
+        }
+
+        let root = context.create_root();
+        let mut cost_lower_bound = context.unweighted_lower_bound(&root.identifier);
+        self.costs
+            .insert(root.identifier.clone(), (root.cost, None));
+        self.open_list
+            .push(root.priority(), (root.cost, root.identifier));
+
+        let mut best = None::<AnytimeAlignment<Identifier, Cost>>;
+        loop {
+            debug!("Searching with weight {}", context.weight);
+            let best_cost = best.as_ref().map(|best| best.cost);
+            let target = match self.improve_path(context, cancellation, best_cost) {
+                Ok(target) => target,
+                Err(MultialignError::Cancelled { closed_nodes, .. }) => {
+                    return best.ok_or(MultialignError::Cancelled {
+                        closed_nodes,
+                        cost_lower_bound: Some(cost_lower_bound.as_u64()),
+                    });
+                }
+                Err(error) => return Err(error),
+            };
+            context.take_error()?;
+
+            let mut frontier = self.take_frontier();
+            let mut improved = false;
+            if let Some(target) = target {
+                let edges = self.backtrack(context, target);
+                let cost = edges.first().map_or(Cost::zero(), |edge| edge.cost);
+                if best_cost.is_none_or(|best_cost| cost < best_cost) {
+                    best = Some(AnytimeAlignment {
+                        edges,
+                        cost,
+                        cost_lower_bound,
+                    });
+                    improved = true;
+                }
+            }
+            let best_alignment = best
+                .as_mut()
+                .expect("The first search always finds a target");
+
+            // Each open node is on a path to a target, and the optimal path passes through one of them unless the best alignment is optimal.
+            let frontier_lower_bound = frontier
+                .iter()
+                .map(|(identifier, cost)| {
+                    cost.checked_add(&context.unweighted_lower_bound(identifier))
+                        .unwrap_or_else(Cost::max_value)
+                })
+                .min()
+                .unwrap_or(best_alignment.cost);
+            // The alignment costs at most the weight times the optimal cost.
+            let weighted_lower_bound =
+                i32::try_from((best_alignment.cost.as_f64() / context.weight).ceil() as i64)
+                    .ok()
+                    .and_then(|lower_bound| metric::cost_from_i32::<Cost>(lower_bound).ok())
+                    .unwrap_or_else(Cost::zero);
+            cost_lower_bound = cost_lower_bound
+                .max(frontier_lower_bound)
+                .max(weighted_lower_bound)
+                .min(best_alignment.cost);
+            best_alignment.cost_lower_bound = cost_lower_bound;
+
+            if improved {
+                on_improvement(
+                    &reconstruct_path(
+                        context.sequences.len(),
+                        best_alignment.edges.iter().cloned(),
+                    ),
+                    best_alignment.cost,
+                    best_alignment.cost_lower_bound,
+                    &self.performance_counters,
+                )?;
+            }
+            if cost_lower_bound == best_alignment.cost || context.weight == 1.0 {
+                best_alignment.cost_lower_bound = best_alignment.cost;
+                return Ok(best.unwrap());
+            }
+
+            context.weight = 1.0 + (context.weight - 1.0) / 2.0;
+            if context.weight - 1.0 < MIN_WEIGHT_DECREASE {
+                context.weight = 1.0;
+            }
+            self.closed_list.clear();
+            for (identifier, cost) in frontier.drain() {
+                self.open_list.push(
+                    cost.checked_add(&context.lower_bound(&identifier))
+                        .unwrap_or_else(Cost::max_value),
+                    (cost, identifier),
+                );
+            }
+        }
+    }
+
+    /// Close nodes in order of their priorities until no open node has a lower priority than the cost of the best target.
+    ///
+    /// Returns the best target closed in this iteration, if it is cheaper than the given cost of the best alignment so far.
+    fn improve_path<
+        AlphabetType: Alphabet,
+        SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized,
+        Metric: MultialignMetric<AlphabetType>,
+    >(
+        &mut self,
+        context: &mut Context<'_, AlphabetType, Cost, SequenceType, Identifier, Metric>,
+        cancellation: &CancellationToken,
+        mut best_cost: Option<Cost>,
+    ) -> Result<Option<Identifier>> {
+        let mut target = None;
+        let mut successors = Vec::new();
+
+        while let Some((priority, (cost, identifier))) = self.open_list.pop() {
+            if self.costs[&identifier].0 != cost || self.closed_list.contains(&identifier) {
+                self.performance_counters.suboptimal_opened_nodes += 1;
+                continue;
+            }
+            if best_cost.is_some_and(|best_cost| best_cost <= priority) {
+                self.open_list.push(priority, (cost, identifier));
+                break;
+            }
+            if cancellation.is_cancelled() {
+                return Err(MultialignError::Cancelled {
+                    closed_nodes: self.performance_counters.closed_nodes,
+                    cost_lower_bound: None,
+                });
+            }
+
+            let node = Node {
+                cost,
+                lower_bound: Cost::zero(),
+                identifier,
+                predecessor: None,
+                tie_breaker: 0,
+            };
+            self.closed_list.insert(node.identifier.clone());
+            self.performance_counters.closed_nodes += 1;
+
+            if context.is_target(&node) {
+                best_cost = Some(node.cost);
+                target = Some(node.identifier);
+                continue;
+            }
+
+            successors.clear();
+            context.generate_successors(&node, &mut successors);
+            self.performance_counters.opened_nodes += successors.len();
+            for successor in successors.drain(..) {
+                if self
+                    .costs
+                    .get(&successor.identifier)
+                    .is_some_and(|(cost, _)| *cost <= successor.cost)
+                {
+                    continue;
+                }
+
+                self.costs.insert(
+                    successor.identifier.clone(),
+                    (successor.cost, Some(node.identifier.clone())),
+                );
+                if self.closed_list.contains(&successor.identifier) {
+                    self.inconsistent.push(successor.identifier);
+                } else {
+                    self.open_list
+                        .push(successor.priority(), (successor.cost, successor.identifier));
+                }
+            }
+        }
+
+        Ok(target)
+    }
+
+    /// Remove the open and inconsistent nodes, and return them with their costs.
+    fn take_frontier(&mut self) -> HashMap<Identifier, Cost> {
+        let mut frontier = HashMap::new();
+        while let Some((_, (cost, identifier))) = self.open_list.pop() {
+            if self.costs[&identifier].0 == cost && !self.closed_list.contains(&identifier) {
+                frontier.insert(identifier, cost);
+            }
+        }
+        for identifier in self.inconsistent.drain(..) {
+            let cost = self.costs[&identifier].0;
+            frontier.insert(identifier, cost);
+        }
+        frontier
+    }
+
+    /// Follow the predecessors from the given target to the root.
+    ///
+    /// The cost of a node may have decreased after its successors were generated,
+    /// so the costs of the edges are recomputed by generating the successors along the path from the root.
+    fn backtrack<
+        AlphabetType: Alphabet,
+        SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized,
+        Metric: MultialignMetric<AlphabetType>,
+    >(
+        &self,
+        context: &mut Context<'_, AlphabetType, Cost, SequenceType, Identifier, Metric>,
+        target: Identifier,
+    ) -> Vec<Node<Identifier, Cost>> {
+        let mut identifiers = vec![target];
+        while let Some(predecessor) = &self.costs[identifiers.last().unwrap()].1 {
+            identifiers.push(predecessor.clone());
+        }
+        // The root is not an edge.
+        identifiers.pop();
+
+        context.edges_along(identifiers.into_iter().rev())
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+
+use log::info;
+
+use super::{
+    aligned_fasta::AlignedFastaRecord,
+    error::{MultialignError, Result},
+};
+
+/// A band around the path of a guide alignment, which restricts the search to the nodes close to it.
+///
+/// A node is in the band if there is a column of the guide alignment such that the offset of each sequence differs by at most the width
+/// from the amount of residues of that sequence before the column.
+/// Since the nodes on the path of the guide alignment are in the band, the banded search always finds an alignment,
+/// but it is only optimal among the alignments within the band.
+#[derive(Debug, Clone)]
+pub struct Band {
+    width: usize,
+    /// The amount of columns of the guide alignment.
+    length: usize,
+    /// For each sequence and each amount of its residues, the first column before which the sequence has at least this amount of residues.
+    first_columns: Vec<Vec<usize>>,
+}
+
+impl Band {
+    /// Create a band of the given width around a guide alignment.
+    ///
+    /// The records of the guide alignment are matched to the sequences by their ids, and the residues of each record must be equal to its sequence.
+    pub fn from_guide<'ids, CharacterType: Eq>(
+        guide: &[AlignedFastaRecord<CharacterType>],
+        ids: impl IntoIterator<Item = &'ids str>,
+        sequences: &[Vec<CharacterType>],
+        width: usize,
+    ) -> Result<Self> {
+        let guide_records: BTreeMap<_, _> = guide
+            .iter()
+            .map(|record| (record.id.as_str(), record))
+            .collect();
+        let length = guide.first().map(|record| record.row.len()).unwrap_or(0);
+
+        let first_columns = ids
+            .into_iter()
+            .zip(sequences)
+            .map(|(id, sequence)| {
+                let record = guide_records.get(id).ok_or_else(|| {
+                    MultialignError::InvalidBand(format!(
+                        "Guide alignment contains no record with id {id}"
+                    ))
+                })?;
+                if !record.row.iter().flatten().eq(sequence.iter()) {
+                    return Err(MultialignError::InvalidBand(format!(
+                        "Guide alignment record {id} does not match its input sequence"
+                    )));
+                }
+
+                Ok([0]
+                    .into_iter()
+                    .chain(
+                        record
+                            .row
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, character)| character.is_some())
+                            .map(|(column, _)| column + 1),
+                    )
+                    .collect())
+            })
+            .collect::<Result<Vec<Vec<_>>>>()?;
+
+        info!(
+            "Restricting the search to a band of width {width} around a guide alignment of {length} columns"
+        );
+        Ok(Self {
+            width,
+            length,
+            first_columns,
+        })
+    }
+
+    /// Returns true if the node with the given offsets into the sequences is in the band.
+    ///
+    /// For each sequence, the columns whose amount of preceding residues differs by at most the width from its offset form an interval,
+    /// so the node is in the band if these intervals intersect.
+    pub(crate) fn contains(&self, offsets: impl IntoIterator<Item = usize>) -> bool {
+        let mut first_column = 0;
+        let mut last_column = self.length;
+
+        for (offset, first_columns) in offsets.into_iter().zip(&self.first_columns) {
+            first_column = first_column.max(first_columns[offset.saturating_sub(self.width)]);
+            if let Some(&next_column) = first_columns.get(offset + self.width + 1) {
+                last_column = last_column.min(next_column - 1);
+            }
+        }
+
+        first_column <= last_column
+    }
+}
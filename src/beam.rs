@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use generic_a_star::{cost::AStarCost, AStarContext, AStarPerformanceCounters};
+use log::debug;
+
+use super::{
+    cancellation::CancellationToken,
+    error::{MultialignError, Result},
+    Node, NodeIdentifier,
+};
+
+/// A beam search, which keeps only a limited amount of the most promising nodes of each layer.
+///
+/// The layers contain the nodes by the amount of columns between them and the root.
+/// The successors of all nodes in a layer form the next layer, of which only the nodes with the lowest priority are kept, see [`Node::priority`].
+/// Since nodes that lead to an optimal alignment may be discarded, the alignment found is not necessarily optimal,
+/// but the runtime and memory consumption grow only linearly with the length of the alignment.
+pub struct BeamSearch<Identifier: NodeIdentifier, Cost> {
+    width: usize,
+    /// The kept nodes of each layer, with the index of their predecessor in the previous layer.
+    layers: Vec<Vec<(Node<Identifier, Cost>, usize)>>,
+    performance_counters: AStarPerformanceCounters,
+}
+
+impl<Identifier: NodeIdentifier, Cost: AStarCost> BeamSearch<Identifier, Cost> {
+    /// Create a beam search that keeps the given amount of nodes per layer.
+    pub fn new(width: usize) -> Self {
+        Self {
+            width,
+            layers: Default::default(),
+            performance_counters: Default::default(),
+        }
+    }
+
+    pub fn into_performance_counters(self) -> AStarPerformanceCounters {
+        self.performance_counters
+    }
+
+    /// Search for a target node and return the edges of the path to it.
+    ///
+    /// The edges are ordered from the target to the root, like [`generic_a_star::AStar::backtrack`].
+    /// Returns [`MultialignError::Cancelled`] if the cancellation token is set.
+    pub fn search<Context: AStarContext<Node = Node<Identifier, Cost>>>(
+        &mut self,
+        context: &mut Context,
+        cancellation: &CancellationToken,
+    ) -> Result<Option<Vec<Node<Identifier, Cost>>>> {
+        self.layers.clear();
+        self.layers.push(vec![(context.create_root(), 0)]);
+        // The layer and the index of the cheapest target found.
+        let mut best_target = None::<(usize, usize)>;
+        let mut best_cost = None::<Cost>;
+        let mut successors = Vec::new();
+
+        while let Some(layer) = self.layers.last().filter(|layer| !layer.is_empty()) {
+            let mut next_layer: Vec<(Node<_, _>, _)> = Vec::new();
+            let mut next_layer_indices = HashMap::<_, usize>::new();
+
+            for (predecessor_index, (node, _)) in layer.iter().enumerate() {
+                if cancellation.is_cancelled() {
+                    return Err(MultialignError::Cancelled {
+                        closed_nodes: self.performance_counters.closed_nodes,
+                        cost_lower_bound: None,
+                    });
+                }
+                self.performance_counters.closed_nodes += 1;
+
+                if context.is_target(node) {
+                    if best_cost.is_none_or(|best_cost| node.cost < best_cost) {
+                        best_target = Some((self.layers.len() - 1, predecessor_index));
+                        best_cost = Some(node.cost);
+                    }
+                    continue;
+                }
+
+                successors.clear();
+                context.generate_successors(node, &mut successors);
+                self.performance_counters.opened_nodes += successors.len();
+
+                for successor in successors.drain(..) {
+                    // A successor that cannot lead to a cheaper target is not kept.
+                    if best_cost.is_some_and(|best_cost| best_cost <= successor.priority()) {
+                        continue;
+                    }
+
+                    // Of the nodes with the same identifier, only the cheapest is kept.
+                    match next_layer_indices.get(&successor.identifier) {
+                        Some(&index) => {
+                            let (kept, kept_predecessor_index) = &mut next_layer[index];
+                            if successor.cost < kept.cost {
+                                *kept = successor;
+                                *kept_predecessor_index = predecessor_index;
+                            }
+                            self.performance_counters.suboptimal_opened_nodes += 1;
+                        }
+                        None => {
+                            next_layer_indices
+                                .insert(successor.identifier.clone(), next_layer.len());
+                            next_layer.push((successor, predecessor_index));
+                        }
+                    }
+                }
+            }
+
+            if next_layer.len() > self.width {
+                next_layer.select_nth_unstable_by(self.width, |(a, _), (b, _)| a.cmp(b));
+                next_layer.truncate(self.width);
+            }
+            self.layers.push(next_layer);
+        }
+
+        let Some((mut layer, mut index)) = best_target else {
+            return Ok(None);
+        };
+        debug!("Found a target after {layer} columns");
+
+        let mut edges = Vec::new();
+        // The root is not an edge.
+        while layer > 0 {
+            let (node, predecessor_index) = &self.layers[layer][index];
+            edges.push(node.clone());
+            index = *predecessor_index;
+            layer -= 1;
+        }
+
+        Ok(Some(edges))
+    }
+}
@@ -0,0 +1,33 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// A token to cooperatively cancel a running alignment.
+///
+/// Clones of the token share the same state, so the alignment can be cancelled from another thread, e.g. a signal handler.
+/// The searches check the token once per closed node, and return [`MultialignError::Cancelled`](crate::error::MultialignError::Cancelled) if it is set.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request the cancellation of all alignments using this token.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Returns the flag that is set on cancellation, e.g. to set it from a signal handler.
+    pub fn flag(&self) -> Arc<AtomicBool> {
+        self.cancelled.clone()
+    }
+}
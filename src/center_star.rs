@@ -0,0 +1,115 @@
+use compact_genome::interface::{alphabet::Alphabet, sequence::GenomeSequence};
+use generic_a_star::{cost::AStarCost, AStarPerformanceCounters};
+use log::debug;
+
+use super::{
+    cancellation::CancellationToken,
+    error::{MultialignError, Result},
+    metric::MultialignMetric,
+    profile_alignment::{align_profiles, PartialAlignment},
+    AlignmentColumns,
+};
+
+/// Align the sequences with the center-star method, see [`multialign_center_star`](crate::multialign_center_star).
+///
+/// Each pair of sequences is aligned with [`align_profiles`], and the center is the sequence with the minimum sum of the costs of its pairwise alignments.
+/// The pairwise alignments of the center to each other sequence are then merged, where residues inserted before the same residue of the center are aligned to each other from the left.
+///
+/// Returns the columns of the alignment with one entry per sequence in input order, the index of the center sequence,
+/// and the amount of computed cells as performance counters.
+pub(super) fn center_star_alignment<
+    AlphabetType: Alphabet,
+    Cost: AStarCost,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized,
+    Metric: MultialignMetric<AlphabetType>,
+>(
+    sequences: &[&SequenceType],
+    metric: &mut Metric,
+    cancellation: &CancellationToken,
+) -> Result<(
+    AlignmentColumns<AlphabetType::CharacterType>,
+    usize,
+    AStarPerformanceCounters,
+)>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    let mut performance_counters = AStarPerformanceCounters::default();
+    let mut align_pair = |first: usize, second: usize| {
+        align_profiles::<_, Cost, _>(
+            &PartialAlignment::sequence(first, sequences[first].iter().cloned()),
+            &PartialAlignment::sequence(second, sequences[second].iter().cloned()),
+            sequences.len(),
+            metric,
+            cancellation,
+            &mut performance_counters,
+        )
+    };
+
+    let mut cost_sums = vec![Cost::zero(); sequences.len()];
+    for first in 0..sequences.len() {
+        for second in first + 1..sequences.len() {
+            let (_, cost) = align_pair(first, second)?;
+            for index in [first, second] {
+                cost_sums[index] = cost_sums[index]
+                    .checked_add(&cost)
+                    .ok_or(MultialignError::CostOverflow)?;
+            }
+        }
+    }
+    let center = (0..sequences.len())
+        .min_by_key(|index| cost_sums[*index])
+        .unwrap_or(0);
+    debug!(
+        "Chose sequence {center} as center with a sum of pairwise costs of {}",
+        cost_sums[center]
+    );
+
+    // For each sequence, the residues inserted before each residue of the center and after its last residue,
+    // and the residues aligned to each residue of the center.
+    let center_length = sequences[center].len();
+    let mut insertions = vec![vec![Vec::new(); center_length + 1]; sequences.len()];
+    let mut matches = vec![vec![None; center_length]; sequences.len()];
+    for other in (0..sequences.len()).filter(|index| *index != center) {
+        let (alignment, _) = align_pair(center, other)?;
+        let mut offset = 0;
+        for column in alignment.columns {
+            let mut entries = column.into_iter();
+            let (center_entry, other_entry) = (entries.next().flatten(), entries.next().flatten());
+            if center_entry.is_some() {
+                matches[other][offset] = other_entry;
+                offset += 1;
+            } else if let Some(residue) = other_entry {
+                insertions[other][offset].push(residue);
+            }
+        }
+    }
+
+    let mut columns = Vec::new();
+    for offset in 0..=center_length {
+        let insertion_length = insertions
+            .iter()
+            .map(|insertions| insertions[offset].len())
+            .max()
+            .unwrap_or(0);
+        for insertion_offset in 0..insertion_length {
+            columns.push(
+                insertions
+                    .iter()
+                    .map(|insertions| insertions[offset].get(insertion_offset).cloned())
+                    .collect(),
+            );
+        }
+
+        if offset < center_length {
+            let mut column: Vec<_> = matches
+                .iter()
+                .map(|matches| matches[offset].clone())
+                .collect();
+            column[center] = Some(sequences[center][offset].clone());
+            columns.push(column);
+        }
+    }
+
+    Ok((columns, center, performance_counters))
+}
@@ -0,0 +1,417 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    fs::{self, File},
+    hash::{Hash, Hasher},
+    io::{self, Read, Seek, SeekFrom, Write},
+    mem,
+    path::PathBuf,
+};
+
+use generic_a_star::cost::AStarCost;
+use log::debug;
+
+use super::{
+    error::{MultialignError, Result},
+    spill::{cost_from_i64, create_file, create_spill_file, SpillOptions},
+    NodeIdentifier,
+};
+
+/// The closed list of a [`RederivingSearch`](crate::rederiving_search::RederivingSearch), which stores the cost of each closed node.
+pub(crate) enum ClosedList<Identifier, Cost> {
+    Memory(HashMap<Identifier, Cost>),
+    Spilling(SpillingClosedList<Identifier, Cost>),
+}
+
+impl<Identifier: NodeIdentifier, Cost: AStarCost> ClosedList<Identifier, Cost> {
+    /// Create a closed list for nodes of the given amount of sequences,
+    /// which is written to disk if spill options are given, and kept in memory otherwise.
+    pub fn new(spill: Option<&SpillOptions>, sequence_amount: usize) -> Self {
+        match spill {
+            Some(spill) => Self::Spilling(SpillingClosedList::new(spill.clone(), sequence_amount)),
+            None => Self::Memory(Default::default()),
+        }
+    }
+
+    pub fn get(&mut self, identifier: &Identifier) -> Result<Option<Cost>> {
+        match self {
+            Self::Memory(closed_list) => Ok(closed_list.get(identifier).copied()),
+            Self::Spilling(closed_list) => closed_list.get(identifier),
+        }
+    }
+
+    pub fn contains(&mut self, identifier: &Identifier) -> Result<bool> {
+        Ok(self.get(identifier)?.is_some())
+    }
+
+    pub fn insert(&mut self, identifier: Identifier, cost: Cost) -> Result<()> {
+        match self {
+            Self::Memory(closed_list) => {
+                closed_list.insert(identifier, cost);
+                Ok(())
+            }
+            Self::Spilling(closed_list) => closed_list.insert(identifier, cost),
+        }
+    }
+}
+
+/// A closed list that keeps the recently used nodes in memory, and writes the others into a hash table in a file.
+///
+/// Nodes read from the file are cached in memory again, but stay in the file, so each node is written at most once.
+/// The file is accessed by seeking, so the operating system caches the frequently read parts of it.
+pub(crate) struct SpillingClosedList<Identifier, Cost> {
+    options: SpillOptions,
+    sequence_amount: usize,
+    /// The cached nodes with their cost, the time they were last used, and whether they are stored in the file.
+    cache: HashMap<Identifier, CachedNode<Cost>>,
+    /// The time of the last use of a cached node, which counts the uses.
+    time: u64,
+    file: Option<HashTableFile>,
+}
+
+struct CachedNode<Cost> {
+    cost: Cost,
+    last_use: u64,
+    is_in_file: bool,
+}
+
+impl<Identifier: NodeIdentifier, Cost: AStarCost> SpillingClosedList<Identifier, Cost> {
+    fn new(options: SpillOptions, sequence_amount: usize) -> Self {
+        Self {
+            options,
+            sequence_amount,
+            cache: Default::default(),
+            time: 0,
+            file: None,
+        }
+    }
+
+    /// The words that identify a node in the file, i.e. its offsets and its gaps.
+    fn key(&self, identifier: &Identifier) -> Vec<u64> {
+        (0..self.sequence_amount)
+            .map(|index| identifier.offset(index) as u64)
+            .chain([identifier.gaps() as u64])
+            .collect()
+    }
+
+    fn get(&mut self, identifier: &Identifier) -> Result<Option<Cost>> {
+        self.time += 1;
+        if let Some(cached) = self.cache.get_mut(identifier) {
+            cached.last_use = self.time;
+            return Ok(Some(cached.cost));
+        }
+
+        let key = self.key(identifier);
+        let Some(file) = &mut self.file else {
+            return Ok(None);
+        };
+        let Some(cost) = file.get(&key)? else {
+            return Ok(None);
+        };
+        let cost = cost_from_i64(cost);
+        self.cache.insert(
+            identifier.clone(),
+            CachedNode {
+                cost,
+                last_use: self.time,
+                is_in_file: true,
+            },
+        );
+        self.evict()?;
+        Ok(Some(cost))
+    }
+
+    fn insert(&mut self, identifier: Identifier, cost: Cost) -> Result<()> {
+        self.time += 1;
+        self.cache.insert(
+            identifier,
+            CachedNode {
+                cost,
+                last_use: self.time,
+                is_in_file: false,
+            },
+        );
+        self.evict()
+    }
+
+    /// Write the least recently used half of the cached nodes to the file if the cache is full.
+    fn evict(&mut self) -> Result<()> {
+        if self.cache.len() <= self.options.cached_nodes.max(1) {
+            return Ok(());
+        }
+
+        let mut last_uses: Vec<_> = self.cache.values().map(|cached| cached.last_use).collect();
+        let median = last_uses.len() / 2;
+        let (_, threshold, _) = last_uses.select_nth_unstable(median);
+        let threshold = *threshold;
+
+        if self.file.is_none() {
+            self.file = Some(HashTableFile::create(&self.options)?);
+        }
+        let mut evicted = Vec::new();
+        for (identifier, cached) in &self.cache {
+            if cached.last_use < threshold {
+                if !cached.is_in_file {
+                    let key = self.key(identifier);
+                    let file = self.file.as_mut().unwrap();
+                    file.insert(&key, cached.cost.as_u64() as i64)?;
+                }
+                evicted.push(identifier.clone());
+            }
+        }
+        for identifier in &evicted {
+            self.cache.remove(identifier);
+        }
+
+        debug!(
+            "Moved {} closed nodes to disk, {} are stored in the file",
+            evicted.len(),
+            self.file.as_ref().unwrap().len
+        );
+        Ok(())
+    }
+}
+
+/// A hash table with linear probing in a file, which maps keys of a fixed amount of words to costs.
+///
+/// Each slot consists of a word that is one if the slot is used, the words of the key and the cost.
+/// The table doubles its capacity once it is half full.
+struct HashTableFile {
+    path: PathBuf,
+    file: File,
+    /// The amount of words per key, which is known after the first insertion.
+    key_words: usize,
+    capacity: u64,
+    len: u64,
+}
+
+impl HashTableFile {
+    const INITIAL_CAPACITY: u64 = 1 << 16;
+
+    fn create(options: &SpillOptions) -> Result<Self> {
+        let (path, file) = create_spill_file(options, "closed-list")?;
+        debug!("Writing closed nodes to {path:?}");
+
+        Ok(Self {
+            path,
+            file,
+            key_words: 0,
+            capacity: Self::INITIAL_CAPACITY,
+            len: 0,
+        })
+    }
+
+    fn slot_bytes(&self) -> u64 {
+        (self.key_words as u64 + 2) * 8
+    }
+
+    fn first_slot(&self, key: &[u64]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        hasher.finish() % self.capacity
+    }
+
+    fn read_slot(&mut self, slot: u64, words: &mut [u64]) -> Result<()> {
+        let mut bytes = vec![0; words.len() * 8];
+        let result = self
+            .file
+            .seek(SeekFrom::Start(slot * self.slot_bytes()))
+            .and_then(|_| self.file.read_exact(&mut bytes));
+        match result {
+            Ok(()) => {}
+            // Slots after the end of the file were never written.
+            Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => bytes.fill(0),
+            Err(source) => {
+                return Err(MultialignError::Io {
+                    path: self.path.clone(),
+                    source,
+                })
+            }
+        }
+
+        for (word, bytes) in words.iter_mut().zip(bytes.chunks_exact(8)) {
+            *word = u64::from_le_bytes(bytes.try_into().unwrap());
+        }
+        Ok(())
+    }
+
+    fn write_slot(&mut self, slot: u64, words: &[u64]) -> Result<()> {
+        let bytes: Vec<_> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+        self.file
+            .seek(SeekFrom::Start(slot * self.slot_bytes()))
+            .and_then(|_| self.file.write_all(&bytes))
+            .map_err(|source| MultialignError::Write {
+                path: self.path.clone(),
+                source,
+            })
+    }
+
+    /// Returns the slot that contains the given key, or the free slot where it would be inserted, and whether the key was found.
+    fn find(&mut self, key: &[u64]) -> Result<(u64, Option<i64>)> {
+        let mut words = vec![0; self.key_words + 2];
+        let mut slot = self.first_slot(key);
+        loop {
+            self.read_slot(slot, &mut words)?;
+            if words[0] == 0 {
+                return Ok((slot, None));
+            }
+            if &words[1..=self.key_words] == key {
+                return Ok((slot, Some(words[self.key_words + 1] as i64)));
+            }
+            slot = (slot + 1) % self.capacity;
+        }
+    }
+
+    fn get(&mut self, key: &[u64]) -> Result<Option<i64>> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        Ok(self.find(key)?.1)
+    }
+
+    fn insert(&mut self, key: &[u64], cost: i64) -> Result<()> {
+        if self.len == 0 {
+            self.key_words = key.len();
+        }
+        debug_assert_eq!(key.len(), self.key_words);
+        if (self.len + 1) * 2 > self.capacity {
+            self.grow()?;
+        }
+
+        let (slot, existing) = self.find(key)?;
+        let mut words = Vec::with_capacity(self.key_words + 2);
+        words.push(1);
+        words.extend_from_slice(key);
+        words.push(cost as u64);
+        self.write_slot(slot, &words)?;
+        if existing.is_none() {
+            self.len += 1;
+        }
+        Ok(())
+    }
+
+    /// Move all entries into a new file with twice the capacity.
+    fn grow(&mut self) -> Result<()> {
+        let mut grown = Self {
+            path: self.path.with_extension("grown"),
+            file: create_file(&self.path.with_extension("grown"))?,
+            key_words: self.key_words,
+            capacity: self.capacity * 2,
+            len: 0,
+        };
+        debug!("Growing the closed list file to {} slots", grown.capacity);
+
+        let mut words = vec![0; self.key_words + 2];
+        for slot in 0..self.capacity {
+            self.read_slot(slot, &mut words)?;
+            if words[0] != 0 {
+                grown.insert(&words[1..=self.key_words], words[self.key_words + 1] as i64)?;
+            }
+        }
+
+        let path = self.path.clone();
+        // Dropping the old table removes its file, which is then replaced by the new one.
+        drop(mem::replace(self, grown));
+        fs::rename(&self.path, &path).map_err(|source| MultialignError::Write {
+            path: path.clone(),
+            source,
+        })?;
+        self.path = path;
+        Ok(())
+    }
+}
+
+impl Drop for HashTableFile {
+    fn drop(&mut self) {
+        // The file only serves the search, so it is removed even if the search failed.
+        fs::remove_file(&self.path).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use generic_a_star::cost::{AStarCost, I32Cost};
+
+    use super::{ClosedList, HashTableFile, SpillOptions};
+    use crate::VecIdentifier;
+
+    fn spill_options(cached_nodes: usize) -> SpillOptions {
+        SpillOptions {
+            directory: env::temp_dir(),
+            cached_nodes,
+        }
+    }
+
+    #[test]
+    fn hash_table_file_round_trip() {
+        let mut table = HashTableFile::create(&spill_options(0)).unwrap();
+        let path = table.path.clone();
+        assert_eq!(table.get(&[1, 2, 3]).unwrap(), None);
+
+        table.insert(&[1, 2, 3], 7).unwrap();
+        table.insert(&[3, 2, 1], -7).unwrap();
+        assert_eq!(table.get(&[1, 2, 3]).unwrap(), Some(7));
+        assert_eq!(table.get(&[3, 2, 1]).unwrap(), Some(-7));
+        assert_eq!(table.get(&[1, 2, 4]).unwrap(), None);
+
+        table.insert(&[1, 2, 3], 5).unwrap();
+        assert_eq!(table.get(&[1, 2, 3]).unwrap(), Some(5));
+        assert_eq!(table.len, 2);
+
+        assert!(path.exists());
+        drop(table);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn hash_table_file_grows() {
+        let mut table = HashTableFile::create(&spill_options(0)).unwrap();
+        let path = table.path.clone();
+        let amount = HashTableFile::INITIAL_CAPACITY;
+        for key in 0..amount {
+            table.insert(&[key, key / 3], key as i64 - 100).unwrap();
+        }
+        assert_eq!(table.capacity, HashTableFile::INITIAL_CAPACITY * 2);
+        assert_eq!(table.len, amount);
+        assert_eq!(table.path, path);
+        assert!(!path.with_extension("grown").exists());
+
+        for key in 0..amount {
+            assert_eq!(table.get(&[key, key / 3]).unwrap(), Some(key as i64 - 100));
+        }
+        assert_eq!(table.get(&[amount, amount / 3]).unwrap(), None);
+
+        drop(table);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn spilling_closed_list_keeps_all_nodes() {
+        let mut closed_list = ClosedList::new(Some(&spill_options(4)), 2);
+        let identifier = |index: usize| VecIdentifier {
+            offsets: vec![index, 2 * index],
+            gaps: index % 3,
+        };
+        for index in 0..100 {
+            closed_list
+                .insert(identifier(index), I32Cost::from(index as i32 - 50))
+                .unwrap();
+        }
+        let ClosedList::Spilling(spilling) = &closed_list else {
+            panic!("the closed list is not spilling");
+        };
+        assert!(spilling.cache.len() <= 4);
+
+        for index in (0..100).rev() {
+            assert_eq!(
+                closed_list
+                    .get(&identifier(index))
+                    .unwrap()
+                    .map(|cost| cost.as_primitive()),
+                Some(index as i32 - 50)
+            );
+        }
+        assert!(!closed_list.contains(&identifier(100)).unwrap());
+    }
+}
@@ -0,0 +1,136 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use log::info;
+
+use super::{
+    error::{MultialignError, Result},
+    output::ResidueType,
+};
+
+/// How the consensus residue of a column is chosen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConsensusRule {
+    /// The most frequent residue, where ties are broken by the first occurrence.
+    Majority,
+    /// The most frequent residue if at least the given fraction of the residues of the column are equal to it,
+    /// and otherwise `N` for nucleotides or `X` for amino acids.
+    MinIdentity(f64),
+    /// The IUPAC ambiguity code of all residues of the column.
+    ///
+    /// This is only supported for nucleotides.
+    Iupac,
+}
+
+/// Where and how to output the consensus sequence of an alignment.
+#[derive(Debug, Clone)]
+pub struct ConsensusOutput {
+    pub rule: ConsensusRule,
+    /// If set, the consensus is written to this fasta file.
+    /// Otherwise, it is added to the alignment as an extra row.
+    pub path: Option<PathBuf>,
+    /// The id of the consensus record.
+    pub id: String,
+}
+
+/// Compute the consensus of each column of the alignment, or `None` if it has fewer residues than gaps.
+///
+/// Residues are compared case-insensitively, and the consensus is upper case.
+pub fn consensus_row<CharacterType: Clone + Into<char>>(
+    columns: &[Vec<Option<CharacterType>>],
+    rule: ConsensusRule,
+    residue_type: ResidueType,
+) -> Vec<Option<char>> {
+    columns
+        .iter()
+        .map(|column| {
+            let residues: Vec<char> = column
+                .iter()
+                .flatten()
+                .map(|character| character.clone().into().to_ascii_uppercase())
+                .collect();
+            if residues.len() * 2 < column.len() {
+                return None;
+            }
+
+            Some(match rule {
+                ConsensusRule::Majority => most_frequent(&residues).0,
+                ConsensusRule::MinIdentity(min_identity) => {
+                    let (residue, count) = most_frequent(&residues);
+                    if count as f64 >= min_identity * residues.len() as f64 {
+                        residue
+                    } else if residue_type == ResidueType::AminoAcid {
+                        'X'
+                    } else {
+                        'N'
+                    }
+                }
+                ConsensusRule::Iupac => iupac_code(&residues, residue_type),
+            })
+        })
+        .collect()
+}
+
+/// Write the consensus as a single fasta record without gaps.
+pub fn write_consensus_file(
+    path: impl AsRef<Path>,
+    id: &str,
+    consensus: &[Option<char>],
+) -> Result<()> {
+    let path = path.as_ref();
+    info!("Writing consensus to {path:?}");
+
+    let write_error = |source| MultialignError::Write {
+        path: path.to_owned(),
+        source,
+    };
+    let mut writer = BufWriter::new(File::create(path).map_err(write_error)?);
+    writeln!(writer, ">{id}").map_err(write_error)?;
+    writeln!(writer, "{}", consensus.iter().flatten().collect::<String>()).map_err(write_error)?;
+    writer.flush().map_err(write_error)
+}
+
+/// Returns the most frequent of the given residues with its count, where ties are broken by the first occurrence.
+///
+/// The residues must not be empty.
+fn most_frequent(residues: &[char]) -> (char, usize) {
+    let mut best = (residues[0], 0);
+    for &residue in residues {
+        let count = residues.iter().filter(|&&other| other == residue).count();
+        if count > best.1 {
+            best = (residue, count);
+        }
+    }
+    best
+}
+
+/// The IUPAC codes indexed by sets of nucleotides, where `A`, `C`, `G` and `T`/`U` are the bits from lowest to highest.
+const IUPAC_CODES: [char; 16] = [
+    '-', 'A', 'C', 'M', 'G', 'R', 'S', 'V', 'T', 'W', 'Y', 'H', 'K', 'D', 'B', 'N',
+];
+
+/// Returns the IUPAC ambiguity code of the given residues, where ambiguous residues contribute all nucleotides they stand for.
+///
+/// Unknown residues result in `N`.
+fn iupac_code(residues: &[char], residue_type: ResidueType) -> char {
+    let bits = residues.iter().fold(0, |bits, &residue| {
+        bits | iupac_nucleotides(residue).unwrap_or(15)
+    });
+
+    match IUPAC_CODES[bits] {
+        'T' if residue_type == ResidueType::Rna => 'U',
+        code => code,
+    }
+}
+
+/// Returns the set of nucleotides that the given IUPAC code stands for as bits like in [`IUPAC_CODES`], or `None` if it is not a nucleotide code.
+pub(crate) fn iupac_nucleotides(code: char) -> Option<usize> {
+    let code = if code == 'U' { 'T' } else { code };
+    IUPAC_CODES
+        .iter()
+        .position(|&other| other == code)
+        .filter(|&position| position > 0)
+}
@@ -0,0 +1,162 @@
+use std::{collections::HashMap, fmt::Debug};
+
+use compact_genome::interface::{alphabet::Alphabet, sequence::GenomeSequence};
+use generic_a_star::cost::I32Cost;
+
+use crate::{
+    align, error::Result, metric::pairwise_match_metric::PairwiseMatchMetric,
+    path::operation_columns, MultialignOptions,
+};
+
+/// The residues of another sequence that a residue is paired with, together with the weights of the pairs.
+type ResiduePairs = Vec<(usize, u32)>;
+
+/// A library of weights for pairs of residues of different sequences, like in T-Coffee.
+///
+/// The primary library contains the pairs of residues that are aligned in optimal pairwise alignments of all pairs of sequences,
+/// each weighted by the percent identity of its pairwise alignment.
+/// It is then extended by adding, for each third sequence, the weight of the weaker pair of each path through a residue of the third sequence.
+/// Pairs of residues that are supported by many sequences this way get high weights.
+#[derive(Debug, Clone)]
+pub struct ConsistencyLibrary {
+    sequence_amount: usize,
+    /// For each ordered pair of sequences and each residue of the first sequence, its pairs with residues of the second sequence, sorted by residue.
+    pairs: Vec<Vec<ResiduePairs>>,
+    max_weight: u32,
+}
+
+impl ConsistencyLibrary {
+    /// Build the extended library of the given sequences from optimal pairwise alignments under [`PairwiseMatchMetric`].
+    pub fn from_sequences<
+        AlphabetType: Alphabet + Debug + Clone + Eq + Send + 'static,
+        SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized + Sync,
+    >(
+        sequences: &[&SequenceType],
+    ) -> Result<Self> {
+        let sequence_amount = sequences.len();
+        let mut primary = Self {
+            sequence_amount,
+            pairs: (0..sequence_amount * sequence_amount)
+                .map(|index| vec![Vec::new(); sequences[index / sequence_amount].len()])
+                .collect(),
+            max_weight: 0,
+        };
+
+        for i in 0..sequence_amount {
+            for j in i + 1..sequence_amount {
+                let alignment = align::<_, _, _, I32Cost>(
+                    &[sequences[i], sequences[j]],
+                    PairwiseMatchMetric::new(2)?,
+                    &MultialignOptions::default(),
+                )?;
+
+                let mut aligned_pairs = Vec::new();
+                let mut matches = 0usize;
+                for (column, residues) in operation_columns(&alignment.columns)
+                    .iter()
+                    .zip(&alignment.columns)
+                {
+                    if let (Some(x), Some(y)) = (
+                        column.operations[0].residue(),
+                        column.operations[1].residue(),
+                    ) {
+                        aligned_pairs.push((x, y));
+                        if residues[0] == residues[1] {
+                            matches += 1;
+                        }
+                    }
+                }
+
+                let weight = (100 * matches)
+                    .checked_div(aligned_pairs.len())
+                    .unwrap_or(0) as u32;
+                if weight > 0 {
+                    for (x, y) in aligned_pairs {
+                        primary.pairs[i * sequence_amount + j][x].push((y, weight));
+                        primary.pairs[j * sequence_amount + i][y].push((x, weight));
+                    }
+                }
+            }
+        }
+
+        Ok(primary.extend())
+    }
+
+    /// Extend the library by the pairs of residues that are connected via a residue of a third sequence.
+    fn extend(&self) -> Self {
+        let sequence_amount = self.sequence_amount;
+        let mut extended = Self {
+            sequence_amount,
+            pairs: self
+                .pairs
+                .iter()
+                .map(|residues| vec![Vec::new(); residues.len()])
+                .collect(),
+            max_weight: 0,
+        };
+
+        for i in 0..sequence_amount {
+            for j in i + 1..sequence_amount {
+                let mut weights: HashMap<(usize, usize), u32> = HashMap::new();
+                for (x, pairs) in self.pairs[i * sequence_amount + j].iter().enumerate() {
+                    for (y, weight) in pairs {
+                        *weights.entry((x, *y)).or_default() += weight;
+                    }
+                }
+
+                for k in (0..sequence_amount).filter(|k| *k != i && *k != j) {
+                    for (x, pairs) in self.pairs[i * sequence_amount + k].iter().enumerate() {
+                        for (z, first_weight) in pairs {
+                            for (y, second_weight) in &self.pairs[k * sequence_amount + j][*z] {
+                                *weights.entry((x, *y)).or_default() +=
+                                    (*first_weight).min(*second_weight);
+                            }
+                        }
+                    }
+                }
+
+                for ((x, y), weight) in weights {
+                    extended.pairs[i * sequence_amount + j][x].push((y, weight));
+                    extended.pairs[j * sequence_amount + i][y].push((x, weight));
+                    extended.max_weight = extended.max_weight.max(weight);
+                }
+            }
+        }
+
+        for residues in &mut extended.pairs {
+            for pairs in residues {
+                pairs.sort_unstable();
+            }
+        }
+
+        extended
+    }
+
+    /// Returns the amount of sequences of the library.
+    pub fn sequence_amount(&self) -> usize {
+        self.sequence_amount
+    }
+
+    /// Returns the highest weight of any pair of residues.
+    pub fn max_weight(&self) -> u32 {
+        self.max_weight
+    }
+
+    /// Returns the weight of the pair of the given residues of the given sequences, or zero if the library does not contain it.
+    ///
+    /// **Panics** if a sequence or residue does not exist.
+    pub fn weight(
+        &self,
+        first_sequence: usize,
+        first_offset: usize,
+        second_sequence: usize,
+        second_offset: usize,
+    ) -> u32 {
+        let pairs =
+            &self.pairs[first_sequence * self.sequence_amount + second_sequence][first_offset];
+        pairs
+            .binary_search_by_key(&second_offset, |(offset, _)| *offset)
+            .map(|index| pairs[index].1)
+            .unwrap_or(0)
+    }
+}
@@ -0,0 +1,91 @@
+use log::info;
+
+/// Compute the consensus of the given alignment columns.
+///
+/// The consensus of a column is its most frequent residue, where ties are broken by the first occurrence.
+/// Columns without residues have no consensus.
+pub fn consensus<CharacterType: Clone + Eq>(
+    columns: &[Vec<Option<CharacterType>>],
+) -> Vec<Option<CharacterType>> {
+    columns
+        .iter()
+        .map(|column| {
+            let mut best: Option<(&CharacterType, usize)> = None;
+            for character in column.iter().flatten() {
+                let count = column
+                    .iter()
+                    .filter(|other| other.as_ref() == Some(character))
+                    .count();
+                if best.is_none_or(|(_, best_count)| count > best_count) {
+                    best = Some((character, count));
+                }
+            }
+            best.map(|(character, _)| character.clone())
+        })
+        .collect()
+}
+
+/// Compute the identity of each row of the given alignment columns to the consensus.
+///
+/// The identity of a row is the fraction of its residues that are equal to the consensus of their column.
+/// Rows without residues have an identity of zero.
+pub fn identities_to_consensus<CharacterType: Clone + Eq>(
+    columns: &[Vec<Option<CharacterType>>],
+) -> Vec<f64> {
+    let sequence_amount = columns.first().map(Vec::len).unwrap_or(0);
+    let consensus = consensus(columns);
+    let mut residues = vec![0usize; sequence_amount];
+    let mut matches = vec![0usize; sequence_amount];
+
+    for (column, consensus) in columns.iter().zip(&consensus) {
+        for (row, character) in column.iter().enumerate() {
+            if let Some(character) = character {
+                residues[row] += 1;
+                if Some(character) == consensus.as_ref() {
+                    matches[row] += 1;
+                }
+            }
+        }
+    }
+
+    residues
+        .into_iter()
+        .zip(matches)
+        .map(|(residues, matches)| {
+            if residues == 0 {
+                0.0
+            } else {
+                matches as f64 / residues as f64
+            }
+        })
+        .collect()
+}
+
+/// Remove the rows whose identity to the consensus is below the given threshold, and then remove all columns that consist only of gaps.
+///
+/// Returns the remaining columns and the indices of the remaining rows.
+pub fn filter_by_identity_to_consensus<CharacterType: Clone + Eq>(
+    columns: Vec<Vec<Option<CharacterType>>>,
+    min_identity: f64,
+) -> (Vec<Vec<Option<CharacterType>>>, Vec<usize>) {
+    let identities = identities_to_consensus(&columns);
+    let mut kept_rows = Vec::new();
+
+    for (row, identity) in identities.into_iter().enumerate() {
+        if identity >= min_identity {
+            kept_rows.push(row);
+        } else {
+            info!(
+                "Removing sequence {row} because its identity to the consensus is {identity:.3} < {min_identity}"
+            );
+        }
+    }
+
+    let columns: Vec<Vec<_>> = columns
+        .into_iter()
+        .map(|column| kept_rows.iter().map(|&row| column[row].clone()).collect())
+        .filter(|column: &Vec<Option<_>>| column.iter().any(Option::is_some))
+        .collect();
+
+    (columns, kept_rows)
+}
@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+
+use generic_a_star::cost::AStarCost;
+
+use super::NodeIdentifier;
+
+/// The closed nodes of a search grouped by their offsets, which prunes the nodes they dominate,
+/// see [`MultialignOptions::dominance_pruning`](crate::MultialignOptions::dominance_pruning).
+///
+/// A closed node dominates a node with the same offsets if it costs at most as much and is in a gap in at least the same sequences.
+/// The continuations of both nodes are the same, and each of them costs at most as much after the closed node,
+/// since only the first column of a continuation may differ in cost, where a gap in a sequence that is already in a gap is extended instead of opened.
+/// This relies on opening a gap costing at least as much as extending it, as required by [`MultialignMetric::has_affine_gaps`](crate::metric::MultialignMetric::has_affine_gaps).
+pub(crate) struct DominanceTable<Identifier, Cost> {
+    /// The gaps and the cost of the closed nodes that are not dominated by other closed nodes, by their identifier without gaps.
+    closed: HashMap<Identifier, Vec<(usize, Cost)>>,
+    pruned_nodes: usize,
+}
+
+impl<Identifier, Cost> DominanceTable<Identifier, Cost> {
+    pub fn new() -> Self {
+        Self {
+            closed: HashMap::new(),
+            pruned_nodes: 0,
+        }
+    }
+
+    /// The amount of nodes for which [`Self::dominates`] returned true.
+    pub fn pruned_nodes(&self) -> usize {
+        self.pruned_nodes
+    }
+}
+
+impl<Identifier: NodeIdentifier, Cost: AStarCost> DominanceTable<Identifier, Cost> {
+    /// Record that the node with the given identifier was closed with the given cost.
+    pub fn insert(&mut self, identifier: &Identifier, cost: Cost) {
+        let gaps = identifier.gaps();
+        let entries = self.closed.entry(Self::key(identifier)).or_default();
+        if entries
+            .iter()
+            .any(|&(closed_gaps, closed_cost)| dominates(closed_gaps, closed_cost, gaps, cost))
+        {
+            return;
+        }
+
+        entries
+            .retain(|&(closed_gaps, closed_cost)| !dominates(gaps, cost, closed_gaps, closed_cost));
+        entries.push((gaps, cost));
+    }
+
+    /// Returns true if a closed node dominates the node with the given identifier and cost, and counts it as pruned.
+    pub fn dominates(&mut self, identifier: &Identifier, cost: Cost) -> bool {
+        let gaps = identifier.gaps();
+        let dominated = self
+            .closed
+            .get(&Self::key(identifier))
+            .is_some_and(|entries| {
+                entries.iter().any(|&(closed_gaps, closed_cost)| {
+                    dominates(closed_gaps, closed_cost, gaps, cost)
+                })
+            });
+        self.pruned_nodes += usize::from(dominated);
+        dominated
+    }
+
+    fn key(identifier: &Identifier) -> Identifier {
+        let mut key = identifier.clone();
+        key.set_gaps(0);
+        key
+    }
+}
+
+/// Returns true if a node with the first gaps and cost dominates a node with the same offsets and the second gaps and cost.
+fn dominates<Cost: AStarCost>(
+    gaps: usize,
+    cost: Cost,
+    other_gaps: usize,
+    other_cost: Cost,
+) -> bool {
+    gaps & other_gaps == other_gaps && cost <= other_cost
+}
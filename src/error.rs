@@ -0,0 +1,147 @@
+use std::{io, path::PathBuf};
+
+use thiserror::Error;
+
+/// The errors returned by the aligner and its metrics.
+///
+/// New kinds of errors may be added in the future, so matches on this type need a wildcard arm.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum MultialignError {
+    /// A file could not be opened or read.
+    #[error("Error reading file {path:?}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// A file could not be created or written.
+    #[error("Error writing file {path:?}")]
+    Write {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// A CSV file could not be parsed.
+    #[cfg(feature = "csv")]
+    #[error("Error reading CSV file {path:?}")]
+    Csv {
+        path: PathBuf,
+        #[source]
+        source: csv::Error,
+    },
+
+    /// Less than two sequences were given.
+    #[error("Found {amount} sequences, but at least two are required")]
+    TooFewSequences { amount: usize },
+
+    /// More sequences were given than supported.
+    #[error("Found {amount} sequences, but at most {max} are supported")]
+    TooManySequences { amount: usize, max: usize },
+
+    /// The cost table does not contain a cost for a pair of characters, where `None` is a gap.
+    #[error("Missing cost for row {}, column {}", .from.as_deref().unwrap_or("gap"), .to.as_deref().unwrap_or("gap"))]
+    IncompleteCostTable {
+        from: Option<String>,
+        to: Option<String>,
+    },
+
+    /// The cost table is malformed.
+    #[error("Invalid cost table: {0}")]
+    InvalidCostTable(String),
+
+    /// The gap profile is malformed.
+    #[error("Invalid gap profile: {0}")]
+    InvalidGapProfile(String),
+
+    /// The arguments given for constructing a metric by name do not fit the metric.
+    #[error("Invalid metric arguments: {0}")]
+    InvalidMetricArguments(String),
+
+    /// The profile alignment is malformed or does not match the sequences.
+    #[error("Invalid profile: {0}")]
+    InvalidProfile(String),
+
+    /// The profile HMM is malformed.
+    #[cfg(feature = "hmm")]
+    #[error("Invalid profile HMM: {0}")]
+    InvalidProfileHmm(String),
+
+    /// The script of a script metric is malformed or fails to evaluate.
+    #[cfg(feature = "script")]
+    #[error("Invalid script: {0}")]
+    InvalidScript(String),
+
+    /// The aligned fasta file is malformed.
+    #[error("Invalid aligned fasta file: {0}")]
+    InvalidAlignment(String),
+
+    /// An index of a fasta file is malformed or does not match the fasta file.
+    #[error("Invalid index: {0}")]
+    InvalidIndex(String),
+
+    /// The Newick tree is malformed or does not match the sequences.
+    #[error("Invalid Newick tree: {0}")]
+    InvalidTree(String),
+
+    /// The guide tree cannot be constructed with the given options.
+    #[error("Invalid guide tree: {0}")]
+    InvalidGuideTree(String),
+
+    /// The anchors are inconsistent with the sequences.
+    #[error("Invalid anchors: {0}")]
+    InvalidAnchors(String),
+
+    /// The guide alignment of a band is inconsistent with the sequences.
+    #[error("Invalid band: {0}")]
+    InvalidBand(String),
+
+    /// The metric does not support the requested operation.
+    #[error("Unsupported metric: {0}")]
+    UnsupportedMetric(String),
+
+    /// The weight of the lower bounds is not a finite number of at least one.
+    #[error("The weight of the lower bounds must be a finite number of at least one, but is {0}")]
+    InvalidWeight(f64),
+
+    /// The memory required by the aligner could not be allocated.
+    #[error("Could not allocate {bytes} bytes")]
+    MemoryLimit { bytes: usize },
+
+    /// The search stored more nodes than allowed by [`MultialignOptions::memory_limit`](crate::MultialignOptions::memory_limit).
+    ///
+    /// The cost of the last closed node plus its lower bound is a lower bound of the optimal cost.
+    #[error("Exceeded the memory limit after closing {closed_nodes} nodes{}", .cost_lower_bound.map(|cost| format!(", the optimal cost is at least {cost}")).unwrap_or_default())]
+    ExceededMemoryLimit {
+        closed_nodes: usize,
+        cost_lower_bound: Option<u64>,
+    },
+
+    /// The alignment was cancelled with a [`CancellationToken`](crate::cancellation::CancellationToken).
+    ///
+    /// If the search was cancelled before finding a target, the cost of the last closed node plus its lower bound is a lower bound of the optimal cost.
+    #[error("Alignment cancelled after closing {closed_nodes} nodes{}", .cost_lower_bound.map(|cost| format!(", the optimal cost is at least {cost}")).unwrap_or_default())]
+    Cancelled {
+        closed_nodes: usize,
+        cost_lower_bound: Option<u64>,
+    },
+
+    /// A cost exceeded the range of the cost type.
+    #[error("Cost overflowed")]
+    CostOverflow,
+
+    /// A cost cannot be represented by the cost type chosen for the search.
+    #[error(
+        "Cost {cost} is out of range of the cost type {cost_type}, choose a larger or a signed cost type"
+    )]
+    CostOutOfRange { cost: i64, cost_type: &'static str },
+
+    /// The GPU failed to compute the lower bounds of the heuristic.
+    #[cfg(feature = "gpu")]
+    #[error("GPU error: {0}")]
+    Gpu(String),
+}
+
+pub type Result<T> = std::result::Result<T, MultialignError>;
@@ -0,0 +1,203 @@
+//! A C ABI for the aligner.
+//!
+//! Build it as a shared library with `cargo rustc --release --lib --features multialign-ffi --crate-type cdylib`,
+//! and include `include/multialign.h` in the C or C++ code.
+//! The sequences are aligned with the default metric and options, like on the command line without further arguments.
+
+use std::{
+    ffi::{c_char, CStr, CString},
+    panic::{catch_unwind, AssertUnwindSafe},
+    ptr,
+};
+
+use compact_genome::{
+    implementation::{
+        alphabets::{
+            dna_iupac_nucleic_acid_alphabet::DnaIupacNucleicAcidAlphabet,
+            iupac_amino_acid_alphabet::IupacAminoAcidAlphabet,
+            rna_iupac_nucleic_acid_alphabet::RnaIupacNucleicAcidAlphabet,
+        },
+        vec_sequence::VectorGenome,
+    },
+    interface::{
+        alphabet::Alphabet,
+        sequence::{GenomeSequence, OwnedGenomeSequence},
+    },
+};
+use generic_a_star::cost::{AStarCost, I32Cost};
+
+use crate::{
+    align, error::MultialignError, metric::pairwise_match_metric::PairwiseMatchMetric,
+    GapCharacters, MultialignOptions,
+};
+
+/// The alphabet of the sequences given to [`multialign_align`].
+///
+/// The IUPAC alphabets are used, such that ambiguous residues are accepted.
+/// The alphabet is passed as its integer value, since a C caller may pass any integer.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy)]
+pub enum MultialignAlphabet {
+    Dna = 0,
+    Rna = 1,
+    AminoAcid = 2,
+}
+
+impl MultialignAlphabet {
+    /// Returns the alphabet with the given integer value, or `None` if there is none.
+    fn from_raw(alphabet: u32) -> Option<Self> {
+        [Self::Dna, Self::Rna, Self::AminoAcid]
+            .into_iter()
+            .find(|candidate| *candidate as u32 == alphabet)
+    }
+}
+
+/// The result status of [`multialign_align`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum MultialignStatus {
+    Ok = 0,
+    /// A pointer was null, the alphabet is unknown, or less than two sequences were given.
+    InvalidArgument = 1,
+    /// A sequence contains a character that is not part of the alphabet.
+    InvalidCharacter = 2,
+    /// More sequences were given than supported.
+    TooManySequences = 3,
+    /// The memory required by the aligner could not be allocated.
+    MemoryLimit = 4,
+    /// Any other error, including panics of the aligner.
+    Error = 5,
+}
+
+/// An alignment returned by [`multialign_align`].
+///
+/// It must be freed with [`multialign_alignment_free`].
+#[repr(C)]
+#[derive(Debug)]
+pub struct MultialignAlignment {
+    /// The gapped rows of the alignment as null-terminated strings, in the order of the input sequences.
+    pub rows: *mut *mut c_char,
+    pub row_amount: usize,
+    /// The cost of the alignment.
+    pub cost: i64,
+}
+
+/// Align the given null-terminated sequences and write the alignment to `result`.
+///
+/// On error, `result` is left unchanged.
+///
+/// `alphabet` is the value of a [`MultialignAlphabet`], and other values are rejected as invalid arguments.
+///
+/// # Safety
+///
+/// `sequences` must point to `sequence_amount` valid pointers to null-terminated strings,
+/// and `result` must point to memory that is valid for writing a [`MultialignAlignment`].
+#[no_mangle]
+pub unsafe extern "C" fn multialign_align(
+    sequences: *const *const c_char,
+    sequence_amount: usize,
+    alphabet: u32,
+    result: *mut MultialignAlignment,
+) -> MultialignStatus {
+    let Some(alphabet) = MultialignAlphabet::from_raw(alphabet) else {
+        return MultialignStatus::InvalidArgument;
+    };
+    if sequences.is_null() || result.is_null() {
+        return MultialignStatus::InvalidArgument;
+    }
+
+    let mut input = Vec::with_capacity(sequence_amount);
+    for index in 0..sequence_amount {
+        let sequence = *sequences.add(index);
+        if sequence.is_null() {
+            return MultialignStatus::InvalidArgument;
+        }
+        input.push(CStr::from_ptr(sequence).to_bytes());
+    }
+
+    let alignment = catch_unwind(AssertUnwindSafe(|| match alphabet {
+        MultialignAlphabet::Dna => align_rows::<DnaIupacNucleicAcidAlphabet>(&input),
+        MultialignAlphabet::Rna => align_rows::<RnaIupacNucleicAcidAlphabet>(&input),
+        MultialignAlphabet::AminoAcid => align_rows::<IupacAminoAcidAlphabet>(&input),
+    }));
+
+    match alignment {
+        Ok(Ok((rows, cost))) => {
+            let rows: Box<[*mut c_char]> = rows.into_iter().map(CString::into_raw).collect();
+            let row_amount = rows.len();
+            result.write(MultialignAlignment {
+                rows: Box::into_raw(rows).cast(),
+                row_amount,
+                cost,
+            });
+            MultialignStatus::Ok
+        }
+        Ok(Err(status)) => status,
+        Err(_) => MultialignStatus::Error,
+    }
+}
+
+/// Free the rows of an alignment returned by [`multialign_align`].
+///
+/// # Safety
+///
+/// `alignment` must be null or point to an alignment written by [`multialign_align`] that was not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn multialign_alignment_free(alignment: *mut MultialignAlignment) {
+    let Some(alignment) = alignment.as_mut() else {
+        return;
+    };
+    if alignment.rows.is_null() {
+        return;
+    }
+
+    let rows = Box::from_raw(ptr::slice_from_raw_parts_mut(
+        alignment.rows,
+        alignment.row_amount,
+    ));
+    for row in rows {
+        drop(CString::from_raw(row));
+    }
+    alignment.rows = ptr::null_mut();
+    alignment.row_amount = 0;
+}
+
+fn align_rows<AlphabetType: Alphabet + std::fmt::Debug + Clone + Eq + 'static>(
+    sequences: &[&[u8]],
+) -> Result<(Vec<CString>, i64), MultialignStatus>
+where
+    AlphabetType::CharacterType: Sync,
+{
+    let genomes = sequences
+        .iter()
+        .map(|sequence| {
+            VectorGenome::<AlphabetType>::from_iter_u8(sequence.iter().map(u8::to_ascii_uppercase))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| MultialignStatus::InvalidCharacter)?;
+    let genomes: Vec<_> = genomes
+        .iter()
+        .map(|genome| genome.as_genome_subsequence())
+        .collect();
+
+    let alignment = PairwiseMatchMetric::new(genomes.len())
+        .and_then(|metric| {
+            align::<_, _, _, I32Cost>(&genomes, metric, &MultialignOptions::default())
+        })
+        .map_err(|error| match error {
+            MultialignError::TooFewSequences { .. } => MultialignStatus::InvalidArgument,
+            MultialignError::TooManySequences { .. } => MultialignStatus::TooManySequences,
+            MultialignError::MemoryLimit { .. } | MultialignError::ExceededMemoryLimit { .. } => {
+                MultialignStatus::MemoryLimit
+            }
+            _ => MultialignStatus::Error,
+        })?;
+
+    let rows = (0..alignment.sequence_amount())
+        .map(|row| {
+            // The rendered rows consist of alphabet and gap characters, so they contain no null bytes.
+            CString::new(alignment.render_row(row, &GapCharacters::default())).unwrap()
+        })
+        .collect();
+    Ok((rows, alignment.cost.as_primitive().into()))
+}
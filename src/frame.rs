@@ -0,0 +1,66 @@
+/// The reading frame of a reference sequence in the alignment.
+#[derive(Debug, Clone)]
+pub struct FrameAnnotation {
+    /// The index of the reference sequence.
+    pub reference: usize,
+    /// The codon position of the first residue of the reference sequence, counting from zero.
+    pub offset: usize,
+}
+
+/// Annotate the codon positions of the reference sequence in the given alignment columns.
+///
+/// Each column in which the reference has a residue is annotated with the codon position `1`, `2` or `3` of that residue.
+/// Columns in which the reference has a gap are annotated with `.`.
+/// Gap runs in other sequences whose length is not a multiple of three show up as shifted codon positions relative to their residues.
+pub fn annotate_frame<CharacterType>(
+    columns: &[Vec<Option<CharacterType>>],
+    frame_annotation: &FrameAnnotation,
+) -> String {
+    let mut codon_position = frame_annotation.offset % 3;
+
+    columns
+        .iter()
+        .map(|column| {
+            if column[frame_annotation.reference].is_some() {
+                let annotation = char::from(b'1' + codon_position as u8);
+                codon_position = (codon_position + 1) % 3;
+                annotation
+            } else {
+                '.'
+            }
+        })
+        .collect()
+}
+
+/// The standard genetic code, indexed by the nucleotides of a codon in base four with `T`, `C`, `A`, `G` as digits.
+const STANDARD_GENETIC_CODE: &[u8; 64] =
+    b"FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG";
+
+/// Translate a codon with the standard genetic code into the one-letter code of its amino acid, or `*` for stop codons.
+///
+/// `U` is read as `T`, and codons containing any other character than the four nucleotides are translated to `X`.
+pub fn translate_codon(codon: [char; 3]) -> char {
+    let mut index = 0;
+    for nucleotide in codon {
+        let digit = match nucleotide.to_ascii_uppercase() {
+            'T' | 'U' => 0,
+            'C' => 1,
+            'A' => 2,
+            'G' => 3,
+            _ => return 'X',
+        };
+        index = index * 4 + digit;
+    }
+    char::from(STANDARD_GENETIC_CODE[index])
+}
+
+/// Translate the given nucleotides codon by codon, see [`translate_codon`].
+///
+/// Nucleotides after the last complete codon are ignored.
+pub fn translate(nucleotides: impl IntoIterator<Item = impl Into<char>>) -> Vec<char> {
+    let nucleotides: Vec<char> = nucleotides.into_iter().map(Into::into).collect();
+    nucleotides
+        .chunks_exact(3)
+        .map(|codon| translate_codon([codon[0], codon[1], codon[2]]))
+        .collect()
+}
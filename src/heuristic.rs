@@ -0,0 +1,222 @@
+use compact_genome::interface::{
+    alphabet::{Alphabet, AlphabetCharacter},
+    sequence::GenomeSequence,
+};
+use generic_a_star::cost::AStarCost;
+use log::{info, warn};
+
+use super::{
+    error::{MultialignError, Result},
+    metric::MultialignMetric,
+    HeuristicKind, NodeIdentifier,
+};
+
+#[cfg(feature = "gpu")]
+mod gpu;
+
+/// A lower bound of the cost of aligning the remainders of the sequences, in the style of Carrillo and Lipman.
+///
+/// With a sum-of-pairs metric (see [`MultialignMetric::is_sum_of_pairs`]), each alignment of the remainders projects onto an alignment of each subset of the remainders,
+/// and its cost is the sum of the costs of the projections onto disjoint sets of pairs of sequences, plus the cost of pairs of gaps.
+/// Hence the sum of the costs of optimal alignments of the remainders of each projection is a lower bound of the remaining cost,
+/// as long as each pair of sequences is part of only one projection.
+/// This lower bound is also consistent, so the search still closes each node at most once.
+pub struct Heuristic<Cost> {
+    tables: Vec<ProjectionTable<Cost>>,
+}
+
+/// The costs of optimal alignments of all combinations of suffixes of a few sequences.
+struct ProjectionTable<Cost> {
+    /// The indices of the projected sequences.
+    sequences: Vec<usize>,
+    /// The distance between the costs of consecutive offsets of each projected sequence.
+    strides: Vec<usize>,
+    /// The cost of aligning the suffixes starting at each combination of offsets, in row-major order.
+    costs: Vec<Cost>,
+}
+
+impl<Cost: AStarCost> Heuristic<Cost>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    /// Align the suffixes of the projections of the sequences selected by the given kind under the given metric, which must be a sum-of-pairs metric.
+    ///
+    /// With [`HeuristicKind::Pairwise`], this takes time and memory quadratic in the length of the sequences for each pair of sequences.
+    /// With [`HeuristicKind::Triple`], the sequences are grouped into triples in input order, which each take cubic time and memory,
+    /// and the pairs of sequences that are not in the same triple are aligned pairwise.
+    ///
+    /// If `gpu` is set, the pairwise alignments are computed on a GPU, see [`MultialignOptions::gpu`](crate::MultialignOptions::gpu).
+    pub fn new<
+        AlphabetType: Alphabet,
+        SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized,
+    >(
+        sequences: &[&SequenceType],
+        metric: &mut impl MultialignMetric<AlphabetType>,
+        kind: HeuristicKind,
+        gpu: bool,
+    ) -> Result<Self> {
+        debug_assert!(metric.is_sum_of_pairs());
+
+        let triple_amount = match kind {
+            HeuristicKind::Pairwise => 0,
+            HeuristicKind::Triple => sequences.len() / 3,
+        };
+        let triple = |index: usize| (index < 3 * triple_amount).then_some(index / 3);
+
+        let mut tables = Vec::new();
+        for first in (0..triple_amount).map(|triple| 3 * triple) {
+            let table = ProjectionTable::new(sequences, vec![first, first + 1, first + 2], metric)?;
+            info!(
+                "Computed the triple-wise lower bounds of sequences {:?} with {} entries",
+                table.sequences,
+                table.costs.len()
+            );
+            tables.push(table);
+        }
+        let pairs: Vec<_> = (0..sequences.len())
+            .flat_map(|first| (first + 1..sequences.len()).map(move |second| (first, second)))
+            .filter(|&(first, second)| triple(first).is_none() || triple(first) != triple(second))
+            .collect();
+
+        #[cfg(feature = "gpu")]
+        if gpu {
+            match gpu::Gpu::new() {
+                Some(gpu) => {
+                    tables.extend(gpu.pairwise_tables(sequences, &pairs, metric)?);
+                    return Ok(Self { tables });
+                }
+                None => warn!("Found no GPU, computing the pairwise lower bounds on the CPU"),
+            }
+        }
+        #[cfg(not(feature = "gpu"))]
+        if gpu {
+            warn!("Built without the gpu feature, computing the pairwise lower bounds on the CPU");
+        }
+
+        for (first, second) in pairs {
+            tables.push(ProjectionTable::new(
+                sequences,
+                vec![first, second],
+                metric,
+            )?);
+        }
+
+        Ok(Self { tables })
+    }
+}
+
+impl<Cost: AStarCost> Heuristic<Cost> {
+    /// Returns the sum of the costs of optimal alignments of the remainders of each projection after the given node.
+    ///
+    /// The sum saturates at the maximum cost instead of overflowing, such that the search fails on the cost of an actual path instead.
+    pub fn lower_bound(&self, identifier: &impl NodeIdentifier) -> Cost {
+        self.tables.iter().fold(Cost::zero(), |lower_bound, table| {
+            let index = table
+                .sequences
+                .iter()
+                .zip(&table.strides)
+                .map(|(sequence, stride)| identifier.offset(*sequence) * stride)
+                .sum::<usize>();
+            lower_bound
+                .checked_add(&table.costs[index])
+                .unwrap_or_else(Cost::max_value)
+        })
+    }
+}
+
+impl<Cost: AStarCost> ProjectionTable<Cost>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    /// Align all combinations of suffixes of the projected sequences with a dynamic programming algorithm.
+    fn new<
+        AlphabetType: Alphabet,
+        SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized,
+    >(
+        sequences: &[&SequenceType],
+        projected: Vec<usize>,
+        metric: &mut impl MultialignMetric<AlphabetType>,
+    ) -> Result<Self> {
+        let dimensions = projected.len();
+        let lengths: Vec<_> = projected
+            .iter()
+            .map(|sequence| sequences[*sequence].len())
+            .collect();
+        let mut strides = vec![1; dimensions];
+        for dimension in (0..dimensions - 1).rev() {
+            strides[dimension] = strides[dimension + 1] * (lengths[dimension + 1] + 1);
+        }
+        let size = strides[0] * (lengths[0] + 1);
+
+        // The metric may depend on the indices of the sequences, so the cost of each column is cached per projection.
+        // The columns are indexed by the characters of their entries, where the index of the alphabet size is the gap.
+        let entry_amount = usize::from(AlphabetType::SIZE) + 1;
+        let mut column_costs = vec![None; entry_amount.pow(dimensions.try_into().unwrap())];
+
+        let mut costs = vec![Cost::zero(); size];
+        let mut offsets = vec![0; dimensions];
+        // The last entry is the end of all sequences, which costs nothing.
+        for index in (0..size - 1).rev() {
+            let mut rest = index;
+            for (offset, stride) in offsets.iter_mut().zip(&strides) {
+                *offset = rest / stride;
+                rest %= stride;
+            }
+
+            let mut cost = None::<Cost>;
+            for residues in 1..(1usize << dimensions) {
+                let advances = |dimension: usize| residues & (1 << dimension) != 0;
+                if (0..dimensions).any(|dimension| {
+                    advances(dimension) && offsets[dimension] == lengths[dimension]
+                }) {
+                    continue;
+                }
+
+                let mut successor = index;
+                let mut column = 0;
+                for dimension in 0..dimensions {
+                    let entry = if advances(dimension) {
+                        successor += strides[dimension];
+                        usize::from(sequences[projected[dimension]][offsets[dimension]].index())
+                    } else {
+                        usize::from(AlphabetType::SIZE)
+                    };
+                    column = column * entry_amount + entry;
+                }
+
+                let column_cost = match column_costs[column] {
+                    Some(column_cost) => column_cost,
+                    None => {
+                        metric.reset_character_counts();
+                        for (dimension, sequence) in projected.iter().copied().enumerate() {
+                            if advances(dimension) {
+                                metric.count_character(
+                                    &sequences[sequence][offsets[dimension]],
+                                    sequence,
+                                );
+                            } else {
+                                metric.count_gap(sequence);
+                            }
+                        }
+                        let column_cost = metric.compute_cost_increment()?;
+                        column_costs[column] = Some(column_cost);
+                        column_cost
+                    }
+                };
+
+                let candidate = costs[successor]
+                    .checked_add(&column_cost)
+                    .ok_or(MultialignError::CostOverflow)?;
+                cost = Some(cost.map_or(candidate, |cost| cost.min(candidate)));
+            }
+
+            costs[index] = cost.expect("Every entry except the last has a successor");
+        }
+
+        Ok(Self {
+            sequences: projected,
+            strides,
+            costs,
+        })
+    }
+}
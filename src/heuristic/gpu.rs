@@ -0,0 +1,362 @@
+use std::sync::mpsc;
+
+use compact_genome::interface::{
+    alphabet::{Alphabet, AlphabetCharacter},
+    sequence::GenomeSequence,
+};
+use generic_a_star::cost::AStarCost;
+use log::{debug, info};
+use wgpu::util::DeviceExt;
+
+use super::{
+    super::{
+        error::{MultialignError, Result},
+        metric::{cost_from_i32, MultialignMetric},
+    },
+    ProjectionTable,
+};
+
+/// The amount of words of a pair of sequences in the input of the compute shader.
+const PAIR_WORDS: usize = 7;
+
+/// A GPU with the compute shader that aligns all combinations of suffixes of pairs of sequences, see `pairwise.wgsl`.
+pub(super) struct Gpu {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    /// The maximum amount of bytes of the costs computed in one dispatch.
+    max_cost_bytes: u64,
+    /// The maximum amount of pairs of sequences aligned in one dispatch.
+    max_pairs: usize,
+}
+
+/// A pair of sequences whose suffixes are aligned on the GPU.
+struct PairJob {
+    sequences: [usize; 2],
+    /// The entries of each sequence, i.e. the indices of their characters.
+    entries: [Vec<u32>; 2],
+    /// The amount of different entries of a column, i.e. the size of the alphabet plus the gap.
+    entry_amount: usize,
+    /// The cost of each column of entries of the two sequences, where the last entry is the gap.
+    column_costs: Vec<i32>,
+}
+
+impl PairJob {
+    fn cell_amount(&self) -> usize {
+        (self.entries[0].len() + 1) * (self.entries[1].len() + 1)
+    }
+}
+
+impl Gpu {
+    /// Request a GPU from the operating system, or return `None` if there is none.
+    pub fn new() -> Option<Self> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))?;
+        let limits = adapter.limits();
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("multialign"),
+                required_features: wgpu::Features::empty(),
+                required_limits: limits.clone(),
+                memory_hints: wgpu::MemoryHints::Performance,
+            },
+            None,
+        ))
+        .map_err(|error| debug!("Could not use the GPU: {error}"))
+        .ok()?;
+        info!(
+            "Computing the pairwise lower bounds on {}",
+            adapter.get_info().name
+        );
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("pairwise"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("pairwise.wgsl").into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("pairwise"),
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        Some(Self {
+            device,
+            queue,
+            pipeline,
+            max_cost_bytes: limits
+                .max_buffer_size
+                .min(limits.max_storage_buffer_binding_size.into()),
+            max_pairs: limits.max_compute_workgroups_per_dimension as usize,
+        })
+    }
+
+    /// Align all combinations of suffixes of the given pairs of sequences, like [`ProjectionTable::new`].
+    ///
+    /// The costs are computed as 32-bit integers on the GPU, so pairs whose costs may exceed this range or whose table does not fit into a buffer of the GPU are aligned on the CPU.
+    pub fn pairwise_tables<
+        AlphabetType: Alphabet,
+        SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized,
+        Cost: AStarCost,
+    >(
+        &self,
+        sequences: &[&SequenceType],
+        pairs: &[(usize, usize)],
+        metric: &mut impl MultialignMetric<AlphabetType>,
+    ) -> Result<Vec<ProjectionTable<Cost>>>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        let mut tables = Vec::new();
+        let mut jobs = Vec::new();
+        for &(first, second) in pairs {
+            match pair_job::<_, _, Cost>(sequences, [first, second], metric)? {
+                Some(job) if (job.cell_amount() * 4) as u64 <= self.max_cost_bytes => {
+                    jobs.push(job)
+                }
+                _ => {
+                    debug!("Aligning the sequences {first} and {second} on the CPU");
+                    tables.push(ProjectionTable::new(
+                        sequences,
+                        vec![first, second],
+                        metric,
+                    )?);
+                }
+            }
+        }
+
+        let mut batch = Vec::new();
+        let mut batch_bytes = 0;
+        for job in jobs {
+            let job_bytes = (job.cell_amount() * 4) as u64;
+            if !batch.is_empty()
+                && (batch_bytes + job_bytes > self.max_cost_bytes || batch.len() == self.max_pairs)
+            {
+                tables.extend(self.align(&batch)?);
+                batch.clear();
+                batch_bytes = 0;
+            }
+            batch.push(job);
+            batch_bytes += job_bytes;
+        }
+        if !batch.is_empty() {
+            tables.extend(self.align(&batch)?);
+        }
+
+        Ok(tables)
+    }
+
+    /// Align the suffixes of the given pairs of sequences in a single dispatch of the compute shader.
+    fn align<Cost: AStarCost>(&self, jobs: &[PairJob]) -> Result<Vec<ProjectionTable<Cost>>>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        let mut pairs = Vec::with_capacity(jobs.len() * PAIR_WORDS);
+        let mut characters = Vec::new();
+        let mut column_costs = Vec::new();
+        let mut cell_amount = 0;
+        for job in jobs {
+            for entries in &job.entries {
+                pairs.extend([characters.len() as u32, entries.len() as u32]);
+                characters.extend_from_slice(entries);
+            }
+            pairs.extend([
+                cell_amount as u32,
+                column_costs.len() as u32,
+                job.entry_amount as u32,
+            ]);
+            column_costs.extend_from_slice(&job.column_costs);
+            cell_amount += job.cell_amount();
+        }
+        // Buffers must not be empty, which the characters are if all sequences are empty.
+        characters.push(0);
+        let cost_bytes = (cell_amount * 4) as u64;
+
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        self.device.push_error_scope(wgpu::ErrorFilter::OutOfMemory);
+        let input_buffer = |label, words: Vec<[u8; 4]>| {
+            self.device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(label),
+                    contents: words.as_flattened(),
+                    usage: wgpu::BufferUsages::STORAGE,
+                })
+        };
+        let pairs = input_buffer(
+            "pairs",
+            pairs.iter().map(|word| word.to_le_bytes()).collect(),
+        );
+        let characters = input_buffer(
+            "characters",
+            characters.iter().map(|word| word.to_le_bytes()).collect(),
+        );
+        let column_costs = input_buffer(
+            "column costs",
+            column_costs.iter().map(|word| word.to_le_bytes()).collect(),
+        );
+        let costs = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("costs"),
+            size: cost_bytes,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("staging"),
+            size: cost_bytes,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("pairwise"),
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[&pairs, &characters, &column_costs, &costs]
+                .into_iter()
+                .enumerate()
+                .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                    binding: binding as u32,
+                    resource: buffer.as_entire_binding(),
+                })
+                .collect::<Vec<_>>(),
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("pairwise"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(jobs.len() as u32, 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(&costs, 0, &staging, 0, cost_bytes);
+        self.queue.submit([encoder.finish()]);
+        let errors = [
+            pollster::block_on(self.device.pop_error_scope()),
+            pollster::block_on(self.device.pop_error_scope()),
+        ];
+        if let Some(error) = errors.into_iter().flatten().next() {
+            return Err(MultialignError::Gpu(error.to_string()));
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let slice = staging.slice(..);
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            sender.send(result).ok();
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .map_err(|error| MultialignError::Gpu(error.to_string()))?
+            .map_err(|error| MultialignError::Gpu(error.to_string()))?;
+
+        let bytes = slice.get_mapped_range();
+        let mut cells = bytes
+            .chunks_exact(4)
+            .map(|bytes| i32::from_le_bytes(bytes.try_into().unwrap()));
+        let tables = jobs
+            .iter()
+            .map(|job| {
+                Ok(ProjectionTable {
+                    sequences: job.sequences.to_vec(),
+                    strides: vec![job.entries[1].len() + 1, 1],
+                    costs: cells
+                        .by_ref()
+                        .take(job.cell_amount())
+                        .map(cost_from_i32)
+                        .collect::<Result<_>>()?,
+                })
+            })
+            .collect();
+        drop(bytes);
+        staging.unmap();
+        tables
+    }
+}
+
+/// Compute the costs of the columns of a pair of sequences under the given metric for the GPU,
+/// or return `None` if the costs of its alignments may exceed the range of 32-bit integers.
+///
+/// Only the costs of columns of characters that occur in the sequences are computed, since the metric may not allow the others.
+fn pair_job<
+    AlphabetType: Alphabet,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized,
+    Cost: AStarCost,
+>(
+    sequences: &[&SequenceType],
+    pair: [usize; 2],
+    metric: &mut impl MultialignMetric<AlphabetType>,
+) -> Result<Option<PairJob>>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    let entry_amount = usize::from(AlphabetType::SIZE) + 1;
+    let gap = entry_amount - 1;
+    // For each entry that occurs in a sequence, one of its characters, and `None` for the gap.
+    let occurring: Vec<Vec<_>> = pair
+        .iter()
+        .map(|&sequence| {
+            let mut characters = vec![None; gap];
+            for character in sequences[sequence].iter() {
+                characters[usize::from(character.index())].get_or_insert(character);
+            }
+            characters
+                .into_iter()
+                .enumerate()
+                .filter_map(|(entry, character)| Some((entry, Some(character?))))
+                .chain([(gap, None)])
+                .collect()
+        })
+        .collect();
+
+    let mut column_costs = vec![0; entry_amount * entry_amount];
+    let mut max_column_cost = 0;
+    for &(first_entry, first_character) in &occurring[0] {
+        for &(second_entry, second_character) in &occurring[1] {
+            if first_character.is_none() && second_character.is_none() {
+                continue;
+            }
+
+            metric.reset_character_counts();
+            for (sequence, character) in pair.into_iter().zip([first_character, second_character]) {
+                match character {
+                    Some(character) => metric.count_character(character, sequence),
+                    None => metric.count_gap(sequence),
+                }
+            }
+            let cost = metric.compute_cost_increment::<Cost>()?.as_f64();
+            max_column_cost = max_column_cost.max(cost.abs() as u64);
+            let Ok(cost) = i32::try_from(cost as i64) else {
+                return Ok(None);
+            };
+            column_costs[first_entry * entry_amount + second_entry] = cost;
+        }
+    }
+
+    let column_amount = pair
+        .iter()
+        .map(|&sequence| sequences[sequence].len() as u64)
+        .sum::<u64>();
+    if max_column_cost.saturating_mul(column_amount) > i32::MAX as u64 {
+        return Ok(None);
+    }
+
+    Ok(Some(PairJob {
+        sequences: pair,
+        entries: pair.map(|sequence| {
+            sequences[sequence]
+                .iter()
+                .map(|character| u32::from(character.index()))
+                .collect()
+        }),
+        entry_amount,
+        column_costs,
+    }))
+}
@@ -0,0 +1,75 @@
+// Computes the costs of optimal alignments of all combinations of suffixes of two sequences, with one workgroup per pair of sequences.
+// Each cell only depends on the cells of the following anti-diagonals,
+// so the threads of a workgroup compute the cells of one anti-diagonal at a time, from the ends of the sequences to their starts.
+
+struct Pair {
+    first_offset: u32,
+    first_length: u32,
+    second_offset: u32,
+    second_length: u32,
+    // The index of the first cell of the pair in the costs, whose cells are in row-major order with one row per offset into the first sequence.
+    cost_offset: u32,
+    // The index of the first column cost of the pair, whose columns are indexed by the entries of the first and the second sequence.
+    column_cost_offset: u32,
+    // The amount of different entries of a column, where the last one is the gap.
+    entry_amount: u32,
+}
+
+const WORKGROUP_SIZE: u32 = 64u;
+const MAX_COST: i32 = 2147483647;
+
+@group(0) @binding(0) var<storage, read> pairs: array<Pair>;
+@group(0) @binding(1) var<storage, read> characters: array<u32>;
+@group(0) @binding(2) var<storage, read> column_costs: array<i32>;
+@group(0) @binding(3) var<storage, read_write> costs: array<i32>;
+
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn main(@builtin(workgroup_id) workgroup_id: vec3<u32>, @builtin(local_invocation_index) thread: u32) {
+    let pair = pairs[workgroup_id.x];
+    let first_length = pair.first_length;
+    let second_length = pair.second_length;
+    let width = second_length + 1u;
+    let entry_amount = pair.entry_amount;
+    let gap = entry_amount - 1u;
+
+    for (var remaining = first_length + second_length + 1u; remaining > 0u; remaining -= 1u) {
+        let diagonal = remaining - 1u;
+        let first_start = select(0u, diagonal - second_length, diagonal > second_length);
+        let first_end = min(diagonal, first_length);
+
+        for (var i = first_start + thread; i <= first_end; i += WORKGROUP_SIZE) {
+            let j = diagonal - i;
+            let index = pair.cost_offset + i * width + j;
+
+            // The end of both sequences costs nothing.
+            var cost = 0;
+            if (i < first_length || j < second_length) {
+                cost = MAX_COST;
+                var first_entry = gap;
+                if (i < first_length) {
+                    first_entry = characters[pair.first_offset + i];
+                }
+                var second_entry = gap;
+                if (j < second_length) {
+                    second_entry = characters[pair.second_offset + j];
+                }
+
+                if (i < first_length && j < second_length) {
+                    let column = first_entry * entry_amount + second_entry;
+                    cost = min(cost, costs[index + width + 1u] + column_costs[pair.column_cost_offset + column]);
+                }
+                if (i < first_length) {
+                    let column = first_entry * entry_amount + gap;
+                    cost = min(cost, costs[index + width] + column_costs[pair.column_cost_offset + column]);
+                }
+                if (j < second_length) {
+                    let column = gap * entry_amount + second_entry;
+                    cost = min(cost, costs[index + 1u] + column_costs[pair.column_cost_offset + column]);
+                }
+            }
+            costs[index] = cost;
+        }
+
+        storageBarrier();
+    }
+}
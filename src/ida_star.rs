@@ -0,0 +1,99 @@
+use generic_a_star::{cost::AStarCost, AStarContext, AStarPerformanceCounters};
+use log::debug;
+
+use super::{
+    cancellation::CancellationToken,
+    error::{MultialignError, Result},
+    Node, NodeIdentifier,
+};
+
+/// An iterative-deepening A* search, which stores only the current path instead of an open and a closed list.
+///
+/// Each iteration is a depth-first search from the root that does not extend nodes whose priority exceeds a threshold, see [`Node::priority`].
+/// The first threshold is the priority of the root, and each further threshold is the smallest priority that exceeded the previous one.
+/// Since nodes are not stored, a node is searched again for each path leading to it and in each iteration.
+/// This makes the search much slower than [`generic_a_star::AStar`], but its memory consumption grows only with the length of the alignment.
+pub struct IdaStarSearch<Identifier: NodeIdentifier, Cost> {
+    /// The nodes from the root to the node whose successors are searched.
+    path: Vec<Node<Identifier, Cost>>,
+    /// For each node on the path, its successors that were not searched yet, ordered by decreasing priority.
+    unsearched_successors: Vec<Vec<Node<Identifier, Cost>>>,
+    performance_counters: AStarPerformanceCounters,
+}
+
+impl<Identifier: NodeIdentifier, Cost: AStarCost> IdaStarSearch<Identifier, Cost> {
+    pub fn new() -> Self {
+        Self {
+            path: Default::default(),
+            unsearched_successors: Default::default(),
+            performance_counters: Default::default(),
+        }
+    }
+
+    pub fn into_performance_counters(self) -> AStarPerformanceCounters {
+        self.performance_counters
+    }
+
+    /// Search for a target node and return the edges of the path to it.
+    ///
+    /// The edges are ordered from the target to the root, like [`generic_a_star::AStar::backtrack`].
+    /// Returns [`MultialignError::Cancelled`] if the cancellation token is set.
+    pub fn search<Context: AStarContext<Node = Node<Identifier, Cost>>>(
+        &mut self,
+        context: &mut Context,
+        cancellation: &CancellationToken,
+    ) -> Result<Option<Vec<Node<Identifier, Cost>>>> {
+        let root = context.create_root();
+        let mut threshold = root.priority();
+
+        loop {
+            debug!("Searching with threshold {threshold}");
+            self.path.clear();
+            self.unsearched_successors.clear();
+            self.unsearched_successors.push(vec![root.clone()]);
+            let mut next_threshold = None::<Cost>;
+
+            while let Some(successors) = self.unsearched_successors.last_mut() {
+                let Some(node) = successors.pop() else {
+                    self.unsearched_successors.pop();
+                    self.path.pop();
+                    continue;
+                };
+                if node.priority() > threshold {
+                    next_threshold =
+                        Some(next_threshold.map_or(node.priority(), |next_threshold| {
+                            next_threshold.min(node.priority())
+                        }));
+                    continue;
+                }
+                if cancellation.is_cancelled() {
+                    // No path is cheaper than the threshold, since the previous iteration found none.
+                    return Err(MultialignError::Cancelled {
+                        closed_nodes: self.performance_counters.closed_nodes,
+                        cost_lower_bound: Some(threshold.as_u64()),
+                    });
+                }
+                self.performance_counters.closed_nodes += 1;
+
+                if context.is_target(&node) {
+                    self.path.push(node);
+                    // The root is not an edge.
+                    return Ok(Some(self.path.drain(1..).rev().collect()));
+                }
+
+                let mut successors = Vec::new();
+                context.generate_successors(&node, &mut successors);
+                self.performance_counters.opened_nodes += successors.len();
+                // Successors are searched from the end, so the most promising one is searched first.
+                successors.sort_unstable_by(|a, b| b.cmp(a));
+                self.path.push(node);
+                self.unsearched_successors.push(successors);
+            }
+
+            match next_threshold {
+                Some(next_threshold) => threshold = next_threshold,
+                None => return Ok(None),
+            }
+        }
+    }
+}
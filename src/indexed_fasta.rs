@@ -0,0 +1,417 @@
+use std::{
+    ffi::OsString,
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use flate2::read::MultiGzDecoder;
+use log::info;
+
+use super::error::{MultialignError, Result};
+
+/// A record read from an indexed fasta file.
+pub struct IndexedFastaRecord {
+    /// The id of the fasta record.
+    pub id: String,
+    /// The sequence of the fasta record as raw bytes, without line breaks.
+    pub sequence: Vec<u8>,
+}
+
+/// An entry of a `.fai` index.
+struct FaiEntry {
+    name: String,
+    length: u64,
+    offset: u64,
+    line_bases: u64,
+    line_bytes: u64,
+}
+
+/// Read the records selected by `is_selected` from a fasta file using its `.fai` index, without scanning the whole file.
+///
+/// The index is expected at the path of the fasta file with `.fai` appended.
+/// If the fasta file is compressed with bgzip, i.e. its name ends in `.gz` or `.bgz`, then a `.gzi` index is required as well.
+/// Returns `None` if any of the required indices does not exist.
+///
+/// The records are returned in the order of the index.
+/// Returns an error if an index is older than the fasta file, or if the fasta file does not match the index.
+pub fn read_indexed_fasta_records(
+    path: impl AsRef<Path>,
+    is_selected: impl Fn(&str) -> bool,
+) -> Result<Option<Vec<IndexedFastaRecord>>> {
+    let path = path.as_ref();
+    let fai_path = append_extension(path, "fai");
+    let is_bgzip = path
+        .extension()
+        .is_some_and(|extension| extension == "gz" || extension == "bgz");
+    let gzi_path = is_bgzip.then(|| append_extension(path, "gzi"));
+
+    if !fai_path.exists() || gzi_path.as_ref().is_some_and(|gzi_path| !gzi_path.exists()) {
+        return Ok(None);
+    }
+
+    info!("Loading indexed fasta file {path:?}");
+    check_not_stale(path, &fai_path)?;
+    if let Some(gzi_path) = &gzi_path {
+        check_not_stale(path, gzi_path)?;
+    }
+
+    let fai = read_fai(&fai_path)?;
+    let gzi = gzi_path
+        .as_ref()
+        .map(|gzi_path| read_gzi(gzi_path))
+        .transpose()?;
+    let io_error = |source| MultialignError::Io {
+        path: path.to_owned(),
+        source,
+    };
+    let mut file = File::open(path).map_err(io_error)?;
+    let mut records = Vec::new();
+
+    for entry in fai.iter().filter(|entry| is_selected(&entry.name)) {
+        let invalid = |message: &str| {
+            MultialignError::InvalidIndex(format!(
+                "Record {} of {path:?} does not match the index {fai_path:?}: {message}",
+                entry.name
+            ))
+        };
+
+        if entry.line_bases == 0 || entry.line_bytes < entry.line_bases {
+            return Err(invalid("invalid line lengths"));
+        }
+        if entry.offset == 0 {
+            return Err(invalid("sequence starts before the header"));
+        }
+
+        // The sequence ends with its last base, since the last line may not be terminated at the end of the file.
+        // One additional byte is read before the sequence, which must terminate the header line.
+        let byte_length = match entry.length.checked_sub(1) {
+            Some(last_base) => (last_base / entry.line_bases)
+                .checked_mul(entry.line_bytes)
+                .and_then(|bytes| bytes.checked_add(last_base % entry.line_bases + 2)),
+            None => Some(1),
+        }
+        .and_then(|byte_length| usize::try_from(byte_length).ok())
+        .ok_or_else(|| invalid("record is too long"))?;
+        let mut bytes = vec![0; byte_length];
+        match &gzi {
+            Some(gzi) => read_bgzip_range(&mut file, gzi, entry.offset - 1, &mut bytes),
+            None => file
+                .seek(SeekFrom::Start(entry.offset - 1))
+                .and_then(|_| file.read_exact(&mut bytes)),
+        }
+        .map_err(|error| {
+            if error.kind() == io::ErrorKind::UnexpectedEof {
+                invalid("file ends before the end of the record")
+            } else {
+                io_error(error)
+            }
+        })?;
+
+        if bytes[0] != b'\n' {
+            return Err(invalid(
+                "sequence does not start at the beginning of a line",
+            ));
+        }
+
+        let line_bases = usize::try_from(entry.line_bases).unwrap();
+        let line_bytes = usize::try_from(entry.line_bytes).unwrap();
+        let mut sequence = Vec::with_capacity(usize::try_from(entry.length).unwrap());
+        for line in bytes[1..].chunks(line_bytes) {
+            let (bases, line_break) = line.split_at(line.len().min(line_bases));
+            if bases
+                .iter()
+                .any(|byte| matches!(byte, b'\n' | b'\r' | b'>'))
+                || line_break.iter().any(|byte| !matches!(byte, b'\n' | b'\r'))
+            {
+                return Err(invalid("line lengths differ from the index"));
+            }
+            sequence.extend_from_slice(bases);
+        }
+
+        records.push(IndexedFastaRecord {
+            id: entry.name.clone(),
+            sequence,
+        });
+    }
+
+    Ok(Some(records))
+}
+
+fn append_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut path = OsString::from(path);
+    path.push(".");
+    path.push(extension);
+    path.into()
+}
+
+/// Return an error if the index was modified before the indexed file.
+fn check_not_stale(path: &Path, index_path: &Path) -> Result<()> {
+    let modified = |path: &Path| {
+        path.metadata()
+            .and_then(|metadata| metadata.modified())
+            .map_err(|source| MultialignError::Io {
+                path: path.to_owned(),
+                source,
+            })
+    };
+
+    if modified(index_path)? < modified(path)? {
+        return Err(MultialignError::InvalidIndex(format!(
+            "Index {index_path:?} is older than {path:?}, it needs to be recreated"
+        )));
+    }
+
+    Ok(())
+}
+
+fn read_fai(path: &Path) -> Result<Vec<FaiEntry>> {
+    let io_error = |source| MultialignError::Io {
+        path: path.to_owned(),
+        source,
+    };
+    let file = File::open(path).map_err(io_error)?;
+    let mut entries = Vec::new();
+
+    for (line_index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(io_error)?;
+        if line.is_empty() {
+            continue;
+        }
+
+        let invalid = || {
+            MultialignError::InvalidIndex(format!(
+                "Line {} of {path:?} is not a valid fai entry",
+                line_index + 1
+            ))
+        };
+        let columns: Vec<_> = line.split('\t').collect();
+        if columns.len() < 5 {
+            return Err(invalid());
+        }
+        let parse = |column: &str| column.parse::<u64>().map_err(|_| invalid());
+
+        entries.push(FaiEntry {
+            name: columns[0].to_string(),
+            length: parse(columns[1])?,
+            offset: parse(columns[2])?,
+            line_bases: parse(columns[3])?,
+            line_bytes: parse(columns[4])?,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Read a `.gzi` index, which maps compressed offsets of bgzip blocks to their uncompressed offsets.
+///
+/// The implicit first block at offset zero is included in the returned pairs.
+fn read_gzi(path: &Path) -> Result<Vec<(u64, u64)>> {
+    let bytes = std::fs::read(path).map_err(|source| MultialignError::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+    let invalid = || MultialignError::InvalidIndex(format!("{path:?} is not a valid gzi index"));
+
+    let mut integers = bytes
+        .chunks(8)
+        .map(|chunk| chunk.try_into().map(u64::from_le_bytes));
+    let amount = integers
+        .next()
+        .ok_or_else(invalid)?
+        .map_err(|_| invalid())?;
+    let integers = integers
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|_| invalid())?;
+    if amount.checked_mul(2) != Some(u64::try_from(integers.len()).unwrap()) {
+        return Err(invalid());
+    }
+
+    Ok([(0, 0)]
+        .into_iter()
+        .chain(integers.chunks(2).map(|pair| (pair[0], pair[1])))
+        .collect())
+}
+
+/// Read `buffer.len()` uncompressed bytes starting at the uncompressed offset `start` from a bgzip file.
+fn read_bgzip_range(
+    file: &mut File,
+    gzi: &[(u64, u64)],
+    start: u64,
+    buffer: &mut [u8],
+) -> io::Result<()> {
+    let block = gzi.partition_point(|(_, uncompressed)| *uncompressed <= start) - 1;
+    let (compressed, uncompressed) = gzi[block];
+
+    file.seek(SeekFrom::Start(compressed))?;
+    let mut decoder = MultiGzDecoder::new(BufReader::new(&mut *file));
+    io::copy(
+        &mut (&mut decoder).take(start - uncompressed),
+        &mut io::sink(),
+    )?;
+    decoder.read_exact(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        io::Write,
+        path::{Path, PathBuf},
+    };
+
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::{append_extension, read_gzi, read_indexed_fasta_records};
+    use crate::error::MultialignError;
+
+    const FASTA: &[u8] = b">a\nACGT\nACGT\n>b\nACGA\nACGA";
+    const FAI: &str = "a\t8\t3\t4\t5\nb\t8\t16\t4\t5\n";
+
+    /// Write a fasta file and its indices into the temporary directory, and return the path of the fasta file.
+    fn write_indexed(name: &str, fasta: &[u8], fai: &str, gzi: Option<&[u8]>) -> PathBuf {
+        let path = env::temp_dir().join(format!(
+            "multialign-test-indexed-fasta-{}-{name}",
+            std::process::id()
+        ));
+        fs::write(&path, fasta).unwrap();
+        fs::write(append_extension(&path, "fai"), fai).unwrap();
+        if let Some(gzi) = gzi {
+            fs::write(append_extension(&path, "gzi"), gzi).unwrap();
+        }
+        path
+    }
+
+    fn remove_indexed(path: &Path) {
+        for path in [
+            path.to_owned(),
+            append_extension(path, "fai"),
+            append_extension(path, "gzi"),
+        ] {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    /// Read the records with the given ids, and return their ids and sequences.
+    fn read(path: &Path, ids: &[&str]) -> super::Result<Option<Vec<(String, String)>>> {
+        Ok(
+            read_indexed_fasta_records(path, |id| ids.contains(&id))?.map(|records| {
+                records
+                    .into_iter()
+                    .map(|record| (record.id, String::from_utf8(record.sequence).unwrap()))
+                    .collect()
+            }),
+        )
+    }
+
+    fn records(records: &[(&str, &str)]) -> Option<Vec<(String, String)>> {
+        Some(
+            records
+                .iter()
+                .map(|(id, sequence)| (id.to_string(), sequence.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn reads_selected_records() {
+        let path = write_indexed("plain.fa", FASTA, FAI, None);
+        let both = read(&path, &["a", "b"]);
+        let second = read(&path, &["b"]);
+        remove_indexed(&path);
+
+        assert_eq!(
+            both.unwrap(),
+            records(&[("a", "ACGTACGT"), ("b", "ACGAACGA")])
+        );
+        assert_eq!(second.unwrap(), records(&[("b", "ACGAACGA")]));
+    }
+
+    #[test]
+    fn reads_records_with_carriage_returns() {
+        let path = write_indexed("crlf.fa", b">a\r\nACGT\r\nAC\r\n", "a\t6\t4\t4\t6\n", None);
+        let result = read(&path, &["a"]);
+        remove_indexed(&path);
+
+        assert_eq!(result.unwrap(), records(&[("a", "ACGTAC")]));
+    }
+
+    #[test]
+    fn rejects_mismatching_index() {
+        let path = write_indexed("mismatch.fa", FASTA, "a\t8\t3\t3\t4\n", None);
+        let line_lengths = read(&path, &["a"]);
+        fs::write(append_extension(&path, "fai"), "b\t9\t16\t4\t5\n").unwrap();
+        let too_long = read(&path, &["b"]);
+        remove_indexed(&path);
+
+        assert!(matches!(
+            line_lengths,
+            Err(MultialignError::InvalidIndex(_))
+        ));
+        assert!(matches!(too_long, Err(MultialignError::InvalidIndex(_))));
+    }
+
+    #[test]
+    fn returns_none_without_index() {
+        let path = write_indexed("unindexed.fa.gz", FASTA, FAI, None);
+        let result = read(&path, &["a"]);
+        remove_indexed(&path);
+
+        assert!(matches!(result, Ok(None)));
+    }
+
+    #[test]
+    fn reads_bgzip_records() {
+        // Split the file into two gzip members, such that the first record spans both of them.
+        let split = 7;
+        let compress = |block: &[u8]| {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(block).unwrap();
+            encoder.finish().unwrap()
+        };
+        let mut compressed = compress(&FASTA[..split]);
+        let gzi: Vec<u8> = [1, compressed.len() as u64, split as u64]
+            .into_iter()
+            .flat_map(u64::to_le_bytes)
+            .collect();
+        compressed.extend(compress(&FASTA[split..]));
+
+        let path = write_indexed("bgzip.fa.gz", &compressed, FAI, Some(&gzi));
+        let both = read(&path, &["a", "b"]);
+        let second = read(&path, &["b"]);
+        remove_indexed(&path);
+
+        assert_eq!(
+            both.unwrap(),
+            records(&[("a", "ACGTACGT"), ("b", "ACGAACGA")])
+        );
+        assert_eq!(second.unwrap(), records(&[("b", "ACGAACGA")]));
+    }
+
+    #[test]
+    fn rejects_invalid_gzi() {
+        let path = write_indexed("invalid.fa.gz", b"", "", None);
+        let gzi_path = append_extension(&path, "gzi");
+        let mut results = Vec::new();
+        for gzi in [
+            vec![],
+            [1u64].into_iter().flat_map(u64::to_le_bytes).collect(),
+            [u64::MAX, 0, 0]
+                .into_iter()
+                .flat_map(u64::to_le_bytes)
+                .collect(),
+            vec![0; 12],
+        ] {
+            fs::write(&gzi_path, gzi).unwrap();
+            results.push(read_gzi(&gzi_path));
+        }
+        fs::write(&gzi_path, [0u8; 8]).unwrap();
+        let empty = read_gzi(&gzi_path);
+        remove_indexed(&path);
+
+        for result in results {
+            assert!(matches!(result, Err(MultialignError::InvalidIndex(_))));
+        }
+        assert_eq!(empty.unwrap(), vec![(0, 0)]);
+    }
+}
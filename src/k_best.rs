@@ -0,0 +1,141 @@
+use std::collections::{HashMap, HashSet};
+
+use generic_a_star::{cost::AStarCost, AStarContext, AStarPerformanceCounters};
+
+use super::{
+    cancellation::CancellationToken,
+    error::{MultialignError, Result},
+    open_list::OpenList,
+    Node, NodeIdentifier, OpenListKind,
+};
+
+/// A best-first search that finds the `k` cheapest paths to a target.
+///
+/// Paths are extended in order of their cost plus the lower bound of their last node, see [`Node::priority`].
+/// Instead of closing each node once, each node may be closed up to `k` times, each time with a different path leading to it.
+/// Since all paths are stored as chains of labels, the memory consumption grows with `k`.
+pub struct KBestSearch<Identifier, Cost> {
+    labels: Vec<Label<Identifier, Cost>>,
+    open_list: OpenList<Cost, usize>,
+    close_counts: HashMap<Identifier, usize>,
+    performance_counters: AStarPerformanceCounters,
+}
+
+/// A path to a node, represented by the node and the label of its predecessor on the path.
+struct Label<Identifier, Cost> {
+    identifier: Identifier,
+    cost: Cost,
+    predecessor: Option<usize>,
+}
+
+impl<Identifier: NodeIdentifier, Cost: AStarCost> KBestSearch<Identifier, Cost> {
+    pub fn new(open_list_kind: OpenListKind) -> Self {
+        Self {
+            labels: Default::default(),
+            open_list: OpenList::new(open_list_kind),
+            close_counts: Default::default(),
+            performance_counters: Default::default(),
+        }
+    }
+
+    pub fn into_performance_counters(self) -> AStarPerformanceCounters {
+        self.performance_counters
+    }
+
+    /// Search for the `k` cheapest paths to a target, or less if there are not as many paths.
+    ///
+    /// The paths are returned ordered by cost, and the edges of each path are ordered from the target to the root, like [`generic_a_star::AStar::backtrack`].
+    /// Edges that do not change the node and parallel edges are ignored, since they do not lead to distinct alignments.
+    /// Returns [`MultialignError::Cancelled`] if the cancellation token is set.
+    pub fn search<Context: AStarContext<Node = Node<Identifier, Cost>>>(
+        &mut self,
+        context: &mut Context,
+        k: usize,
+        cancellation: &CancellationToken,
+    ) -> Result<Vec<Vec<Node<Identifier, Cost>>>> {
+        let root = context.create_root();
+        self.open_list.push(root.priority(), 0);
+        self.labels.push(Label {
+            identifier: root.identifier,
+            cost: root.cost,
+            predecessor: None,
+        });
+        let mut successors = Vec::new();
+        let mut successor_identifiers = HashSet::new();
+        let mut paths = Vec::new();
+
+        while let Some((priority, label)) = self.open_list.pop() {
+            let identifier = self.labels[label].identifier.clone();
+            let close_count = self.close_counts.entry(identifier.clone()).or_default();
+            if *close_count >= k {
+                self.performance_counters.suboptimal_opened_nodes += 1;
+                continue;
+            }
+            if cancellation.is_cancelled() {
+                self.open_list.clear();
+                return Err(MultialignError::Cancelled {
+                    closed_nodes: self.performance_counters.closed_nodes,
+                    // Once a target was found, the priority of the popped node bounds the next alignment and not the optimal one.
+                    cost_lower_bound: paths.is_empty().then(|| priority.as_u64()),
+                });
+            }
+            *close_count += 1;
+            self.performance_counters.closed_nodes += 1;
+
+            let node = Node {
+                cost: self.labels[label].cost,
+                lower_bound: Cost::zero(),
+                identifier,
+                predecessor: None,
+                tie_breaker: 0,
+            };
+
+            if context.is_target(&node) {
+                paths.push(self.backtrack(label));
+                if paths.len() == k {
+                    break;
+                }
+                continue;
+            }
+
+            successors.clear();
+            successor_identifiers.clear();
+            context.generate_successors(&node, &mut successors);
+            for successor in successors.drain(..) {
+                if successor.identifier == node.identifier
+                    || !successor_identifiers.insert(successor.identifier.clone())
+                {
+                    continue;
+                }
+
+                self.performance_counters.opened_nodes += 1;
+                self.open_list.push(successor.priority(), self.labels.len());
+                self.labels.push(Label {
+                    identifier: successor.identifier,
+                    cost: successor.cost,
+                    predecessor: Some(label),
+                });
+            }
+        }
+
+        self.open_list.clear();
+        Ok(paths)
+    }
+
+    fn backtrack(&self, mut label: usize) -> Vec<Node<Identifier, Cost>> {
+        let mut edges = Vec::new();
+
+        while let Some(predecessor) = self.labels[label].predecessor {
+            edges.push(Node {
+                cost: self.labels[label].cost,
+                lower_bound: Cost::zero(),
+                identifier: self.labels[label].identifier.clone(),
+                predecessor: Some(self.labels[predecessor].identifier.clone()),
+                tie_breaker: 0,
+            });
+            label = predecessor;
+        }
+
+        edges
+    }
+}
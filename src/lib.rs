@@ -0,0 +1,3819 @@
+//! An exact multiple sequence aligner based on A*.
+//!
+//! The aligner searches for a cheapest path through the multi-dimensional alignment graph of the given sequences,
+//! where the cost of each column is computed by a [`MultialignMetric`].
+//! Use [`align`] to compute an optimal alignment without writing any output,
+//! or [`multialign_astar`] to additionally write the output requested in the [`MultialignOptions`].
+//! All fallible functions return a [`MultialignError`], which can be matched on to distinguish the kinds of errors.
+//!
+//! ```ignore
+//! let metric = PairwiseMatchMetric::<DnaAlphabet>::new(sequences.len())?;
+//! let alignment = multialign::align(&sequences, metric, &MultialignOptions::default())?;
+//! println!("Cost {}: {}", alignment.cost, alignment.render_row(0, &GapCharacters::default()));
+//! ```
+
+use std::{
+    cmp::Ordering,
+    collections::{BTreeSet, HashSet},
+    fmt::{Debug, Display},
+    hash::Hash,
+    marker::PhantomData,
+    num::NonZeroUsize,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{self, AtomicUsize},
+        Arc, Mutex,
+    },
+    thread::{self, available_parallelism},
+    time::Duration,
+    vec,
+};
+
+// `std::time::Instant` is not implemented on `wasm32-unknown-unknown`.
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+use aligned_fasta::{records_to_columns, AlignedFastaRecord};
+use anytime::{AnytimeAlignment, AnytimeSearch};
+use band::Band;
+use beam::BeamSearch;
+use cancellation::CancellationToken;
+use center_star::center_star_alignment;
+use closed_list::ClosedList;
+use compact_genome::interface::{alphabet::Alphabet, sequence::GenomeSequence};
+use consensus::{consensus_row, write_consensus_file, ConsensusOutput};
+use curation::filter_by_identity_to_consensus;
+use dominance::DominanceTable;
+use error::{MultialignError, Result};
+use frame::{annotate_frame, FrameAnnotation};
+use generic_a_star::{
+    cost::{AStarCost, I16Cost, I32Cost, I64Cost, U64Cost},
+    reset::Reset,
+    AStar, AStarContext, AStarNode, AStarPerformanceCounters, AStarResult,
+};
+use heuristic::Heuristic;
+use ida_star::IdaStarSearch;
+use k_best::KBestSearch;
+use log::{debug, info, warn};
+use metric::{cost_type_name, pairwise_match_metric::PairwiseMatchMetric, MultialignMetric};
+use open_list::NodeOpenList;
+use output::{
+    compression::{OutputCompression, COMPRESSION_EXTENSIONS},
+    write_alignment_file, write_alignment_to_terminal, OutputAlignment, OutputFormat, ResidueType,
+};
+use pairwise::{align_two_sequences, fits_memory_limit};
+use parallel::ParallelSearch;
+use path::{operation_columns, verify_columns, AlignmentColumn, AlignmentPath, StreamingAlignment};
+use profile_alignment::{align_profiles, PartialAlignment};
+use progress::Progress;
+use progressive::progressive_alignment;
+use rederiving_search::RederivingSearch;
+use refine::refine_alignment;
+use reproducibility::alignment_hash;
+use search_graph::{write_search_graph, SearchGraph, SearchGraphDump, SearchGraphNode};
+use spill::SpillOptions;
+use statistics::{
+    append_statistics, write_column_costs, write_column_statistics, write_statistics_json,
+    SearchDetails, SearchStatistics, StatisticsJsonOutput, StatisticsOutput,
+};
+use tree::{alignment_distance_matrix, kmer_guide_tree, neighbor_joining};
+
+pub mod aligned_fasta;
+pub mod anchors;
+mod anytime;
+pub mod band;
+mod beam;
+pub mod cancellation;
+mod center_star;
+mod closed_list;
+pub mod consensus;
+pub mod consistency;
+pub mod curation;
+mod display;
+mod dominance;
+pub mod error;
+#[cfg(feature = "multialign-ffi")]
+pub mod ffi;
+pub mod frame;
+mod heuristic;
+mod ida_star;
+pub mod indexed_fasta;
+mod k_best;
+pub mod metric;
+pub mod newick;
+mod open_list;
+pub mod output;
+mod pairwise;
+mod parallel;
+pub mod path;
+pub mod profile;
+pub mod profile_alignment;
+mod progress;
+mod progressive;
+mod rederiving_search;
+mod refine;
+pub mod reproducibility;
+pub mod search_graph;
+pub mod soft_mask;
+pub mod spill;
+pub mod statistics;
+pub mod tree;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+trait NodeIdentifier: Debug + Display + Clone + Eq + Ord + Hash + Send + Sync {
+    fn create_root(sequence_amount: usize) -> Self;
+
+    /// Create the identifier of the node with the given offsets and gaps, e.g. of a node that was read from disk.
+    fn from_offsets(offsets: &[usize], gaps: usize) -> Self;
+
+    fn offset(&self, index: usize) -> usize;
+
+    fn increment(&mut self, index: usize);
+
+    fn decrement(&mut self, index: usize);
+
+    /// The sequences that have a gap in the column leading to this node, as a bit mask.
+    ///
+    /// This is only tracked for metrics with affine gaps, and is zero otherwise.
+    fn gaps(&self) -> usize;
+
+    fn set_gaps(&mut self, gaps: usize);
+
+    /// True if the identifier is specialised on the amount of sequences.
+    ///
+    /// Such identifiers are only used by the A* search, see [`search_columns`].
+    const SPECIALISED: bool;
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
+struct ArrayIdentifier<const SEQUENCE_AMOUNT: usize> {
+    offsets: [usize; SEQUENCE_AMOUNT],
+    gaps: usize,
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
+struct VecIdentifier {
+    offsets: Vec<usize>,
+    gaps: usize,
+}
+
+impl<const SEQUENCE_AMOUNT: usize> NodeIdentifier for ArrayIdentifier<SEQUENCE_AMOUNT> {
+    const SPECIALISED: bool = true;
+
+    fn create_root(sequence_amount: usize) -> Self {
+        assert_eq!(sequence_amount, SEQUENCE_AMOUNT);
+        Self {
+            offsets: [0; SEQUENCE_AMOUNT],
+            gaps: 0,
+        }
+    }
+
+    fn from_offsets(offsets: &[usize], gaps: usize) -> Self {
+        Self {
+            offsets: offsets.try_into().unwrap(),
+            gaps,
+        }
+    }
+
+    fn offset(&self, index: usize) -> usize {
+        self.offsets[index]
+    }
+
+    fn increment(&mut self, index: usize) {
+        self.offsets[index] += 1;
+    }
+
+    fn decrement(&mut self, index: usize) {
+        self.offsets[index] -= 1;
+    }
+
+    fn gaps(&self) -> usize {
+        self.gaps
+    }
+
+    fn set_gaps(&mut self, gaps: usize) {
+        self.gaps = gaps;
+    }
+}
+
+impl NodeIdentifier for VecIdentifier {
+    const SPECIALISED: bool = false;
+
+    fn create_root(sequence_amount: usize) -> Self {
+        Self {
+            offsets: vec![0; sequence_amount],
+            gaps: 0,
+        }
+    }
+
+    fn from_offsets(offsets: &[usize], gaps: usize) -> Self {
+        Self {
+            offsets: offsets.to_vec(),
+            gaps,
+        }
+    }
+
+    fn offset(&self, index: usize) -> usize {
+        self.offsets[index]
+    }
+
+    fn increment(&mut self, index: usize) {
+        self.offsets[index] += 1;
+    }
+
+    fn decrement(&mut self, index: usize) {
+        self.offsets[index] -= 1;
+    }
+
+    fn gaps(&self) -> usize {
+        self.gaps
+    }
+
+    fn set_gaps(&mut self, gaps: usize) {
+        self.gaps = gaps;
+    }
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+struct Node<Identifier: NodeIdentifier, Cost> {
+    cost: Cost,
+    /// A lower bound of the cost from this node to the target, see [`Heuristic`].
+    ///
+    /// This is zero if the metric does not allow to compute a lower bound, and for nodes that are only used to generate successors.
+    lower_bound: Cost,
+    identifier: Identifier,
+    predecessor: Option<Identifier>,
+    /// Of the nodes with equal priority, the ones with a lower tie breaker are searched first, see [`TieBreak`].
+    tie_breaker: u32,
+}
+
+impl<Identifier: NodeIdentifier, Cost: AStarCost> Node<Identifier, Cost> {
+    /// The cost of this node plus its lower bound, which is a lower bound of the cost of any alignment through this node.
+    ///
+    /// Nodes are searched in order of this priority, saturating at the maximum cost.
+    fn priority(&self) -> Cost {
+        self.cost
+            .checked_add(&self.lower_bound)
+            .unwrap_or_else(Cost::max_value)
+    }
+}
+
+impl<Identifier: NodeIdentifier, Cost: AStarCost> Ord for Node<Identifier, Cost> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Among nodes with equal priority and tie breaker, prefer the ones closer to the target.
+        self.priority()
+            .cmp(&other.priority())
+            .then_with(|| self.tie_breaker.cmp(&other.tie_breaker))
+            .then_with(|| other.cost.cmp(&self.cost))
+            .then_with(|| self.identifier.cmp(&other.identifier))
+            .then_with(|| self.predecessor.cmp(&other.predecessor))
+    }
+}
+
+impl<Identifier: NodeIdentifier, Cost: AStarCost> PartialOrd for Node<Identifier, Cost> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<Identifier: NodeIdentifier, Cost: AStarCost> AStarNode for Node<Identifier, Cost> {
+    type Identifier = Identifier;
+
+    type EdgeType = Self;
+
+    type Cost = Cost;
+
+    fn identifier(&self) -> &Self::Identifier {
+        &self.identifier
+    }
+
+    fn cost(&self) -> Self::Cost {
+        self.cost
+    }
+
+    fn a_star_lower_bound(&self) -> Self::Cost {
+        self.lower_bound
+    }
+
+    fn predecessor(&self) -> Option<&Self::Identifier> {
+        self.predecessor.as_ref()
+    }
+
+    fn predecessor_edge_type(&self) -> Option<Self::EdgeType> {
+        self.predecessor.as_ref().map(|_| self.clone())
+    }
+}
+
+struct Context<
+    'sequences,
+    AlphabetType: Alphabet,
+    Cost,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized,
+    Identifier,
+    Metric: MultialignMetric<AlphabetType>,
+> {
+    sequences: &'sequences [&'sequences SequenceType],
+    metric: Metric,
+    segment: Segment<'sequences>,
+    /// True if the metric distinguishes opening gaps from extending them, such that nodes track which sequences are in a gap.
+    affine_gaps: bool,
+    /// True if the metric depends on the residues in each column, such that it is given the offsets of the sequences.
+    uses_offsets: bool,
+    /// True if gaps before the first residue of a sequence are counted as terminal gaps.
+    free_leading_gaps: bool,
+    /// True if gaps after the last residue of a sequence are counted as terminal gaps.
+    free_trailing_gaps: bool,
+    /// The index of the sequence whose end gaps are not terminal gaps, see [`full_length_sequence`].
+    full_length_sequence: usize,
+    /// The lower bound of the remaining cost of each node, if the metric allows to compute one.
+    ///
+    /// It is shared between the contexts of the threads of a [`ParallelSearch`].
+    heuristic: Option<Arc<Heuristic<Cost>>>,
+    /// Successors outside of this band are not generated, see [`MultialignOptions::band`].
+    band: Option<Arc<Band>>,
+    /// The factor by which the lower bounds are multiplied, see [`MultialignOptions::weight`].
+    weight: f64,
+    /// How nodes of equal priority are ordered, see [`MultialignOptions::tie_break`].
+    tie_break: TieBreak,
+    /// Logs the progress of the search, see [`MultialignOptions::progress_interval`].
+    progress: Option<Progress>,
+    /// The closed nodes that prune the nodes they dominate, see [`MultialignOptions::dominance_pruning`].
+    dominance: Option<DominanceTable<Identifier, Cost>>,
+    /// The amount of nodes opened but not yet closed, counting nodes that are closed as suboptimal as open.
+    open_nodes: usize,
+    /// The maximum of [`Self::open_nodes`], which is an upper bound of the maximum size of the open list.
+    peak_open_nodes: usize,
+    /// The approximate amount of bytes that [`AStar`] may use, see [`MultialignOptions::memory_limit`].
+    memory_limit: Option<usize>,
+    /// The cost of a known alignment, such that successors whose cost plus unweighted lower bound exceeds it are not generated,
+    /// see [`MultialignOptions::upper_bound_pruning`].
+    cost_upper_bound: Option<Cost>,
+    /// The first error that occurred while generating successors.
+    ///
+    /// [`AStarContext`] does not allow to return errors, so they are stored here and returned after the search.
+    error: Option<MultialignError>,
+
+    phantom_data: PhantomData<(Identifier, Cost)>,
+    /// The alphabet is only a marker, so it does not keep the context from being sent to other threads.
+    alphabet: PhantomData<fn() -> AlphabetType>,
+}
+
+impl<
+        AlphabetType: Alphabet,
+        Cost: Clone,
+        SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized,
+        Identifier,
+        Metric: MultialignMetric<AlphabetType> + Clone,
+    > Clone for Context<'_, AlphabetType, Cost, SequenceType, Identifier, Metric>
+{
+    /// Returns a context for the same search, which does not contain the error of this context.
+    fn clone(&self) -> Self {
+        Self {
+            sequences: self.sequences,
+            metric: self.metric.clone(),
+            segment: self.segment,
+            affine_gaps: self.affine_gaps,
+            uses_offsets: self.uses_offsets,
+            free_leading_gaps: self.free_leading_gaps,
+            free_trailing_gaps: self.free_trailing_gaps,
+            full_length_sequence: self.full_length_sequence,
+            heuristic: self.heuristic.clone(),
+            band: self.band.clone(),
+            weight: self.weight,
+            tie_break: self.tie_break,
+            // Only the original context reports the progress, e.g. not the contexts of the threads of a parallel search.
+            progress: None,
+            // The nodes closed by this context are not closed by the clone.
+            dominance: self.dominance.as_ref().map(|_| DominanceTable::new()),
+            open_nodes: self.open_nodes,
+            peak_open_nodes: self.peak_open_nodes,
+            memory_limit: self.memory_limit,
+            cost_upper_bound: self.cost_upper_bound.clone(),
+            error: None,
+            phantom_data: PhantomData,
+            alphabet: PhantomData,
+        }
+    }
+}
+
+impl<
+        AlphabetType: Alphabet,
+        Cost: AStarCost,
+        SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized,
+        Identifier: NodeIdentifier,
+        Metric: MultialignMetric<AlphabetType>,
+    > AStarContext for Context<'_, AlphabetType, Cost, SequenceType, Identifier, Metric>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    type Node = Node<Identifier, Cost>;
+
+    fn create_root(&self) -> Self::Node {
+        let identifier = Identifier::create_root(self.sequences.len());
+        Self::Node {
+            cost: Cost::zero(),
+            lower_bound: self.lower_bound(&identifier),
+            identifier,
+            predecessor: None,
+            tie_breaker: 0,
+        }
+    }
+
+    fn generate_successors(&mut self, node: &Self::Node, output: &mut impl Extend<Self::Node>) {
+        debug_assert!(self.sequences.len() < usize::BITS.try_into().unwrap());
+        if let Some(dominance) = &mut self.dominance {
+            dominance.insert(&node.identifier, node.cost);
+        }
+
+        // Selecting a sequence that already ended results in a gap, which is the same successor as not selecting it.
+        // So only the sequences that did not end are selected, which yields each successor once.
+        let unfinished = self
+            .sequences
+            .iter()
+            .enumerate()
+            .filter(|(index, sequence)| node.identifier.offset(*index) < sequence.len())
+            .fold(0usize, |unfinished, (index, _)| unfinished | (1 << index));
+        let mut successor_amount = 0;
+        // Iterates the non-empty subsets of the unfinished sequences in increasing order.
+        let mut gaps = 0usize;
+        loop {
+            gaps = gaps.wrapping_sub(unfinished) & unfinished;
+            if gaps == 0 {
+                break;
+            }
+
+            // Compute next identifier and cost.
+            let mut identifier = node.identifier.clone();
+            self.metric.reset_character_counts();
+            self.metric
+                .set_reference_offset(self.segment.start(0) + identifier.offset(0));
+
+            let mut column_gaps = 0;
+            // The first residue of the column, and whether all residues are equal to it.
+            let mut first_residue = None;
+            let mut column_matches = true;
+            for (index, sequence) in self.sequences.iter().enumerate() {
+                if self.uses_offsets {
+                    self.metric
+                        .set_offset(index, self.segment.start(index) + identifier.offset(index));
+                }
+                if gaps & (1 << index) != 0 && identifier.offset(index) < sequence.len() {
+                    let residue = &sequence[identifier.offset(index)];
+                    self.metric.count_character(residue, index);
+                    column_matches &= *first_residue.get_or_insert(residue) == residue;
+                    identifier.increment(index);
+                } else if index != self.full_length_sequence
+                    && ((self.free_leading_gaps && identifier.offset(index) == 0)
+                        || (self.free_trailing_gaps && identifier.offset(index) == sequence.len()))
+                {
+                    self.metric.count_terminal_gap(index);
+                    column_gaps |= 1 << index;
+                } else if self.affine_gaps && node.identifier.gaps() & (1 << index) == 0 {
+                    self.metric.count_gap_open(index);
+                    column_gaps |= 1 << index;
+                } else {
+                    // Last entry represents a gap.
+                    self.metric.count_gap(index);
+                    column_gaps |= 1 << index;
+                }
+            }
+            debug_assert_ne!(
+                column_gaps,
+                AlignmentPath::full_column(self.sequences.len()),
+                "A selected sequence always advances"
+            );
+            if self.affine_gaps {
+                identifier.set_gaps(column_gaps);
+            }
+            let identifier = identifier;
+            let tie_breaker = match self.tie_break {
+                TieBreak::HigherCost => 0,
+                TieBreak::Matches => u32::from(column_gaps != 0 || !column_matches),
+                TieBreak::FewerGaps => column_gaps.count_ones(),
+            };
+            if self.band.as_ref().is_some_and(|band| {
+                !band.contains(
+                    (0..self.sequences.len())
+                        .map(|index| self.segment.start(index) + identifier.offset(index)),
+                )
+            }) {
+                continue;
+            }
+
+            // Compute cost increment.
+            let cost = self
+                .metric
+                .compute_cost_increment::<Cost>()
+                .and_then(|cost_increment| {
+                    node.cost
+                        .checked_add(&cost_increment)
+                        .ok_or(MultialignError::CostOverflow)
+                });
+
+            if let (Ok(cost), Some(cost_upper_bound)) = (&cost, self.cost_upper_bound) {
+                // The optimal alignment costs at most the upper bound, so its nodes are never pruned.
+                if cost
+                    .checked_add(&self.unweighted_lower_bound(&identifier))
+                    .is_none_or(|priority| priority > cost_upper_bound)
+                {
+                    continue;
+                }
+            }
+            if let (Ok(cost), Some(dominance)) = (&cost, &mut self.dominance) {
+                if dominance.dominates(&identifier, *cost) {
+                    continue;
+                }
+            }
+
+            match cost {
+                Ok(cost) => {
+                    successor_amount += 1;
+                    output.extend(Some(Self::Node {
+                        cost,
+                        lower_bound: self.lower_bound(&identifier),
+                        identifier,
+                        predecessor: Some(node.identifier.clone()),
+                        tie_breaker,
+                    }))
+                }
+                Err(error) => {
+                    self.error.get_or_insert(error);
+                }
+            }
+        }
+
+        self.open_nodes = self.open_nodes.saturating_sub(1) + successor_amount;
+        self.peak_open_nodes = self.peak_open_nodes.max(self.open_nodes);
+        if let Some(progress) = &mut self.progress {
+            progress.record(node.priority().as_f64(), successor_amount);
+        }
+    }
+
+    fn is_target(&self, node: &Self::Node) -> bool {
+        self.sequences.iter().enumerate().all(|(index, sequence)| {
+            debug_assert!(node.identifier.offset(index) <= sequence.len());
+            node.identifier.offset(index) == sequence.len()
+        })
+    }
+
+    fn cost_limit(&self) -> Option<Cost> {
+        None
+    }
+
+    fn memory_limit(&self) -> Option<usize> {
+        self.memory_limit
+    }
+
+    fn is_label_setting(&self) -> bool {
+        false
+    }
+}
+
+impl<
+        AlphabetType: Alphabet,
+        Cost: AStarCost,
+        SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized,
+        Identifier: NodeIdentifier,
+        Metric: MultialignMetric<AlphabetType>,
+    > Reset for Context<'_, AlphabetType, Cost, SequenceType, Identifier, Metric>
+{
+    fn reset(&mut self) {
+        // Do nothing.
+    }
+}
+
+impl<
+        'sequences,
+        AlphabetType: Alphabet,
+        Cost: AStarCost,
+        SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized,
+        Identifier: NodeIdentifier,
+        Metric: MultialignMetric<AlphabetType>,
+    > Context<'sequences, AlphabetType, Cost, SequenceType, Identifier, Metric>
+{
+    fn new(
+        sequences: &'sequences [&'sequences SequenceType],
+        metric: Metric,
+        segment: Segment<'sequences>,
+        heuristic: Option<Arc<Heuristic<Cost>>>,
+        weight: f64,
+    ) -> Self {
+        Self {
+            sequences,
+            affine_gaps: metric.has_affine_gaps(),
+            uses_offsets: metric.uses_offsets(),
+            free_leading_gaps: metric.has_free_end_gaps() && segment.is_start,
+            free_trailing_gaps: metric.has_free_end_gaps() && segment.is_end,
+            full_length_sequence: segment.full_length_sequence,
+            metric,
+            segment,
+            heuristic,
+            band: None,
+            weight,
+            tie_break: TieBreak::default(),
+            progress: None,
+            dominance: None,
+            // The root is opened when the search starts.
+            open_nodes: 1,
+            peak_open_nodes: 1,
+            memory_limit: None,
+            cost_upper_bound: None,
+            error: None,
+            phantom_data: PhantomData,
+            alphabet: PhantomData,
+        }
+    }
+
+    /// Returns the lower bound of the cost from the node with the given identifier to the target, or zero without a heuristic.
+    fn unweighted_lower_bound(&self, identifier: &Identifier) -> Cost {
+        self.heuristic
+            .as_ref()
+            .map_or_else(Cost::zero, |heuristic| heuristic.lower_bound(identifier))
+    }
+
+    /// Returns the lower bound of the node with the given identifier multiplied by the weight, which guides the search.
+    ///
+    /// The product is rounded down, which keeps the guarantee that the alignment costs at most the weight times the optimal cost.
+    fn lower_bound(&self, identifier: &Identifier) -> Cost
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        let lower_bound = self.unweighted_lower_bound(identifier);
+        if self.weight == 1.0 {
+            return lower_bound;
+        }
+
+        let weighted_lower_bound = (lower_bound.as_f64() * self.weight).floor();
+        i32::try_from(weighted_lower_bound as i64)
+            .ok()
+            .and_then(|lower_bound| metric::cost_from_i32(lower_bound).ok())
+            .unwrap_or_else(Cost::max_value)
+    }
+
+    /// Return the first error that occurred during the search, if any.
+    fn take_error(&mut self) -> Result<()> {
+        self.error.take().map_or(Ok(()), Err)
+    }
+
+    /// Returns the edges of the path from the root through the nodes with the given identifiers, excluding the root.
+    ///
+    /// The edges are generated again, so their costs are exact even if the searches only stored outdated costs of the nodes.
+    /// They are ordered from the last node to the root, like [`generic_a_star::AStar::backtrack`].
+    fn edges_along(
+        &mut self,
+        identifiers: impl IntoIterator<Item = Identifier>,
+    ) -> Vec<Node<Identifier, Cost>>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        // The nodes along the path were closed, so they would be pruned as dominated by themselves.
+        let dominance = self.dominance.take();
+        let mut edges = Vec::new();
+        let mut current = self.create_root();
+        let mut successors = Vec::new();
+        for identifier in identifiers {
+            successors.clear();
+            self.generate_successors(&current, &mut successors);
+            let successor = successors
+                .drain(..)
+                .find(|successor| successor.identifier == identifier)
+                .expect("Each node is a successor of its predecessor");
+            edges.push(successor.clone());
+            current = successor;
+        }
+
+        edges.reverse();
+        self.dominance = dominance;
+        edges
+    }
+
+    /// Returns the cost of the alignment with the given columns, which contain the mask of the sequences with a residue like the columns of an [`AlignmentPath`].
+    ///
+    /// The columns must be an alignment of the sequences within the band of the context.
+    fn path_cost(&mut self, columns: impl IntoIterator<Item = usize>) -> Result<Cost>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        let full_column = AlignmentPath::full_column(self.sequences.len());
+        let mut identifier = Identifier::create_root(self.sequences.len());
+        let identifiers: Vec<_> = columns
+            .into_iter()
+            .map(|column| {
+                for index in 0..self.sequences.len() {
+                    if column & (1 << index) != 0 {
+                        identifier.increment(index);
+                    }
+                }
+                // Each sequence without a residue in the column is in a gap.
+                if self.affine_gaps {
+                    identifier.set_gaps(full_column & !column);
+                }
+                identifier.clone()
+            })
+            .collect();
+
+        let edges = self.edges_along(identifiers);
+        self.take_error()?;
+        Ok(edges.first().map_or_else(Cost::zero, |edge| edge.cost))
+    }
+
+    /// Returns the details of the search known to the context.
+    fn search_details(&self) -> SearchDetails<Cost> {
+        SearchDetails {
+            peak_open_nodes: Some(self.peak_open_nodes),
+            dominated_nodes: self.dominance.as_ref().map(DominanceTable::pruned_nodes),
+            ..Default::default()
+        }
+    }
+}
+
+/// A cost type the aligner can search with.
+///
+/// Only the default [`I32Cost`] uses node identifiers specialised on the amount of sequences, and only in the default A* search of up to eight sequences.
+/// The other cost types use the same identifier for any amount of sequences, such that each of them adds only a single search to the binary.
+pub trait MultialignCost: AStarCost + Send + Sync {
+    /// True if the search uses node identifiers specialised on the amount of sequences.
+    const SPECIALISED_IDENTIFIERS: bool;
+}
+
+impl MultialignCost for I16Cost {
+    const SPECIALISED_IDENTIFIERS: bool = false;
+}
+
+impl MultialignCost for I32Cost {
+    const SPECIALISED_IDENTIFIERS: bool = true;
+}
+
+impl MultialignCost for I64Cost {
+    const SPECIALISED_IDENTIFIERS: bool = false;
+}
+
+impl MultialignCost for U64Cost {
+    const SPECIALISED_IDENTIFIERS: bool = false;
+}
+
+/// The columns of an alignment.
+///
+/// Each column contains one entry per sequence, which is `None` if the sequence has a gap in that column.
+pub type AlignmentColumns<CharacterType> = Vec<Vec<Option<CharacterType>>>;
+
+/// An alignment computed by the aligner.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Alignment<CharacterType, Cost = I32Cost> {
+    /// The columns of the alignment, with one entry per sequence in input order.
+    pub columns: AlignmentColumns<CharacterType>,
+    /// The cost of the alignment under the metric used for aligning.
+    pub cost: Cost,
+    /// A lower bound of the cost of an optimal alignment.
+    ///
+    /// It is only lower than the cost if the search was weighted, see [`MultialignOptions::weight`], or if this is not the cheapest of multiple alignments.
+    pub cost_lower_bound: Cost,
+    /// The statistics of the search that found the alignment.
+    ///
+    /// When computing multiple alignments in a single search, they all share the same statistics.
+    pub search_statistics: SearchStatistics,
+}
+
+impl<CharacterType: Clone, Cost> Alignment<CharacterType, Cost> {
+    /// Returns the amount of aligned sequences.
+    pub fn sequence_amount(&self) -> usize {
+        self.columns.first().map(Vec::len).unwrap_or(0)
+    }
+
+    /// Returns the factor by which the alignment costs at most as much as an optimal alignment, see [`suboptimality_bound`].
+    pub fn suboptimality_bound(&self) -> f64
+    where
+        Cost: AStarCost,
+    {
+        suboptimality_bound(self.cost, self.cost_lower_bound)
+    }
+
+    /// Returns the amount of columns.
+    pub fn len(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// Returns true if the alignment has no columns.
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    /// Returns the columns as operations, i.e. with the offsets of the residues instead of the residues themselves.
+    pub fn operations(&self) -> Vec<AlignmentColumn> {
+        operation_columns(&self.columns)
+    }
+
+    /// Returns the gapped view of the given sequence, i.e. its entry in each column, where gaps are `None`.
+    pub fn row(&self, sequence: usize) -> Vec<Option<CharacterType>> {
+        self.columns
+            .iter()
+            .map(|column| column[sequence].clone())
+            .collect()
+    }
+
+    /// Returns the gapped view of each sequence, see [`Self::row`].
+    pub fn rows(&self) -> Vec<Vec<Option<CharacterType>>> {
+        (0..self.sequence_amount())
+            .map(|sequence| self.row(sequence))
+            .collect()
+    }
+
+    /// Render the gapped view of the given sequence as string, with gaps rendered as gap characters.
+    pub fn render_row(&self, sequence: usize, gap_characters: &GapCharacters) -> String
+    where
+        CharacterType: Into<char>,
+    {
+        gap_characters.render_row(&self.row(sequence))
+    }
+}
+
+/// Returns the ratio between the cost of an alignment and a lower bound of the optimal cost.
+///
+/// This is one if the alignment is known to be optimal, and infinite if the lower bound is zero but the cost is not.
+pub fn suboptimality_bound<Cost: AStarCost>(cost: Cost, cost_lower_bound: Cost) -> f64 {
+    if cost <= cost_lower_bound {
+        1.0
+    } else {
+        cost.as_f64() / cost_lower_bound.as_f64()
+    }
+}
+
+/// Compute an optimal alignment of the sequences under the metric, without writing any output.
+///
+/// Of the options, only those that affect the search are used, and those that only affect the output are ignored.
+/// These are the search algorithm ([`MultialignOptions::search`], [`MultialignOptions::rederive_predecessors`], [`MultialignOptions::open_list`]),
+/// the lower bounds ([`MultialignOptions::heuristic`], [`MultialignOptions::weight`]),
+/// the pruning ([`MultialignOptions::band`], [`MultialignOptions::dominance_pruning`], [`MultialignOptions::upper_bound_pruning`]),
+/// the resources ([`MultialignOptions::memory_limit`], [`MultialignOptions::memory_limit_fallback`], [`MultialignOptions::spill`], [`MultialignOptions::cancellation`]),
+/// as well as [`MultialignOptions::tie_break`] and [`MultialignOptions::progress_interval`].
+pub fn align<
+    AlphabetType: Alphabet + Debug + Clone + Eq + 'static,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized + Sync,
+    Metric: MultialignMetric<AlphabetType> + Clone + Send,
+    Cost: MultialignCost,
+>(
+    sequences: &[&SequenceType],
+    metric: Metric,
+    options: &MultialignOptions,
+) -> Result<Alignment<AlphabetType::CharacterType, Cost>>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    let alignment = align_streaming(sequences, metric, options)?;
+    Ok(Alignment {
+        columns: residue_columns(&alignment.path, sequences),
+        cost: alignment.cost,
+        cost_lower_bound: alignment.cost_lower_bound,
+        search_statistics: alignment.search_statistics,
+    })
+}
+
+/// Like [`align`], but the columns of the alignment are not materialised.
+///
+/// The returned alignment stores only which sequences have a residue in each column,
+/// and its columns are reconstructed one by one with [`StreamingAlignment::columns`], whose residues can be looked up in the sequences with [`AlignmentColumn::residues`].
+/// This allows to process very long alignments without holding all of their columns in memory.
+pub fn align_streaming<
+    AlphabetType: Alphabet + Debug + Clone + Eq + 'static,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized + Sync,
+    Metric: MultialignMetric<AlphabetType> + Clone + Send,
+    Cost: MultialignCost,
+>(
+    sequences: &[&SequenceType],
+    metric: Metric,
+    options: &MultialignOptions,
+) -> Result<StreamingAlignment<Cost>>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    if sequences.len() < 2 {
+        return Err(MultialignError::TooFewSequences {
+            amount: sequences.len(),
+        });
+    }
+
+    let search_mode = match options.search_mode() {
+        SearchMode::KBest(_, open_list_kind) => SearchMode::AStar(open_list_kind),
+        search_mode => search_mode,
+    };
+    let heuristic = options.search_heuristic(search_mode)?;
+    let start_time = Instant::now();
+    let output = search_columns(
+        sequences,
+        metric,
+        Segment::whole(full_length_sequence(
+            sequences.iter().map(|sequence| sequence.len()),
+        )),
+        search_mode,
+        heuristic,
+        false,
+        SearchControl {
+            cancellation: &options.cancellation,
+            on_improvement: &mut |_, _, _, _| Ok(()),
+            memory_limit: options.memory_limit,
+            memory_limit_fallback: options.memory_limit_fallback,
+            upper_bound_pruning: options.upper_bound(),
+            band: options.band.clone(),
+            tie_break: options.tie_break,
+            progress_interval: options.progress_interval,
+            dominance_pruning: options.dominance_pruning,
+            spill: options.spill.as_ref(),
+        },
+    )?;
+    let search_statistics =
+        SearchStatistics::new(&output.performance_counters, start_time.elapsed());
+    let (path, cost) = output.alignments.into_iter().next().unwrap();
+    Ok(StreamingAlignment {
+        path,
+        cost,
+        cost_lower_bound: output.cost_lower_bound,
+        search_statistics,
+    })
+}
+
+/// Align the sequences and output the alignment, or multiple cheapest alignments if [`MultialignOptions::k_best`] is set.
+///
+/// Returns the alignments found, ordered by cost.
+pub fn multialign_astar<
+    AlphabetType: Alphabet + Debug + Clone + Eq + 'static,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized + Sync,
+    Metric: MultialignMetric<AlphabetType> + Clone + Send,
+    Cost: MultialignCost,
+>(
+    sequences: &[&SequenceType],
+    metric: Metric,
+    options: MultialignOptions,
+) -> Result<Vec<Alignment<AlphabetType::CharacterType, Cost>>>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    info!("Aligning {} sequences", sequences.len());
+    let search_mode = options.search_mode();
+    match search_mode {
+        SearchMode::AStar(_) => {}
+        SearchMode::RederivePredecessors(_) => info!("Using search with re-derived predecessors"),
+        SearchMode::KBest(k, _) => info!("Searching for the {k} best alignments"),
+        SearchMode::Anytime(_) => info!("Using anytime search"),
+        SearchMode::IdaStar => info!("Using IDA* search"),
+        SearchMode::Beam(width) => info!("Using beam search with width {width}"),
+        SearchMode::Parallel(thread_amount, _) => {
+            info!("Searching with {thread_amount} threads")
+        }
+    }
+    if options.open_list == OpenListKind::Bucket {
+        info!("Using a bucket queue as open list");
+    }
+    let heuristic = options.search_heuristic(search_mode)?;
+
+    let start_time = Instant::now();
+    // The metric is moved into the search, so copies are kept for computing the column costs and refining afterwards.
+    let mut column_cost_metric = options.column_costs.as_ref().map(|_| metric.clone());
+    let mut refinement_metric = (options.refine > 0).then(|| metric.clone());
+    let record_search_graph = options.search_graph_dump.is_some();
+    if record_search_graph && !matches!(search_mode, SearchMode::AStar(OpenListKind::Heap)) {
+        warn!("The search graph can only be dumped with the default search, not dumping it");
+    }
+    let SearchOutput {
+        alignments,
+        cost_lower_bound,
+        performance_counters,
+        search_graph,
+        details,
+    } = search_columns(
+        sequences,
+        metric,
+        Segment::whole(full_length_sequence(
+            sequences.iter().map(|sequence| sequence.len()),
+        )),
+        search_mode,
+        heuristic,
+        record_search_graph,
+        SearchControl {
+            cancellation: &options.cancellation,
+            on_improvement: &mut |path, cost, cost_lower_bound, performance_counters| {
+                // Each improved alignment is output right away, such that it is available even if the search is stopped later.
+                info!("Found an alignment of cost {cost}");
+                log_suboptimality_bound(cost, cost_lower_bound);
+                let duration = start_time.elapsed();
+                let alignment = Alignment {
+                    columns: residue_columns(path, sequences),
+                    cost,
+                    cost_lower_bound,
+                    search_statistics: SearchStatistics::new(performance_counters, duration),
+                };
+                output_alignment::<AlphabetType, _>(&alignment, duration, &options, None)
+            },
+            memory_limit: options.memory_limit,
+            memory_limit_fallback: options.memory_limit_fallback,
+            upper_bound_pruning: options.upper_bound(),
+            band: options.band.clone(),
+            tie_break: options.tie_break,
+            progress_interval: options.progress_interval,
+            dominance_pruning: options.dominance_pruning,
+            spill: options.spill.as_ref(),
+        },
+    )?;
+    let end_time = Instant::now();
+    let duration = end_time - start_time;
+    let search_statistics = SearchStatistics::new(&performance_counters, duration);
+    let alignments: Vec<_> = alignments
+        .into_iter()
+        .map(|(path, cost)| Alignment {
+            columns: residue_columns(&path, sequences),
+            cost,
+            cost_lower_bound,
+            search_statistics: search_statistics.clone(),
+        })
+        .collect();
+    let mut alignments = alignments;
+    // Refining only one of multiple alignments could reorder or duplicate them.
+    let refined = match &mut refinement_metric {
+        Some(metric) if !matches!(search_mode, SearchMode::KBest(..)) => {
+            refine(sequences, &mut alignments[0], metric, &options)?
+        }
+        _ => false,
+    };
+    log_suboptimality_bound(alignments[0].cost, alignments[0].cost_lower_bound);
+
+    info!("Runtime: {:.2}s", duration.as_secs_f64());
+    info!("Performance: {:?}", performance_counters);
+    if let Some(dominated_nodes) = details.dominated_nodes {
+        info!("Pruned {dominated_nodes} dominated nodes");
+    }
+    log_branching_factor(
+        &performance_counters,
+        alignments.first().map(Alignment::len),
+    );
+    if let (Some(search_graph_dump), Some(search_graph)) =
+        (&options.search_graph_dump, &search_graph)
+    {
+        write_search_graph(search_graph_dump, search_graph)?;
+    }
+    if let (Some(statistics), Some(alignment)) = (&options.statistics, alignments.first()) {
+        append_statistics(
+            statistics,
+            &alignment.columns,
+            alignment.cost,
+            duration,
+            &performance_counters,
+        )?;
+    }
+
+    let output_start = Instant::now();
+    if let SearchMode::KBest(..) = search_mode {
+        info!("Found {} alignments", alignments.len());
+        for (rank, alignment) in alignments.iter().enumerate() {
+            info!("Alignment {} cost {}", rank + 1, alignment.cost);
+            if let Some(metric) = &mut column_cost_metric {
+                output_column_costs::<_, Cost, _>(
+                    &alignment.columns,
+                    metric,
+                    &options,
+                    Some(rank + 1),
+                )?;
+            }
+            output_alignment::<AlphabetType, _>(alignment, duration, &options, Some(rank + 1))?;
+        }
+    } else {
+        let alignment = alignments.first().unwrap();
+        info!("Alignment cost {}", alignment.cost);
+        if let Some(metric) = &mut column_cost_metric {
+            output_column_costs::<_, Cost, _>(&alignment.columns, metric, &options, None)?;
+        }
+        // The anytime search has already output the alignment when it was found, unless it was refined afterwards.
+        if !matches!(search_mode, SearchMode::Anytime(_)) || refined {
+            output_alignment::<AlphabetType, _>(alignment, duration, &options, None)?;
+        }
+    }
+    if let Some(statistics_json) = &options.statistics_json {
+        write_statistics_json(
+            statistics_json,
+            &alignments[0],
+            &details,
+            output_start.elapsed(),
+        )?;
+    }
+
+    Ok(alignments)
+}
+
+/// Align the sequences in independent segments between the given anchors.
+///
+/// Each anchor contains one offset per sequence, and the anchors are aligned as a single column.
+/// The offsets of the anchors must be strictly increasing in each sequence.
+/// The segments between the anchors are aligned in parallel, each with an exact search.
+/// Only a single alignment is computed, i.e. [`MultialignOptions::k_best`] and [`MultialignOptions::anytime`] are ignored.
+///
+/// Returns the alignment.
+pub fn multialign_astar_anchored<
+    AlphabetType: Alphabet + Debug + Clone + Eq + 'static,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized + Sync,
+    Metric: MultialignMetric<AlphabetType> + Clone + Send,
+    Cost: MultialignCost,
+>(
+    sequences: &[&SequenceType],
+    anchors: &[Vec<usize>],
+    mut metric: Metric,
+    options: MultialignOptions,
+) -> Result<Alignment<AlphabetType::CharacterType, Cost>>
+where
+    AlphabetType::CharacterType: Send,
+    Cost::CostType: TryFrom<i32>,
+{
+    info!(
+        "Aligning {} sequences in {} segments between {} anchors",
+        sequences.len(),
+        anchors.len() + 1,
+        anchors.len()
+    );
+    let search_mode = options.segment_search_mode();
+    if options.search_graph_dump.is_some() {
+        warn!("The search graph cannot be dumped when aligning with anchors, not dumping it");
+    }
+    let heuristic = options.search_heuristic(search_mode)?;
+
+    let mut segment_starts = vec![vec![0; sequences.len()]];
+    let mut segment_ends = Vec::new();
+    for anchor in anchors {
+        debug_assert_eq!(anchor.len(), sequences.len());
+        for (index, (offset, start)) in anchor
+            .iter()
+            .zip(segment_starts.last().unwrap())
+            .enumerate()
+        {
+            if offset < start || *offset >= sequences[index].len() {
+                return Err(MultialignError::InvalidAnchors(format!(
+                    "Anchor offsets are not strictly increasing or out of bounds in sequence {index}"
+                )));
+            }
+        }
+
+        segment_ends.push(anchor.clone());
+        segment_starts.push(anchor.iter().map(|offset| offset + 1).collect());
+    }
+    segment_ends.push(sequences.iter().map(|sequence| sequence.len()).collect());
+
+    let full_length_sequence =
+        full_length_sequence(sequences.iter().map(|sequence| sequence.len()));
+    let start_time = Instant::now();
+    let next_segment = AtomicUsize::new(0);
+    let segment_outputs = Mutex::new((0..segment_starts.len()).map(|_| None).collect::<Vec<_>>());
+    let thread_amount = available_parallelism()
+        .map(NonZeroUsize::get)
+        .unwrap_or(1)
+        .min(segment_starts.len());
+
+    thread::scope(|scope| {
+        for _ in 0..thread_amount {
+            let metric = metric.clone();
+            scope.spawn(|| {
+                let metric = metric;
+                loop {
+                    let segment = next_segment.fetch_add(1, atomic::Ordering::Relaxed);
+                    if segment >= segment_starts.len() {
+                        break;
+                    }
+
+                    let segment_sequences: Vec<_> = sequences
+                        .iter()
+                        .zip(segment_starts[segment].iter().zip(&segment_ends[segment]))
+                        .map(|(sequence, (start, end))| &sequence[*start..*end])
+                        .collect();
+                    let output = search_columns::<_, _, _, Cost>(
+                        &segment_sequences,
+                        metric.clone(),
+                        Segment {
+                            starts: &segment_starts[segment],
+                            is_start: segment == 0,
+                            is_end: segment == anchors.len(),
+                            full_length_sequence,
+                        },
+                        search_mode,
+                        heuristic,
+                        false,
+                        SearchControl {
+                            cancellation: &options.cancellation,
+                            on_improvement: &mut |_, _, _, _| Ok(()),
+                            memory_limit: options.memory_limit,
+                            memory_limit_fallback: options.memory_limit_fallback,
+                            upper_bound_pruning: options.upper_bound(),
+                            band: options.band.clone(),
+                            tie_break: options.tie_break,
+                            progress_interval: options.progress_interval,
+                            dominance_pruning: options.dominance_pruning,
+                            spill: options.spill.as_ref(),
+                        },
+                    );
+                    segment_outputs.lock().unwrap()[segment] = Some(output);
+                }
+            });
+        }
+    });
+
+    let mut path = AlignmentPath::new(sequences.len());
+    let mut performance_counters = AStarPerformanceCounters::default();
+    let mut details = SearchDetails::default();
+    // The amount by which the alignment of each segment may cost more than an optimal one.
+    let mut cost_slack = Cost::zero();
+    for (segment, output) in segment_outputs
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .enumerate()
+    {
+        let output = output.unwrap()?;
+        path.extend(&output.alignments[0].0);
+        cost_slack = cost_slack
+            .checked_add(&(output.alignments[0].1 - output.cost_lower_bound))
+            .unwrap_or_else(Cost::max_value);
+        performance_counters.opened_nodes += output.performance_counters.opened_nodes;
+        performance_counters.suboptimal_opened_nodes +=
+            output.performance_counters.suboptimal_opened_nodes;
+        performance_counters.closed_nodes += output.performance_counters.closed_nodes;
+        details = if segment == 0 {
+            output.details
+        } else {
+            details.combine(&output.details)
+        };
+
+        if segment < anchors.len() {
+            path.push(AlignmentPath::full_column(sequences.len()));
+        }
+    }
+    let columns = residue_columns(&path, sequences);
+
+    let end_time = Instant::now();
+    let duration = end_time - start_time;
+    let cost: Cost = score_alignment(&columns, &mut metric)?;
+    let cost_lower_bound = cost.saturating_sub(&cost_slack);
+
+    info!("Alignment cost {}", cost);
+    log_suboptimality_bound(cost, cost_lower_bound);
+    info!("Runtime: {:.2}s", duration.as_secs_f64());
+    info!("Performance: {:?}", performance_counters);
+    if let Some(dominated_nodes) = details.dominated_nodes {
+        info!("Pruned {dominated_nodes} dominated nodes");
+    }
+    // The segments are separate searches, so there is no common depth.
+    log_branching_factor(&performance_counters, None);
+    if let Some(statistics) = &options.statistics {
+        append_statistics(statistics, &columns, cost, duration, &performance_counters)?;
+    }
+    let output_start = Instant::now();
+    output_column_costs::<_, Cost, _>(&columns, &mut metric, &options, None)?;
+
+    let alignment = Alignment {
+        columns,
+        cost,
+        cost_lower_bound,
+        search_statistics: SearchStatistics::new(&performance_counters, duration),
+    };
+    output_alignment::<AlphabetType, _>(&alignment, duration, &options, None)?;
+    if let Some(statistics_json) = &options.statistics_json {
+        write_statistics_json(
+            statistics_json,
+            &alignment,
+            &details,
+            output_start.elapsed(),
+        )?;
+    }
+    Ok(alignment)
+}
+
+/// An estimate of the resources needed to align sequences, see [`estimate_search`].
+#[derive(Debug, Clone)]
+pub struct SearchEstimate<Cost> {
+    /// The lower bound of the optimal cost at the root, or `None` if the metric does not allow to compute lower bounds.
+    pub root_lower_bound: Option<Cost>,
+    /// The amount of nodes in the search space, which is the product of the sequence lengths plus one, summed over the segments between anchors.
+    ///
+    /// This easily exceeds the range of integers, so it is a floating point number.
+    pub nodes: f64,
+    /// The approximate amount of bytes per node stored by the search, including the overhead of the open and closed list.
+    pub bytes_per_node: f64,
+}
+
+impl<Cost> SearchEstimate<Cost> {
+    /// Returns the approximate amount of bytes needed to store all nodes of the search space.
+    pub fn bytes(&self) -> f64 {
+        self.nodes * self.bytes_per_node
+    }
+}
+
+/// Estimate the resources needed to align the sequences between the given anchors, without searching.
+///
+/// The lower bound at the root is computed like before a search, which takes as long as computing the heuristic.
+/// The search space is a worst case, since the lower bounds usually guide the search through a small fraction of it,
+/// and a band, see [`MultialignOptions::band`], is not considered.
+pub fn estimate_search<
+    AlphabetType: Alphabet + Debug + Clone + Eq + 'static,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized + Sync,
+    Metric: MultialignMetric<AlphabetType> + Clone + Send,
+    Cost: MultialignCost,
+>(
+    sequences: &[&SequenceType],
+    anchors: &[Vec<usize>],
+    mut metric: Metric,
+    options: &MultialignOptions,
+) -> Result<SearchEstimate<Cost>>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    if sequences.len() < 2 {
+        return Err(MultialignError::TooFewSequences {
+            amount: sequences.len(),
+        });
+    }
+    let segment = Segment::whole(full_length_sequence(
+        sequences.iter().map(|sequence| sequence.len()),
+    ));
+    check_cost_type::<_, Cost, _, _>(sequences, &mut metric, segment)?;
+    let root_lower_bound = root_lower_bound(sequences, metric, segment, options)?;
+
+    let mut nodes = 0.0;
+    let mut starts = vec![0; sequences.len()];
+    let ends = sequences.iter().map(|sequence| sequence.len()).collect();
+    for ends in anchors.iter().chain([&ends]) {
+        nodes += starts
+            .iter()
+            .zip(ends)
+            .map(|(start, end)| (end.saturating_sub(*start) + 1) as f64)
+            .product::<f64>();
+        starts = ends.iter().map(|end| end + 1).collect();
+    }
+
+    // Each node stores its identifier and the one of its predecessor, which consist of one offset per sequence.
+    let node_size =
+        size_of::<Node<ArrayIdentifier<0>, Cost>>() + 2 * sequences.len() * size_of::<usize>();
+    Ok(SearchEstimate {
+        root_lower_bound,
+        nodes,
+        bytes_per_node: node_size as f64 * NODE_MEMORY_FACTOR,
+    })
+}
+
+/// Returns the lower bound of the optimal cost at the root of the search, or `None` if the metric does not allow to compute lower bounds.
+///
+/// This takes as long as computing the lower bounds of the [`MultialignOptions::heuristic`].
+fn root_lower_bound<
+    AlphabetType: Alphabet + Debug + Clone + Eq + 'static,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized + Sync,
+    Metric: MultialignMetric<AlphabetType> + Clone + Send,
+    Cost: MultialignCost,
+>(
+    sequences: &[&SequenceType],
+    mut metric: Metric,
+    segment: Segment<'_>,
+    options: &MultialignOptions,
+) -> Result<Option<Cost>>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    if !allows_lower_bounds(&metric) {
+        return Ok(None);
+    }
+
+    let search_heuristic = options.search_heuristic(options.search_mode())?;
+    let heuristic = Arc::new(Heuristic::new(
+        sequences,
+        &mut metric,
+        search_heuristic.kind,
+        search_heuristic.gpu,
+    )?);
+    let context = Context::<_, Cost, _, VecIdentifier, _>::new(
+        sequences,
+        metric,
+        segment,
+        Some(heuristic),
+        1.0,
+    );
+    Ok(Some(
+        context.unweighted_lower_bound(&context.create_root().identifier),
+    ))
+}
+
+/// Align the sequences progressively along a guide tree, and output the alignment.
+///
+/// The guide tree is constructed from the k-mers shared by the sequences with [`kmer_guide_tree`], see [`MultialignOptions::guide_tree`].
+/// Then, from the leaves to the root, the alignments of the two subtrees of each node are aligned to each other with a dynamic program
+/// that keeps the columns of both and scores each column with the metric.
+/// This takes time quadratic in the length of the alignments per node instead of exponential in the amount of sequences,
+/// so it is feasible when the exact search is not, but the alignment is not necessarily optimal.
+/// The cost of the alignment is computed exactly with [`score_alignment`], also for metrics with affine gaps or free end gaps,
+/// which the dynamic program only approximates.
+/// If [`MultialignOptions::refine`] is set, the alignment is then refined along the same guide tree.
+/// If the metric allows to compute lower bounds, the lower bound of the optimal cost is computed like before a search,
+/// see [`MultialignOptions::heuristic`], and otherwise it is zero.
+/// Of the search options, only [`MultialignOptions::heuristic`] and [`MultialignOptions::cancellation`] are used.
+///
+/// Returns the alignment.
+pub fn multialign_progressive<
+    AlphabetType: Alphabet + Debug + Clone + Eq + 'static,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized + Sync,
+    Metric: MultialignMetric<AlphabetType> + Clone + Send,
+    Cost: MultialignCost,
+>(
+    sequences: &[&SequenceType],
+    mut metric: Metric,
+    options: MultialignOptions,
+) -> Result<Alignment<AlphabetType::CharacterType, Cost>>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    if sequences.len() < 2 {
+        return Err(MultialignError::TooFewSequences {
+            amount: sequences.len(),
+        });
+    }
+    info!("Aligning {} sequences progressively", sequences.len());
+
+    let start_time = Instant::now();
+    let sequence_characters: Vec<Vec<_>> = sequences
+        .iter()
+        .map(|sequence| sequence.iter().cloned().collect())
+        .collect();
+    let guide_tree = kmer_guide_tree(&sequence_characters, &options.guide_tree)?;
+    debug!(
+        "Constructed the guide tree in {:.2}s",
+        start_time.elapsed().as_secs_f64()
+    );
+    let (columns, performance_counters) = progressive_alignment::<_, Cost, _, _>(
+        sequences,
+        &guide_tree,
+        &mut metric.clone(),
+        &options.cancellation,
+    )?;
+    let cost: Cost = score_alignment(&columns, &mut metric)?;
+    let (columns, cost) = if options.refine > 0 {
+        info!("Progressive alignment cost {}", cost);
+        refine_alignment(
+            columns,
+            cost,
+            &guide_tree,
+            options.refine,
+            &mut metric,
+            &options.cancellation,
+        )?
+    } else {
+        (columns, cost)
+    };
+    let duration = start_time.elapsed();
+
+    output_approximate_alignment(
+        sequences,
+        columns,
+        cost,
+        duration,
+        &performance_counters,
+        metric,
+        &options,
+    )
+}
+
+/// Align the sequences with the center-star method, and output the alignment.
+///
+/// The center is the sequence with the minimum sum of the costs of its optimal pairwise alignments to all other sequences,
+/// where each pair is aligned with the same dynamic program as in [`multialign_progressive`].
+/// The pairwise alignments of the center to each other sequence are then merged into one alignment that keeps all of them.
+/// For sum-of-pairs metrics whose pairwise costs fulfil the triangle inequality, the alignment costs at most twice as much as an optimal one,
+/// but usually much less.
+/// The cost of the alignment is computed exactly with [`score_alignment`],
+/// and it is logged next to the lower bound of the optimal cost to show how far it may be from optimal.
+/// If [`MultialignOptions::refine`] is set, the alignment is then refined along a guide tree constructed with [`kmer_guide_tree`].
+/// Lower bounds and the used options are as in [`multialign_progressive`].
+///
+/// Returns the alignment.
+pub fn multialign_center_star<
+    AlphabetType: Alphabet + Debug + Clone + Eq + 'static,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized + Sync,
+    Metric: MultialignMetric<AlphabetType> + Clone + Send,
+    Cost: MultialignCost,
+>(
+    sequences: &[&SequenceType],
+    mut metric: Metric,
+    options: MultialignOptions,
+) -> Result<Alignment<AlphabetType::CharacterType, Cost>>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    if sequences.len() < 2 {
+        return Err(MultialignError::TooFewSequences {
+            amount: sequences.len(),
+        });
+    }
+    info!(
+        "Aligning {} sequences with the center-star method",
+        sequences.len()
+    );
+
+    let start_time = Instant::now();
+    let (columns, center, performance_counters) = center_star_alignment::<_, Cost, _, _>(
+        sequences,
+        &mut metric.clone(),
+        &options.cancellation,
+    )?;
+    info!("Used sequence {} as center", center + 1);
+    let cost: Cost = score_alignment(&columns, &mut metric)?;
+    let (columns, cost) = if options.refine > 0 {
+        info!("Center-star alignment cost {}", cost);
+        let sequence_characters: Vec<Vec<_>> = sequences
+            .iter()
+            .map(|sequence| sequence.iter().cloned().collect())
+            .collect();
+        let guide_tree = kmer_guide_tree(&sequence_characters, &options.guide_tree)?;
+        refine_alignment(
+            columns,
+            cost,
+            &guide_tree,
+            options.refine,
+            &mut metric,
+            &options.cancellation,
+        )?
+    } else {
+        (columns, cost)
+    };
+    let duration = start_time.elapsed();
+
+    output_approximate_alignment(
+        sequences,
+        columns,
+        cost,
+        duration,
+        &performance_counters,
+        metric,
+        &options,
+    )
+}
+
+/// Compute the lower bound of the optimal cost for an alignment that was not found by a search, log it next to the cost of the alignment, and output the alignment.
+///
+/// If the metric does not allow to compute lower bounds, the lower bound is zero.
+fn output_approximate_alignment<
+    AlphabetType: Alphabet + Debug + Clone + Eq + 'static,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized + Sync,
+    Metric: MultialignMetric<AlphabetType> + Clone + Send,
+    Cost: MultialignCost,
+>(
+    sequences: &[&SequenceType],
+    columns: AlignmentColumns<AlphabetType::CharacterType>,
+    cost: Cost,
+    duration: Duration,
+    performance_counters: &AStarPerformanceCounters,
+    mut metric: Metric,
+    options: &MultialignOptions,
+) -> Result<Alignment<AlphabetType::CharacterType, Cost>>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    let segment = Segment::whole(full_length_sequence(
+        sequences.iter().map(|sequence| sequence.len()),
+    ));
+    let cost_lower_bound = match root_lower_bound(sequences, metric.clone(), segment, options)? {
+        Some(root_lower_bound) => {
+            info!("Alignment cost {cost}, lower bound of the optimal cost {root_lower_bound}");
+            log_suboptimality_bound(cost, root_lower_bound);
+            root_lower_bound.min(cost)
+        }
+        None => {
+            info!("Alignment cost {}", cost);
+            info!("The metric does not allow to compute a lower bound of the optimal cost");
+            Cost::zero()
+        }
+    };
+    info!("Runtime: {:.2}s", duration.as_secs_f64());
+    info!("Performance: {:?}", performance_counters);
+    if let Some(statistics) = &options.statistics {
+        append_statistics(statistics, &columns, cost, duration, performance_counters)?;
+    }
+    let output_start = Instant::now();
+    output_column_costs::<_, Cost, _>(&columns, &mut metric, options, None)?;
+
+    let alignment = Alignment {
+        columns,
+        cost,
+        cost_lower_bound,
+        search_statistics: SearchStatistics::new(performance_counters, duration),
+    };
+    output_alignment::<AlphabetType, _>(&alignment, duration, options, None)?;
+    if let Some(statistics_json) = &options.statistics_json {
+        write_statistics_json(
+            statistics_json,
+            &alignment,
+            &SearchDetails::default(),
+            output_start.elapsed(),
+        )?;
+    }
+    Ok(alignment)
+}
+
+/// Refine the given alignment with [`MultialignOptions::refine`] rounds along a guide tree constructed from the sequences, see [`refine_alignment`].
+///
+/// Its lower bound of the optimal cost stays valid, but is capped at the cost of the refined alignment.
+/// Returns true if the alignment was improved.
+fn refine<
+    AlphabetType: Alphabet + Debug + Clone + Eq + 'static,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized + Sync,
+    Metric: MultialignMetric<AlphabetType>,
+    Cost: MultialignCost,
+>(
+    sequences: &[&SequenceType],
+    alignment: &mut Alignment<AlphabetType::CharacterType, Cost>,
+    metric: &mut Metric,
+    options: &MultialignOptions,
+) -> Result<bool>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    info!("Refining the alignment of cost {}", alignment.cost);
+    let sequence_characters: Vec<Vec<_>> = sequences
+        .iter()
+        .map(|sequence| sequence.iter().cloned().collect())
+        .collect();
+    let guide_tree = kmer_guide_tree(&sequence_characters, &options.guide_tree)?;
+    let (columns, cost) = refine_alignment(
+        alignment.columns.clone(),
+        alignment.cost,
+        &guide_tree,
+        options.refine,
+        metric,
+        &options.cancellation,
+    )?;
+    if cost >= alignment.cost {
+        return Ok(false);
+    }
+
+    alignment.columns = columns;
+    alignment.cost = cost;
+    alignment.cost_lower_bound = alignment.cost_lower_bound.min(cost);
+    Ok(true)
+}
+
+/// Add a sequence to an existing alignment without changing its columns, and output the resulting alignment.
+///
+/// The sequence is aligned to the columns of the existing alignment with a dynamic program, which finds an alignment of minimum cost
+/// among those that keep the existing columns, see [`align_profiles`]. This is a much smaller search than realigning all sequences.
+/// The rows of the result are the records of the existing alignment followed by the sequence,
+/// and the metric and [`MultialignOptions::record_ids`] must be given for them in this order.
+/// The metric must not have affine gaps, free end gaps or offset-dependent costs.
+///
+/// Returns the alignment.
+pub fn multialign_add_to<
+    AlphabetType: Alphabet + Debug + Clone + Eq + 'static,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized,
+    Metric: MultialignMetric<AlphabetType> + Clone,
+    Cost: MultialignCost,
+>(
+    existing: &[AlignedFastaRecord<AlphabetType::CharacterType>],
+    sequence: &SequenceType,
+    mut metric: Metric,
+    options: MultialignOptions,
+) -> Result<Alignment<AlphabetType::CharacterType, Cost>>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    if existing.is_empty() {
+        return Err(MultialignError::InvalidAlignment(
+            "The alignment to add to contains no records".to_string(),
+        ));
+    }
+    if metric.has_affine_gaps() || metric.has_free_end_gaps() || metric.uses_offsets() {
+        return Err(MultialignError::UnsupportedMetric(
+            "Adding a sequence to an alignment does not support affine gaps, free end gaps or offset-dependent costs".to_string(),
+        ));
+    }
+    let existing_columns = records_to_columns(existing);
+    info!(
+        "Adding a sequence of length {} to an alignment of {} sequences with {} columns",
+        sequence.len(),
+        existing.len(),
+        existing_columns.len()
+    );
+
+    let start_time = Instant::now();
+    let mut performance_counters = AStarPerformanceCounters::default();
+    let (alignment, cost) = align_profiles::<_, Cost, _>(
+        &PartialAlignment {
+            rows: (0..existing.len()).collect(),
+            columns: existing_columns,
+        },
+        &PartialAlignment::sequence(existing.len(), sequence.iter().cloned()),
+        existing.len() + 1,
+        &mut metric.clone(),
+        &options.cancellation,
+        &mut performance_counters,
+    )?;
+    let columns = alignment.columns;
+    let duration = start_time.elapsed();
+
+    info!("Alignment cost {}", cost);
+    info!("Runtime: {:.2}s", duration.as_secs_f64());
+    info!("Performance: {:?}", performance_counters);
+    if let Some(statistics) = &options.statistics {
+        append_statistics(statistics, &columns, cost, duration, &performance_counters)?;
+    }
+    let output_start = Instant::now();
+    output_column_costs::<_, Cost, _>(&columns, &mut metric, &options, None)?;
+
+    let alignment = Alignment {
+        columns,
+        cost,
+        // The alignment is optimal among those that keep the existing columns.
+        cost_lower_bound: cost,
+        search_statistics: SearchStatistics::new(&performance_counters, duration),
+    };
+    output_alignment::<AlphabetType, _>(&alignment, duration, &options, None)?;
+    if let Some(statistics_json) = &options.statistics_json {
+        write_statistics_json(
+            statistics_json,
+            &alignment,
+            &SearchDetails::default(),
+            output_start.elapsed(),
+        )?;
+    }
+    Ok(alignment)
+}
+
+/// Merge two existing alignments of disjoint sets of sequences into one without changing the columns of either, and output the merged alignment.
+///
+/// The columns of both alignments are aligned to each other with a dynamic program, see [`align_profiles`],
+/// which finds a merge of minimum cost among those that keep the columns of both alignments.
+/// Columns that consist only of gaps in either alignment are kept, and cost nothing.
+/// The rows of the result are the records of the first alignment followed by those of the second,
+/// and the metric and [`MultialignOptions::record_ids`] must be given for them in this order.
+/// The metric must not have affine gaps or free end gaps, since these depend on more than a single column.
+///
+/// Returns the merged alignment.
+pub fn multialign_merge<
+    AlphabetType: Alphabet + Debug + Clone + Eq + 'static,
+    Metric: MultialignMetric<AlphabetType> + Clone,
+    Cost: MultialignCost,
+>(
+    first: &[AlignedFastaRecord<AlphabetType::CharacterType>],
+    second: &[AlignedFastaRecord<AlphabetType::CharacterType>],
+    mut metric: Metric,
+    options: MultialignOptions,
+) -> Result<Alignment<AlphabetType::CharacterType, Cost>>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    if first.is_empty() || second.is_empty() {
+        return Err(MultialignError::InvalidAlignment(
+            "An alignment to merge contains no records".to_string(),
+        ));
+    }
+    if metric.has_affine_gaps() || metric.has_free_end_gaps() {
+        return Err(MultialignError::UnsupportedMetric(
+            "Merging alignments does not support affine gaps or free end gaps".to_string(),
+        ));
+    }
+    let first_alignment = PartialAlignment {
+        rows: (0..first.len()).collect(),
+        columns: records_to_columns(first),
+    };
+    let second_alignment = PartialAlignment {
+        rows: (first.len()..first.len() + second.len()).collect(),
+        columns: records_to_columns(second),
+    };
+    info!(
+        "Merging an alignment of {} sequences with {} columns and an alignment of {} sequences with {} columns",
+        first.len(),
+        first_alignment.columns.len(),
+        second.len(),
+        second_alignment.columns.len()
+    );
+
+    let start_time = Instant::now();
+    let mut performance_counters = AStarPerformanceCounters::default();
+    let (alignment, cost) = align_profiles::<_, Cost, _>(
+        &first_alignment,
+        &second_alignment,
+        first.len() + second.len(),
+        &mut metric.clone(),
+        &options.cancellation,
+        &mut performance_counters,
+    )?;
+    let columns = alignment.columns;
+    let duration = start_time.elapsed();
+
+    info!("Alignment cost {}", cost);
+    info!("Runtime: {:.2}s", duration.as_secs_f64());
+    info!("Performance: {:?}", performance_counters);
+    if let Some(statistics) = &options.statistics {
+        append_statistics(statistics, &columns, cost, duration, &performance_counters)?;
+    }
+    let output_start = Instant::now();
+    output_column_costs::<_, Cost, _>(&columns, &mut metric, &options, None)?;
+
+    let alignment = Alignment {
+        columns,
+        cost,
+        // The alignment is optimal among those that keep the columns of both alignments.
+        cost_lower_bound: cost,
+        search_statistics: SearchStatistics::new(&performance_counters, duration),
+    };
+    output_alignment::<AlphabetType, _>(&alignment, duration, &options, None)?;
+    if let Some(statistics_json) = &options.statistics_json {
+        write_statistics_json(
+            statistics_json,
+            &alignment,
+            &SearchDetails::default(),
+            output_start.elapsed(),
+        )?;
+    }
+    Ok(alignment)
+}
+
+/// Realign the given column range of an existing alignment of the sequences with an exact search, and output the resulting alignment.
+///
+/// The residues of each sequence within the columns of the region are aligned with the search selected by the options like a segment between anchors,
+/// such that the offsets passed to the metric are those within the whole sequences.
+/// Gaps at the borders of the region only count as terminal gaps if the region starts at the first or ends at the last column.
+/// The realigned columns replace those of the region, and the columns outside of the region are kept.
+/// If the metric has affine gaps, the realigned region may cost more in the context of the whole alignment,
+/// in which case the original alignment is kept.
+/// Only a single alignment is computed, i.e. [`MultialignOptions::k_best`] and [`MultialignOptions::anytime`] are ignored.
+///
+/// The columns must be an alignment of the given sequences, and the region must be a non-empty range of its columns.
+/// Returns the resulting alignment.
+pub fn multialign_realign_region<
+    AlphabetType: Alphabet + Debug + Clone + Eq + 'static,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized + Sync,
+    Metric: MultialignMetric<AlphabetType> + Clone + Send,
+    Cost: MultialignCost,
+>(
+    sequences: &[&SequenceType],
+    columns: &[Vec<Option<AlphabetType::CharacterType>>],
+    region: Range<usize>,
+    mut metric: Metric,
+    options: MultialignOptions,
+) -> Result<Alignment<AlphabetType::CharacterType, Cost>>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    verify_columns(
+        &operation_columns(columns),
+        &sequences
+            .iter()
+            .map(|sequence| sequence.len())
+            .collect::<Vec<_>>(),
+    )?;
+    if region.is_empty() || region.end > columns.len() {
+        return Err(MultialignError::InvalidAlignment(format!(
+            "The region of columns {}..{} is empty or not within the {} columns of the alignment",
+            region.start,
+            region.end,
+            columns.len()
+        )));
+    }
+    info!(
+        "Realigning columns {}..{} of an alignment of {} sequences with {} columns",
+        region.start,
+        region.end,
+        sequences.len(),
+        columns.len()
+    );
+    let search_mode = options.segment_search_mode();
+    if options.search_graph_dump.is_some() {
+        warn!("The search graph cannot be dumped when realigning a region, not dumping it");
+    }
+    let heuristic = options.search_heuristic(search_mode)?;
+
+    let residue_counts = |columns: &[Vec<Option<AlphabetType::CharacterType>>]| {
+        let mut counts = vec![0; sequences.len()];
+        for column in columns {
+            for (count, entry) in counts.iter_mut().zip(column) {
+                *count += usize::from(entry.is_some());
+            }
+        }
+        counts
+    };
+    let starts = residue_counts(&columns[..region.start]);
+    let region_sequences: Vec<_> = sequences
+        .iter()
+        .zip(&starts)
+        .zip(residue_counts(&columns[region.clone()]))
+        .map(|((sequence, start), length)| &sequence[*start..*start + length])
+        .collect();
+
+    let start_time = Instant::now();
+    let output = search_columns::<_, _, _, Cost>(
+        &region_sequences,
+        metric.clone(),
+        Segment {
+            starts: &starts,
+            is_start: region.start == 0,
+            is_end: region.end == columns.len(),
+            full_length_sequence: full_length_sequence(
+                sequences.iter().map(|sequence| sequence.len()),
+            ),
+        },
+        search_mode,
+        heuristic,
+        false,
+        SearchControl {
+            cancellation: &options.cancellation,
+            on_improvement: &mut |_, _, _, _| Ok(()),
+            memory_limit: options.memory_limit,
+            memory_limit_fallback: options.memory_limit_fallback,
+            upper_bound_pruning: options.upper_bound(),
+            band: None,
+            tie_break: options.tie_break,
+            progress_interval: options.progress_interval,
+            dominance_pruning: options.dominance_pruning,
+            spill: options.spill.as_ref(),
+        },
+    )?;
+    let (path, region_cost) = &output.alignments[0];
+    let realigned: AlignmentColumns<_> = columns[..region.start]
+        .iter()
+        .cloned()
+        .chain(residue_columns(path, &region_sequences))
+        .chain(columns[region.end..].iter().cloned())
+        .collect();
+    let duration = start_time.elapsed();
+
+    let original_cost: Cost = score_alignment(columns, &mut metric)?;
+    let realigned_cost: Cost = score_alignment(&realigned, &mut metric)?;
+    info!("Original alignment cost {}", original_cost);
+    let (columns, cost) = if realigned_cost <= original_cost {
+        (realigned, realigned_cost)
+    } else {
+        warn!("The realigned region costs more in the context of the whole alignment, keeping the original alignment");
+        (columns.to_vec(), original_cost)
+    };
+    // The alignment is optimal among those that keep the columns outside of the region, up to the slack of the search.
+    let cost_lower_bound = cost.saturating_sub(&(*region_cost - output.cost_lower_bound).min(cost));
+
+    info!("Alignment cost {}", cost);
+    log_suboptimality_bound(cost, cost_lower_bound);
+    info!("Runtime: {:.2}s", duration.as_secs_f64());
+    info!("Performance: {:?}", output.performance_counters);
+    if let Some(statistics) = &options.statistics {
+        append_statistics(
+            statistics,
+            &columns,
+            cost,
+            duration,
+            &output.performance_counters,
+        )?;
+    }
+    let output_start = Instant::now();
+    output_column_costs::<_, Cost, _>(&columns, &mut metric, &options, None)?;
+
+    let alignment = Alignment {
+        columns,
+        cost,
+        cost_lower_bound,
+        search_statistics: SearchStatistics::new(&output.performance_counters, duration),
+    };
+    output_alignment::<AlphabetType, _>(&alignment, duration, &options, None)?;
+    if let Some(statistics_json) = &options.statistics_json {
+        write_statistics_json(
+            statistics_json,
+            &alignment,
+            &output.details,
+            output_start.elapsed(),
+        )?;
+    }
+    Ok(alignment)
+}
+
+/// Log the guarantee on the cost of the alignment if it may not be optimal.
+fn log_suboptimality_bound<Cost: AStarCost>(cost: Cost, cost_lower_bound: Cost) {
+    if cost_lower_bound < cost {
+        info!(
+            "The optimal cost is at least {cost_lower_bound}, so the alignment costs at most {:.3} times as much",
+            suboptimality_bound(cost, cost_lower_bound)
+        );
+    }
+}
+
+/// Log the average number of successors per expanded node and, if the depth of the solution is given, the effective branching factor.
+///
+/// The effective branching factor is the branching factor `b` of a uniform tree of the given depth that contains as many nodes as were generated,
+/// i.e. it solves `generated = b + b^2 + ... + b^depth`.
+/// The closer it is to one, the better the search is guided towards the target.
+fn log_branching_factor(performance_counters: &AStarPerformanceCounters, depth: Option<usize>) {
+    let generated = performance_counters.opened_nodes as f64;
+    let expanded = performance_counters.closed_nodes as f64;
+    if expanded > 0.0 {
+        info!("Successors per expansion: {:.3}", generated / expanded);
+    }
+
+    let Some(depth) = depth.filter(|depth| *depth > 0) else {
+        return;
+    };
+    info!(
+        "Effective branching factor: {:.3} at depth {depth}",
+        effective_branching_factor(performance_counters.opened_nodes, depth)
+    );
+}
+
+/// Returns the branching factor `b` of a uniform tree of the given depth that contains as many nodes as were generated, see [`log_branching_factor`].
+fn effective_branching_factor(generated: usize, depth: usize) -> f64 {
+    let generated = generated as f64;
+    let depth = i32::try_from(depth).unwrap_or(i32::MAX);
+    let tree_size = |b: f64| (1..=depth).map(|level| b.powi(level)).sum::<f64>();
+
+    // The tree size grows monotonically with the branching factor, so we can find it by bisection.
+    let mut lower = 0.0;
+    let mut upper = generated.max(1.0);
+    for _ in 0..100 {
+        let middle = (lower + upper) / 2.0;
+        if tree_size(middle) < generated {
+            lower = middle;
+        } else {
+            upper = middle;
+        }
+    }
+    upper
+}
+
+/// The search algorithm used to find alignments, and the kind of its open list.
+#[derive(Debug, Clone, Copy)]
+enum SearchMode {
+    /// Find an optimal alignment with [`AStar`], or with a dynamic programming algorithm for two sequences.
+    ///
+    /// Since [`AStar`] always uses a binary heap, a [`KBestSearch`] for a single alignment is used instead if a bucket queue is requested.
+    AStar(OpenListKind),
+    /// Find an optimal alignment with [`RederivingSearch`].
+    RederivePredecessors(OpenListKind),
+    /// Find the given amount of cheapest alignments with [`KBestSearch`].
+    KBest(usize, OpenListKind),
+    /// Find alignments of decreasing cost with [`AnytimeSearch`] until one is optimal.
+    Anytime(OpenListKind),
+    /// Find an optimal alignment with [`IdaStarSearch`], which has no open list.
+    IdaStar,
+    /// Find an alignment that is not necessarily optimal with [`BeamSearch`] of the given width.
+    Beam(usize),
+    /// Find an optimal alignment with [`ParallelSearch`] using the given amount of threads.
+    Parallel(usize, OpenListKind),
+}
+
+/// Receives each improved alignment of an [`AnytimeSearch`] as it is found,
+/// with its cost, a lower bound of the optimal cost and the statistics of the search so far.
+type Improvements<'callback, Cost> =
+    &'callback mut dyn FnMut(&AlignmentPath, Cost, Cost, &AStarPerformanceCounters) -> Result<()>;
+
+/// Allows to interact with a running search.
+struct SearchControl<'control, Cost> {
+    /// Cancels the search, see [`MultialignOptions::cancellation`].
+    cancellation: &'control CancellationToken,
+    /// Receives the alignments found before the search ends, which is only done by [`SearchMode::Anytime`].
+    on_improvement: Improvements<'control, Cost>,
+    /// Limits the memory of the search, see [`MultialignOptions::memory_limit`].
+    memory_limit: Option<MemoryLimit>,
+    /// Continue with [`SearchMode::IdaStar`] if the memory limit is exceeded, see [`MultialignOptions::memory_limit_fallback`].
+    memory_limit_fallback: bool,
+    /// Prune the search with the cost of a quickly found alignment, see [`MultialignOptions::upper_bound_pruning`].
+    upper_bound_pruning: Option<UpperBound>,
+    /// Writes the open and closed list of [`SearchMode::RederivePredecessors`] to disk, see [`MultialignOptions::spill`].
+    spill: Option<&'control SpillOptions>,
+    /// Restricts the search to a band around a guide alignment, see [`MultialignOptions::band`].
+    band: Option<Arc<Band>>,
+    /// Orders the nodes of equal priority, see [`MultialignOptions::tie_break`].
+    tie_break: TieBreak,
+    /// Logs the progress of the search periodically, see [`MultialignOptions::progress_interval`].
+    progress_interval: Option<Duration>,
+    /// Prune the nodes dominated by closed nodes, see [`MultialignOptions::dominance_pruning`].
+    dominance_pruning: bool,
+}
+
+/// The position of the aligned sequences within the input sequences, which differ when aligning the segments between anchors.
+#[derive(Debug, Clone, Copy)]
+struct Segment<'starts> {
+    /// The offset of each sequence within its input sequence, which is added to the offsets passed on to the metric.
+    ///
+    /// If this is empty, then all sequences start at offset zero.
+    starts: &'starts [usize],
+    /// True if the segment starts at the start of the input sequences, such that gaps before its first residues are terminal.
+    is_start: bool,
+    /// True if the segment ends at the end of the input sequences, such that gaps after its last residues are terminal.
+    is_end: bool,
+    /// The index of the sequence whose end gaps are not terminal gaps, see [`full_length_sequence`].
+    full_length_sequence: usize,
+}
+
+impl Segment<'_> {
+    /// The input sequences as a whole.
+    fn whole(full_length_sequence: usize) -> Self {
+        Self {
+            starts: &[],
+            is_start: true,
+            is_end: true,
+            full_length_sequence,
+        }
+    }
+
+    /// Returns the offset of the given sequence within its input sequence.
+    fn start(&self, sequence_index: usize) -> usize {
+        self.starts.get(sequence_index).copied().unwrap_or(0)
+    }
+}
+
+/// Returns the index of the first longest of the sequences with the given lengths.
+///
+/// With free end gaps, the end gaps of this sequence are still penalised, such that the other sequences are aligned as fragments of it.
+/// Otherwise, placing all sequences next to each other, such that each pair of residues is aligned with a terminal gap, would be free.
+fn full_length_sequence(lengths: impl IntoIterator<Item = usize>) -> usize {
+    let mut full_length_sequence = 0;
+    let mut max_length = 0;
+    for (index, length) in lengths.into_iter().enumerate() {
+        if length > max_length {
+            full_length_sequence = index;
+            max_length = length;
+        }
+    }
+    full_length_sequence
+}
+
+/// The result of a single search.
+struct SearchOutput<Cost> {
+    /// The alignments found, ordered by cost.
+    alignments: Vec<(AlignmentPath, Cost)>,
+    /// A lower bound of the optimal cost, which is the cost of the first alignment unless the search was weighted.
+    cost_lower_bound: Cost,
+    performance_counters: AStarPerformanceCounters,
+    /// The nodes closed by the search, if requested and supported by the search mode.
+    search_graph: Option<SearchGraph<Cost>>,
+    details: SearchDetails<Cost>,
+}
+
+/// Search for an optimal alignment of the given sequences, or for multiple cheapest alignments depending on the search mode.
+///
+/// The `segment` locates the sequences within the input sequences when aligning between anchors.
+fn search_columns<
+    AlphabetType: Alphabet + Debug + Clone + Eq + 'static,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized + Sync,
+    Metric: MultialignMetric<AlphabetType> + Clone + Send,
+    Cost: MultialignCost,
+>(
+    sequences: &[&SequenceType],
+    mut metric: Metric,
+    segment: Segment<'_>,
+    search_mode: SearchMode,
+    heuristic: SearchHeuristic,
+    record_search_graph: bool,
+    control: SearchControl<'_, Cost>,
+) -> Result<SearchOutput<Cost>>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    let max_sequence_amount = usize::try_from(usize::BITS - 1).unwrap();
+    if sequences.len() > max_sequence_amount {
+        return Err(MultialignError::TooManySequences {
+            amount: sequences.len(),
+            max: max_sequence_amount,
+        });
+    }
+    check_cost_type::<_, Cost, _, _>(sequences, &mut metric, segment)?;
+
+    match sequences.len() {
+        amount @ (0 | 1) => Err(MultialignError::TooFewSequences { amount }),
+        2 if matches!(search_mode, SearchMode::AStar(_))
+            && !record_search_graph
+            && control.band.is_none()
+            && fits_memory_limit::<Cost>(
+                sequences[0].len(),
+                sequences[1].len(),
+                metric.has_affine_gaps(),
+                control.memory_limit,
+            ) =>
+        {
+            align_two_sequences(sequences, metric, segment, control.cancellation)
+        }
+        // Each specialised identifier compiles the search once more, so only the default search
+        // for small amounts of sequences is specialised to keep the binary small enough to build.
+        _ if !Cost::SPECIALISED_IDENTIFIERS
+            || !matches!(search_mode, SearchMode::AStar(OpenListKind::Heap)) =>
+        {
+            multialign_astar_with_identifier::<AlphabetType, SequenceType, VecIdentifier, _, Cost>(
+                sequences,
+                metric,
+                segment,
+                search_mode,
+                heuristic,
+                record_search_graph,
+                control,
+            )
+        }
+        2 => multialign_astar_with_identifier::<
+            AlphabetType,
+            SequenceType,
+            ArrayIdentifier<2>,
+            _,
+            Cost,
+        >(
+            sequences,
+            metric,
+            segment,
+            search_mode,
+            heuristic,
+            record_search_graph,
+            control,
+        ),
+        3 => multialign_astar_with_identifier::<
+            AlphabetType,
+            SequenceType,
+            ArrayIdentifier<3>,
+            _,
+            Cost,
+        >(
+            sequences,
+            metric,
+            segment,
+            search_mode,
+            heuristic,
+            record_search_graph,
+            control,
+        ),
+        4 => multialign_astar_with_identifier::<
+            AlphabetType,
+            SequenceType,
+            ArrayIdentifier<4>,
+            _,
+            Cost,
+        >(
+            sequences,
+            metric,
+            segment,
+            search_mode,
+            heuristic,
+            record_search_graph,
+            control,
+        ),
+        5 => multialign_astar_with_identifier::<
+            AlphabetType,
+            SequenceType,
+            ArrayIdentifier<5>,
+            _,
+            Cost,
+        >(
+            sequences,
+            metric,
+            segment,
+            search_mode,
+            heuristic,
+            record_search_graph,
+            control,
+        ),
+        6 => multialign_astar_with_identifier::<
+            AlphabetType,
+            SequenceType,
+            ArrayIdentifier<6>,
+            _,
+            Cost,
+        >(
+            sequences,
+            metric,
+            segment,
+            search_mode,
+            heuristic,
+            record_search_graph,
+            control,
+        ),
+        7 => multialign_astar_with_identifier::<
+            AlphabetType,
+            SequenceType,
+            ArrayIdentifier<7>,
+            _,
+            Cost,
+        >(
+            sequences,
+            metric,
+            segment,
+            search_mode,
+            heuristic,
+            record_search_graph,
+            control,
+        ),
+        8 => multialign_astar_with_identifier::<
+            AlphabetType,
+            SequenceType,
+            ArrayIdentifier<8>,
+            _,
+            Cost,
+        >(
+            sequences,
+            metric,
+            segment,
+            search_mode,
+            heuristic,
+            record_search_graph,
+            control,
+        ),
+        _ => {
+            multialign_astar_with_identifier::<AlphabetType, SequenceType, VecIdentifier, _, Cost>(
+                sequences,
+                metric,
+                segment,
+                search_mode,
+                heuristic,
+                record_search_graph,
+                control,
+            )
+        }
+    }
+}
+
+fn multialign_astar_with_identifier<
+    AlphabetType: Alphabet + Debug + Clone + Eq + 'static,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized + Sync,
+    Identifier: NodeIdentifier,
+    Metric: MultialignMetric<AlphabetType> + Clone + Send,
+    Cost: MultialignCost,
+>(
+    sequences: &[&SequenceType],
+    mut metric: Metric,
+    segment: Segment<'_>,
+    search_mode: SearchMode,
+    search_heuristic: SearchHeuristic,
+    record_search_graph: bool,
+    control: SearchControl<'_, Cost>,
+) -> Result<SearchOutput<Cost>>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    let heuristic_kind = search_heuristic.kind;
+    let preprocessing_start = Instant::now();
+    let heuristic = if allows_lower_bounds(&metric) {
+        let start_time = Instant::now();
+        let heuristic = Arc::new(Heuristic::new(
+            sequences,
+            &mut metric,
+            heuristic_kind,
+            search_heuristic.gpu,
+        )?);
+        debug!(
+            "Computed {heuristic_kind:?} lower bounds in {:.2}s",
+            start_time.elapsed().as_secs_f64()
+        );
+        Some(heuristic)
+    } else {
+        debug!("The metric does not allow to compute lower bounds, so the search uses none");
+        None
+    };
+    // The anytime search computes its own lower bounds of the optimal cost.
+    let weighted = heuristic.is_some()
+        && search_heuristic.weight != 1.0
+        && !matches!(search_mode, SearchMode::Anytime(_) | SearchMode::Beam(_));
+    // The nodes outside of the band are not searched, so only the lower bound of the root is a lower bound of the optimal cost.
+    let banded = control.band.is_some();
+    let context = Context::<_, Cost, _, Identifier, _>::new(
+        sequences,
+        metric,
+        segment,
+        heuristic,
+        search_heuristic.weight,
+    );
+    let root_lower_bound = context.unweighted_lower_bound(&context.create_root().identifier);
+    let preprocessing_runtime = preprocessing_start.elapsed();
+
+    let mut output = match search_with_context(context, search_mode, record_search_graph, control) {
+        // The weighted lower bounds of the open nodes are no lower bounds of the optimal cost.
+        Err(MultialignError::Cancelled { closed_nodes, .. }) if weighted || banded => {
+            return Err(MultialignError::Cancelled {
+                closed_nodes,
+                cost_lower_bound: Some(root_lower_bound.as_u64()),
+            });
+        }
+        Err(MultialignError::ExceededMemoryLimit { closed_nodes, .. }) if weighted || banded => {
+            return Err(MultialignError::ExceededMemoryLimit {
+                closed_nodes,
+                cost_lower_bound: Some(root_lower_bound.as_u64()),
+            });
+        }
+        output => output?,
+    };
+
+    if weighted {
+        // The alignment costs at most the weight times the optimal cost.
+        let cost = output.alignments[0].1.as_f64();
+        let weighted_lower_bound = (cost / search_heuristic.weight).ceil();
+        output.cost_lower_bound = i32::try_from(weighted_lower_bound as i64)
+            .ok()
+            .and_then(|lower_bound| metric::cost_from_i32::<Cost>(lower_bound).ok())
+            .map_or(root_lower_bound, |lower_bound| {
+                lower_bound.max(root_lower_bound)
+            })
+            .min(output.alignments[0].1);
+    }
+    if banded {
+        output.cost_lower_bound = root_lower_bound.min(output.alignments[0].1);
+    }
+    output.details.root_lower_bound = Some(root_lower_bound);
+    output.details.preprocessing_runtime = preprocessing_runtime;
+
+    Ok(output)
+}
+
+/// Returns true if the lower bounds of the [`Heuristic`] can be computed for the metric.
+fn allows_lower_bounds<AlphabetType: Alphabet, Metric: MultialignMetric<AlphabetType>>(
+    metric: &Metric,
+) -> bool {
+    metric.is_sum_of_pairs()
+        && !metric.has_affine_gaps()
+        && !metric.has_free_end_gaps()
+        && !metric.uses_offsets()
+}
+
+/// Run the search selected by the search mode on the given context.
+fn search_with_context<
+    AlphabetType: Alphabet,
+    Cost: MultialignCost,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized + Sync,
+    Identifier: NodeIdentifier,
+    Metric: MultialignMetric<AlphabetType> + Clone + Send,
+>(
+    mut context: Context<'_, AlphabetType, Cost, SequenceType, Identifier, Metric>,
+    search_mode: SearchMode,
+    record_search_graph: bool,
+    control: SearchControl<'_, Cost>,
+) -> Result<SearchOutput<Cost>>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    let sequences = context.sequences;
+    let SearchControl {
+        cancellation,
+        on_improvement,
+        memory_limit,
+        memory_limit_fallback,
+        spill,
+        upper_bound_pruning,
+        band,
+        tie_break,
+        progress_interval,
+        dominance_pruning,
+    } = control;
+    context.band = band;
+    context.tie_break = tie_break;
+
+    // `AStar` always uses a binary heap, and a k-best search for a single alignment is equivalent to it.
+    let search_mode = match search_mode {
+        SearchMode::AStar(OpenListKind::Bucket) => SearchMode::KBest(1, OpenListKind::Bucket),
+        search_mode => search_mode,
+    };
+
+    // Pruning would remove the more expensive alignments of a k-best search, and a beam search is not sped up by a beam search.
+    let upper_bound_pruning = upper_bound_pruning.filter(|_| {
+        !matches!(search_mode, SearchMode::Beam(_))
+            && !matches!(search_mode, SearchMode::KBest(k, _) if k > 1)
+    });
+    match upper_bound_pruning {
+        // The progressive alignment may leave the band, such that its cost is no upper bound of the alignments within the band.
+        Some(UpperBound::Progressive(guide_tree)) if context.band.is_none() => {
+            let sequence_characters: Vec<Vec<_>> = sequences
+                .iter()
+                .map(|sequence| sequence.iter().cloned().collect())
+                .collect();
+            let guide_tree = kmer_guide_tree(&sequence_characters, &guide_tree)?;
+            let (columns, _) = progressive_alignment::<_, Cost, _, _>(
+                sequences,
+                &guide_tree,
+                &mut context.metric.clone(),
+                cancellation,
+            )?;
+            // The context scores the columns exactly, also with affine gaps, free end gaps or anchors.
+            let cost = context.clone().path_cost(columns.iter().map(|column| {
+                column
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, entry)| entry.is_some())
+                    .fold(0, |mask, (index, _)| mask | (1 << index))
+            }))?;
+            debug!("Found an alignment of cost {cost} by progressive alignment, pruning the nodes that cannot lead to a cheaper one");
+            context.cost_upper_bound = Some(cost);
+        }
+        Some(_) => {
+            let mut beam_context = context.clone();
+            let result =
+                BeamSearch::new(UPPER_BOUND_BEAM_WIDTH).search(&mut beam_context, cancellation)?;
+            beam_context.take_error()?;
+            if let Some(cost) = result.and_then(|edges| edges.first().map(|edge| edge.cost)) {
+                debug!("Found an alignment of cost {cost} with a beam search, pruning the nodes that cannot lead to a cheaper one");
+                context.cost_upper_bound = Some(cost);
+            }
+        }
+        None => {}
+    }
+    if let Some(interval) = progress_interval.filter(|interval| !interval.is_zero()) {
+        context.progress = Some(Progress::new(
+            interval,
+            context.create_root().priority().as_f64(),
+            context.cost_upper_bound.map(|cost| cost.as_f64()),
+        ));
+    }
+    // Searches that expand nodes more than once or that need more than the cheapest path to a node would lose alignments,
+    // and the threads of a parallel search would count their pruned nodes separately.
+    if dominance_pruning
+        && matches!(
+            search_mode,
+            SearchMode::AStar(_)
+                | SearchMode::RederivePredecessors(_)
+                | SearchMode::Anytime(_)
+                | SearchMode::KBest(1, _)
+        )
+    {
+        context.dominance = Some(DominanceTable::new());
+    }
+
+    match search_mode {
+        // Since the guard is a constant, these searches are not compiled for specialised identifiers.
+        SearchMode::Anytime(_)
+        | SearchMode::Beam(_)
+        | SearchMode::Parallel(..)
+        | SearchMode::RederivePredecessors(_)
+        | SearchMode::KBest(..)
+            if Identifier::SPECIALISED =>
+        {
+            unreachable!("Specialised identifiers are only used by the A* search")
+        }
+        SearchMode::Anytime(open_list_kind) => {
+            let mut search = AnytimeSearch::new(open_list_kind);
+            let AnytimeAlignment {
+                edges,
+                cost,
+                cost_lower_bound,
+            } = search.search(&mut context, cancellation, on_improvement)?;
+            Ok(SearchOutput {
+                alignments: vec![(reconstruct_path(sequences.len(), edges), cost)],
+                cost_lower_bound,
+                performance_counters: search.into_performance_counters(),
+                search_graph: None,
+                details: context.search_details(),
+            })
+        }
+        SearchMode::IdaStar => {
+            let mut search = IdaStarSearch::new();
+            let result = search.search(&mut context, cancellation)?;
+            context.take_error()?;
+            let Some(edges) = result else {
+                unreachable!("Search always finds a target");
+            };
+
+            let cost = edges.first().map_or(Cost::zero(), |edge| edge.cost);
+            Ok(SearchOutput {
+                alignments: vec![(reconstruct_path(sequences.len(), edges), cost)],
+                cost_lower_bound: cost,
+                performance_counters: search.into_performance_counters(),
+                search_graph: None,
+                details: context.search_details(),
+            })
+        }
+        SearchMode::Beam(width) => {
+            let root = context.create_root();
+            let mut search = BeamSearch::new(width);
+            let result = search.search(&mut context, cancellation)?;
+            context.take_error()?;
+            let Some(edges) = result else {
+                unreachable!("Search always finds a target");
+            };
+
+            let cost = edges.first().map_or(Cost::zero(), |edge| edge.cost);
+            Ok(SearchOutput {
+                alignments: vec![(reconstruct_path(sequences.len(), edges), cost)],
+                // Only the lower bound of the root is known to be a lower bound of the optimal cost.
+                cost_lower_bound: context.unweighted_lower_bound(&root.identifier).min(cost),
+                performance_counters: search.into_performance_counters(),
+                search_graph: None,
+                details: context.search_details(),
+            })
+        }
+        SearchMode::Parallel(thread_amount, open_list_kind) => {
+            let mut search = ParallelSearch::new(thread_amount, open_list_kind);
+            let result = search.search(&mut context, cancellation)?;
+            let Some(edges) = result else {
+                unreachable!("Search always finds a target");
+            };
+
+            let cost = edges.first().map_or(Cost::zero(), |edge| edge.cost);
+            Ok(SearchOutput {
+                alignments: vec![(reconstruct_path(sequences.len(), edges), cost)],
+                cost_lower_bound: cost,
+                performance_counters: search.into_performance_counters(),
+                search_graph: None,
+                // The threads count their open nodes separately.
+                details: Default::default(),
+            })
+        }
+        SearchMode::RederivePredecessors(open_list_kind) => {
+            let mut search = RederivingSearch::new(
+                NodeOpenList::new(open_list_kind, spill, sequences.len()),
+                ClosedList::new(spill, sequences.len()),
+            );
+            let result = search.search(&mut context, cancellation)?;
+            context.take_error()?;
+            let Some((target, cost)) = result else {
+                unreachable!("Search always finds a target");
+            };
+
+            let backtrack_start = Instant::now();
+            let details = context.search_details();
+            // Backtracking generates the successors of closed nodes again, which would be pruned as dominated by themselves.
+            context.dominance = None;
+            let affine_gaps = context.affine_gaps;
+            let edges = search.backtrack(&mut context, target, sequences.len(), affine_gaps)?;
+            let path = reconstruct_path(sequences.len(), edges);
+            Ok(SearchOutput {
+                alignments: vec![(path, cost)],
+                cost_lower_bound: cost,
+                performance_counters: search.into_performance_counters(),
+                search_graph: None,
+                details: SearchDetails {
+                    backtrack_runtime: backtrack_start.elapsed(),
+                    ..details
+                },
+            })
+        }
+        SearchMode::KBest(k, open_list_kind) => {
+            let mut search = KBestSearch::new(open_list_kind);
+            let paths = search.search(&mut context, k, cancellation)?;
+            context.take_error()?;
+            let alignments: Vec<_> = paths
+                .into_iter()
+                .map(|edges| {
+                    let cost = edges.first().map(|edge| edge.cost).unwrap_or(Cost::zero());
+                    (reconstruct_path(sequences.len(), edges), cost)
+                })
+                .collect();
+
+            Ok(SearchOutput {
+                cost_lower_bound: alignments[0].1,
+                alignments,
+                performance_counters: search.into_performance_counters(),
+                search_graph: None,
+                details: context.search_details(),
+            })
+        }
+        SearchMode::AStar(_) => {
+            context.memory_limit = memory_limit.map(MemoryLimit::bytes::<Node<Identifier, Cost>>);
+            let mut a_star = AStar::new(context);
+            a_star.initialise();
+
+            let mut closed_nodes = Vec::new();
+            let mut cancelled_at = None;
+            let mut last_priority = None;
+            let result = a_star.search_until(|context, node| {
+                if context.error.is_some() {
+                    return true;
+                }
+                if record_search_graph {
+                    closed_nodes.push(node.clone());
+                }
+                last_priority = Some(node.priority());
+                if cancellation.is_cancelled() {
+                    cancelled_at = Some(node.priority());
+                    return true;
+                }
+                context.is_target(node)
+            });
+            if a_star.context().error.is_some() {
+                return Err(a_star.into_context().error.unwrap());
+            }
+            if let Some(cost) = cancelled_at {
+                return Err(MultialignError::Cancelled {
+                    closed_nodes: a_star.performance_counters().closed_nodes,
+                    cost_lower_bound: Some(cost.as_u64()),
+                });
+            }
+
+            let cost = match result {
+                AStarResult::FoundTarget { cost, .. } => cost,
+                AStarResult::ExceededCostLimit { .. } => unreachable!("No cost limit set"),
+                AStarResult::ExceededMemoryLimit { .. } if memory_limit_fallback => {
+                    let closed_nodes = a_star.performance_counters().closed_nodes;
+                    warn!("Exceeded the memory limit after closing {closed_nodes} nodes, continuing with IDA* search");
+                    let mut context = a_star.into_context();
+                    context.memory_limit = None;
+                    // The nodes closed by the A* search are not closed again by the IDA* search, which would then miss the nodes they dominate.
+                    context.dominance = None;
+                    let band = context.band.clone();
+                    let tie_break = context.tie_break;
+                    let progress_interval = context.progress.as_ref().map(Progress::interval);
+                    return search_with_context(
+                        context,
+                        SearchMode::IdaStar,
+                        false,
+                        SearchControl {
+                            cancellation,
+                            on_improvement,
+                            memory_limit: None,
+                            memory_limit_fallback: false,
+                            // The context keeps the upper bound that was already computed.
+                            upper_bound_pruning: None,
+                            band,
+                            tie_break,
+                            progress_interval,
+                            dominance_pruning: false,
+                            spill: None,
+                        },
+                    );
+                }
+                AStarResult::ExceededMemoryLimit { .. } => {
+                    // Nodes are closed in order of increasing priority.
+                    return Err(MultialignError::ExceededMemoryLimit {
+                        closed_nodes: a_star.performance_counters().closed_nodes,
+                        cost_lower_bound: last_priority.map(|priority| priority.as_u64()),
+                    });
+                }
+                AStarResult::NoTarget => unreachable!("Search always finds a target"),
+            };
+
+            let backtrack_start = Instant::now();
+            let details = a_star.context().search_details();
+            let edges: Vec<_> = a_star.backtrack().collect();
+            let search_graph = record_search_graph.then(|| {
+                let optimal_path: HashSet<_> = edges
+                    .iter()
+                    .flat_map(|edge| [Some(&edge.identifier), edge.predecessor.as_ref()])
+                    .flatten()
+                    .collect();
+                let offsets = |identifier: &Identifier| {
+                    (0..sequences.len())
+                        .map(|index| identifier.offset(index))
+                        .collect()
+                };
+
+                SearchGraph {
+                    nodes: closed_nodes
+                        .iter()
+                        .map(|node| SearchGraphNode {
+                            offsets: offsets(&node.identifier),
+                            cost: node.cost,
+                            lower_bound: node.a_star_lower_bound(),
+                            predecessor: node.predecessor.as_ref().map(offsets),
+                            on_optimal_path: optimal_path.contains(&node.identifier),
+                        })
+                        .collect(),
+                }
+            });
+            let path = reconstruct_path(sequences.len(), edges);
+            let backtrack_runtime = backtrack_start.elapsed();
+            let performance_counters = a_star.performance_counters();
+            Ok(SearchOutput {
+                alignments: vec![(path, cost)],
+                cost_lower_bound: cost,
+                performance_counters: AStarPerformanceCounters {
+                    opened_nodes: performance_counters.opened_nodes,
+                    suboptimal_opened_nodes: performance_counters.suboptimal_opened_nodes,
+                    closed_nodes: performance_counters.closed_nodes,
+                },
+                search_graph,
+                details: SearchDetails {
+                    backtrack_runtime,
+                    ..details
+                },
+            })
+        }
+    }
+}
+
+/// Write the cost increment of each column of the alignment under the given metric, if requested by the options.
+///
+/// If a rank is given, it is inserted into the name of the output file, to distinguish multiple alignments.
+fn output_column_costs<
+    AlphabetType: Alphabet,
+    Cost: AStarCost,
+    Metric: MultialignMetric<AlphabetType>,
+>(
+    columns: &[Vec<Option<AlphabetType::CharacterType>>],
+    metric: &mut Metric,
+    options: &MultialignOptions,
+    rank: Option<usize>,
+) -> Result<()>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    let Some(path) = &options.column_costs else {
+        return Ok(());
+    };
+    let path = match rank {
+        Some(rank) => ranked_path(path, rank),
+        None => path.clone(),
+    };
+
+    write_column_costs(path, &column_costs::<_, Cost, _>(columns, metric)?)
+}
+
+/// Log the hash of the alignment and then output it in the order requested by the options.
+///
+/// The cost and runtime are written into output formats that support metadata.
+/// If a rank is given, it is inserted into the name of the output file, to distinguish multiple alignments.
+fn output_alignment<AlphabetType: Alphabet, Cost: AStarCost>(
+    alignment: &Alignment<AlphabetType::CharacterType, Cost>,
+    runtime: Duration,
+    options: &MultialignOptions,
+    rank: Option<usize>,
+) -> Result<()> {
+    info!(
+        "Alignment hash: {:016x}",
+        alignment_hash(&alignment.columns)
+    );
+
+    let sequence_amount = alignment.sequence_amount();
+    let columns = alignment.columns.clone();
+    let cost = alignment.cost;
+    let (columns, kept_rows) = match options.min_identity_to_consensus {
+        Some(min_identity) => filter_by_identity_to_consensus(columns, min_identity),
+        None => (columns, (0..sequence_amount).collect()),
+    };
+    if kept_rows.is_empty() {
+        warn!("All sequences were removed from the alignment");
+        return Ok(());
+    } else if kept_rows.len() < sequence_amount {
+        info!(
+            "Kept {} of {} sequences with {} columns",
+            kept_rows.len(),
+            sequence_amount,
+            columns.len()
+        );
+    }
+
+    if options.report_scores {
+        report_scores::<AlphabetType>(&columns)?;
+    }
+
+    if let Some(frame_annotation) = &options.frame_annotation {
+        if let Some(reference) = kept_rows
+            .iter()
+            .position(|&row| row == frame_annotation.reference)
+        {
+            let frame_annotation = FrameAnnotation {
+                reference,
+                ..frame_annotation.clone()
+            };
+            info!("#=GC frame {}", annotate_frame(&columns, &frame_annotation));
+        } else {
+            warn!("Not annotating the reading frame because the frame reference was removed");
+        }
+    }
+
+    let (columns, rows) = match options.output_order {
+        OutputOrder::Input => (columns, kept_rows),
+        OutputOrder::Tree => {
+            let order = neighbor_joining(&alignment_distance_matrix(&columns)).leaf_order();
+            let rows: Vec<_> = order.iter().map(|&row| kept_rows[row]).collect();
+            info!("Output row order: {rows:?}");
+            let columns = columns
+                .into_iter()
+                .map(|column| order.iter().map(|&row| column[row].clone()).collect())
+                .collect();
+            (columns, rows)
+        }
+    };
+
+    debug!(
+        "Alignment: {}",
+        format_cigar(&columns, &options.gap_characters)
+    );
+
+    let mut alignment = OutputAlignment {
+        ids: rows
+            .iter()
+            .map(|&row| {
+                options
+                    .record_ids
+                    .get(row)
+                    .cloned()
+                    .unwrap_or_else(|| row.to_string())
+            })
+            .collect(),
+        rows: (0..rows.len())
+            .map(|row| {
+                let soft_mask = options
+                    .soft_masks
+                    .get(rows[row])
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                let mut offset = 0;
+                columns
+                    .iter()
+                    .map(|column| {
+                        column[row].clone().map(|character| {
+                            let character: char = character.into();
+                            let is_soft_masked = soft_mask.get(offset).copied().unwrap_or(false);
+                            offset += 1;
+                            if is_soft_masked {
+                                character.to_ascii_lowercase()
+                            } else {
+                                character
+                            }
+                        })
+                    })
+                    .collect()
+            })
+            .collect(),
+        columns: operation_columns(&columns),
+        // The alignment is global, so each row covers its whole sequence.
+        starts: vec![0; rows.len()],
+        source_lengths: (0..rows.len())
+            .map(|row| {
+                columns
+                    .iter()
+                    .filter(|column| column[row].is_some())
+                    .count()
+            })
+            .collect(),
+        reference: rows.iter().position(|&row| row == options.reference),
+        gap_characters: options.gap_characters,
+        residue_type: options.residue_type,
+        cost: cost.as_f64(),
+        metric_name: options.metric_name.clone(),
+        runtime,
+    };
+
+    if let Some(path) = &options.column_statistics {
+        let path = match rank {
+            Some(rank) => ranked_path(path, rank),
+            None => path.clone(),
+        };
+        write_column_statistics(path, &columns)?;
+    }
+
+    if let Some(consensus) = &options.consensus {
+        let row = consensus_row(&columns, consensus.rule, options.residue_type);
+        match &consensus.path {
+            Some(path) => {
+                let path = match rank {
+                    Some(rank) => ranked_path(path, rank),
+                    None => path.clone(),
+                };
+                write_consensus_file(path, &consensus.id, &row)?;
+            }
+            None => {
+                alignment.ids.push(consensus.id.clone());
+                alignment.starts.push(0);
+                alignment.source_lengths.push(row.iter().flatten().count());
+                alignment.rows.push(row);
+            }
+        }
+    }
+
+    match &options.output {
+        Some(output) => {
+            let path = match rank {
+                Some(rank) => ranked_path(output, rank),
+                None => output.clone(),
+            };
+            write_alignment_file(
+                path,
+                &alignment,
+                &options.output_format,
+                options.output_compression,
+            )
+        }
+        None => write_alignment_to_terminal(&alignment),
+    }
+}
+
+/// Insert the rank before the extension of the given path, e.g. `alignment.fa` becomes `alignment.2.fa`.
+///
+/// Compression extensions are kept at the end, e.g. `alignment.fa.gz` becomes `alignment.2.fa.gz`.
+fn ranked_path(path: &Path, rank: usize) -> PathBuf {
+    let compression_extension = path.extension().filter(|extension| {
+        COMPRESSION_EXTENSIONS
+            .iter()
+            .any(|other| *extension == *other)
+    });
+    let uncompressed_path = if compression_extension.is_some() {
+        path.with_extension("")
+    } else {
+        path.to_owned()
+    };
+
+    let mut file_name = uncompressed_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_os_string();
+    file_name.push(format!(".{rank}"));
+    for extension in [uncompressed_path.extension(), compression_extension]
+        .into_iter()
+        .flatten()
+    {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+    path.with_file_name(file_name)
+}
+
+/// Options for [`multialign_astar`].
+#[derive(Debug, Clone, Default)]
+pub struct MultialignOptions {
+    /// The characters used to render gaps in the output.
+    pub gap_characters: GapCharacters,
+
+    /// The algorithm that searches for an alignment.
+    ///
+    /// With [`SearchAlgorithm::IdaStar`] and [`SearchAlgorithm::Beam`], the options that select other searches are ignored,
+    /// i.e. [`Self::rederive_predecessors`], [`Self::k_best`], [`Self::anytime`] and [`Self::threads`].
+    pub search: SearchAlgorithm,
+
+    /// If set, the search stores no predecessors, and they are re-derived when backtracking.
+    ///
+    /// This roughly halves the memory per node at the cost of a longer runtime.
+    /// Every closed node is still stored, so the memory consumption does not stop growing under memory pressure.
+    pub rederive_predecessors: bool,
+
+    /// If set, the open and closed list of the search with [`Self::rederive_predecessors`] keep only some nodes in memory, and write the others to files.
+    ///
+    /// The lists of [`generic_a_star::AStar`] cannot be written to disk, so this implies [`Self::rederive_predecessors`] for the A* search.
+    /// It is ignored by the searches that select other algorithms, i.e. with [`Self::search`], [`Self::k_best`] or [`Self::anytime`].
+    pub spill: Option<SpillOptions>,
+
+    /// The order of the sequences in the output.
+    pub output_order: OutputOrder,
+
+    /// If set to more than one, the given amount of cheapest alignments is computed and output, ordered by cost.
+    ///
+    /// This stores all explored paths, so it cannot be combined with [`Self::rederive_predecessors`].
+    pub k_best: Option<usize>,
+
+    /// If set, the codon positions of the reading frame of a reference sequence are annotated in the output.
+    pub frame_annotation: Option<FrameAnnotation>,
+
+    /// If set, the sequences whose identity to the consensus is below this fraction are removed from the output.
+    ///
+    /// Columns that consist only of gaps after removing these sequences are removed as well.
+    pub min_identity_to_consensus: Option<f64>,
+
+    /// If set, the sum-of-pairs and total-column scores of the alignment are reported, independently of the metric used for aligning.
+    pub report_scores: bool,
+
+    /// The data structure used as open list of the search.
+    pub open_list: OpenListKind,
+
+    /// How nodes of equal priority are ordered in the open list.
+    ///
+    /// This is only used by the searches that order whole nodes, i.e. the A* search with [`OpenListKind::Heap`] and [`SearchAlgorithm::Beam`].
+    pub tie_break: TieBreak,
+
+    /// If set, the progress of the search is logged in this interval, with the amount of closed and opened nodes, the current priority,
+    /// and with [`Self::upper_bound_pruning`] an estimate of the remaining time.
+    ///
+    /// The progress of a search with [`Self::threads`] is not logged.
+    pub progress_interval: Option<Duration>,
+
+    /// If set, the search does not generate nodes that are dominated by a closed node, i.e. that have the same offsets,
+    /// cost at least as much and are in a gap in at most the same sequences.
+    ///
+    /// The alignment is still optimal, since each continuation of a dominated node costs at most as much after the closed node,
+    /// given that the metric only reports [`MultialignMetric::has_affine_gaps`] if opening a gap costs at least as much as extending it.
+    /// Without affine gaps, only nodes equal to closed nodes are dominated, which the searches would otherwise open and discard later.
+    /// The closed nodes are stored again for this, so the memory of the closed list grows.
+    /// The amount of pruned nodes is logged.
+    /// It is ignored by [`SearchAlgorithm::IdaStar`], [`SearchAlgorithm::Beam`], with [`Self::threads`], and when computing multiple alignments with [`Self::k_best`].
+    pub dominance_pruning: bool,
+
+    /// If set to more than one, the search is distributed over the given amount of threads, which each own the nodes with some of the hashes.
+    ///
+    /// This is ignored by the searches that select other algorithms, i.e. with [`Self::search`], [`Self::k_best`], [`Self::anytime`] or [`Self::rederive_predecessors`],
+    /// and when aligning with anchors, whose segments are already aligned in parallel.
+    pub threads: Option<usize>,
+
+    /// If set, the search stops with [`MultialignError::ExceededMemoryLimit`] when it stores more than the given amount of memory or nodes.
+    ///
+    /// This is only checked by the A* search with [`OpenListKind::Heap`], and only approximates the memory used by the open and closed list.
+    /// Two sequences are only aligned with the dynamic programming algorithm if its matrix fits into the limit, and with the A* search otherwise.
+    pub memory_limit: Option<MemoryLimit>,
+
+    /// If set, the search continues with [`SearchAlgorithm::IdaStar`] from the start when the [`Self::memory_limit`] is exceeded,
+    /// instead of returning an error.
+    pub memory_limit_fallback: bool,
+
+    /// If set, an alignment is first found with a beam search of width [`UPPER_BOUND_BEAM_WIDTH`],
+    /// and the search does not generate nodes whose cost plus lower bound exceeds its cost.
+    ///
+    /// The alignment is still optimal, since no node of an optimal alignment is pruned.
+    /// This reduces the amount of open nodes, but not the amount of closed nodes of the searches that close nodes by increasing lower bound.
+    /// It is ignored by [`SearchAlgorithm::Beam`] and when computing multiple alignments with [`Self::k_best`].
+    pub upper_bound_pruning: bool,
+
+    /// How the alignment whose cost prunes the search with [`Self::upper_bound_pruning`] is found.
+    pub upper_bound_source: UpperBoundSource,
+
+    /// How the guide tree of [`multialign_progressive`] and of [`UpperBoundSource::Progressive`] is constructed.
+    pub guide_tree: GuideTreeOptions,
+
+    /// The amount of rounds in which the alignment is refined after it was found, or zero to not refine it.
+    ///
+    /// In each round, the sequences are partitioned into two groups along each branch of the guide tree, see [`Self::guide_tree`],
+    /// and the two groups are realigned to each other, keeping the realignment if it is cheaper.
+    /// The refinement stops early after a round without improvement.
+    /// It only improves alignments that are not optimal, e.g. of a weighted or beam search or of [`multialign_progressive`].
+    /// It is ignored by [`multialign_astar_anchored`] and when computing multiple alignments with [`Self::k_best`].
+    pub refine: usize,
+
+    /// If set, the search only explores the nodes within this band around a guide alignment.
+    ///
+    /// The alignment is then only optimal among the alignments within the band, and the reported lower bound of the optimal cost is the lower bound of the root.
+    /// The lower bounds reported by the [`Self::anytime`] search before it ends only hold within the band.
+    pub band: Option<Arc<Band>>,
+
+    /// The lower bound of the remaining cost that guides the search, if the metric is a sum-of-pairs metric.
+    pub heuristic: HeuristicKind,
+
+    /// If set, the pairwise lower bounds of the [`Self::heuristic`] are computed on a GPU, which requires the `gpu` feature.
+    ///
+    /// The costs are computed as 32-bit integers, so the pairs of sequences whose costs may exceed this range are still aligned on the CPU,
+    /// like the triples of [`HeuristicKind::Triple`].
+    /// Without the feature or if there is no GPU, a warning is logged and all lower bounds are computed on the CPU.
+    pub gpu: bool,
+
+    /// If set, the lower bounds of the [`Self::heuristic`] are multiplied by this factor, which must be at least one.
+    ///
+    /// This makes the search explore fewer nodes, but the alignment is only guaranteed to cost at most this factor times the optimal cost,
+    /// see [`Alignment::suboptimality_bound`].
+    /// It is ignored when computing multiple alignments with [`Self::k_best`].
+    pub weight: Option<f64>,
+
+    /// If set, an anytime search is used that quickly finds an alignment and then keeps improving it until it is optimal.
+    ///
+    /// The search starts with the [`Self::weight`], or [`DEFAULT_ANYTIME_WEIGHT`] if none is set, and decreases it after each alignment found.
+    /// Each improved alignment is output as it is found, and when the [`Self::cancellation`] token is set, the best alignment found so far is returned.
+    /// It is ignored when computing multiple alignments with [`Self::k_best`].
+    pub anytime: bool,
+
+    /// If set, the alignment is written to this file in the [`Self::output_format`].
+    /// Otherwise, it is written to the standard output in a human-readable format.
+    ///
+    /// When computing multiple alignments, the rank of each alignment is inserted into the file name before the extension.
+    pub output: Option<PathBuf>,
+
+    /// The format of the output file.
+    pub output_format: OutputFormat,
+
+    /// The compression of the output file.
+    ///
+    /// If not set, it is chosen from the extension of [`Self::output`].
+    pub output_compression: Option<OutputCompression>,
+
+    /// The index of the reference sequence for output formats that project the alignment onto a reference.
+    pub reference: usize,
+
+    /// The type of the residues of the sequences, which determines how some output formats annotate the alignment.
+    pub residue_type: ResidueType,
+
+    /// A human-readable name of the metric used for aligning, which is written into output formats that support metadata.
+    pub metric_name: String,
+
+    /// The ids of the sequences, which are used as record ids in the output file.
+    ///
+    /// Sequences without id are named by their index.
+    pub record_ids: Vec<String>,
+
+    /// For each sequence, true for each of its residues that is soft-masked, which is written in lower case in the output file.
+    ///
+    /// Sequences without soft mask are written in upper case.
+    pub soft_masks: Vec<Vec<bool>>,
+
+    /// If set, the consensus sequence of the alignment is written to a separate file or added to the alignment.
+    pub consensus: Option<ConsensusOutput>,
+
+    /// A token to cancel the alignment from another thread.
+    ///
+    /// If it is cancelled, the aligner returns [`MultialignError::Cancelled`] as soon as possible,
+    /// or the best alignment found so far with [`Self::anytime`].
+    pub cancellation: CancellationToken,
+
+    /// If set, the nodes closed by the search are written to a DOT file.
+    ///
+    /// This is only supported by the default search, i.e. not with [`Self::rederive_predecessors`], [`Self::k_best`], a bucket queue or anchors.
+    pub search_graph_dump: Option<SearchGraphDump>,
+
+    /// If set, the cost increment of each column of the alignment under the metric used for aligning is written to this TSV file.
+    ///
+    /// When computing multiple alignments, the rank of each alignment is inserted into the file name before the extension.
+    pub column_costs: Option<PathBuf>,
+
+    /// If set, a TSV file with statistics of each column of the alignment is written to this file.
+    ///
+    /// When computing multiple alignments, the rank of each alignment is inserted into the file name before the extension.
+    pub column_statistics: Option<PathBuf>,
+
+    /// If set, a row with statistics of the run is appended to a TSV file.
+    ///
+    /// When computing multiple alignments, the statistics are those of the cheapest one.
+    pub statistics: Option<StatisticsOutput>,
+
+    /// If set, detailed statistics of the search are written to a JSON file, see [`StatisticsJsonOutput`].
+    ///
+    /// When computing multiple alignments, the statistics are those of the cheapest one.
+    pub statistics_json: Option<StatisticsJsonOutput>,
+}
+
+impl MultialignOptions {
+    fn search_mode(&self) -> SearchMode {
+        match self.search {
+            SearchAlgorithm::IdaStar => SearchMode::IdaStar,
+            SearchAlgorithm::Beam { width } => SearchMode::Beam(width),
+            SearchAlgorithm::AStar => match self.k_best {
+                Some(k) if k > 1 => SearchMode::KBest(k, self.open_list),
+                _ if self.anytime => SearchMode::Anytime(self.open_list),
+                _ if self.rederives_predecessors() => {
+                    SearchMode::RederivePredecessors(self.open_list)
+                }
+                _ => match self.threads {
+                    Some(thread_amount) if thread_amount > 1 => {
+                        SearchMode::Parallel(thread_amount, self.open_list)
+                    }
+                    _ => SearchMode::AStar(self.open_list),
+                },
+            },
+        }
+    }
+
+    /// Returns true if the A* search re-derives predecessors, which is implied by writing its lists to disk.
+    fn rederives_predecessors(&self) -> bool {
+        self.rederive_predecessors || self.spill.is_some()
+    }
+
+    /// Returns the search mode of the searches for a single alignment of a segment of the sequences, and logs it.
+    ///
+    /// Such searches ignore [`Self::k_best`], [`Self::anytime`] and [`Self::threads`].
+    fn segment_search_mode(&self) -> SearchMode {
+        let search_mode = if self.search == SearchAlgorithm::IdaStar {
+            info!("Using IDA* search");
+            SearchMode::IdaStar
+        } else if let SearchAlgorithm::Beam { width } = self.search {
+            info!("Using beam search with width {width}");
+            SearchMode::Beam(width)
+        } else if self.rederives_predecessors() {
+            info!("Using search with re-derived predecessors");
+            SearchMode::RederivePredecessors(self.open_list)
+        } else {
+            SearchMode::AStar(self.open_list)
+        };
+        if self.open_list == OpenListKind::Bucket {
+            info!("Using a bucket queue as open list");
+        }
+        search_mode
+    }
+
+    /// Returns the lower bounds that guide the given search.
+    fn search_heuristic(&self, search_mode: SearchMode) -> Result<SearchHeuristic> {
+        let weight = match (self.weight, search_mode) {
+            (Some(weight), _) if !weight.is_finite() || weight < 1.0 => {
+                return Err(MultialignError::InvalidWeight(weight));
+            }
+            (Some(_), SearchMode::KBest(..)) => {
+                warn!("The lower bounds cannot be weighted when computing multiple alignments, ignoring the weight");
+                1.0
+            }
+            (None, SearchMode::Anytime(_)) => DEFAULT_ANYTIME_WEIGHT,
+            (weight, _) => weight.unwrap_or(1.0),
+        };
+
+        Ok(SearchHeuristic {
+            kind: self.heuristic,
+            weight,
+            gpu: self.gpu,
+        })
+    }
+
+    /// Returns how the alignment is found whose cost prunes the search, if [`Self::upper_bound_pruning`] is set.
+    fn upper_bound(&self) -> Option<UpperBound> {
+        self.upper_bound_pruning
+            .then_some(match self.upper_bound_source {
+                UpperBoundSource::Beam => UpperBound::Beam,
+                UpperBoundSource::Progressive => UpperBound::Progressive(self.guide_tree),
+            })
+    }
+}
+
+/// The initial weight of the lower bounds of the anytime search, see [`MultialignOptions::anytime`].
+pub const DEFAULT_ANYTIME_WEIGHT: f64 = 2.0;
+
+/// The width of the beam search if none is given, see [`SearchAlgorithm::Beam`].
+pub const DEFAULT_BEAM_WIDTH: usize = 100;
+
+/// The width of the beam search that finds the alignment whose cost prunes the search, see [`MultialignOptions::upper_bound_pruning`].
+pub const UPPER_BOUND_BEAM_WIDTH: usize = 10;
+
+/// The lower bounds that guide the search, see [`MultialignOptions::heuristic`] and [`MultialignOptions::weight`].
+#[derive(Debug, Clone, Copy)]
+struct SearchHeuristic {
+    kind: HeuristicKind,
+    weight: f64,
+    /// Compute the lower bounds on a GPU, see [`MultialignOptions::gpu`].
+    gpu: bool,
+}
+
+/// The alignment whose cost prunes the search, see [`MultialignOptions::upper_bound_source`].
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum UpperBoundSource {
+    /// A beam search of width [`UPPER_BOUND_BEAM_WIDTH`].
+    #[default]
+    Beam,
+    /// A progressive alignment along a guide tree, like the one of [`multialign_progressive`].
+    ///
+    /// This is usually cheaper than the alignment of the beam search if the sequences are long or diverse.
+    /// With a [`MultialignOptions::band`], the progressive alignment may leave the band, so the beam search is used instead.
+    Progressive,
+}
+
+/// The alignment whose cost prunes the search, see [`MultialignOptions::upper_bound_pruning`].
+#[derive(Debug, Clone, Copy)]
+enum UpperBound {
+    Beam,
+    Progressive(GuideTreeOptions),
+}
+
+/// How a guide tree is constructed from the sequences, see [`MultialignOptions::guide_tree`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GuideTreeOptions {
+    /// The length of the k-mers whose shared amount determines the distances between the sequences, see [`tree::kmer_distance_matrix`].
+    pub kmer_length: usize,
+
+    /// Correct the distances for multiple substitutions, see [`tree::kimura_correction`].
+    pub kimura_correction: bool,
+
+    /// The algorithm that constructs the tree from the distances.
+    pub method: GuideTreeMethod,
+}
+
+/// The algorithm that constructs a guide tree from the distances between the sequences, see [`GuideTreeOptions::method`].
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum GuideTreeMethod {
+    /// See [`neighbor_joining`].
+    #[default]
+    NeighborJoining,
+    /// See [`tree::upgma`].
+    Upgma,
+}
+
+/// The length of the k-mers of a guide tree if none is given, see [`GuideTreeOptions::kmer_length`].
+pub const DEFAULT_KMER_LENGTH: usize = 4;
+
+impl Default for GuideTreeOptions {
+    fn default() -> Self {
+        Self {
+            kmer_length: DEFAULT_KMER_LENGTH,
+            kimura_correction: false,
+            method: GuideTreeMethod::default(),
+        }
+    }
+}
+
+/// The order of the sequences in the output.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum OutputOrder {
+    /// The order in which the sequences were given.
+    #[default]
+    Input,
+    /// The leaf order of a neighbor-joining tree built from the pairwise distances in the alignment.
+    ///
+    /// This places similar sequences next to each other.
+    Tree,
+}
+
+/// The algorithm that searches for an alignment.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum SearchAlgorithm {
+    /// A best-first search that stores each node it reaches, see [`MultialignOptions::rederive_predecessors`] for storing less per node.
+    #[default]
+    AStar,
+    /// An iterative-deepening A* search, which is a series of depth-first searches with increasing limits on the cost plus the lower bound of the nodes.
+    ///
+    /// It stores only the path to the current node, so it uses almost no memory, but it searches each node again for each path to it and in each iteration.
+    /// This is only feasible with tight lower bounds, see [`MultialignOptions::heuristic`], and it does not use the [`MultialignOptions::open_list`].
+    IdaStar,
+    /// A beam search, which keeps only the given amount of nodes with the lowest cost plus lower bound among those reached with the same amount of columns.
+    ///
+    /// It is fast and uses little memory, but the alignment is not necessarily optimal.
+    /// The width must be at least one.
+    Beam { width: usize },
+}
+
+/// A limit of the memory used by the search, see [`MultialignOptions::memory_limit`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum MemoryLimit {
+    /// An approximate amount of bytes.
+    Bytes(usize),
+    /// An amount of nodes stored in the open and closed list.
+    Nodes(usize),
+}
+
+/// `AStar` estimates that each node takes this factor times its size, including the overhead of the open and closed list.
+const NODE_MEMORY_FACTOR: f64 = 2.3;
+
+impl MemoryLimit {
+    /// Returns the amount of bytes that allows to store the limited amount of the given type of nodes.
+    fn bytes<Node>(self) -> usize {
+        match self {
+            Self::Bytes(bytes) => bytes,
+            Self::Nodes(nodes) => {
+                (nodes as f64 * size_of::<Node>() as f64 * NODE_MEMORY_FACTOR).round() as usize
+            }
+        }
+    }
+}
+
+/// The data structure used as open list of the search.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum OpenListKind {
+    /// A binary heap, which supports arbitrary costs.
+    #[default]
+    Heap,
+    /// A bucket queue with one bucket per integer cost.
+    ///
+    /// Its operations take constant time for the small integer costs of the metrics, but its memory consumption grows with the cost of the alignment.
+    /// Negative and very large costs are not supported, and the open list becomes a binary heap when they occur.
+    /// This cannot be used by the default A* search, which is replaced by an equivalent but usually slower search implemented in this crate.
+    Bucket,
+}
+
+/// How nodes of equal priority are ordered in the open list, see [`MultialignOptions::tie_break`].
+///
+/// Near the target, many nodes have the same priority, so the order among them affects how many nodes are searched.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum TieBreak {
+    /// Prefer the nodes with the higher cost, which are closer to the target.
+    #[default]
+    HigherCost,
+    /// Prefer the nodes reached by a column in which all sequences have the same residue, and then the ones with the higher cost.
+    Matches,
+    /// Prefer the nodes reached by a column with fewer gaps, and then the ones with the higher cost.
+    FewerGaps,
+}
+
+/// The lower bound of the remaining cost of each node that guides the search.
+///
+/// Lower bounds are only computed for sum-of-pairs metrics without affine gaps, free end gaps or offsets, see [`MultialignMetric::is_sum_of_pairs`].
+/// Otherwise, the search explores nodes in order of their cost alone.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum HeuristicKind {
+    /// The sum of the costs of optimal alignments of the remainders of each pair of sequences.
+    #[default]
+    Pairwise,
+    /// Like [`Self::Pairwise`], but the sequences are grouped into triples in input order, whose remainders are aligned optimally as a whole.
+    ///
+    /// This gives tighter lower bounds and thus reduces the amount of nodes explored by the search,
+    /// but computing them takes time and memory cubic in the length of the sequences.
+    Triple,
+}
+
+/// The characters used to render gaps in the output.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct GapCharacters {
+    /// The character used for gaps that are not preceded or not followed by any residue of the same sequence.
+    pub terminal: char,
+    /// The character used for gaps that are surrounded by residues of the same sequence.
+    pub internal: char,
+}
+
+impl GapCharacters {
+    /// Returns the gap character for a gap at the given column of a row,
+    /// whose first and last residue are at the given columns, if it has any.
+    fn gap_character(&self, residue_columns: Option<(usize, usize)>, column: usize) -> char {
+        if residue_columns.is_some_and(|(first, last)| first < column && column < last) {
+            self.internal
+        } else {
+            self.terminal
+        }
+    }
+
+    /// Returns the columns of the first and last residue of the given row, if it has any.
+    fn residue_columns<CharacterType>(row: &[Option<CharacterType>]) -> Option<(usize, usize)> {
+        Some((
+            row.iter().position(Option::is_some)?,
+            row.iter().rposition(Option::is_some)?,
+        ))
+    }
+
+    /// Render a row of an alignment as string, with gaps rendered as gap characters.
+    pub fn render_row<CharacterType: Clone + Into<char>>(
+        &self,
+        row: &[Option<CharacterType>],
+    ) -> String {
+        let residue_columns = Self::residue_columns(row);
+
+        row.iter()
+            .enumerate()
+            .map(|(index, character)| match character {
+                Some(character) => character.clone().into(),
+                None => self.gap_character(residue_columns, index),
+            })
+            .collect()
+    }
+}
+
+impl Default for GapCharacters {
+    fn default() -> Self {
+        Self {
+            terminal: '-',
+            internal: '-',
+        }
+    }
+}
+
+/// Reconstruct the alignment columns from the backtrack of the A* search.
+///
+/// The backtrack is given from the target to the root, and the columns are returned from the root to the target.
+/// Each column contains one entry per sequence, which is `None` if the sequence has a gap in that column.
+fn reconstruct_path<Cost: AStarCost, Identifier: NodeIdentifier>(
+    sequence_amount: usize,
+    edges: impl IntoIterator<Item = Node<Identifier, Cost>>,
+) -> AlignmentPath {
+    let mut path = AlignmentPath::new(sequence_amount);
+
+    for edge in edges {
+        let mut column = 0;
+
+        for index in 0..sequence_amount {
+            let predecessor_offset = edge.predecessor.as_ref().unwrap().offset(index);
+            let offset = edge.identifier.offset(index);
+
+            if predecessor_offset != offset {
+                debug_assert_eq!(predecessor_offset + 1, offset);
+                column |= 1 << index;
+            }
+        }
+
+        path.push(column);
+    }
+
+    path.reverse();
+    path
+}
+
+/// Look up the residues of the columns of the path in the aligned sequences.
+///
+/// In debug builds, this also verifies that the path is an alignment of the sequences.
+fn residue_columns<
+    AlphabetType: Alphabet,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized,
+>(
+    path: &AlignmentPath,
+    sequences: &[&SequenceType],
+) -> AlignmentColumns<AlphabetType::CharacterType> {
+    let columns: Vec<_> = path.columns().collect();
+    debug_assert!(verify_columns(
+        &columns,
+        &sequences
+            .iter()
+            .map(|sequence| sequence.len())
+            .collect::<Vec<_>>()
+    )
+    .is_ok());
+    columns
+        .iter()
+        .map(|column| column.residues(sequences))
+        .collect()
+}
+
+/// Check that the costs of the search fit into the cost type.
+///
+/// The cost of the alignment in which each residue has a column of its own is an upper bound of the optimal cost.
+/// Nodes off the optimal path may be more expensive, so twice this bound has to fit into the cost type.
+fn check_cost_type<
+    AlphabetType: Alphabet,
+    Cost: AStarCost,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized,
+    Metric: MultialignMetric<AlphabetType>,
+>(
+    sequences: &[&SequenceType],
+    metric: &mut Metric,
+    segment: Segment<'_>,
+) -> Result<()> {
+    let mut upper_bound = 0i64;
+    let mut offset = segment.start(0);
+    let affine_gaps = metric.has_affine_gaps();
+    let uses_offsets = metric.uses_offsets();
+    let free_end_gaps = metric.has_free_end_gaps();
+    let mut in_gap = vec![false; sequences.len()];
+
+    for (index, sequence) in sequences.iter().enumerate() {
+        for (character_offset, character) in sequence.iter().enumerate() {
+            metric.reset_character_counts();
+            metric.set_reference_offset(offset);
+            if index == 0 {
+                offset += 1;
+            }
+            for (other_index, in_gap) in in_gap.iter_mut().enumerate() {
+                // The sequences before the current one are already complete.
+                let (other_offset, is_terminal) = match other_index.cmp(&index) {
+                    Ordering::Less => (sequences[other_index].len(), segment.is_end),
+                    Ordering::Equal => (character_offset, false),
+                    Ordering::Greater => (0, segment.is_start),
+                };
+                if uses_offsets {
+                    metric.set_offset(other_index, segment.start(other_index) + other_offset);
+                }
+                if other_index == index {
+                    metric.count_character(character, other_index);
+                    *in_gap = false;
+                } else if free_end_gaps
+                    && is_terminal
+                    && other_index != segment.full_length_sequence
+                {
+                    metric.count_terminal_gap(other_index);
+                    *in_gap = true;
+                } else if affine_gaps && !*in_gap {
+                    metric.count_gap_open(other_index);
+                    *in_gap = true;
+                } else {
+                    metric.count_gap(other_index);
+                }
+            }
+
+            let cost_increment: I64Cost = metric.compute_cost_increment()?;
+            upper_bound = upper_bound.saturating_add(cost_increment.as_primitive());
+        }
+    }
+
+    let required = upper_bound.saturating_mul(2);
+    if u64::try_from(required).is_ok_and(|required| required > Cost::max_value().as_u64()) {
+        Err(MultialignError::CostOutOfRange {
+            cost: required,
+            cost_type: cost_type_name::<Cost>(),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Compute the cost of the given alignment columns under the given metric.
+///
+/// Columns consisting only of gaps are skipped, since they do not correspond to an edge in the alignment graph.
+pub fn score_alignment<
+    AlphabetType: Alphabet,
+    Cost: AStarCost,
+    Metric: MultialignMetric<AlphabetType>,
+>(
+    columns: &[Vec<Option<AlphabetType::CharacterType>>],
+    metric: &mut Metric,
+) -> Result<Cost>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    column_costs(columns, metric)?
+        .into_iter()
+        .flatten()
+        .try_fold(Cost::zero(), |cost, increment| cost.checked_add(&increment))
+        .ok_or(MultialignError::CostOverflow)
+}
+
+/// Compute the cost increment of each of the given alignment columns under the given metric.
+///
+/// Columns consisting only of gaps have no cost increment, since they do not correspond to an edge in the alignment graph.
+/// If the metric has affine gaps, such columns also do not interrupt the gaps of a sequence.
+pub fn column_costs<
+    AlphabetType: Alphabet,
+    Cost: AStarCost,
+    Metric: MultialignMetric<AlphabetType>,
+>(
+    columns: &[Vec<Option<AlphabetType::CharacterType>>],
+    metric: &mut Metric,
+) -> Result<Vec<Option<Cost>>>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    let mut costs = Vec::with_capacity(columns.len());
+    let mut reference_offset = 0;
+    let affine_gaps = metric.has_affine_gaps();
+    let uses_offsets = metric.uses_offsets();
+    let free_end_gaps = metric.has_free_end_gaps();
+    let mut in_gap = vec![false; columns.first().map(Vec::len).unwrap_or(0)];
+    let mut offsets = vec![0; in_gap.len()];
+    let lengths: Vec<usize> = (0..in_gap.len())
+        .map(|index| {
+            columns
+                .iter()
+                .filter(|column| column[index].is_some())
+                .count()
+        })
+        .collect();
+    let full_length_sequence = full_length_sequence(lengths.iter().copied());
+
+    for column in columns {
+        if column.iter().all(Option::is_none) {
+            costs.push(None);
+            continue;
+        }
+
+        metric.reset_character_counts();
+        metric.set_reference_offset(reference_offset);
+        if column[0].is_some() {
+            reference_offset += 1;
+        }
+        for (index, (character, in_gap)) in column.iter().zip(&mut in_gap).enumerate() {
+            if uses_offsets {
+                metric.set_offset(index, offsets[index]);
+            }
+            if let Some(character) = character {
+                metric.count_character(character, index);
+                offsets[index] += 1;
+                *in_gap = false;
+            } else if free_end_gaps
+                && index != full_length_sequence
+                && (offsets[index] == 0 || offsets[index] == lengths[index])
+            {
+                metric.count_terminal_gap(index);
+                *in_gap = true;
+            } else if affine_gaps && !*in_gap {
+                metric.count_gap_open(index);
+                *in_gap = true;
+            } else {
+                metric.count_gap(index);
+            }
+        }
+
+        costs.push(Some(metric.compute_cost_increment()?));
+    }
+
+    Ok(costs)
+}
+
+/// Log the sum-of-pairs cost and the total-column score of the given alignment.
+///
+/// The sum-of-pairs cost is computed with [`PairwiseMatchMetric`], i.e. it is the number of pairs of entries that are not equal in each column.
+/// The total-column score is the number of columns in which all sequences have the same residue.
+pub fn report_scores<AlphabetType: Alphabet>(
+    columns: &[Vec<Option<AlphabetType::CharacterType>>],
+) -> Result<()> {
+    let sequence_amount = columns.first().map(Vec::len).unwrap_or(0);
+    let sum_of_pairs_cost: I32Cost = score_alignment(
+        columns,
+        &mut PairwiseMatchMetric::<AlphabetType>::new(sequence_amount)?,
+    )?;
+    let total_column_score = total_column_score(columns);
+
+    info!("Sum-of-pairs cost {sum_of_pairs_cost}");
+    info!(
+        "Total-column score {total_column_score} of {} columns",
+        columns.len()
+    );
+
+    Ok(())
+}
+
+/// Count the columns in which all sequences have the same residue.
+pub fn total_column_score<CharacterType: Eq>(columns: &[Vec<Option<CharacterType>>]) -> usize {
+    columns
+        .iter()
+        .filter(|column| {
+            column[0].is_some() && column.iter().all(|character| character == &column[0])
+        })
+        .count()
+}
+
+fn format_cigar<CharacterType: Clone + Ord + Into<char>>(
+    columns: &[Vec<Option<CharacterType>>],
+    gap_characters: &GapCharacters,
+) -> String {
+    enum CigarElement {
+        Match { amount: usize },
+        Mismatch { column: Vec<char> },
+    }
+
+    let mut cigar = Vec::new();
+    let residue_columns: Vec<_> = (0..columns.first().map_or(0, Vec::len))
+        .map(|row| {
+            let row: Vec<_> = columns.iter().map(|column| column[row].as_ref()).collect();
+            GapCharacters::residue_columns(&row)
+        })
+        .collect();
+
+    for (column_index, column) in columns.iter().enumerate() {
+        let column_set: BTreeSet<_> = column.iter().collect();
+        if column_set.len() == 1 {
+            if let Some(CigarElement::Match { amount }) = cigar.last_mut() {
+                *amount += 1;
+            } else {
+                cigar.push(CigarElement::Match { amount: 1 });
+            }
+        } else {
+            cigar.push(CigarElement::Mismatch {
+                column: column
+                    .iter()
+                    .enumerate()
+                    .map(|(row, character)| {
+                        character.clone().map(Into::into).unwrap_or_else(|| {
+                            gap_characters.gap_character(residue_columns[row], column_index)
+                        })
+                    })
+                    .collect(),
+            });
+        }
+    }
+
+    let mut cigar_string = String::new();
+    for element in &cigar {
+        match element {
+            CigarElement::Match { amount } => cigar_string.push_str(&format!("{amount}M")),
+            CigarElement::Mismatch { column } => {
+                cigar_string.push('[');
+                cigar_string.extend(column);
+                cigar_string.push(']');
+            }
+        }
+    }
+
+    cigar_string
+}
@@ -1,7 +1,21 @@
-use std::{fmt::Debug, path::PathBuf};
+use std::{
+    collections::BTreeSet,
+    ffi::OsStr,
+    fmt::Debug,
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
 
 use anyhow::{bail, Context, Result};
-use clap::{Parser, ValueEnum};
+use clap::{
+    builder::{PossibleValue, PossibleValuesParser, TypedValueParser},
+    error::ErrorKind,
+    Arg, Command as ClapCommand, Parser, Subcommand, ValueEnum,
+};
 use compact_genome::{
     implementation::{
         alphabets::{
@@ -12,57 +26,928 @@ use compact_genome::{
             rna_alphabet_or_n::RnaAlphabetOrN,
             rna_iupac_nucleic_acid_alphabet::RnaIupacNucleicAcidAlphabet,
         },
+        vec_sequence::VectorGenome,
         DefaultSequenceStore,
     },
     interface::{alphabet::Alphabet, sequence::GenomeSequence, sequence_store::SequenceStore},
-    io::fasta::read_fasta_file,
+    io::fasta::{read_fasta_file, FastaRecord},
 };
-use log::{error, info, LevelFilter};
+use generic_a_star::cost::{I16Cost, I32Cost, I64Cost, U64Cost};
+use log::{error, info, warn, LevelFilter};
+#[cfg(feature = "hmm")]
+use multialign::metric::profile_hmm_metric::ProfileHmmMetric;
 use multialign::{
+    aligned_fasta::{read_aligned_fasta_file, records_to_columns, AlignedFastaRecord},
+    anchors::{anchors_from_matches, anchors_from_seed, read_anchors_file},
+    band::Band,
+    cancellation::CancellationToken,
+    column_costs,
+    consensus::{ConsensusOutput, ConsensusRule},
+    consistency::ConsistencyLibrary,
+    estimate_search,
+    frame::{translate, FrameAnnotation},
+    indexed_fasta::read_indexed_fasta_records,
     metric::{
-        pairwise_cost_metric::PairwiseCostMetric, pairwise_match_metric::PairwiseMatchMetric,
+        affine_gap_metric::AffineGapMetric,
+        codon_metric::CodonMetric,
+        consistency_metric::ConsistencyMetric,
+        end_gap_metric::EndGapMetric,
+        gap_profile_metric::GapProfileMetric,
+        pair_weighted_metric::PairWeightedMetric,
+        pairwise_cost_metric::{
+            AsymmetricCosts, CostTableIssue, CostTableOptions, PairwiseCostMetric, StopCodonCosts,
+        },
+        parse_metric_specification,
+        sequence_weighted_metric::SequenceWeightedMetric,
+        soft_mask_metric::SoftMaskMetric,
+        wildcard_metric::WildcardMetric,
+        BoxedMetric, MetricArguments, MetricRegistry, MultialignMetric,
+        TransitionTransversionCosts,
+    },
+    multialign_add_to, multialign_astar, multialign_astar_anchored, multialign_center_star,
+    multialign_merge, multialign_progressive, multialign_realign_region,
+    newick::{read_newick_file, write_newick_file},
+    output::{
+        compression::OutputCompression, phylip_format::PhylipIds, ResidueType, WriterOptions,
+        WriterRegistry,
     },
-    multialign_astar,
+    report_scores,
+    reproducibility::{StableHash, StableHasher},
+    score_alignment,
+    search_graph::SearchGraphDump,
+    soft_mask::{read_soft_masks, soft_mask},
+    spill::SpillOptions,
+    statistics::{write_column_costs, StatisticsJsonOutput, StatisticsOutput},
+    tree::{
+        alignment_distance_matrix, kmer_guide_tree, neighbor_joining, sequence_guide_tree,
+        GuideTree,
+    },
+    AlignmentColumns, GapCharacters, GuideTreeMethod, GuideTreeOptions, HeuristicKind, MemoryLimit,
+    MultialignCost, MultialignOptions, OpenListKind, OutputOrder, SearchAlgorithm, TieBreak,
+    UpperBoundSource, DEFAULT_BEAM_WIDTH,
 };
+use signal_hook::consts::{SIGINT, SIGTERM};
 use simplelog::{ColorChoice, TermLogger, TerminalMode};
-
-mod multialign;
+use traitsequence::interface::Sequence;
 
 #[derive(Parser)]
 struct Cli {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
     /// The minimum importance of log messages to output.
-    #[clap(long, short = 'l', default_value = "info")]
+    #[clap(long, short = 'l', default_value = "info", global = true)]
     log_level: LevelFilter,
 
     /// The input sequences.
     /// This can be multiple fasta files where each file may contain multiple sequences as fasta records.
-    #[clap(long, short = 'i')]
+    #[clap(long, short = 'i', global = true)]
     input: Vec<PathBuf>,
 
+    /// Write the alignment to this file, with the ids of the input records.
+    ///
+    /// With `--k-best`, the rank of each alignment is inserted into the file name before the extension.
+    /// If the file name ends with `.gz` or `.zst`, the file is compressed with gzip or zstd, respectively, unless `--output-compression` is given.
+    #[clap(long, short = 'o', global = true)]
+    output: Option<PathBuf>,
+
+    /// The compression of the output file, overriding the choice by its extension.
+    #[clap(long, requires = "output", global = true)]
+    output_compression: Option<Compression>,
+
+    /// The format of the output file.
+    ///
+    /// Formats that project the alignment onto a reference use the sequence given by `--reference-id`.
+    #[clap(long, default_value = "fasta", value_parser = output_format_parser(), requires = "output", global = true)]
+    output_format: String,
+
+    /// The id of the reference sequence for output formats that project the alignment onto a reference.
+    ///
+    /// If not given, the first input sequence is used.
+    #[clap(long, global = true)]
+    reference_id: Option<String>,
+
+    /// The amount of residues per row in SVG output.
+    #[clap(long, default_value = "60", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), global = true)]
+    svg_residues_per_row: usize,
+
+    /// The font size in pixels in SVG output.
+    #[clap(long, default_value = "14", value_parser = clap::value_parser!(u32).range(1..), global = true)]
+    svg_font_size: u32,
+
+    /// How ids longer than ten characters are handled in PHYLIP output.
+    #[clap(long, default_value = "truncate", global = true)]
+    phylip_ids: PhylipIdScheme,
+
+    /// Only align the input records with the given ids.
+    ///
+    /// If an input file has a `.fai` index (and a `.gzi` index if it is compressed with bgzip), then the selected records are read directly without scanning the whole file.
+    /// Otherwise, the whole file is read.
+    #[clap(long, value_delimiter = ',', global = true)]
+    select: Vec<String>,
+
     /// The alphabet present in the input files.
     ///
     /// This must also match the alphabet used in the config.
-    #[clap(long, short = 'a', default_value = "famsa-amino-acid")]
+    #[clap(long, short = 'a', default_value = "famsa-amino-acid", global = true)]
     alphabet: InputAlphabet,
 
-    /// The cost metric used for alignment.
+    /// The name of the cost metric used for alignment.
+    ///
+    /// Since this algorithm searches for a _shortest_ path through the alignment graph, the metric must be a **lower-is-better** alignment _cost_, and not an alignment _score_.
     ///
-    /// Since this algorithm searches for a _shortest_ path through the alignment graph, the metric must be provided in a **lower-is-better** format.
-    /// In other words, the metric must be an alignment _cost_, and not an alignment _score_.
+    /// If no metric is given, `pairwise-cost` is used if `--cost-table` is given, `profile` if `--profile` is given, `script` if `--script` is given, and `pairwise-match` otherwise.
     ///
-    /// The metric must be a CSV file containing an alignment matrix.
+    /// Multiple metrics can be combined into a weighted sum like `pairwise-cost:3+consensus:1`, where each weight defaults to one.
+    /// The cost table is given to the metrics that use one.
+    #[clap(long, short = 'm', value_parser = metric_parser(), global = true)]
+    metric: Option<String>,
+
+    /// The cost table of the `pairwise-cost` metric.
+    ///
+    /// The cost table must be a CSV file containing an alignment matrix.
     /// Dash (`-`) or star (`*`) characters imply a gap character.
-    /// In each step in the multialignment, the metric is applied to all pairs in the step, and summed up.
     ///
-    /// If no metric is given, the default sum of pairs metric is applied, where each mismatching pair has a cost of one.
-    #[clap(long, short = 'm')]
-    metric: Option<PathBuf>,
+    /// Alternatively, it can be a substitution matrix in NCBI or EMBOSS format, e.g. `BLOSUM62`, which is detected by its lack of commas.
+    /// Its scores are converted into costs by subtracting them from the maximum score between two residues, and its `*` entries are used for gaps.
+    /// Characters of the matrix that are not part of the alphabet are ignored.
+    /// In each step in the multialignment, the table is applied to all pairs in the step, and summed up.
+    #[clap(long, global = true)]
+    cost_table: Option<PathBuf>,
+
+    /// The maximum amount of decimal digits that are kept of fractional cost table entries.
+    ///
+    /// If a cost table has fractional entries, then all entries are multiplied by a power of ten and rounded, such that they become integers.
+    /// The power is the largest amount of decimal digits of an entry, but at most this precision, and the reported costs are scaled accordingly.
+    #[clap(long, default_value = "3", global = true)]
+    cost_table_precision: u32,
+
+    /// Accept cost tables whose entry for row `a` and column `b` differs from their entry for row `b` and column `a`, which are rejected by default.
+    ///
+    /// Such tables are either made symmetric, or used for directional cost models by scoring each pair of sequences with the row of the earlier sequence in the input.
+    #[clap(long, global = true)]
+    allow_asymmetric_matrix: Option<AsymmetricMatrix>,
+
+    /// The cost of pairs of different characters that are missing from the cost table.
+    ///
+    /// Without this, aligning fails as soon as such a pair occurs.
+    /// The cost is given in the units of the table, and scaled like its entries.
+    #[clap(long, global = true)]
+    default_substitution_cost: Option<i32>,
+
+    /// The cost of pairs of a character and a gap that are missing from the cost table.
+    ///
+    /// Without this, aligning fails as soon as such a pair occurs.
+    /// The cost is given in the units of the table, and scaled like its entries.
+    #[clap(long, global = true)]
+    default_gap_cost: Option<i32>,
+
+    /// The cost of the stop character `*` against any other character in cost tables.
+    ///
+    /// Cost tables use `*` for gaps, so without this, aligning with a cost table fails if the sequences contain stop characters.
+    /// This requires an alphabet with a stop character, i.e. `famsa-amino-acid`.
+    #[clap(long, requires = "stop_codon_gap_cost", global = true)]
+    stop_codon_cost: Option<i32>,
+
+    /// The cost of the stop character `*` against a gap in cost tables, see `--stop-codon-cost`.
+    #[clap(long, requires = "stop_codon_cost", global = true)]
+    stop_codon_gap_cost: Option<i32>,
+
+    /// Cost tables under which the computed alignments are re-scored after aligning.
+    ///
+    /// This does not search again, but only computes the costs of the alignments found with the metric under each table, and reports the cheapest.
+    /// With `--k-best`, all found alignments are candidates, otherwise only the optimal one.
+    /// This is fast, but misses alignments that are optimal only under the new table, so the reported cost is only an upper bound of the optimal cost.
+    ///
+    /// The tables have the same format as `--cost-table`, and the gap profile and wildcards are applied to them as well.
+    #[clap(long, value_delimiter = ',', global = true)]
+    rescore_table: Vec<PathBuf>,
+
+    /// Search again with each table given by `--rescore-table` instead of only re-scoring the computed alignments.
+    #[clap(long, requires = "rescore_table", global = true)]
+    rescore_full_search: bool,
+
+    /// A file of position-specific gap penalties that are added to the metric.
+    ///
+    /// The file must contain one non-negative integer per position of the reference sequence, separated by whitespace.
+    /// The reference sequence is the first input sequence.
+    /// Each gap in a column is charged the penalty of the reference position that the column is aligned to.
+    /// The file ends with one more integer, which is charged for gaps in columns after the end of the reference.
+    #[clap(long, global = true)]
+    gap_profile: Option<PathBuf>,
+
+    /// An existing alignment of a sequence family in aligned fasta format, against whose position-specific profile the columns are scored by the `profile` metric.
+    ///
+    /// The positions of the profile are the residues of the first record of the alignment, and the first input sequence is aligned to them, so it should be the same sequence.
+    /// Each other residue or gap costs its frequency in the column of the profile that the residue of the first input sequence is aligned to.
+    #[clap(long, global = true)]
+    profile: Option<PathBuf>,
+
+    /// The cost of a transition, i.e. a substitution between two purines or two pyrimidines, in the `transition-transversion` metric [default: 1].
+    ///
+    /// If no metric is given, then giving any of `--transition-cost`, `--transversion-cost` or `--gap-cost` selects the `transition-transversion` metric.
+    #[clap(long, global = true)]
+    transition_cost: Option<i32>,
+
+    /// The cost of a transversion, i.e. a substitution between a purine and a pyrimidine, in the `transition-transversion` metric [default: 2].
+    #[clap(long, global = true)]
+    transversion_cost: Option<i32>,
+
+    /// The cost of a gap in the `transition-transversion` metric [default: 3].
+    #[clap(long, global = true)]
+    gap_cost: Option<i32>,
+
+    /// The factor by which the negative log-frequencies of the profile are multiplied before rounding them to integer costs.
+    #[clap(long, default_value = "10", requires = "profile", global = true)]
+    profile_scale: f64,
+
+    /// A Rhai script that computes the cost of each column for the `script` metric, which is used if no other metric is given.
+    ///
+    /// The script defines a function `cost(column)` that returns a non-negative integer,
+    /// where the column has the properties `entries`, `residues`, `gaps`, `wildcards`, `distinct`, `max_count` and `counts`,
+    /// e.g. `fn cost(column) { column.residues - column.wildcards - column.max_count + 2 * column.gaps }`.
+    #[cfg(feature = "script")]
+    #[clap(long, global = true)]
+    script: Option<PathBuf>,
+
+    /// The cost of opening a gap, which is added to the cost of the gap itself.
+    ///
+    /// A gap opens if its sequence has a residue in the previous column, and the cost is charged once per residue in the column of the gap.
+    /// With a nonzero cost, the search tracks which sequences are in a gap, which makes it considerably slower.
+    /// For two sequences, the dynamic programming instead keeps a state for each kind of the previous column.
+    #[clap(long, default_value = "0", global = true)]
+    gap_open_cost: i32,
+
+    /// How gaps before the first and after the last residue of a sequence are scored.
+    ///
+    /// Free end gaps give a semi-global alignment, which is useful when aligning fragments against a full-length sequence.
+    /// The longest sequence is taken to be full-length, and its end gaps are still penalised.
+    /// With anchors, only the gaps at the ends of the input sequences are free, not those at the anchors.
+    #[clap(long, default_value = "penalised", global = true)]
+    end_gaps: EndGaps,
+
+    /// Treat lower-case residues in the input as soft-masked, and write them in lower case in the output.
+    ///
+    /// Soft masking usually marks repeats and low-complexity regions.
+    /// Without this, all residues are converted to upper case.
+    #[clap(long, global = true)]
+    soft_masking: bool,
+
+    /// The factor by which the cost of each column containing a soft-masked residue is multiplied, before rounding it to an integer.
+    #[clap(long, default_value = "1", requires = "soft_masking", global = true)]
+    soft_mask_cost_factor: f64,
+
+    /// A penalty that is added to the cost of each column containing a soft-masked residue.
+    #[clap(long, default_value = "0", requires = "soft_masking", global = true)]
+    soft_mask_penalty: i32,
+
+    /// A file of weights for the input sequences, by which the costs of the pairs of sequences are multiplied, like in ClustalW.
+    ///
+    /// The file must contain one non-negative number per sequence in input order, separated by whitespace.
+    /// The weights are scaled to an average of one, and each column cost is rounded to an integer.
+    /// The metric is applied to each pair of sequences separately, so it must not depend on the position in the alignment.
+    #[clap(long, conflicts_with = "guide_tree_weights", global = true)]
+    sequence_weights: Option<PathBuf>,
+
+    /// Weight the sequences like ClustalW, such that sequences on long branches of a neighbor-joining guide tree get higher weights.
+    ///
+    /// When aligning, the guide tree is built from optimal pairwise alignments of all pairs of sequences, and when scoring, from the given alignment.
+    /// The weights are applied like with `--sequence-weights`.
+    #[clap(long, global = true)]
+    guide_tree_weights: bool,
+
+    /// A phylogenetic tree of the input sequences in Newick format, by whose distances the costs of the pairs of sequences are multiplied.
+    ///
+    /// The leaves must be labelled with the ids of the fasta records, and missing branch lengths are taken to be one.
+    /// The weight of a pair of sequences is the length of the path between their leaves, such that pairs of closely related sequences contribute less.
+    /// The weights are scaled to an average of one over all pairs, and each column cost is rounded to an integer.
+    /// Like with `--sequence-weights`, the metric must not depend on the position in the alignment.
+    #[clap(long, conflicts_with_all = ["sequence_weights", "guide_tree_weights"], global = true)]
+    tree: Option<PathBuf>,
+
+    /// Instead of a metric, use a T-Coffee-style consistency library, which rewards aligning pairs of residues that are aligned in optimal pairwise alignments.
+    ///
+    /// The library is built from optimal pairwise alignments of all pairs of sequences, and a pair of residues gains weight for each third sequence whose residue is aligned to both.
+    /// Each column costs the difference of its weight to the maximum weight of a pair, such that the cost is lower for alignments that agree with more pairwise alignments.
+    /// When scoring, the library is built from the sequences of the given alignment.
+    #[clap(long, conflicts_with_all = ["metric", "cost_table", "sequence_weights", "guide_tree_weights", "tree", "anchors_from", "anchors", "auto_anchors"], global = true)]
+    consistency: bool,
+
+    /// Align coding nucleotide sequences in codon space, where the metric and the cost table are applied to the translated amino acids.
+    ///
+    /// Each sequence is translated with the standard genetic code, starting at its first residue.
+    /// Each column is scored by the metric like an amino acid column, where each residue is replaced by the amino acid of its codon.
+    /// A residue at another codon position than most residues of the column is counted as a gap and additionally charged `--frameshift-cost`,
+    /// such that gaps whose length is not a multiple of three are penalised for each residue until the frame is restored.
+    /// The cost table must cover the FAMSA amino acid alphabet, e.g. `BLOSUM62`, and stop codons are scored like `X`.
+    #[clap(long, conflicts_with_all = ["consistency", "profile", "sequence_weights", "guide_tree_weights", "tree", "rescore_table"], global = true)]
+    codons: bool,
+
+    /// The cost of a residue that is at another codon position than most residues of its column, see `--codons`.
+    #[clap(long, default_value = "10", requires = "codons", global = true)]
+    frameshift_cost: i32,
 
     /// A string of (ASCII) characters that should be skipped in the input fasta.
     ///
     /// For example, `-` characters caused by alignment hints can be skipped this way.
-    #[clap(long, default_value = "")]
+    #[clap(long, default_value = "", global = true)]
     skip_characters: String,
+
+    /// The character used in the output for gaps that are not preceded or not followed by any residue of the same sequence.
+    #[clap(long, default_value = "-", global = true)]
+    terminal_gap_char: char,
+
+    /// The character used in the output for gaps that are both preceded and followed by residues of the same sequence.
+    #[clap(long, default_value = "-", global = true)]
+    internal_gap_char: char,
+
+    /// How the sequences are aligned.
+    #[clap(long, default_value = "exact", global = true)]
+    mode: Mode,
+
+    /// The length of the k-mers whose shared amount determines the distances between the sequences for the guide tree of `--mode progressive`
+    /// and of `--upper-bound-from progressive`.
+    #[clap(long, default_value = "4", global = true)]
+    kmer_length: usize,
+
+    /// Correct the k-mer distances of the guide tree for multiple substitutions with Kimura's formula.
+    ///
+    /// This makes the distances between diverse sequences more additive, which helps neighbor-joining and UPGMA.
+    #[clap(long, global = true)]
+    kimura_correction: bool,
+
+    /// The algorithm that constructs the guide tree from the k-mer distances.
+    #[clap(long, default_value = "neighbor-joining", global = true)]
+    guide_tree_method: TreeMethod,
+
+    /// Write the guide tree constructed from the k-mer distances to this file in Newick format, labelled with the record ids.
+    ///
+    /// This is the tree along which `--mode progressive` aligns the sequences, but it is written in every mode.
+    #[clap(long, conflicts_with = "add_to", global = true)]
+    write_guide_tree: Option<PathBuf>,
+
+    /// Refine the alignment in up to this many rounds after it was found.
+    ///
+    /// In each round, the sequences are split into two groups along each branch of the guide tree (see `--guide-tree-method`),
+    /// the columns with only gaps in a group are removed, and the two groups are realigned to each other.
+    /// Realignments that are cheaper under the metric are kept, and the refinement stops after a round without improvement.
+    /// This only helps if the alignment is not optimal, e.g. with `--mode progressive`, `--weight` or `--search beam`.
+    #[clap(long, default_value = "0", global = true)]
+    refine: usize,
+
+    /// The algorithm that searches for an alignment.
+    #[clap(long, default_value = "a-star", global = true)]
+    search: Search,
+
+    /// The amount of nodes kept per column by `--search beam`.
+    ///
+    /// Wider beams find cheaper alignments, but take longer.
+    /// Defaults to 100.
+    #[clap(long, global = true)]
+    beam_width: Option<usize>,
+
+    /// Store no predecessors in the search, which roughly halves the memory per node.
+    ///
+    /// The predecessors on the optimal path are re-derived after the search, which increases the runtime.
+    /// Every closed node is still stored, so this only delays running out of memory, but does not avoid it.
+    #[clap(long, global = true)]
+    rederive_predecessors: bool,
+
+    /// Write the open and closed nodes that are not needed soon into files in the given directory.
+    ///
+    /// The files are removed after the search. This implies `--rederive-predecessors` for the A* search,
+    /// and is ignored by the other searches.
+    #[clap(long, global = true)]
+    spill_dir: Option<PathBuf>,
+
+    /// The amount of open and of closed nodes kept in memory with `--spill-dir`.
+    #[clap(long, default_value = "4194304", global = true)]
+    spill_cache_nodes: usize,
+
+    /// Output the given amount of cheapest alignments instead of only a single optimal one.
+    ///
+    /// The alignments are numbered in order of increasing cost.
+    /// Since all explored paths are stored, this cannot be combined with `--rederive-predecessors`.
+    #[clap(long, conflicts_with_all = ["rederive_predecessors", "anchors_from", "anchors", "auto_anchors"], global = true)]
+    k_best: Option<usize>,
+
+    /// The data structure used as open list (priority queue) of the search.
+    ///
+    /// The default A* search only supports a binary heap, so with a bucket queue, an equivalent search implemented in this crate is used instead,
+    /// which is usually slower than the default search. With `--rederive-predecessors`, a bucket queue is usually slightly faster than a binary heap.
+    /// Negative and very large costs are not supported by a bucket queue, and the search continues with a binary heap when they occur.
+    #[clap(long, default_value = "heap", global = true)]
+    queue: Queue,
+
+    /// How nodes of equal priority are ordered in the open list.
+    ///
+    /// Near the target, many nodes have equal priority, so this can change the amount of nodes searched considerably, but not the cost of the alignment.
+    /// It is only used by the default A* search with `--queue heap` and by `--search beam`.
+    #[clap(long, default_value = "higher-cost", global = true)]
+    tie_break: TieBreakPolicy,
+
+    /// Search with the given amount of threads.
+    ///
+    /// The nodes are distributed among the threads by the hash of their position in the sequences, and each thread searches with its own `--queue`.
+    /// The alignment is still optimal, but the threads may explore more nodes in total than a single thread.
+    #[clap(
+        long,
+        conflicts_with_all = ["rederive_predecessors", "k_best", "anytime", "anchors_from", "anchors", "auto_anchors"],
+        global = true
+    )]
+    threads: Option<usize>,
+
+    /// Stop the search when it stores more than the given amount of memory, e.g. `512M` or `4G`.
+    ///
+    /// The memory is estimated from the amount of nodes in the open and closed list.
+    /// Only the A* search with `--queue heap` is limited, and the error reports a lower bound of the optimal cost.
+    #[clap(long, value_parser = parse_memory_size, conflicts_with = "node_limit", global = true)]
+    memory_limit: Option<usize>,
+
+    /// Stop the search when it stores more than the given amount of nodes, like `--memory-limit`.
+    #[clap(long, global = true)]
+    node_limit: Option<usize>,
+
+    /// When the `--memory-limit` or `--node-limit` is exceeded, restart with IDA* search instead of failing.
+    #[clap(long, global = true)]
+    memory_limit_fallback: bool,
+
+    /// Before searching, quickly find an alignment with a narrow beam search, and prune all nodes that cannot lead to a cheaper one.
+    ///
+    /// The alignment is still optimal. This mostly reduces the size of the open list.
+    #[clap(long, conflicts_with = "k_best", global = true)]
+    upper_bound_pruning: bool,
+
+    /// How the alignment whose cost prunes the search with `--upper-bound-pruning` is found.
+    #[clap(
+        long,
+        default_value = "beam",
+        requires = "upper_bound_pruning",
+        global = true
+    )]
+    upper_bound_from: UpperBoundFrom,
+
+    /// Do not generate nodes that are dominated by a closed node, i.e. that have the same offsets, cost at least as much
+    /// and are in a gap in at most the same sequences.
+    ///
+    /// The alignment is still optimal, and the amount of pruned nodes is logged.
+    /// This mostly helps with `--gap-open-cost`, where nodes that differ only in their gaps are not equal.
+    /// It requires a non-negative `--gap-open-cost`, and is ignored by `--search ida-star`, `--search beam`, `--threads` and `--k-best`.
+    #[clap(long, global = true)]
+    dominance_pruning: bool,
+
+    /// The lower bound of the remaining cost that guides the search.
+    ///
+    /// Lower bounds are only computed for sum-of-pairs metrics like `pairwise-cost` and `pairwise-match`,
+    /// and not with `--gap-open-cost`, free `--end-gaps`, `--gap-profile` or `--soft-masking`.
+    /// Otherwise, the search explores nodes in order of their cost alone.
+    #[clap(long, default_value = "pairwise", global = true)]
+    heuristic: Heuristic,
+
+    /// Compute the pairwise lower bounds of the `--heuristic` on a GPU, which speeds up the preprocessing of long sequences.
+    ///
+    /// Pairs of sequences whose costs may exceed the range of 32-bit integers are still aligned on the CPU.
+    /// If there is no GPU, the lower bounds are computed on the CPU.
+    #[cfg(feature = "gpu")]
+    #[clap(long, global = true)]
+    gpu: bool,
+
+    /// Multiply the lower bounds of the `--heuristic` by the given factor of at least one.
+    ///
+    /// This makes the search faster, but the alignment is only guaranteed to cost at most this factor times the optimal cost.
+    /// The actual bound is reported after the search.
+    #[clap(long, conflicts_with = "k_best", global = true)]
+    weight: Option<f64>,
+
+    /// Quickly find an alignment and keep improving it until it is optimal, writing each improved alignment to the output.
+    ///
+    /// The lower bounds are weighted like with `--weight`, which defaults to 2 here, and the weight decreases after each alignment found.
+    /// When the search is interrupted or reaches the `--time-limit`, the best alignment found so far is kept.
+    #[clap(long, conflicts_with_all = ["k_best", "anchors_from", "anchors", "auto_anchors"], global = true)]
+    anytime: bool,
+
+    /// Stop the search after the given wall-clock time, e.g. `90s`, `30m`, `2h` or `1h30m`, or seconds without a unit.
+    ///
+    /// With `--anytime`, the best alignment found so far is output, otherwise the alignment fails like when interrupted.
+    #[clap(long, value_parser = parse_duration, global = true)]
+    time_limit: Option<Duration>,
+
+    /// Fail with a non-zero exit code when the `--time-limit` is reached, even with `--anytime`.
+    ///
+    /// The lower bound of the optimal cost found so far is logged before failing,
+    /// and with `--anytime` the cost of the best alignment, which is output nevertheless.
+    #[clap(long, requires = "time_limit", global = true)]
+    fail_on_time_limit: bool,
+
+    /// Log the progress of the search in this interval, e.g. `10s` or `1m`, or `0` to not log it.
+    ///
+    /// The progress consists of the amount of closed and opened nodes, the current priority compared to the lower bound at the root,
+    /// and with `--upper-bound-pruning` an estimate of the remaining time.
+    /// The progress of a search with `--threads` is not logged.
+    #[clap(long, value_parser = parse_duration, default_value = "10s", global = true)]
+    progress_interval: Duration,
+
+    /// Estimate the resources needed for the alignment instead of aligning.
+    ///
+    /// This computes the lower bound of the optimal cost at the root, the amount of nodes in the search space between the anchors,
+    /// and the memory needed to store all of them, which is compared to `--memory-limit` or the physical memory.
+    /// If they do not fit, ways to make the search feasible are recommended, since the search then only finishes if the lower bounds guide it well.
+    #[clap(long, global = true)]
+    dry_run: bool,
+
+    /// The integer type of the costs during the search.
+    ///
+    /// Larger types allow for more expensive alignments, e.g. with large cost tables or many sequences, but use more memory.
+    /// Unsigned costs do not support negative entries in the cost table.
+    /// Before searching, it is checked that the costs fit into the type.
+    #[clap(long, default_value = "i32", global = true)]
+    cost_type: CostType,
+
+    /// The order of the sequences in the output.
+    #[clap(long, default_value = "input", global = true)]
+    sort_output: SortOutput,
+
+    /// Restrict the alphabet to a subset of its characters.
+    ///
+    /// If given without a value, the alphabet is restricted to the characters observed in the input.
+    /// If given a string of (ASCII) characters, the alphabet is restricted to these, and it is an error if the input contains any other character.
+    ///
+    /// In both cases, the metric only needs to cover the restricted alphabet, and this is checked before aligning.
+    #[clap(long, num_args = 0..=1, default_missing_value = "", global = true)]
+    restrict_alphabet: Option<String>,
+
+    /// A string of (ASCII) characters that are treated as wildcards.
+    ///
+    /// Wildcards match every residue at no cost, which is useful for masked or unknown residues.
+    /// How wildcards are scored against gaps depends on the metric.
+    #[clap(long, default_value = "", global = true)]
+    wildcards: String,
+
+    /// Report the sum-of-pairs cost and the total-column score of the alignment.
+    ///
+    /// Both are computed independently of the metric used for aligning.
+    /// The sum-of-pairs cost counts the pairs of unequal entries in each column, and the total-column score counts the columns in which all sequences have the same residue.
+    #[clap(long, global = true)]
+    report_scores: bool,
+
+    /// Append a row with statistics of the run to this TSV file, and write a header first if the file is new.
+    ///
+    /// The columns are the dataset (the input files), the amount of sequences, the total amount of residues, the alignment length, the cost,
+    /// the cost per residue, the amount of columns in which all sequences have the same residue, the runtime in seconds, and the amount of expanded nodes.
+    #[clap(long, global = true)]
+    stats_tsv: Option<PathBuf>,
+
+    /// Write detailed statistics of the run to this JSON file, e.g. for comparing parameters in benchmarks.
+    ///
+    /// The file contains the configuration and alignment hashes that are also logged, the amounts of expanded and generated nodes, the peak sizes of the open and closed list,
+    /// the lower bound at the root compared to the cost, the successors per expansion, the effective branching factor,
+    /// the runtime of reading the input, computing the lower bounds, searching, backtracking and writing the output, and the peak resident set size.
+    #[clap(long, global = true)]
+    stats_file: Option<PathBuf>,
+
+    /// Write a TSV file with statistics of each column of the alignment, e.g. for trimming poorly aligned regions.
+    ///
+    /// The columns are the column number, the amount of pairs of unequal entries, the Shannon entropy of the residues in bits,
+    /// the fraction of gaps, and the most frequent residue.
+    #[clap(long, global = true)]
+    column_stats_tsv: Option<PathBuf>,
+
+    /// Write a TSV file with the cost increment of each column under the metric, and the cost accumulated up to the column.
+    ///
+    /// Columns consisting only of gaps have an empty cost increment.
+    /// This also works with the score subcommand, e.g. to compare where different metrics accumulate cost on the same alignment.
+    #[clap(long, global = true)]
+    column_costs_tsv: Option<PathBuf>,
+
+    /// Write the nodes closed by the search to this file in Graphviz DOT format.
+    ///
+    /// Each node is labelled with its offsets and its g, h and f values, and the optimal path is highlighted.
+    /// This is meant for small inputs, and is not supported with `--rederive-predecessors`, `--k-best`, `--queue bucket` or anchors.
+    #[clap(long, global = true)]
+    dump_search_graph: Option<PathBuf>,
+
+    /// Only dump nodes whose offsets differ by at most this amount in each sequence from a node on the optimal path.
+    #[clap(long, requires = "dump_search_graph", global = true)]
+    dump_search_graph_max_distance: Option<usize>,
+
+    /// Remove sequences from the output whose identity to the consensus is below this fraction.
+    ///
+    /// The consensus of a column is its most frequent residue, and the identity of a sequence is the fraction of its residues that are equal to the consensus.
+    /// Columns that consist only of gaps after removing sequences are removed as well.
+    #[clap(long, global = true)]
+    min_pid_to_consensus: Option<f64>,
+
+    /// Compute a consensus sequence of the alignment with the given rule.
+    ///
+    /// Columns with fewer residues than gaps are left out of the consensus.
+    /// The consensus is added to the alignment as an extra record, unless `--consensus-output` is given.
+    #[clap(long, global = true)]
+    consensus: Option<Consensus>,
+
+    /// The fraction of residues of a column that must be equal to its most frequent residue for `--consensus min-identity`.
+    #[clap(long, default_value = "0.5", global = true)]
+    consensus_min_identity: f64,
+
+    /// Write the consensus sequence without gaps to this fasta file instead of adding it to the alignment.
+    #[clap(long, requires = "consensus", global = true)]
+    consensus_output: Option<PathBuf>,
+
+    /// The id of the consensus record.
+    #[clap(long, default_value = "consensus", global = true)]
+    consensus_id: String,
+
+    /// Add the single input sequence to this existing alignment in aligned fasta format, instead of aligning all sequences.
+    ///
+    /// The columns of the existing alignment are kept fixed, and the sequence is aligned to them exactly,
+    /// such that the result is the cheapest alignment that keeps the existing columns.
+    /// This is a much smaller search than realigning all sequences, but it does not support affine gaps, free end gaps or anchors.
+    #[clap(long, conflicts_with_all = ["anchors_from", "anchors", "auto_anchors", "band_guide", "k_best", "anytime", "dry_run", "consistency", "sequence_weights", "guide_tree_weights", "tree", "codons", "restrict_alphabet"], global = true)]
+    add_to: Option<PathBuf>,
+
+    /// A seed alignment from which anchor columns are taken.
+    ///
+    /// The records of the seed alignment must have the same ids and residues as the input sequences.
+    /// Columns of the seed alignment without gaps that are trusted according to `--anchor-threshold` are kept fixed.
+    /// The segments between these anchor columns are aligned independently and in parallel, and then concatenated.
+    #[clap(long, global = true)]
+    anchors_from: Option<PathBuf>,
+
+    /// A file of anchors through which the alignment is forced, with one anchor per line.
+    ///
+    /// Each anchor consists of one zero-based residue offset per input sequence, separated by whitespace or commas, and these residues are aligned in the same column.
+    /// The anchors must be strictly increasing in every sequence.
+    /// Like with `--anchors-from`, the segments between the anchors are aligned independently and in parallel, and then concatenated.
+    #[clap(long, conflicts_with = "anchors_from", global = true)]
+    anchors: Option<PathBuf>,
+
+    /// Find anchors automatically by chaining the exact matches shared by all sequences, which are seeded by k-mers of the given length.
+    ///
+    /// Only k-mers that occur exactly once in every sequence are used as seeds, and they are extended into maximal exact matches.
+    /// The chain of matches that covers the most residues is kept fixed, like with `--anchors`.
+    /// This speeds up aligning long similar sequences, but the alignment is only optimal among those that align the chained matches.
+    #[clap(long, conflicts_with_all = ["anchors_from", "anchors"], global = true)]
+    auto_anchors: Option<usize>,
+
+    /// The minimum fraction of the most frequent residue in a seed alignment column for it to be used as anchor.
+    #[clap(long, default_value = "1.0", global = true)]
+    anchor_threshold: f64,
+
+    /// A guide alignment around which the search is restricted to a band of `--band-width`.
+    ///
+    /// The records of the guide alignment must have the same ids and residues as the input sequences.
+    /// The alignment is then only optimal among the alignments within the band, which makes the search faster the narrower the band is.
+    #[clap(
+        long,
+        requires = "band_width",
+        conflicts_with = "anytime",
+        global = true
+    )]
+    band_guide: Option<PathBuf>,
+
+    /// The maximum amount of residues by which the offset of each sequence may deviate from the path of the `--band-guide`.
+    #[clap(long, requires = "band_guide", global = true)]
+    band_width: Option<usize>,
+
+    /// Annotate the codon positions of the reading frame of a reference sequence in the output.
+    ///
+    /// This outputs a `#=GC frame` line that marks each column in which the reference has a residue with its codon position (1, 2 or 3), and all other columns with a dot.
+    /// It is meant for coding DNA, where it helps to spot frameshifts.
+    #[clap(long, global = true)]
+    annotate_frame: bool,
+
+    /// The id of the reference sequence for `--annotate-frame`.
+    ///
+    /// If not given, the first input sequence is used.
+    #[clap(long, requires = "annotate_frame", global = true)]
+    frame_reference: Option<String>,
+
+    /// The codon position of the first residue of the reference sequence for `--annotate-frame`, counting from zero.
+    #[clap(long, default_value = "0", value_parser = clap::value_parser!(u8).range(0..=2), global = true)]
+    frame_offset: u8,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Compute the cost of an existing alignment without running the aligner.
+    ///
+    /// The alignment is scored with the same metric that would be used for aligning.
+    Score {
+        /// The alignment as aligned fasta file.
+        ///
+        /// Dash (`-`) and dot (`.`) characters are interpreted as gaps.
+        alignment: PathBuf,
+    },
+
+    /// Merge two existing alignments of different sequences into one, keeping the columns of both.
+    ///
+    /// The columns of the two alignments are aligned to each other with a dynamic program that scores each merged column with the metric,
+    /// which finds a cheapest merge among those that keep all columns of both alignments.
+    /// The records of the first alignment are output before those of the second, and their ids must be distinct.
+    /// The metric must not have affine gaps or free end gaps.
+    Merge {
+        /// The first alignment as aligned fasta file.
+        ///
+        /// Dash (`-`) and dot (`.`) characters are interpreted as gaps.
+        first: PathBuf,
+
+        /// The second alignment as aligned fasta file.
+        second: PathBuf,
+    },
+
+    /// Realign a window of columns of an existing alignment with the exact search.
+    ///
+    /// The residues of each sequence within the window are aligned to each other with the search selected by the other options,
+    /// and the resulting columns replace those of the window, while all other columns are kept.
+    /// If the realigned window costs more in the context of the whole alignment, which can happen with affine gaps, the original alignment is output.
+    Realign {
+        /// The alignment as aligned fasta file.
+        ///
+        /// Dash (`-`) and dot (`.`) characters are interpreted as gaps.
+        alignment: PathBuf,
+
+        /// The columns to realign, given as the first and last column separated by a dash, counting from one, e.g. `120-180`.
+        #[clap(long, value_parser = parse_column_range)]
+        region: Range<usize>,
+    },
+
+    /// Align the input sequences with a metric derived from a profile HMM.
+    ///
+    /// The emission and transition probabilities are averaged over all positions of the model.
+    /// Columns where at least half of the entries are residues are scored like match states, and other columns like insert states.
+    #[cfg(feature = "hmm")]
+    Hmm {
+        /// The profile HMM in HMMER3 format.
+        model: PathBuf,
+
+        /// The factor by which the negative log-probabilities of the model are multiplied before rounding them to integer costs.
+        #[clap(long, default_value = "100")]
+        scale: f64,
+    },
+
+    /// Inspect cost tables.
+    Matrix {
+        #[clap(subcommand)]
+        command: MatrixCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum MatrixCommand {
+    /// Check a cost table for properties that the aligner relies on, and report the maximum cost of a column.
+    ///
+    /// Missing, negative and asymmetric entries are reported as errors, and non-zero diagonal entries and violations of the triangle inequality as warnings.
+    /// The maximum cost of a column determines how long alignments can become with each `--cost-type` before the cost overflows.
+    /// The table is read with the alphabet given by `--alphabet` and the precision given by `--cost-table-precision`.
+    Check {
+        /// The cost table, in any format accepted by `--cost-table`.
+        table: PathBuf,
+
+        /// The amount of aligned sequences for which the maximum cost of a column is computed.
+        #[clap(long, default_value = "2", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(2..))]
+        sequence_amount: usize,
+    },
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, ValueEnum)]
+enum PhylipIdScheme {
+    /// Truncate ids to ten characters, and make colliding ids unique by replacing their ends with the index of the sequence.
+    Truncate,
+    /// Replace ids with `S` followed by the index of the sequence, and log the mapping.
+    Rename,
+    /// Write the full ids separated by a space, which is supported by e.g. RAxML and PhyML, but not by strict PHYLIP parsers.
+    Relaxed,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, ValueEnum)]
+enum Consensus {
+    /// The most frequent residue of each column.
+    Majority,
+    /// The most frequent residue of each column if enough residues are equal to it, see `--consensus-min-identity`, and `N` or `X` otherwise.
+    MinIdentity,
+    /// The IUPAC ambiguity code of the residues of each column, which is only supported for nucleotides.
+    Iupac,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, ValueEnum)]
+enum Compression {
+    /// Do not compress.
+    None,
+    Gzip,
+    Zstd,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, ValueEnum)]
+enum Queue {
+    /// A binary heap.
+    Heap,
+    /// A bucket queue with one bucket per integer cost.
+    Bucket,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, ValueEnum)]
+enum TieBreakPolicy {
+    /// Prefer the nodes with the higher cost, which are closer to the target.
+    HigherCost,
+    /// Prefer the nodes reached by a column of equal residues without gaps.
+    Matches,
+    /// Prefer the nodes reached by a column with fewer gaps.
+    FewerGaps,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, ValueEnum)]
+enum Mode {
+    /// Search for an optimal alignment with the `--search` algorithm.
+    Exact,
+    /// Align the sequences progressively along a guide tree built from their shared k-mers, see `--kmer-length`.
+    ///
+    /// From the leaves to the root, the alignments of the two subtrees of each node are aligned to each other, keeping their columns.
+    /// This is fast even for many or long sequences, but the alignment is not necessarily optimal.
+    /// The search options are ignored, and it cannot be combined with anchors, `--k-best`, `--dry-run` or `--add-to`.
+    Progressive,
+    /// Align all sequences to the center sequence, which has the minimum sum of pairwise alignment costs to all others, and merge these pairwise alignments.
+    ///
+    /// For sum-of-pairs metrics whose pairwise costs fulfil the triangle inequality, the alignment costs at most twice as much as an optimal one.
+    /// Its cost is reported next to the lower bound of the optimal cost, which shows how far from optimal it may be.
+    /// The search options are ignored, and it cannot be combined with anchors, `--k-best`, `--dry-run` or `--add-to`.
+    CenterStar,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, ValueEnum)]
+enum TreeMethod {
+    /// Neighbor-joining, which does not assume that the sequences evolve at the same rate.
+    NeighborJoining,
+    /// UPGMA, which joins the closest clusters and constructs a tree with the same distance from each inner node to all leaves below it.
+    Upgma,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, ValueEnum)]
+enum UpperBoundFrom {
+    /// A beam search of width 10.
+    Beam,
+    /// A progressive alignment like the one of `--mode progressive`, which is usually cheaper for long or diverse sequences.
+    ///
+    /// With `--band-guide`, the beam search is used instead.
+    Progressive,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, ValueEnum)]
+enum Search {
+    /// A best-first search that stores each node it reaches.
+    AStar,
+    /// An iterative-deepening A* search, which uses almost no memory, but searches each node again for each path to it.
+    ///
+    /// This is only feasible with tight lower bounds, i.e. for sum-of-pairs metrics and few or similar sequences, see `--heuristic`.
+    /// It cannot be combined with `--rederive-predecessors`, `--k-best`, `--anytime` or `--threads`.
+    IdaStar,
+    /// A beam search, which keeps only the `--beam-width` most promising nodes per column.
+    ///
+    /// It is fast and uses little memory, but the alignment is not necessarily optimal.
+    /// It cannot be combined with `--rederive-predecessors`, `--k-best`, `--anytime` or `--threads`.
+    Beam,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, ValueEnum)]
+enum Heuristic {
+    /// Optimal alignments of the remainders of each pair of sequences.
+    Pairwise,
+    /// Optimal alignments of the remainders of triples of sequences in input order, and of the remaining pairs.
+    ///
+    /// This explores fewer nodes, but takes time and memory cubic in the length of the sequences to precompute.
+    Triple,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, ValueEnum)]
+enum EndGaps {
+    /// Score end gaps like all other gaps.
+    Penalised,
+    /// End gaps cost nothing, including their gap open cost.
+    Free,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, ValueEnum)]
+enum AsymmetricMatrix {
+    /// Replace both entries of a pair with their average.
+    Average,
+    /// Replace both entries of a pair with the larger cost.
+    Max,
+    /// Use the row of the earlier and the column of the later sequence of each pair.
+    Ordered,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, ValueEnum)]
+enum CostType {
+    I16,
+    I32,
+    I64,
+    U64,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, ValueEnum)]
+enum SortOutput {
+    /// Keep the order of the input.
+    Input,
+    /// Order the sequences by a neighbor-joining tree built from the alignment, such that similar sequences are adjacent.
+    Tree,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, ValueEnum)]
@@ -71,11 +956,13 @@ enum InputAlphabet {
     DnaN,
     Rna,
     RnaN,
+    /// DNA with IUPAC ambiguity codes, which the `pairwise-match` metric scores as matches with the nucleotides they stand for.
     DnaIupac,
+    /// RNA with IUPAC ambiguity codes, which the `pairwise-match` metric scores as matches with the nucleotides they stand for.
     RnaIupac,
-    /// The IUPAC amino acid alphabet.
+    /// The IUPAC amino acid alphabet, which has no stop character `*`.
     IupacAminoAcid,
-    /// The FAMSA amino acid alphabet.
+    /// The FAMSA amino acid alphabet, which has the stop character `*`, see `--stop-codon-cost`.
     FamsaAminoAcid,
 }
 
@@ -107,43 +994,147 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn execute_with_alphabet<AlphabetType: Alphabet + Debug + Clone + Eq + 'static>(
+fn execute_with_alphabet<AlphabetType: Alphabet + Debug + Clone + Eq + Send + Sync + 'static>(
     cli: Cli,
-) -> Result<()> {
+) -> Result<()>
+where
+    AlphabetType::CharacterType: Send + Sync,
+{
+    let skip_characters = parse_skip_characters(&cli.skip_characters);
+
+    if let Some(Command::Score { alignment }) = &cli.command {
+        return score_with_alphabet::<AlphabetType>(alignment, &cli, &skip_characters);
+    }
+    if let Some(Command::Merge { first, second }) = &cli.command {
+        return merge_with_alphabet::<AlphabetType>(first, second, &cli, &skip_characters);
+    }
+    if let Some(Command::Realign { alignment, region }) = &cli.command {
+        return realign_with_alphabet::<AlphabetType>(
+            alignment,
+            region.clone(),
+            &cli,
+            &skip_characters,
+        );
+    }
+    if let Some(Command::Matrix {
+        command: MatrixCommand::Check {
+            table,
+            sequence_amount,
+        },
+    }) = &cli.command
+    {
+        return check_matrix_with_alphabet::<AlphabetType>(table, *sequence_amount, &cli);
+    }
+
     if cli.input.is_empty() {
         bail!("No input files given");
     }
 
-    let mut skip_characters = Vec::new();
-    for character in cli.skip_characters.bytes().map(usize::from) {
-        if skip_characters.len() <= character {
-            skip_characters.resize(character + 1, false);
-        }
-        skip_characters[character] = true;
-    }
-    let skip_characters = skip_characters;
+    let selected_ids: BTreeSet<_> = cli.select.iter().map(String::as_str).collect();
+    let is_selected = |id: &str| selected_ids.is_empty() || selected_ids.contains(id);
+    let mut found_ids = BTreeSet::new();
 
+    let input_start = Instant::now();
     let mut sequence_store = DefaultSequenceStore::<AlphabetType>::new();
     let mut records = Vec::new();
+    let mut soft_masks = Vec::new();
     for path in &cli.input {
-        info!("Loading fasta file {path:?}");
-        let path_records =
-            read_fasta_file(path, &mut sequence_store, false, true, &skip_characters)
-                .with_context(|| format!("Error loading file: {path:?}"))?;
+        let indexed_records = if selected_ids.is_empty() {
+            None
+        } else {
+            read_indexed_fasta_records(path, is_selected)
+                .with_context(|| format!("Error loading file: {path:?}"))?
+        };
+
+        let path_records = if let Some(indexed_records) = indexed_records {
+            indexed_records
+                .into_iter()
+                .map(|record| {
+                    let soft_mask = if cli.soft_masking {
+                        soft_mask(&record.sequence, &skip_characters)
+                    } else {
+                        Vec::new()
+                    };
+                    let sequence_handle = sequence_store
+                        .add_from_iter_u8(
+                            record
+                                .sequence
+                                .into_iter()
+                                .map(|character| character.to_ascii_uppercase())
+                                .filter(|character| {
+                                    !skip_characters
+                                        .get(usize::from(*character))
+                                        .copied()
+                                        .unwrap_or(false)
+                                }),
+                        )
+                        .with_context(|| {
+                            format!("Error loading record {} of file: {path:?}", record.id)
+                        })?;
+                    Ok((
+                        FastaRecord {
+                            id: record.id,
+                            comment: String::new(),
+                            sequence_handle,
+                        },
+                        soft_mask,
+                    ))
+                })
+                .collect::<Result<Vec<_>>>()?
+        } else {
+            info!("Loading fasta file {path:?}");
+            let path_records =
+                read_fasta_file(path, &mut sequence_store, false, true, &skip_characters)
+                    .with_context(|| format!("Error loading file: {path:?}"))?;
+            let path_soft_masks = if cli.soft_masking {
+                read_soft_masks(path, &skip_characters)
+                    .with_context(|| format!("Error loading soft masks of file: {path:?}"))?
+            } else {
+                vec![Vec::new(); path_records.len()]
+            };
+            if path_soft_masks.len() != path_records.len() {
+                bail!(
+                    "Found {} soft masks, but {} fasta records in file: {path:?}",
+                    path_soft_masks.len(),
+                    path_records.len()
+                );
+            }
+
+            path_records
+                .into_iter()
+                .zip(path_soft_masks)
+                .filter(|(record, _)| is_selected(&record.id))
+                .collect()
+        };
 
-        for mut record in path_records {
+        for (mut record, soft_mask) in path_records {
+            found_ids.insert(record.id.clone());
             if cli.input.len() > 1 {
                 record.id = format!("{path:?}-{}", record.id);
             }
 
             records.push(record);
+            soft_masks.push(soft_mask);
         }
     }
 
+    let missing_ids: Vec<_> = selected_ids
+        .iter()
+        .filter(|id| !found_ids.contains(**id))
+        .collect();
+    if !missing_ids.is_empty() {
+        bail!("Found no fasta records with selected ids {missing_ids:?}");
+    }
+
     if records.is_empty() {
         bail!("Found no fasta records in input files");
-    } else if records.len() == 1 {
+    } else if records.len() == 1 && cli.add_to.is_none() {
         bail!("Found only one fasta record in input files");
+    } else if records.len() > 1 && cli.add_to.is_some() {
+        bail!(
+            "Found {} fasta records in input files, but --add-to adds a single sequence",
+            records.len()
+        );
     }
 
     let mut record_ids: Vec<_> = records.iter().map(|record| record.id.clone()).collect();
@@ -158,6 +1149,7 @@ fn execute_with_alphabet<AlphabetType: Alphabet + Debug + Clone + Eq + 'static>(
     }
 
     info!("Loaded {} sequences", records.len());
+    let input_runtime = input_start.elapsed();
 
     let sequences: Vec<_> = records
         .iter()
@@ -168,13 +1160,1977 @@ fn execute_with_alphabet<AlphabetType: Alphabet + Debug + Clone + Eq + 'static>(
         })
         .collect();
 
-    match &cli.metric {
-        Some(csv) => multialign_astar(&sequences, PairwiseCostMetric::from_csv_file(csv)?),
-        None => multialign_astar(
-            &sequences,
-            PairwiseMatchMetric::<AlphabetType>::new(sequences.len())?,
-        ),
+    let soft_masks = if cli.soft_masking {
+        for ((record, sequence), soft_mask) in records.iter().zip(&sequences).zip(&soft_masks) {
+            if soft_mask.len() != sequence.len() {
+                bail!(
+                    "The soft mask of record {} has length {}, but its sequence has length {}",
+                    record.id,
+                    soft_mask.len(),
+                    sequence.len()
+                );
+            }
+        }
+        soft_masks
+    } else {
+        Vec::new()
+    };
+
+    let configuration_hash = configuration_hash(&cli, &records, &sequences, &soft_masks)?;
+    info!("Configuration hash: {configuration_hash:016x}");
+
+    if cli.search != Search::AStar
+        && (cli.rederive_predecessors
+            || cli.k_best.is_some()
+            || cli.anytime
+            || cli.threads.is_some())
+    {
+        bail!(
+            "Only the A* search can be combined with --rederive-predecessors, --k-best, --anytime or --threads"
+        );
+    }
+    if cli.mode != Mode::Exact
+        && (cli.anchors_from.is_some()
+            || cli.anchors.is_some()
+            || cli.auto_anchors.is_some()
+            || cli.k_best.is_some()
+            || cli.dry_run
+            || cli.add_to.is_some())
+    {
+        bail!(
+            "--mode {} cannot be combined with anchors, --k-best, --dry-run or --add-to",
+            cli.mode.to_possible_value().unwrap().get_name()
+        );
+    }
+    if cli.refine > 0
+        && (cli.anchors_from.is_some()
+            || cli.anchors.is_some()
+            || cli.auto_anchors.is_some()
+            || cli.k_best.is_some())
+    {
+        bail!("--refine cannot be combined with anchors or --k-best");
+    }
+    if cli.upper_bound_pruning && cli.search == Search::Beam {
+        bail!("--upper-bound-pruning cannot be combined with --search beam");
+    }
+    if cli.dominance_pruning && cli.gap_open_cost < 0 {
+        bail!("--dominance-pruning requires a non-negative --gap-open-cost");
+    }
+    if cli.beam_width.is_some() && cli.search != Search::Beam {
+        bail!("--beam-width requires --search beam");
+    }
+    if cli.beam_width == Some(0) {
+        bail!("The --beam-width must be at least one");
+    }
+    if cli.threads == Some(0) {
+        bail!("The amount of --threads must be at least one");
+    }
+
+    let cancellation = cancel_on_signals()?;
+    let search_start = Instant::now();
+    if let Some(time_limit) = cli.time_limit {
+        cancel_after(&cancellation, time_limit, "time limit");
+    }
+
+    let options = search_options(
+        &cli,
+        MultialignOptions {
+            band: cli
+                .band_guide
+                .as_ref()
+                .zip(cli.band_width)
+                .map(|(guide, width)| {
+                    let guide_records =
+                        read_aligned_fasta_file::<AlphabetType>(guide, &skip_characters)
+                            .with_context(|| format!("Error loading file: {guide:?}"))?;
+                    let sequence_characters: Vec<Vec<_>> = sequences
+                        .iter()
+                        .map(|sequence| sequence.iter().cloned().collect())
+                        .collect();
+                    Ok::<_, anyhow::Error>(Arc::new(Band::from_guide(
+                        &guide_records,
+                        records.iter().map(|record| record.id.as_str()),
+                        &sequence_characters,
+                        width,
+                    )?))
+                })
+                .transpose()?,
+            frame_annotation: cli
+                .annotate_frame
+                .then(|| {
+                    let reference = match &cli.frame_reference {
+                        Some(id) => records
+                            .iter()
+                            .position(|record| &record.id == id)
+                            .with_context(|| {
+                                format!("Found no fasta record with frame reference id {id}")
+                            })?,
+                        None => 0,
+                    };
+
+                    Ok::<_, anyhow::Error>(FrameAnnotation {
+                        reference,
+                        offset: cli.frame_offset.into(),
+                    })
+                })
+                .transpose()?,
+            reference: match &cli.reference_id {
+                Some(id) => records
+                    .iter()
+                    .position(|record| &record.id == id)
+                    .with_context(|| format!("Found no fasta record with reference id {id}"))?,
+                None => 0,
+            },
+            search_graph_dump: cli.dump_search_graph.as_ref().map(|path| SearchGraphDump {
+                path: path.clone(),
+                max_distance_to_optimal_path: cli.dump_search_graph_max_distance,
+            }),
+            cancellation,
+            statistics: cli.stats_tsv.as_ref().map(|path| StatisticsOutput {
+                path: path.clone(),
+                dataset: cli
+                    .input
+                    .iter()
+                    .map(|input| input.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(","),
+            }),
+            statistics_json: cli.stats_file.as_ref().map(|path| StatisticsJsonOutput {
+                path: path.clone(),
+                input_runtime,
+                configuration_hash: Some(configuration_hash),
+            }),
+            ..output_options(
+                &cli,
+                records.iter().map(|record| record.id.clone()).collect(),
+                soft_masks.clone(),
+            )?
+        },
+    );
+
+    let alphabet_subset = restrict_alphabet::<AlphabetType>(
+        &cli,
+        sequences.iter().flat_map(|sequence| sequence.iter()),
+    )?;
+
+    let anchors = cli
+        .anchors_from
+        .as_ref()
+        .map(|seed| {
+            let seed_records = read_aligned_fasta_file::<AlphabetType>(seed, &skip_characters)
+                .with_context(|| format!("Error loading file: {seed:?}"))?;
+            let sequence_characters: Vec<Vec<_>> = sequences
+                .iter()
+                .map(|sequence| sequence.iter().cloned().collect())
+                .collect();
+            Ok::<_, anyhow::Error>(anchors_from_seed(
+                &seed_records,
+                records.iter().map(|record| record.id.as_str()),
+                &sequence_characters,
+                cli.anchor_threshold,
+            )?)
+        })
+        .transpose()?
+        .or(cli
+            .anchors
+            .as_ref()
+            .map(|path| read_anchors_file(path, sequences.len()))
+            .transpose()?)
+        .or(cli
+            .auto_anchors
+            .map(|k| {
+                let sequence_characters: Vec<Vec<_>> = sequences
+                    .iter()
+                    .map(|sequence| sequence.iter().cloned().collect())
+                    .collect();
+                anchors_from_matches(&sequence_characters, k)
+            })
+            .transpose()?);
+
+    let sequence_ids: Vec<_> = records.iter().map(|record| record.id.as_str()).collect();
+    let weights = weights(&cli, &sequence_ids, || Ok(sequence_guide_tree(&sequences)?))?;
+    let consistency_library = consistency_library(&cli, &sequences)?;
+    let translations =
+        codon_translations::<AlphabetType>(&cli, sequences.iter().map(|sequence| sequence.iter()))?;
+    let sequence_info = SequenceInfo {
+        reference_length: sequences[0].len(),
+        soft_masks: &soft_masks,
+        translations: translations.as_ref(),
+    };
+    if let Some(add_to) = &cli.add_to {
+        let existing = read_aligned_fasta_file::<AlphabetType>(add_to, &skip_characters)
+            .with_context(|| format!("Error loading file: {add_to:?}"))?;
+        let Some(reference) = existing.first() else {
+            bail!("Found no fasta records in alignment: {add_to:?}");
+        };
+        if existing.iter().any(|record| record.id == records[0].id) {
+            bail!(
+                "The alignment {add_to:?} already contains a record with id {}",
+                records[0].id
+            );
+        }
+
+        // The sequence is added as the last row.
+        let record_ids = existing
+            .iter()
+            .map(|record| record.id.clone())
+            .chain([records[0].id.clone()])
+            .collect();
+        let soft_masks: Vec<_> = if cli.soft_masking {
+            existing
+                .iter()
+                .map(|record| record.soft_mask.clone())
+                .chain(soft_masks.iter().cloned())
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let sequence_info = SequenceInfo {
+            reference_length: reference.row.iter().flatten().count(),
+            soft_masks: &soft_masks,
+            translations: None,
+        };
+        return with_metric(
+            &cli,
+            existing.len() + 1,
+            sequence_info,
+            alphabet_subset.as_deref(),
+            None,
+            None,
+            AddTo {
+                existing: &existing,
+                sequence: sequences[0],
+                options: MultialignOptions {
+                    record_ids,
+                    soft_masks: soft_masks.clone(),
+                    ..options
+                },
+            },
+        );
+    }
+    if let Some(path) = &cli.write_guide_tree {
+        let sequence_characters: Vec<Vec<_>> = sequences
+            .iter()
+            .map(|sequence| sequence.iter().cloned().collect())
+            .collect();
+        let guide_tree = kmer_guide_tree(&sequence_characters, &options.guide_tree)?;
+        write_newick_file(path, &guide_tree, &sequence_ids)?;
+    }
+    if cli.dry_run {
+        return with_metric(
+            &cli,
+            sequences.len(),
+            sequence_info,
+            alphabet_subset.as_deref(),
+            weights.as_ref(),
+            consistency_library.as_ref(),
+            Estimate {
+                sequences: &sequences,
+                anchors: anchors.as_deref().unwrap_or_default(),
+                options: &options,
+                available_memory: cli
+                    .memory_limit
+                    .map(|bytes| bytes as f64)
+                    .or_else(physical_memory),
+            },
+        );
+    }
+    let alignments = with_metric(
+        &cli,
+        sequences.len(),
+        sequence_info,
+        alphabet_subset.as_deref(),
+        weights.as_ref(),
+        consistency_library.as_ref(),
+        Align {
+            sequences: &sequences,
+            mode: cli.mode,
+            anchors: anchors.clone(),
+            options: options.clone(),
+        },
+    )?;
+    if let Some(time_limit) = cli.time_limit.filter(|_| cli.fail_on_time_limit) {
+        // The search returns the best alignment found so far only with `--anytime`.
+        if options.cancellation.is_cancelled() && search_start.elapsed() >= time_limit {
+            bail!(
+                "Reached the time limit of {time_limit:?} before the alignment was proven optimal"
+            );
+        }
+    }
+    for table in &cli.rescore_table {
+        if cli.rescore_full_search {
+            info!("Realigning with cost table {table:?}");
+            with_cost_table(
+                &cli,
+                table,
+                sequence_info,
+                alphabet_subset.as_deref(),
+                weights.as_ref(),
+                Align {
+                    sequences: &sequences,
+                    mode: cli.mode,
+                    anchors: anchors.clone(),
+                    options: MultialignOptions {
+                        metric_name: metric_name(&cli, "pairwise-cost", Some(table)),
+                        ..options.clone()
+                    },
+                },
+            )?;
+        } else {
+            info!("Rescoring with cost table {table:?}");
+            with_cost_table::<AlphabetType, _>(
+                &cli,
+                table,
+                sequence_info,
+                alphabet_subset.as_deref(),
+                weights.as_ref(),
+                Rescore {
+                    alignments: &alignments,
+                },
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn score_with_alphabet<AlphabetType: Alphabet + Debug + Clone + Eq + Send + Sync + 'static>(
+    alignment: &PathBuf,
+    cli: &Cli,
+    skip_characters: &[bool],
+) -> Result<()>
+where
+    AlphabetType::CharacterType: Sync,
+{
+    let records = read_aligned_fasta_file::<AlphabetType>(alignment, skip_characters)
+        .with_context(|| format!("Error loading file: {alignment:?}"))?;
+
+    if records.len() < 2 {
+        bail!("Found less than two fasta records in alignment");
+    }
+
+    info!(
+        "Loaded alignment of {} sequences with {} columns",
+        records.len(),
+        records[0].row.len()
+    );
+
+    let columns = records_to_columns(&records);
+    let reference_length = records[0].row.iter().flatten().count();
+    let soft_masks: Vec<_> = if cli.soft_masking {
+        records
+            .iter()
+            .map(|record| record.soft_mask.clone())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let translations = codon_translations::<AlphabetType>(
+        cli,
+        records.iter().map(|record| record.row.iter().flatten()),
+    )?;
+    let sequence_info = SequenceInfo {
+        reference_length,
+        soft_masks: &soft_masks,
+        translations: translations.as_ref(),
+    };
+
+    let alphabet_subset =
+        restrict_alphabet::<AlphabetType>(cli, columns.iter().flatten().flatten())?;
+
+    if cli.report_scores {
+        report_scores::<AlphabetType>(&columns)?;
+    }
+
+    let sequence_ids: Vec<_> = records.iter().map(|record| record.id.as_str()).collect();
+    let weights = weights(cli, &sequence_ids, || {
+        Ok(neighbor_joining(&alignment_distance_matrix(&columns)))
+    })?;
+    let sequences: Vec<_> = records
+        .iter()
+        .map(|record| {
+            let mut sequence = VectorGenome::<AlphabetType>::default();
+            sequence.extend(record.row.iter().flatten().cloned());
+            sequence
+        })
+        .collect();
+    let sequences: Vec<_> = sequences
+        .iter()
+        .map(|sequence| sequence.as_genome_subsequence())
+        .collect();
+    let consistency_library = consistency_library(cli, &sequences)?;
+    with_metric::<AlphabetType, _>(
+        cli,
+        records.len(),
+        sequence_info,
+        alphabet_subset.as_deref(),
+        weights.as_ref(),
+        consistency_library.as_ref(),
+        Score {
+            columns: &columns,
+            column_costs: cli.column_costs_tsv.as_deref(),
+        },
+    )?;
+
+    for table in &cli.rescore_table {
+        info!("Rescoring with cost table {table:?}");
+        with_cost_table::<AlphabetType, _>(
+            cli,
+            table,
+            sequence_info,
+            alphabet_subset.as_deref(),
+            weights.as_ref(),
+            Rescore {
+                alignments: &[columns.clone()],
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+/// The options that select and configure the search, which do not depend on the input records, on top of the given options.
+fn search_options(cli: &Cli, options: MultialignOptions) -> MultialignOptions {
+    MultialignOptions {
+        search: match cli.search {
+            Search::AStar => SearchAlgorithm::AStar,
+            Search::IdaStar => SearchAlgorithm::IdaStar,
+            Search::Beam => SearchAlgorithm::Beam {
+                width: cli.beam_width.unwrap_or(DEFAULT_BEAM_WIDTH),
+            },
+        },
+        rederive_predecessors: cli.rederive_predecessors,
+        spill: cli.spill_dir.clone().map(|directory| SpillOptions {
+            directory,
+            cached_nodes: cli.spill_cache_nodes,
+        }),
+        k_best: cli.k_best,
+        open_list: match cli.queue {
+            Queue::Heap => OpenListKind::Heap,
+            Queue::Bucket => OpenListKind::Bucket,
+        },
+        tie_break: match cli.tie_break {
+            TieBreakPolicy::HigherCost => TieBreak::HigherCost,
+            TieBreakPolicy::Matches => TieBreak::Matches,
+            TieBreakPolicy::FewerGaps => TieBreak::FewerGaps,
+        },
+        threads: cli.threads,
+        progress_interval: Some(cli.progress_interval).filter(|interval| !interval.is_zero()),
+        memory_limit: cli
+            .memory_limit
+            .map(MemoryLimit::Bytes)
+            .or(cli.node_limit.map(MemoryLimit::Nodes)),
+        memory_limit_fallback: cli.memory_limit_fallback,
+        upper_bound_pruning: cli.upper_bound_pruning,
+        refine: cli.refine,
+        upper_bound_source: match cli.upper_bound_from {
+            UpperBoundFrom::Beam => UpperBoundSource::Beam,
+            UpperBoundFrom::Progressive => UpperBoundSource::Progressive,
+        },
+        guide_tree: GuideTreeOptions {
+            kmer_length: cli.kmer_length,
+            kimura_correction: cli.kimura_correction,
+            method: match cli.guide_tree_method {
+                TreeMethod::NeighborJoining => GuideTreeMethod::NeighborJoining,
+                TreeMethod::Upgma => GuideTreeMethod::Upgma,
+            },
+        },
+        dominance_pruning: cli.dominance_pruning,
+        heuristic: match cli.heuristic {
+            Heuristic::Pairwise => HeuristicKind::Pairwise,
+            Heuristic::Triple => HeuristicKind::Triple,
+        },
+        #[cfg(feature = "gpu")]
+        gpu: cli.gpu,
+        #[cfg(not(feature = "gpu"))]
+        gpu: false,
+        weight: cli.weight,
+        anytime: cli.anytime,
+        ..options
+    }
+}
+
+/// The options that control how an alignment of the records with the given ids and soft masks is output.
+///
+/// The other options are left at their defaults.
+fn output_options(
+    cli: &Cli,
+    record_ids: Vec<String>,
+    soft_masks: Vec<Vec<bool>>,
+) -> Result<MultialignOptions> {
+    let residue_type = match cli.alphabet {
+        InputAlphabet::Dna | InputAlphabet::DnaN | InputAlphabet::DnaIupac => ResidueType::Dna,
+        InputAlphabet::Rna | InputAlphabet::RnaN | InputAlphabet::RnaIupac => ResidueType::Rna,
+        InputAlphabet::IupacAminoAcid | InputAlphabet::FamsaAminoAcid => ResidueType::AminoAcid,
+    };
+
+    Ok(MultialignOptions {
+        gap_characters: GapCharacters {
+            terminal: cli.terminal_gap_char,
+            internal: cli.internal_gap_char,
+        },
+        output_order: match cli.sort_output {
+            SortOutput::Input => OutputOrder::Input,
+            SortOutput::Tree => OutputOrder::Tree,
+        },
+        min_identity_to_consensus: cli.min_pid_to_consensus,
+        report_scores: cli.report_scores,
+        output: cli.output.clone(),
+        metric_name: metric_name(cli, selected_metric(cli), cli.cost_table.as_deref()),
+        output_compression: cli
+            .output_compression
+            .as_ref()
+            .map(|compression| match compression {
+                Compression::None => OutputCompression::None,
+                Compression::Gzip => OutputCompression::Gzip,
+                Compression::Zstd => OutputCompression::Zstd,
+            }),
+        output_format: WriterRegistry::default()
+            .output_format(
+                &cli.output_format,
+                &WriterOptions {
+                    phylip_ids: match cli.phylip_ids {
+                        PhylipIdScheme::Truncate => PhylipIds::Truncate,
+                        PhylipIdScheme::Rename => PhylipIds::Rename,
+                        PhylipIdScheme::Relaxed => PhylipIds::Relaxed,
+                    },
+                    svg_residues_per_row: cli.svg_residues_per_row,
+                    svg_font_size: cli.svg_font_size,
+                },
+            )
+            .with_context(|| format!("Unknown output format {}", cli.output_format))?,
+        residue_type,
+        record_ids,
+        soft_masks,
+        column_statistics: cli.column_stats_tsv.clone(),
+        column_costs: cli.column_costs_tsv.clone(),
+        consensus: cli
+            .consensus
+            .as_ref()
+            .map(|consensus| {
+                let rule = match consensus {
+                    Consensus::Majority => ConsensusRule::Majority,
+                    Consensus::MinIdentity => {
+                        ConsensusRule::MinIdentity(cli.consensus_min_identity)
+                    }
+                    Consensus::Iupac => {
+                        if residue_type == ResidueType::AminoAcid {
+                            bail!("IUPAC consensus is only supported for nucleotide alphabets");
+                        }
+                        ConsensusRule::Iupac
+                    }
+                };
+
+                Ok(ConsensusOutput {
+                    rule,
+                    path: cli.consensus_output.clone(),
+                    id: cli.consensus_id.clone(),
+                })
+            })
+            .transpose()?,
+        ..Default::default()
+    })
+}
+
+fn merge_with_alphabet<AlphabetType: Alphabet + Debug + Clone + Eq + Send + Sync + 'static>(
+    first: &PathBuf,
+    second: &PathBuf,
+    cli: &Cli,
+    skip_characters: &[bool],
+) -> Result<()>
+where
+    AlphabetType::CharacterType: Sync,
+{
+    let input_start = Instant::now();
+    let mut alignments = Vec::new();
+    for path in [first, second] {
+        let records = read_aligned_fasta_file::<AlphabetType>(path, skip_characters)
+            .with_context(|| format!("Error loading file: {path:?}"))?;
+        if records.is_empty() {
+            bail!("Found no fasta records in alignment: {path:?}");
+        }
+        info!(
+            "Loaded alignment of {} sequences with {} columns from {path:?}",
+            records.len(),
+            records[0].row.len()
+        );
+        alignments.push(records);
+    }
+    let input_runtime = input_start.elapsed();
+    let (first_records, second_records) = (&alignments[0], &alignments[1]);
+    let records: Vec<_> = first_records.iter().chain(second_records).collect();
+
+    let mut record_ids: Vec<_> = records.iter().map(|record| record.id.clone()).collect();
+    record_ids.sort_unstable();
+    let duplicate_ids = list_duplicates(&record_ids);
+    if !duplicate_ids.is_empty() {
+        for duplicate_id in &duplicate_ids {
+            error!("Found duplicate id {duplicate_id}");
+        }
+
+        bail!("Found {} distinct duplicate ids", duplicate_ids.len());
+    }
+
+    let soft_masks: Vec<_> = if cli.soft_masking {
+        records
+            .iter()
+            .map(|record| record.soft_mask.clone())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let translations = codon_translations::<AlphabetType>(
+        cli,
+        records.iter().map(|record| record.row.iter().flatten()),
+    )?;
+    let sequence_info = SequenceInfo {
+        reference_length: records[0].row.iter().flatten().count(),
+        soft_masks: &soft_masks,
+        translations: translations.as_ref(),
+    };
+    let alphabet_subset = restrict_alphabet::<AlphabetType>(
+        cli,
+        records
+            .iter()
+            .flat_map(|record| record.row.iter().flatten()),
+    )?;
+
+    let sequence_ids: Vec<_> = records.iter().map(|record| record.id.as_str()).collect();
+    let weights = weights(cli, &sequence_ids, || {
+        // Before merging, rows of different alignments share no column, so their distance is one.
+        let columns: Vec<Vec<_>> = records_to_columns(first_records)
+            .into_iter()
+            .map(|column| {
+                column
+                    .into_iter()
+                    .chain(vec![None; second_records.len()])
+                    .collect()
+            })
+            .chain(
+                records_to_columns(second_records)
+                    .into_iter()
+                    .map(|column| {
+                        vec![None; first_records.len()]
+                            .into_iter()
+                            .chain(column)
+                            .collect()
+                    }),
+            )
+            .collect();
+        Ok(neighbor_joining(&alignment_distance_matrix(&columns)))
+    })?;
+
+    let options = MultialignOptions {
+        cancellation: cancel_on_signals()?,
+        statistics: cli.stats_tsv.as_ref().map(|path| StatisticsOutput {
+            path: path.clone(),
+            dataset: format!("{},{}", first.to_string_lossy(), second.to_string_lossy()),
+        }),
+        statistics_json: cli.stats_file.as_ref().map(|path| StatisticsJsonOutput {
+            path: path.clone(),
+            input_runtime,
+            configuration_hash: None,
+        }),
+        ..output_options(
+            cli,
+            records.iter().map(|record| record.id.clone()).collect(),
+            soft_masks.clone(),
+        )?
+    };
+    with_metric::<AlphabetType, _>(
+        cli,
+        records.len(),
+        sequence_info,
+        alphabet_subset.as_deref(),
+        weights.as_ref(),
+        None,
+        Merge {
+            first: first_records,
+            second: second_records,
+            options,
+        },
+    )
+}
+
+fn realign_with_alphabet<AlphabetType: Alphabet + Debug + Clone + Eq + Send + Sync + 'static>(
+    alignment: &PathBuf,
+    region: Range<usize>,
+    cli: &Cli,
+    skip_characters: &[bool],
+) -> Result<()>
+where
+    AlphabetType::CharacterType: Send + Sync,
+{
+    let input_start = Instant::now();
+    let records = read_aligned_fasta_file::<AlphabetType>(alignment, skip_characters)
+        .with_context(|| format!("Error loading file: {alignment:?}"))?;
+
+    if records.len() < 2 {
+        bail!("Found less than two fasta records in alignment");
+    }
+    if region.end > records[0].row.len() {
+        bail!(
+            "The region ends after column {}, but the alignment has only {} columns",
+            region.end,
+            records[0].row.len()
+        );
+    }
+
+    info!(
+        "Loaded alignment of {} sequences with {} columns",
+        records.len(),
+        records[0].row.len()
+    );
+    let input_runtime = input_start.elapsed();
+
+    let columns = records_to_columns(&records);
+    let soft_masks: Vec<_> = if cli.soft_masking {
+        records
+            .iter()
+            .map(|record| record.soft_mask.clone())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let translations = codon_translations::<AlphabetType>(
+        cli,
+        records.iter().map(|record| record.row.iter().flatten()),
+    )?;
+    let sequence_info = SequenceInfo {
+        reference_length: records[0].row.iter().flatten().count(),
+        soft_masks: &soft_masks,
+        translations: translations.as_ref(),
+    };
+    let alphabet_subset =
+        restrict_alphabet::<AlphabetType>(cli, columns.iter().flatten().flatten())?;
+
+    let sequence_ids: Vec<_> = records.iter().map(|record| record.id.as_str()).collect();
+    let weights = weights(cli, &sequence_ids, || {
+        Ok(neighbor_joining(&alignment_distance_matrix(&columns)))
+    })?;
+    let sequences: Vec<_> = records
+        .iter()
+        .map(|record| {
+            let mut sequence = VectorGenome::<AlphabetType>::default();
+            sequence.extend(record.row.iter().flatten().cloned());
+            sequence
+        })
+        .collect();
+    let sequences: Vec<_> = sequences
+        .iter()
+        .map(|sequence| sequence.as_genome_subsequence())
+        .collect();
+    let consistency_library = consistency_library(cli, &sequences)?;
+
+    let options = search_options(
+        cli,
+        MultialignOptions {
+            cancellation: cancel_on_signals()?,
+            statistics: cli.stats_tsv.as_ref().map(|path| StatisticsOutput {
+                path: path.clone(),
+                dataset: alignment.to_string_lossy().into_owned(),
+            }),
+            statistics_json: cli.stats_file.as_ref().map(|path| StatisticsJsonOutput {
+                path: path.clone(),
+                input_runtime,
+                configuration_hash: None,
+            }),
+            ..output_options(
+                cli,
+                records.iter().map(|record| record.id.clone()).collect(),
+                soft_masks.clone(),
+            )?
+        },
+    );
+    with_metric::<AlphabetType, _>(
+        cli,
+        records.len(),
+        sequence_info,
+        alphabet_subset.as_deref(),
+        weights.as_ref(),
+        consistency_library.as_ref(),
+        Realign {
+            sequences: &sequences,
+            columns: &columns,
+            region,
+            options,
+        },
+    )
+}
+
+/// An operation that requires a metric and a cost type, which are chosen at runtime.
+trait MetricOperation<AlphabetType: Alphabet> {
+    type Output;
+
+    fn execute<Cost: MultialignCost, Metric: MultialignMetric<AlphabetType> + Clone + Send>(
+        self,
+        metric: Metric,
+    ) -> Result<Self::Output>
+    where
+        Cost::CostType: TryFrom<i32>;
+}
+
+struct Align<'sequences, SequenceType: ?Sized> {
+    sequences: &'sequences [&'sequences SequenceType],
+    mode: Mode,
+    anchors: Option<Vec<Vec<usize>>>,
+    options: MultialignOptions,
+}
+
+impl<
+        AlphabetType: Alphabet + Debug + Clone + Eq + 'static,
+        SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized + Sync,
+    > MetricOperation<AlphabetType> for Align<'_, SequenceType>
+where
+    AlphabetType::CharacterType: Send,
+{
+    /// The columns of the alignments found, ordered by cost.
+    type Output = Vec<AlignmentColumns<AlphabetType::CharacterType>>;
+
+    fn execute<Cost: MultialignCost, Metric: MultialignMetric<AlphabetType> + Clone + Send>(
+        self,
+        metric: Metric,
+    ) -> Result<Self::Output>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        match self.mode {
+            Mode::Exact => {}
+            Mode::Progressive => {
+                return Ok(vec![
+                    multialign_progressive::<_, _, _, Cost>(self.sequences, metric, self.options)?
+                        .columns,
+                ]);
+            }
+            Mode::CenterStar => {
+                return Ok(vec![
+                    multialign_center_star::<_, _, _, Cost>(self.sequences, metric, self.options)?
+                        .columns,
+                ]);
+            }
+        }
+
+        Ok(match self.anchors {
+            Some(anchors) => vec![
+                multialign_astar_anchored::<_, _, _, Cost>(
+                    self.sequences,
+                    &anchors,
+                    metric,
+                    self.options,
+                )?
+                .columns,
+            ],
+            None => multialign_astar::<_, _, _, Cost>(self.sequences, metric, self.options)?
+                .into_iter()
+                .map(|alignment| alignment.columns)
+                .collect(),
+        })
+    }
+}
+
+/// Add a sequence to an existing alignment, see `--add-to`.
+struct AddTo<'sequences, CharacterType, SequenceType: ?Sized> {
+    existing: &'sequences [AlignedFastaRecord<CharacterType>],
+    sequence: &'sequences SequenceType,
+    options: MultialignOptions,
+}
+
+impl<
+        AlphabetType: Alphabet + Debug + Clone + Eq + 'static,
+        SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized,
+    > MetricOperation<AlphabetType> for AddTo<'_, AlphabetType::CharacterType, SequenceType>
+{
+    type Output = ();
+
+    fn execute<Cost: MultialignCost, Metric: MultialignMetric<AlphabetType> + Clone + Send>(
+        self,
+        metric: Metric,
+    ) -> Result<()>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        multialign_add_to::<_, _, _, Cost>(self.existing, self.sequence, metric, self.options)?;
+        Ok(())
+    }
+}
+
+struct Merge<'records, CharacterType> {
+    first: &'records [AlignedFastaRecord<CharacterType>],
+    second: &'records [AlignedFastaRecord<CharacterType>],
+    options: MultialignOptions,
+}
+
+impl<AlphabetType: Alphabet + Debug + Clone + Eq + 'static> MetricOperation<AlphabetType>
+    for Merge<'_, AlphabetType::CharacterType>
+{
+    type Output = ();
+
+    fn execute<Cost: MultialignCost, Metric: MultialignMetric<AlphabetType> + Clone + Send>(
+        self,
+        metric: Metric,
+    ) -> Result<()>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        multialign_merge::<_, _, Cost>(self.first, self.second, metric, self.options)?;
+        Ok(())
+    }
+}
+
+/// Realign a window of columns of an existing alignment, see the `realign` command.
+struct Realign<'sequences, CharacterType, SequenceType: ?Sized> {
+    sequences: &'sequences [&'sequences SequenceType],
+    columns: &'sequences [Vec<Option<CharacterType>>],
+    region: Range<usize>,
+    options: MultialignOptions,
+}
+
+impl<
+        AlphabetType: Alphabet + Debug + Clone + Eq + 'static,
+        SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized + Sync,
+    > MetricOperation<AlphabetType> for Realign<'_, AlphabetType::CharacterType, SequenceType>
+{
+    type Output = ();
+
+    fn execute<Cost: MultialignCost, Metric: MultialignMetric<AlphabetType> + Clone + Send>(
+        self,
+        metric: Metric,
+    ) -> Result<()>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        multialign_realign_region::<_, _, _, Cost>(
+            self.sequences,
+            self.columns,
+            self.region,
+            metric,
+            self.options,
+        )?;
+        Ok(())
+    }
+}
+
+/// Estimate the resources needed for aligning, see `--dry-run`.
+struct Estimate<'sequences, SequenceType: ?Sized> {
+    sequences: &'sequences [&'sequences SequenceType],
+    anchors: &'sequences [Vec<usize>],
+    options: &'sequences MultialignOptions,
+    /// The amount of bytes the search may use, if known.
+    available_memory: Option<f64>,
+}
+
+impl<
+        AlphabetType: Alphabet + Debug + Clone + Eq + 'static,
+        SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized + Sync,
+    > MetricOperation<AlphabetType> for Estimate<'_, SequenceType>
+{
+    type Output = ();
+
+    fn execute<Cost: MultialignCost, Metric: MultialignMetric<AlphabetType> + Clone + Send>(
+        self,
+        metric: Metric,
+    ) -> Result<()>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        let estimate =
+            estimate_search::<_, _, _, Cost>(self.sequences, self.anchors, metric, self.options)?;
+        match estimate.root_lower_bound {
+            Some(root_lower_bound) => info!("The optimal cost is at least {root_lower_bound}"),
+            None => warn!("The metric does not allow to compute lower bounds, so the search is not guided towards the end of the sequences"),
+        }
+        info!(
+            "The search space contains {:.3e} nodes of about {:.0} bytes each, {:.3e} bytes in total",
+            estimate.nodes,
+            estimate.bytes_per_node,
+            estimate.bytes()
+        );
+
+        let Some(available_memory) = self.available_memory else {
+            info!("The available memory is unknown, so the feasibility of the search cannot be estimated");
+            return Ok(());
+        };
+        if estimate.bytes() <= available_memory {
+            info!("The whole search space fits into {available_memory:.3e} bytes of memory");
+            return Ok(());
+        }
+
+        warn!(
+            "The search space does not fit into {available_memory:.3e} bytes of memory, so the search only finishes if the lower bounds guide it well"
+        );
+        warn!("To make the search feasible, consider");
+        warn!("  restricting it to a band around a guide alignment with --band-guide and --band-width");
+        warn!("  weighting the lower bounds with --weight, which trades optimality for speed");
+        warn!("  splitting it into segments with --auto-anchors or --anchors");
+        warn!("  storing less per node with --rederive-predecessors, or writing the nodes to disk with --spill-dir");
+        warn!("  aligning fewer sequences, e.g. selected with --select");
+        Ok(())
+    }
+}
+
+/// Returns the amount of bytes of physical memory, which is only known on Linux.
+fn physical_memory() -> Option<f64> {
+    let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+    let kilobytes = meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("MemTotal:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<f64>()
+        .ok()?;
+    Some(kilobytes * 1024.0)
+}
+
+struct Score<'columns, CharacterType> {
+    columns: &'columns [Vec<Option<CharacterType>>],
+    /// If set, the cost increment of each column is written to this file.
+    column_costs: Option<&'columns Path>,
+}
+
+impl<AlphabetType: Alphabet> MetricOperation<AlphabetType>
+    for Score<'_, AlphabetType::CharacterType>
+{
+    type Output = ();
+
+    fn execute<Cost: MultialignCost, Metric: MultialignMetric<AlphabetType> + Clone + Send>(
+        self,
+        mut metric: Metric,
+    ) -> Result<()>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        let cost: Cost = score_alignment(self.columns, &mut metric)?;
+        info!("Alignment cost {cost}");
+        if let Some(path) = self.column_costs {
+            write_column_costs(
+                path,
+                &column_costs::<_, Cost, _>(self.columns, &mut metric)?,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Score previously computed alignments without searching again, and report the cheapest.
+struct Rescore<'alignments, CharacterType> {
+    alignments: &'alignments [AlignmentColumns<CharacterType>],
+}
+
+impl<AlphabetType: Alphabet> MetricOperation<AlphabetType>
+    for Rescore<'_, AlphabetType::CharacterType>
+{
+    type Output = ();
+
+    fn execute<Cost: MultialignCost, Metric: MultialignMetric<AlphabetType> + Clone + Send>(
+        self,
+        mut metric: Metric,
+    ) -> Result<()>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        let mut cheapest: Option<(usize, Cost)> = None;
+        for (index, columns) in self.alignments.iter().enumerate() {
+            let cost = score_alignment(columns, &mut metric)?;
+            if self.alignments.len() > 1 {
+                info!("Alignment {} rescored cost {cost}", index + 1);
+            }
+            if cheapest.is_none_or(|(_, cheapest_cost)| cost < cheapest_cost) {
+                cheapest = Some((index, cost));
+            }
+        }
+
+        if let Some((index, cost)) = cheapest {
+            info!(
+                "Rescored cost {cost} of alignment {} (upper bound of the optimal cost)",
+                index + 1
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Check the given cost table, log its issues and the maximum cost of a column, and fail if it has errors.
+fn check_matrix_with_alphabet<AlphabetType: Alphabet>(
+    table: &Path,
+    sequence_amount: usize,
+    cli: &Cli,
+) -> Result<()> {
+    // Asymmetric tables are read as ordered tables, such that all asymmetric entries are reported.
+    let options = CostTableOptions {
+        asymmetric_costs: AsymmetricCosts::Ordered,
+        ..cost_table_options(cli)
+    };
+    let metric = PairwiseCostMetric::<AlphabetType>::from_file(table, &options)
+        .with_context(|| format!("Error loading cost table: {table:?}"))?;
+    let check = metric.check();
+    let is_error = |issue: &CostTableIssue<AlphabetType>| {
+        issue.is_error()
+            && !(matches!(issue, CostTableIssue::Asymmetric { .. })
+                && cli.allow_asymmetric_matrix.is_some())
+    };
+
+    for issue in &check.issues {
+        if is_error(issue) {
+            error!("{issue}");
+        } else {
+            warn!("{issue}");
+        }
+    }
+
+    if let (Some(max_cost), Some(max_column_cost)) =
+        (check.max_cost, check.max_column_cost(sequence_amount))
+    {
+        info!("The maximum cost of a pair is {max_cost}, so a column of {sequence_amount} sequences costs at most {max_column_cost}");
+        if max_column_cost > 0 {
+            for (cost_type, max) in [
+                (CostType::I16, i16::MAX as u128),
+                (CostType::I32, i32::MAX as u128),
+                (CostType::I64, i64::MAX as u128),
+                (CostType::U64, u64::MAX as u128),
+            ] {
+                info!(
+                    "With cost type {}, alignments of up to {} columns cannot overflow",
+                    cost_type.to_possible_value().unwrap().get_name(),
+                    max / max_column_cost
+                );
+            }
+        }
+    }
+
+    let error_amount = check.issues.iter().filter(|issue| is_error(issue)).count();
+    if error_amount > 0 {
+        bail!("The cost table has {error_amount} errors");
+    }
+    info!(
+        "The cost table is valid with {} warnings",
+        check.issues.len()
+    );
+    Ok(())
+}
+
+/// The properties of the aligned sequences that the metrics depend on.
+#[derive(Debug, Clone, Copy)]
+struct SequenceInfo<'sequences> {
+    /// The length of the reference sequence, which is the first sequence.
+    reference_length: usize,
+    /// The soft mask of each sequence, or nothing if soft masking is disabled.
+    soft_masks: &'sequences [Vec<bool>],
+    /// The amino acids of the codons of each sequence, if codons are aligned.
+    translations: Option<&'sequences Arc<Vec<Vec<char>>>>,
+}
+
+/// Construct the metric selected on the command line and execute the operation with it.
+fn with_metric<AlphabetType: Alphabet + Clone + Send + Sync + 'static, Output>(
+    cli: &Cli,
+    sequence_amount: usize,
+    sequence_info: SequenceInfo,
+    alphabet_subset: Option<&[AlphabetType::CharacterType]>,
+    weights: Option<&Weights>,
+    consistency_library: Option<&Arc<ConsistencyLibrary>>,
+    operation: impl MetricOperation<AlphabetType, Output = Output>,
+) -> Result<Output> {
+    #[cfg(feature = "hmm")]
+    if let Some(Command::Hmm { model, scale }) = &cli.command {
+        if cli.metric.is_some() || cli.cost_table.is_some() || cli.consistency {
+            bail!("The hmm subcommand does not support a metric");
+        }
+        if weights.is_some() {
+            bail!("The hmm subcommand does not support weights");
+        }
+        if cli.codons {
+            bail!("The hmm subcommand does not support codons");
+        }
+
+        return with_metric_wrappers(
+            cli,
+            Box::new(ProfileHmmMetric::from_hmm_file(model, *scale)?),
+            sequence_info,
+            alphabet_subset,
+            operation,
+        );
+    }
+
+    if let Some(consistency_library) = consistency_library {
+        return with_metric_wrappers(
+            cli,
+            Box::new(ConsistencyMetric::new(consistency_library.clone())),
+            sequence_info,
+            alphabet_subset,
+            operation,
+        );
+    }
+
+    if let Some(translations) = sequence_info.translations {
+        let amino_acid_metric = MetricRegistry::<FamsaAminoAcidAlphabet>::default()
+            .metric_from_specification(
+                selected_metric(cli),
+                &MetricArguments {
+                    sequence_amount,
+                    cost_table: cli.cost_table.clone(),
+                    cost_table_options: cost_table_options(cli),
+                    ..Default::default()
+                },
+            )
+            .with_context(|| format!("Error constructing metric {}", selected_metric(cli)))?;
+        return with_metric_wrappers(
+            cli,
+            Box::new(CodonMetric::new(
+                amino_acid_metric,
+                translations.clone(),
+                cli.frameshift_cost,
+            )?),
+            sequence_info,
+            alphabet_subset,
+            operation,
+        );
+    }
+
+    with_registered_metric(
+        cli,
+        selected_metric(cli),
+        &MetricArguments {
+            sequence_amount,
+            cost_table: cli.cost_table.clone(),
+            cost_table_options: cost_table_options(cli),
+            nucleotide_ambiguity_codes: matches!(
+                cli.alphabet,
+                InputAlphabet::DnaIupac | InputAlphabet::RnaIupac
+            ),
+            profile: cli.profile.clone(),
+            profile_scale: cli.profile_scale,
+            reference_length: sequence_info.reference_length,
+            #[cfg(feature = "script")]
+            script: cli.script.clone(),
+            #[cfg(not(feature = "script"))]
+            script: None,
+            transition_transversion_costs: transition_transversion_costs(cli),
+        },
+        sequence_info,
+        alphabet_subset,
+        weights,
+        operation,
+    )
+}
+
+/// Construct a metric from the given cost table and execute the operation with it.
+fn with_cost_table<AlphabetType: Alphabet + Clone + Send + Sync + 'static, Output>(
+    cli: &Cli,
+    csv: &Path,
+    sequence_info: SequenceInfo,
+    alphabet_subset: Option<&[AlphabetType::CharacterType]>,
+    weights: Option<&Weights>,
+    operation: impl MetricOperation<AlphabetType, Output = Output>,
+) -> Result<Output> {
+    with_registered_metric(
+        cli,
+        "pairwise-cost",
+        &MetricArguments {
+            cost_table: Some(csv.to_path_buf()),
+            cost_table_options: cost_table_options(cli),
+            ..Default::default()
+        },
+        sequence_info,
+        alphabet_subset,
+        weights,
+        operation,
+    )
+}
+
+/// Construct the metric described by the given specification and execute the operation with it, see [`MetricRegistry::metric_from_specification`].
+///
+/// With weights, the metric is constructed for a single pair of sequences and wrapped into a [`SequenceWeightedMetric`] or [`PairWeightedMetric`].
+fn with_registered_metric<AlphabetType: Alphabet + Clone + Send + Sync + 'static, Output>(
+    cli: &Cli,
+    name: &str,
+    arguments: &MetricArguments,
+    sequence_info: SequenceInfo,
+    alphabet_subset: Option<&[AlphabetType::CharacterType]>,
+    weights: Option<&Weights>,
+    operation: impl MetricOperation<AlphabetType, Output = Output>,
+) -> Result<Output> {
+    let registered_metric = |arguments| {
+        MetricRegistry::default()
+            .metric_from_specification(name, arguments)
+            .with_context(|| format!("Error constructing metric {name}"))
+    };
+    let pair_arguments = MetricArguments {
+        sequence_amount: 2,
+        ..arguments.clone()
+    };
+    let metric: BoxedMetric<AlphabetType> = match weights {
+        Some(Weights::Sequences(sequence_weights)) => Box::new(SequenceWeightedMetric::new(
+            registered_metric(&pair_arguments)?,
+            sequence_weights,
+        )?),
+        Some(Weights::Pairs(pair_weights)) => Box::new(PairWeightedMetric::new(
+            registered_metric(&pair_arguments)?,
+            pair_weights,
+        )?),
+        None => registered_metric(arguments)?,
+    };
+    with_metric_wrappers(cli, metric, sequence_info, alphabet_subset, operation)
+}
+
+/// Wrap the metric with the wildcards, gap profile, gap open cost, soft masks and end gaps given on the command line and execute the operation with it.
+///
+/// The operation is executed with the cost type given on the command line, which is only dispatched here.
+///
+/// All metrics are boxed, such that the aligner is compiled only once for each alphabet.
+fn with_metric_wrappers<AlphabetType: Alphabet + Clone + Send + Sync, Output>(
+    cli: &Cli,
+    metric: BoxedMetric<AlphabetType>,
+    sequence_info: SequenceInfo,
+    alphabet_subset: Option<&[AlphabetType::CharacterType]>,
+    operation: impl MetricOperation<AlphabetType, Output = Output>,
+) -> Result<Output> {
+    let wildcards = parse_characters::<AlphabetType>(&cli.wildcards)
+        .with_context(|| "Error parsing wildcards")?;
+    if !wildcards.is_empty() {
+        info!(
+            "Using wildcards {}",
+            wildcards
+                .iter()
+                .map(ToString::to_string)
+                .collect::<String>()
+        );
+    }
+
+    // Wrapping the metric also when no wildcards, gap profile, gap open cost, soft masks or free end gaps are given avoids duplicating the monomorphisations of the aligner.
+    let metric = WildcardMetric::new(metric, &wildcards);
+    let metric = match &cli.gap_profile {
+        Some(gap_profile) => {
+            GapProfileMetric::from_file(metric, gap_profile, sequence_info.reference_length)?
+        }
+        None => GapProfileMetric::new(metric),
+    };
+    let metric = AffineGapMetric::new(metric, cli.gap_open_cost);
+    let metric = if sequence_info.soft_masks.is_empty() {
+        SoftMaskMetric::new(metric)
+    } else {
+        SoftMaskMetric::with_masks(
+            metric,
+            sequence_info.soft_masks.to_vec(),
+            cli.soft_mask_cost_factor,
+            cli.soft_mask_penalty,
+        )?
+    };
+    let mut metric = EndGapMetric::new(metric, cli.end_gaps == EndGaps::Free);
+
+    if let Some(alphabet_subset) = alphabet_subset {
+        metric
+            .restrict_alphabet(alphabet_subset)
+            .with_context(|| "Metric does not cover the restricted alphabet")?;
+    }
+
+    match cli.cost_type {
+        CostType::I16 => operation.execute::<I16Cost, _>(metric),
+        CostType::I32 => operation.execute::<I32Cost, _>(metric),
+        CostType::I64 => operation.execute::<I64Cost, _>(metric),
+        CostType::U64 => operation.execute::<U64Cost, _>(metric),
+    }
+}
+
+/// The weights of the pairs of sequences of a sum-of-pairs metric.
+enum Weights {
+    /// One weight per sequence, see [`SequenceWeightedMetric`].
+    Sequences(Vec<f64>),
+    /// One weight per pair of sequences, see [`PairWeightedMetric`].
+    Pairs(Vec<Vec<f64>>),
+}
+
+/// Compute the weights selected on the command line for the sequences with the given ids, if any.
+///
+/// The guide tree is only constructed if the weights are computed from it.
+fn weights(
+    cli: &Cli,
+    sequence_ids: &[&str],
+    guide_tree: impl FnOnce() -> Result<GuideTree>,
+) -> Result<Option<Weights>> {
+    let sequence_amount = sequence_ids.len();
+    if let Some(path) = &cli.tree {
+        let pair_weights = read_newick_file(path, sequence_ids)
+            .with_context(|| format!("Error loading tree {path:?}"))?
+            .leaf_distances(sequence_amount);
+        info!("Pair weights: {pair_weights:?}");
+        return Ok(Some(Weights::Pairs(pair_weights)));
+    }
+
+    let sequence_weights = if let Some(path) = &cli.sequence_weights {
+        let sequence_weights = std::fs::read_to_string(path)
+            .with_context(|| format!("Error reading sequence weights {path:?}"))?
+            .split_whitespace()
+            .map(|weight| {
+                weight
+                    .parse()
+                    .with_context(|| format!("Error parsing sequence weight '{weight}'"))
+            })
+            .collect::<Result<Vec<f64>>>()?;
+        if sequence_weights.len() != sequence_amount {
+            bail!(
+                "Found {} sequence weights, but there are {sequence_amount} sequences",
+                sequence_weights.len()
+            );
+        }
+        sequence_weights
+    } else if cli.guide_tree_weights {
+        info!("Computing sequence weights from guide tree");
+        guide_tree()?.sequence_weights(sequence_amount)
+    } else {
+        return Ok(None);
+    };
+
+    info!("Sequence weights: {sequence_weights:?}");
+    Ok(Some(Weights::Sequences(sequence_weights)))
+}
+
+/// Build the consistency library of the given sequences if it is selected on the command line.
+fn consistency_library<
+    AlphabetType: Alphabet + Debug + Clone + Eq + Send + 'static,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized + Sync,
+>(
+    cli: &Cli,
+    sequences: &[&SequenceType],
+) -> Result<Option<Arc<ConsistencyLibrary>>> {
+    if !cli.consistency {
+        return Ok(None);
+    }
+
+    info!("Building consistency library");
+    let library = ConsistencyLibrary::from_sequences(sequences)?;
+    info!(
+        "Built consistency library with maximum weight {}",
+        library.max_weight()
+    );
+    Ok(Some(Arc::new(library)))
+}
+
+/// Translate the given sequences into amino acids if codons are aligned, see [`translate`].
+fn codon_translations<'characters, AlphabetType: Alphabet + 'characters>(
+    cli: &Cli,
+    sequences: impl IntoIterator<
+        Item = impl IntoIterator<Item = &'characters AlphabetType::CharacterType>,
+    >,
+) -> Result<Option<Arc<Vec<Vec<char>>>>> {
+    if !cli.codons {
+        return Ok(None);
+    }
+    if matches!(
+        cli.alphabet,
+        InputAlphabet::IupacAminoAcid | InputAlphabet::FamsaAminoAcid
+    ) {
+        bail!("Aligning codons requires a nucleotide alphabet");
+    }
+
+    info!("Translating codons");
+    Ok(Some(Arc::new(
+        sequences
+            .into_iter()
+            .map(|sequence| translate(sequence.into_iter().cloned()))
+            .collect(),
+    )))
+}
+
+/// Accept the names of the registered output formats, and show their descriptions in the help.
+fn output_format_parser() -> PossibleValuesParser {
+    PossibleValuesParser::new(
+        WriterRegistry::default().iter().map(|registration| {
+            PossibleValue::new(registration.name).help(registration.description)
+        }),
+    )
+}
+
+/// Accept the names of the registered metrics, and show their descriptions in the help.
+fn metric_parser() -> MetricParser {
+    // The registered names do not depend on the alphabet.
+    MetricParser(PossibleValuesParser::new(
+        MetricRegistry::<DnaAlphabet>::default()
+            .iter()
+            .map(|registration| {
+                PossibleValue::new(registration.name).help(registration.description)
+            }),
+    ))
+}
+
+/// Parses a metric specification, checking each of its names against the registered metrics, which are listed as possible values.
+#[derive(Clone)]
+struct MetricParser(PossibleValuesParser);
+
+impl TypedValueParser for MetricParser {
+    type Value = String;
+
+    fn parse_ref(
+        &self,
+        command: &ClapCommand,
+        argument: Option<&Arg>,
+        value: &OsStr,
+    ) -> std::result::Result<String, clap::Error> {
+        let specification = value.to_string_lossy();
+        let components = parse_metric_specification(&specification).map_err(|error| {
+            clap::Error::raw(ErrorKind::ValueValidation, format!("{error}\n")).with_cmd(command)
+        })?;
+        for (name, _) in components {
+            self.0.parse_ref(command, argument, OsStr::new(name))?;
+        }
+        Ok(specification.into_owned())
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue> + '_>> {
+        self.0.possible_values()
+    }
+}
+
+/// The name of the metric selected on the command line.
+fn selected_metric(cli: &Cli) -> &str {
+    if cli.consistency {
+        return "consistency";
+    }
+
+    #[cfg(feature = "script")]
+    let has_script = cli.script.is_some();
+    #[cfg(not(feature = "script"))]
+    let has_script = false;
+
+    match (&cli.metric, &cli.cost_table) {
+        (Some(metric), _) => metric,
+        (None, Some(_)) => "pairwise-cost",
+        (None, None) if cli.profile.is_some() => "profile",
+        (None, None) if has_script => "script",
+        (None, None)
+            if cli.transition_cost.is_some()
+                || cli.transversion_cost.is_some()
+                || cli.gap_cost.is_some() =>
+        {
+            "transition-transversion"
+        }
+        (None, None) => "pairwise-match",
+    }
+}
+
+/// The options for reading cost tables given on the command line.
+fn cost_table_options(cli: &Cli) -> CostTableOptions {
+    CostTableOptions {
+        precision: cli.cost_table_precision,
+        asymmetric_costs: match cli.allow_asymmetric_matrix {
+            None => AsymmetricCosts::Reject,
+            Some(AsymmetricMatrix::Average) => AsymmetricCosts::Average,
+            Some(AsymmetricMatrix::Max) => AsymmetricCosts::Max,
+            Some(AsymmetricMatrix::Ordered) => AsymmetricCosts::Ordered,
+        },
+        default_substitution_cost: cli.default_substitution_cost,
+        default_gap_cost: cli.default_gap_cost,
+        stop_codon_costs: cli
+            .stop_codon_cost
+            .zip(cli.stop_codon_gap_cost)
+            .map(|(substitution, gap)| StopCodonCosts { substitution, gap }),
+    }
+}
+
+/// The costs of the `transition-transversion` metric given on the command line, with the defaults for those that are not given.
+fn transition_transversion_costs(cli: &Cli) -> TransitionTransversionCosts {
+    let default = TransitionTransversionCosts::default();
+    TransitionTransversionCosts {
+        transition: cli.transition_cost.unwrap_or(default.transition),
+        transversion: cli.transversion_cost.unwrap_or(default.transversion),
+        gap: cli.gap_cost.unwrap_or(default.gap),
+    }
+}
+
+/// Returns a token that is cancelled on SIGINT or SIGTERM.
+///
+/// The first signal lets the alignment stop cleanly and report its progress, a second one terminates the process immediately.
+fn cancel_on_signals() -> Result<CancellationToken> {
+    let cancellation = CancellationToken::new();
+    for signal in [SIGINT, SIGTERM] {
+        // Registered first, such that it only triggers if the token was already cancelled by an earlier signal.
+        signal_hook::flag::register_conditional_shutdown(signal, 1, cancellation.flag())
+            .context("Could not register signal handler")?;
+        signal_hook::flag::register(signal, cancellation.flag())
+            .context("Could not register signal handler")?;
+    }
+    Ok(cancellation)
+}
+
+/// Parse an amount of bytes with an optional binary suffix, e.g. `512M` or `4GiB`.
+fn parse_memory_size(size: &str) -> std::result::Result<usize, String> {
+    let size = size.trim();
+    let digits = size
+        .find(|character: char| !character.is_ascii_digit())
+        .unwrap_or(size.len());
+    let (amount, suffix) = size.split_at(digits);
+    let amount: usize = amount
+        .parse()
+        .map_err(|_| format!("Invalid memory size {size:?}"))?;
+    let exponent = match suffix.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" | "KB" | "KIB" => 1,
+        "M" | "MB" | "MIB" => 2,
+        "G" | "GB" | "GIB" => 3,
+        "T" | "TB" | "TIB" => 4,
+        _ => return Err(format!("Unknown memory size suffix {suffix:?}")),
+    };
+    amount
+        .checked_mul(1usize << (10 * exponent))
+        .ok_or_else(|| format!("Memory size {size:?} is too large"))
+}
+
+/// Parse a duration made of amounts with the units `d`, `h`, `m`, `s` or `ms`, e.g. `1h30m`, or seconds without a unit.
+fn parse_duration(duration: &str) -> std::result::Result<Duration, String> {
+    let duration = duration.trim();
+    if duration.is_empty() {
+        return Err("Empty duration".to_string());
+    }
+    if let Ok(seconds) = duration.parse::<f64>() {
+        return Duration::try_from_secs_f64(seconds).map_err(|error| error.to_string());
+    }
+
+    let mut total = Duration::ZERO;
+    let mut remainder = duration;
+    while !remainder.is_empty() {
+        let amount_length = remainder
+            .find(|character: char| !character.is_ascii_digit() && character != '.')
+            .ok_or_else(|| format!("Missing unit in duration {duration:?}"))?;
+        let (amount, rest) = remainder.split_at(amount_length);
+        let unit_length = rest
+            .find(|character: char| character.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (unit, rest) = rest.split_at(unit_length);
+        let amount: f64 = amount
+            .parse()
+            .map_err(|_| format!("Invalid duration {duration:?}"))?;
+        let unit_seconds = match unit {
+            "d" => 86400.0,
+            "h" => 3600.0,
+            "m" | "min" => 60.0,
+            "s" => 1.0,
+            "ms" => 0.001,
+            _ => return Err(format!("Unknown unit {unit:?} in duration {duration:?}")),
+        };
+        total = Duration::try_from_secs_f64(amount * unit_seconds)
+            .ok()
+            .and_then(|amount| total.checked_add(amount))
+            .ok_or_else(|| format!("Duration {duration:?} is too long"))?;
+        remainder = rest;
+    }
+    Ok(total)
+}
+
+/// Parse a range of columns given as the first and last column separated by a dash, counting from one, e.g. `120-180`.
+///
+/// Returns the range of the columns counting from zero.
+fn parse_column_range(range: &str) -> std::result::Result<Range<usize>, String> {
+    let (first, last) = range
+        .split_once('-')
+        .ok_or_else(|| format!("Missing dash in column range {range:?}"))?;
+    let parse_column = |column: &str| {
+        column
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|column| *column > 0)
+            .ok_or_else(|| format!("Invalid column {column:?} in column range {range:?}"))
+    };
+    let (first, last) = (parse_column(first)?, parse_column(last)?);
+    if first > last {
+        return Err(format!(
+            "The first column is after the last column in column range {range:?}"
+        ));
+    }
+    Ok(first - 1..last)
+}
+
+/// Cancel the search once the given duration has passed, which is logged as reaching the given limit.
+fn cancel_after(cancellation: &CancellationToken, duration: Duration, limit: &'static str) {
+    let cancellation = cancellation.clone();
+    thread::spawn(move || {
+        thread::sleep(duration);
+        info!("Reached the {limit} of {duration:?}");
+        cancellation.cancel();
+    });
+}
+
+/// A human-readable name of the given metric with the wrappers selected on the command line.
+fn metric_name(cli: &Cli, metric: &str, cost_table: Option<&Path>) -> String {
+    let mut name = match cost_table {
+        Some(cost_table) => format!("{metric} with cost table {}", cost_table.display()),
+        None => metric.to_string(),
+    };
+
+    #[cfg(feature = "hmm")]
+    if let Some(Command::Hmm { model, scale }) = &cli.command {
+        if cost_table.is_none() {
+            name = format!("profile HMM {} scaled by {scale}", model.display());
+        }
+    }
+
+    let uses_metric = |registered_metric: &str| {
+        parse_metric_specification(metric).is_ok_and(|components| {
+            components
+                .iter()
+                .any(|(name, _)| *name == registered_metric)
+        })
+    };
+    if uses_metric("transition-transversion") {
+        let costs = transition_transversion_costs(cli);
+        name.push_str(&format!(
+            " with transition cost {}, transversion cost {} and gap cost {}",
+            costs.transition, costs.transversion, costs.gap
+        ));
+    }
+    if let (Some(profile), true) = (&cli.profile, uses_metric("profile")) {
+        name.push_str(&format!(
+            " with profile {} scaled by {}",
+            profile.display(),
+            cli.profile_scale
+        ));
+    }
+    #[cfg(feature = "script")]
+    if let (Some(script), true) = (&cli.script, uses_metric("script")) {
+        name.push_str(&format!(" with script {}", script.display()));
+    }
+    if let Some(gap_profile) = &cli.gap_profile {
+        name.push_str(&format!(" with gap profile {}", gap_profile.display()));
+    }
+    if !cli.wildcards.is_empty() {
+        name.push_str(&format!(" with wildcards {}", cli.wildcards));
+    }
+    if cli.gap_open_cost != 0 {
+        name.push_str(&format!(" with gap open cost {}", cli.gap_open_cost));
+    }
+    if cli.soft_masking && (cli.soft_mask_cost_factor != 1.0 || cli.soft_mask_penalty != 0) {
+        name.push_str(&format!(
+            " with soft-masked columns scaled by {} plus {}",
+            cli.soft_mask_cost_factor, cli.soft_mask_penalty
+        ));
+    }
+    if cli.codons {
+        name.push_str(&format!(
+            " on codons with frameshift cost {}",
+            cli.frameshift_cost
+        ));
+    }
+    if cli.end_gaps == EndGaps::Free {
+        name.push_str(" with free end gaps");
+    }
+    if let Some(sequence_weights) = &cli.sequence_weights {
+        name.push_str(&format!(
+            " with sequence weights {}",
+            sequence_weights.display()
+        ));
+    }
+    if cli.guide_tree_weights {
+        name.push_str(" with guide tree weights");
+    }
+    if let Some(tree) = &cli.tree {
+        name.push_str(&format!(" with tree {}", tree.display()));
+    }
+
+    name
+}
+
+/// Compute the restricted alphabet as selected on the command line.
+///
+/// Returns `None` if the alphabet is not restricted.
+fn restrict_alphabet<'characters, AlphabetType: Alphabet>(
+    cli: &Cli,
+    input_characters: impl IntoIterator<Item = &'characters AlphabetType::CharacterType>,
+) -> Result<Option<Vec<AlphabetType::CharacterType>>> {
+    let Some(restrict_alphabet) = &cli.restrict_alphabet else {
+        return Ok(None);
+    };
+
+    let observed_characters: BTreeSet<_> = input_characters.into_iter().cloned().collect();
+
+    let alphabet_subset = if restrict_alphabet.is_empty() {
+        observed_characters
+    } else {
+        let alphabet_subset: BTreeSet<_> = parse_characters::<AlphabetType>(restrict_alphabet)
+            .with_context(|| "Error parsing restricted alphabet")?
+            .into_iter()
+            .collect();
+
+        let unexpected_characters: String = observed_characters
+            .difference(&alphabet_subset)
+            .map(ToString::to_string)
+            .collect();
+        if !unexpected_characters.is_empty() {
+            bail!("Input contains characters outside of the restricted alphabet: {unexpected_characters}");
+        }
+
+        alphabet_subset
+    };
+
+    info!(
+        "Restricted alphabet to {}",
+        alphabet_subset
+            .iter()
+            .map(ToString::to_string)
+            .collect::<String>()
+    );
+
+    Ok(Some(alphabet_subset.into_iter().collect()))
+}
+
+/// Parse a string of (ASCII) characters into characters of the alphabet.
+///
+/// Lower-case characters are parsed as upper-case.
+fn parse_characters<AlphabetType: Alphabet>(
+    characters: &str,
+) -> Result<Vec<AlphabetType::CharacterType>> {
+    characters
+        .bytes()
+        .map(|character| {
+            AlphabetType::ascii_to_character(character.to_ascii_uppercase()).with_context(|| {
+                format!(
+                    "Character is not part of the alphabet: {:?}",
+                    char::from(character)
+                )
+            })
+        })
+        .collect()
+}
+
+/// The name of a value of an enum argument as given on the command line.
+fn value_name(value: &impl ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+/// Compute a stable hash of everything that influences the alignment.
+///
+/// This includes the input records, the alphabet, the contents of all metric-related files, and all relevant flags.
+fn configuration_hash<
+    AlphabetType: Alphabet,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized,
+    Handle,
+>(
+    cli: &Cli,
+    records: &[FastaRecord<Handle>],
+    sequences: &[&SequenceType],
+    soft_masks: &[Vec<bool>],
+) -> Result<u64> {
+    let mut hasher = StableHasher::new();
+
+    value_name(&cli.alphabet).stable_hash(&mut hasher);
+    for (record, sequence) in records.iter().zip(sequences) {
+        record.id.stable_hash(&mut hasher);
+        sequence.as_string().stable_hash(&mut hasher);
+    }
+
+    selected_metric(cli).stable_hash(&mut hasher);
+
+    let mut hash_file = |file: Option<&PathBuf>| -> Result<()> {
+        file.map(|file| {
+            std::fs::read(file).with_context(|| format!("Error reading file {file:?}"))
+        })
+        .transpose()?
+        .stable_hash(&mut hasher);
+        Ok(())
+    };
+    hash_file(cli.cost_table.as_ref())?;
+    hash_file(cli.gap_profile.as_ref())?;
+    hash_file(cli.profile.as_ref())?;
+    #[cfg(feature = "script")]
+    hash_file(cli.script.as_ref())?;
+    hash_file(cli.sequence_weights.as_ref())?;
+    hash_file(cli.tree.as_ref())?;
+    hash_file(cli.anchors_from.as_ref())?;
+    hash_file(cli.anchors.as_ref())?;
+    hash_file(cli.band_guide.as_ref())?;
+    hash_file(cli.add_to.as_ref())?;
+    for rescore_table in &cli.rescore_table {
+        hash_file(Some(rescore_table))?;
+    }
+    #[cfg(feature = "hmm")]
+    if let Some(Command::Hmm { model, scale }) = &cli.command {
+        hash_file(Some(model))?;
+        scale.stable_hash(&mut hasher);
+    }
+    cli.profile_scale.stable_hash(&mut hasher);
+    cli.rescore_full_search.stable_hash(&mut hasher);
+
+    cli.skip_characters.stable_hash(&mut hasher);
+    cli.terminal_gap_char.stable_hash(&mut hasher);
+    cli.internal_gap_char.stable_hash(&mut hasher);
+    value_name(&cli.mode).stable_hash(&mut hasher);
+    cli.kmer_length.stable_hash(&mut hasher);
+    cli.kimura_correction.stable_hash(&mut hasher);
+    value_name(&cli.guide_tree_method).stable_hash(&mut hasher);
+    cli.refine.stable_hash(&mut hasher);
+    value_name(&cli.search).stable_hash(&mut hasher);
+    cli.beam_width.stable_hash(&mut hasher);
+    // Writing the lists to disk selects the search with re-derived predecessors, but where and how much does not matter.
+    (cli.rederive_predecessors || cli.spill_dir.is_some()).stable_hash(&mut hasher);
+    cli.k_best.stable_hash(&mut hasher);
+    value_name(&cli.queue).stable_hash(&mut hasher);
+    value_name(&cli.tie_break).stable_hash(&mut hasher);
+    cli.threads.stable_hash(&mut hasher);
+    cli.memory_limit.stable_hash(&mut hasher);
+    cli.node_limit.stable_hash(&mut hasher);
+    cli.memory_limit_fallback.stable_hash(&mut hasher);
+    cli.upper_bound_pruning.stable_hash(&mut hasher);
+    value_name(&cli.upper_bound_from).stable_hash(&mut hasher);
+    cli.dominance_pruning.stable_hash(&mut hasher);
+    value_name(&cli.heuristic).stable_hash(&mut hasher);
+    cli.weight.stable_hash(&mut hasher);
+    cli.anytime.stable_hash(&mut hasher);
+    // With `--anytime`, the alignment depends on when the search is stopped.
+    cli.time_limit.stable_hash(&mut hasher);
+    cli.fail_on_time_limit.stable_hash(&mut hasher);
+    value_name(&cli.cost_type).stable_hash(&mut hasher);
+    value_name(&cli.sort_output).stable_hash(&mut hasher);
+    cli.restrict_alphabet.stable_hash(&mut hasher);
+    cli.wildcards.stable_hash(&mut hasher);
+    cli.gap_open_cost.stable_hash(&mut hasher);
+    value_name(&cli.end_gaps).stable_hash(&mut hasher);
+    cli.cost_table_precision.stable_hash(&mut hasher);
+    cli.allow_asymmetric_matrix
+        .as_ref()
+        .map(value_name)
+        .stable_hash(&mut hasher);
+    cli.default_substitution_cost.stable_hash(&mut hasher);
+    cli.default_gap_cost.stable_hash(&mut hasher);
+    cli.stop_codon_cost.stable_hash(&mut hasher);
+    cli.stop_codon_gap_cost.stable_hash(&mut hasher);
+    cli.codons.stable_hash(&mut hasher);
+    cli.transition_cost.stable_hash(&mut hasher);
+    cli.transversion_cost.stable_hash(&mut hasher);
+    cli.gap_cost.stable_hash(&mut hasher);
+    cli.frameshift_cost.stable_hash(&mut hasher);
+    cli.soft_masking.stable_hash(&mut hasher);
+    soft_masks.stable_hash(&mut hasher);
+    cli.soft_mask_cost_factor.stable_hash(&mut hasher);
+    cli.soft_mask_penalty.stable_hash(&mut hasher);
+    cli.guide_tree_weights.stable_hash(&mut hasher);
+    cli.report_scores.stable_hash(&mut hasher);
+    cli.min_pid_to_consensus.stable_hash(&mut hasher);
+    cli.consensus
+        .as_ref()
+        .map(value_name)
+        .stable_hash(&mut hasher);
+    cli.consensus_min_identity.stable_hash(&mut hasher);
+    cli.consensus_id.stable_hash(&mut hasher);
+    cli.anchor_threshold.stable_hash(&mut hasher);
+    cli.auto_anchors.stable_hash(&mut hasher);
+    cli.band_width.stable_hash(&mut hasher);
+    cli.annotate_frame.stable_hash(&mut hasher);
+    cli.frame_reference.stable_hash(&mut hasher);
+    cli.frame_offset.stable_hash(&mut hasher);
+
+    Ok(hasher.finish())
+}
+
+fn parse_skip_characters(skip_characters: &str) -> Vec<bool> {
+    let mut result = Vec::new();
+    for character in skip_characters.bytes().map(usize::from) {
+        if result.len() <= character {
+            result.resize(character + 1, false);
+        }
+        result[character] = true;
     }
+    result
 }
 
 fn list_duplicates<T: Eq + Ord>(slice: &[T]) -> Vec<&T> {
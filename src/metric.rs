@@ -0,0 +1,633 @@
+use std::{any::type_name, path::PathBuf};
+
+use compact_genome::interface::alphabet::Alphabet;
+use composite_metric::CompositeMetric;
+use consensus_metric::ConsensusMetric;
+use gap_count_metric::GapCountMetric;
+use generic_a_star::cost::{AStarCost, I64Cost};
+use pairwise_cost_metric::{CostTableOptions, PairwiseCostMetric};
+use pairwise_match_metric::PairwiseMatchMetric;
+use profile_metric::ProfileMetric;
+#[cfg(feature = "script")]
+use script_metric::ScriptMetric;
+
+use super::error::{MultialignError, Result};
+
+pub mod affine_gap_metric;
+pub mod codon_metric;
+pub mod composite_metric;
+pub mod consensus_metric;
+pub mod consistency_metric;
+pub mod end_gap_metric;
+pub mod gap_count_metric;
+pub mod gap_profile_metric;
+pub mod pair_weighted_metric;
+pub mod pairwise_cost_metric;
+pub mod pairwise_match_metric;
+#[cfg(feature = "hmm")]
+pub mod profile_hmm_metric;
+pub mod profile_metric;
+#[cfg(feature = "script")]
+pub mod script_metric;
+pub mod sequence_weighted_metric;
+pub mod soft_mask_metric;
+pub mod wildcard_metric;
+
+/// A metric that computes the cost of a column of an alignment from the characters and gaps of its sequences.
+///
+/// For each column, the counts are reset, and then exactly one of the count methods is called for each sequence.
+/// Each count method receives the index of the sequence among the aligned sequences.
+/// Metrics that only depend on how often each character occurs in a column can ignore it,
+/// while e.g. per-sequence weights or per-pair cost tables require it.
+pub trait MultialignMetric<AlphabetType: Alphabet> {
+    fn reset_character_counts(&mut self);
+
+    /// Sets the offset of the first sequence before the column that is about to be counted.
+    ///
+    /// This is called after [`Self::reset_character_counts`], and the default implementation ignores it.
+    fn set_reference_offset(&mut self, offset: usize) {
+        let _ = offset;
+    }
+
+    /// Restricts the metric to the given characters, which are all the characters that will ever be counted.
+    ///
+    /// Returns an error if the metric cannot score all pairs of the given characters and gaps.
+    /// The default implementation does nothing.
+    fn restrict_alphabet(&mut self, characters: &[AlphabetType::CharacterType]) -> Result<()> {
+        let _ = characters;
+        Ok(())
+    }
+
+    /// Counts the character of the sequence with the given index in the current column.
+    fn count_character(&mut self, character: &AlphabetType::CharacterType, sequence_index: usize);
+
+    /// Counts a wildcard character, which matches every residue.
+    ///
+    /// The default implementation counts it like any other character.
+    fn count_wildcard(&mut self, character: &AlphabetType::CharacterType, sequence_index: usize) {
+        self.count_character(character, sequence_index);
+    }
+
+    /// Counts a gap of the sequence with the given index in the current column.
+    fn count_gap(&mut self, sequence_index: usize);
+
+    /// Counts a gap in a sequence that had a residue in the previous column, or a gap in the first column.
+    ///
+    /// This is only called if [`Self::has_affine_gaps`] returns true, and the default implementation counts it like any other gap.
+    fn count_gap_open(&mut self, sequence_index: usize) {
+        self.count_gap(sequence_index);
+    }
+
+    /// Returns true if opening a gap costs more than extending it.
+    ///
+    /// Then the aligner keeps track of which sequences are in a gap, which multiplies the amount of nodes of the search.
+    fn has_affine_gaps(&self) -> bool {
+        false
+    }
+
+    /// Counts a gap before the first or after the last residue of the sequence with the given index.
+    ///
+    /// Such terminal gaps should not contribute to the cost of the column.
+    /// This is only called if [`Self::has_free_end_gaps`] returns true, and the default implementation counts it like any other gap.
+    fn count_terminal_gap(&mut self, sequence_index: usize) {
+        self.count_gap(sequence_index);
+    }
+
+    /// Returns true if gaps before the first or after the last residue of a sequence are free.
+    ///
+    /// Then the aligner counts such gaps with [`Self::count_terminal_gap`], and they never open an affine gap.
+    /// The end gaps of the longest sequence are still counted as usual gaps, such that the other sequences are aligned as fragments of it.
+    fn has_free_end_gaps(&self) -> bool {
+        false
+    }
+
+    /// Returns true if the cost of a column depends on which residues of the sequences are in it, and not only on their characters.
+    ///
+    /// Then the aligner calls [`Self::set_offset`] for each sequence before counting it.
+    fn uses_offsets(&self) -> bool {
+        false
+    }
+
+    /// Sets the amount of residues that the sequence with the given index has before the column that is about to be counted.
+    ///
+    /// The offsets are relative to the start of the input sequences, also when aligning the segments between anchors.
+    /// This is only called if [`Self::uses_offsets`] returns true, and the default implementation ignores it.
+    fn set_offset(&mut self, sequence_index: usize, offset: usize) {
+        let _ = (sequence_index, offset);
+    }
+
+    /// Returns true if the cost of a column is the sum of the costs of each pair of its entries, including wildcards.
+    ///
+    /// The cost of a pair must be the cost of a column in which only the two entries of the pair are counted, and pairs of gaps must not have a negative cost.
+    /// Then the aligner uses optimal alignments of all pairs of sequences as a lower bound of the remaining cost, which greatly speeds up the search.
+    /// Metrics with affine gaps, free end gaps or offsets must return false, and the default implementation returns false.
+    fn is_sum_of_pairs(&self) -> bool {
+        false
+    }
+
+    fn compute_cost_increment<Cost: AStarCost>(&mut self) -> Result<Cost>
+    where
+        Cost::CostType: TryFrom<i32>;
+}
+
+/// A [`MultialignMetric`] that can be used as a trait object, which is implemented for all cloneable metrics.
+///
+/// [`MultialignMetric::compute_cost_increment`] is generic over the cost type, so the metric computes its costs as [`I64Cost`],
+/// which are then converted into the cost type of the search.
+pub trait DynMultialignMetric<AlphabetType: Alphabet>: Send {
+    fn reset_character_counts(&mut self);
+
+    fn set_reference_offset(&mut self, offset: usize);
+
+    fn restrict_alphabet(&mut self, characters: &[AlphabetType::CharacterType]) -> Result<()>;
+
+    fn count_character(&mut self, character: &AlphabetType::CharacterType, sequence_index: usize);
+
+    fn count_wildcard(&mut self, character: &AlphabetType::CharacterType, sequence_index: usize);
+
+    fn count_gap(&mut self, sequence_index: usize);
+
+    fn count_gap_open(&mut self, sequence_index: usize);
+
+    fn has_affine_gaps(&self) -> bool;
+
+    fn count_terminal_gap(&mut self, sequence_index: usize);
+
+    fn has_free_end_gaps(&self) -> bool;
+
+    fn uses_offsets(&self) -> bool;
+
+    fn set_offset(&mut self, sequence_index: usize, offset: usize);
+
+    fn is_sum_of_pairs(&self) -> bool;
+
+    fn compute_i64_cost_increment(&mut self) -> Result<I64Cost>;
+
+    fn clone_boxed(&self) -> BoxedMetric<AlphabetType>;
+}
+
+/// A metric whose type is chosen at runtime, e.g. by name from a [`MetricRegistry`].
+///
+/// Using a single metric type for all metrics also avoids compiling the aligner once per metric.
+pub type BoxedMetric<AlphabetType> = Box<dyn DynMultialignMetric<AlphabetType>>;
+
+impl<AlphabetType: Alphabet, Metric: MultialignMetric<AlphabetType> + Clone + Send + 'static>
+    DynMultialignMetric<AlphabetType> for Metric
+{
+    fn reset_character_counts(&mut self) {
+        MultialignMetric::reset_character_counts(self);
+    }
+
+    fn set_reference_offset(&mut self, offset: usize) {
+        MultialignMetric::set_reference_offset(self, offset);
+    }
+
+    fn restrict_alphabet(&mut self, characters: &[AlphabetType::CharacterType]) -> Result<()> {
+        MultialignMetric::restrict_alphabet(self, characters)
+    }
+
+    fn count_character(&mut self, character: &AlphabetType::CharacterType, sequence_index: usize) {
+        MultialignMetric::count_character(self, character, sequence_index);
+    }
+
+    fn count_wildcard(&mut self, character: &AlphabetType::CharacterType, sequence_index: usize) {
+        MultialignMetric::count_wildcard(self, character, sequence_index);
+    }
+
+    fn count_gap(&mut self, sequence_index: usize) {
+        MultialignMetric::count_gap(self, sequence_index);
+    }
+
+    fn count_gap_open(&mut self, sequence_index: usize) {
+        MultialignMetric::count_gap_open(self, sequence_index);
+    }
+
+    fn has_affine_gaps(&self) -> bool {
+        MultialignMetric::has_affine_gaps(self)
+    }
+
+    fn count_terminal_gap(&mut self, sequence_index: usize) {
+        MultialignMetric::count_terminal_gap(self, sequence_index);
+    }
+
+    fn has_free_end_gaps(&self) -> bool {
+        MultialignMetric::has_free_end_gaps(self)
+    }
+
+    fn uses_offsets(&self) -> bool {
+        MultialignMetric::uses_offsets(self)
+    }
+
+    fn set_offset(&mut self, sequence_index: usize, offset: usize) {
+        MultialignMetric::set_offset(self, sequence_index, offset);
+    }
+
+    fn is_sum_of_pairs(&self) -> bool {
+        MultialignMetric::is_sum_of_pairs(self)
+    }
+
+    fn compute_i64_cost_increment(&mut self) -> Result<I64Cost> {
+        MultialignMetric::compute_cost_increment(self)
+    }
+
+    fn clone_boxed(&self) -> BoxedMetric<AlphabetType> {
+        Box::new(self.clone())
+    }
+}
+
+impl<AlphabetType: Alphabet> Clone for BoxedMetric<AlphabetType> {
+    fn clone(&self) -> Self {
+        self.as_ref().clone_boxed()
+    }
+}
+
+impl<AlphabetType: Alphabet> MultialignMetric<AlphabetType> for BoxedMetric<AlphabetType> {
+    fn reset_character_counts(&mut self) {
+        self.as_mut().reset_character_counts();
+    }
+
+    fn set_reference_offset(&mut self, offset: usize) {
+        self.as_mut().set_reference_offset(offset);
+    }
+
+    fn restrict_alphabet(&mut self, characters: &[AlphabetType::CharacterType]) -> Result<()> {
+        self.as_mut().restrict_alphabet(characters)
+    }
+
+    fn count_character(&mut self, character: &AlphabetType::CharacterType, sequence_index: usize) {
+        self.as_mut().count_character(character, sequence_index);
+    }
+
+    fn count_wildcard(&mut self, character: &AlphabetType::CharacterType, sequence_index: usize) {
+        self.as_mut().count_wildcard(character, sequence_index);
+    }
+
+    fn count_gap(&mut self, sequence_index: usize) {
+        self.as_mut().count_gap(sequence_index);
+    }
+
+    fn count_gap_open(&mut self, sequence_index: usize) {
+        self.as_mut().count_gap_open(sequence_index);
+    }
+
+    fn has_affine_gaps(&self) -> bool {
+        self.as_ref().has_affine_gaps()
+    }
+
+    fn count_terminal_gap(&mut self, sequence_index: usize) {
+        self.as_mut().count_terminal_gap(sequence_index);
+    }
+
+    fn has_free_end_gaps(&self) -> bool {
+        self.as_ref().has_free_end_gaps()
+    }
+
+    fn uses_offsets(&self) -> bool {
+        self.as_ref().uses_offsets()
+    }
+
+    fn set_offset(&mut self, sequence_index: usize, offset: usize) {
+        self.as_mut().set_offset(sequence_index, offset);
+    }
+
+    fn is_sum_of_pairs(&self) -> bool {
+        self.as_ref().is_sum_of_pairs()
+    }
+
+    fn compute_cost_increment<Cost: AStarCost>(&mut self) -> Result<Cost>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        let cost = self.as_mut().compute_i64_cost_increment()?.as_primitive();
+        i32::try_from(cost)
+            .map_err(|_| MultialignError::CostOutOfRange {
+                cost,
+                cost_type: cost_type_name::<Cost>(),
+            })
+            .and_then(cost_from_i32)
+    }
+}
+
+/// The arguments from which a metric is constructed by a [`MetricRegistration`].
+///
+/// Each metric uses only the arguments it needs.
+#[derive(Debug, Clone, Default)]
+pub struct MetricArguments {
+    /// The amount of sequences that are aligned.
+    pub sequence_amount: usize,
+    /// A CSV file containing a pairwise cost table, or an NCBI substitution matrix.
+    pub cost_table: Option<PathBuf>,
+    /// How the cost table is read, see [`PairwiseCostMetric::from_file`].
+    pub cost_table_options: CostTableOptions,
+    /// True if the alphabet consists of IUPAC nucleotide codes, such that ambiguity codes can match the nucleotides they stand for.
+    pub nucleotide_ambiguity_codes: bool,
+    /// An aligned fasta file of a sequence family, whose profile is used by the `profile` metric.
+    pub profile: Option<PathBuf>,
+    /// The factor by which the negative log-frequencies of the profile are multiplied before rounding them to integer costs.
+    pub profile_scale: f64,
+    /// The length of the reference sequence, which is the first sequence.
+    pub reference_length: usize,
+    /// A script that computes the cost of each column for the `script` metric.
+    pub script: Option<PathBuf>,
+    /// The costs of the `transition-transversion` metric.
+    pub transition_transversion_costs: TransitionTransversionCosts,
+}
+
+/// The costs of a nucleotide metric that distinguishes transitions from transversions, see [`PairwiseCostMetric::from_transition_transversion_costs`].
+#[derive(Debug, Clone, Copy)]
+pub struct TransitionTransversionCosts {
+    pub transition: i32,
+    pub transversion: i32,
+    pub gap: i32,
+}
+
+impl Default for TransitionTransversionCosts {
+    fn default() -> Self {
+        Self {
+            transition: 1,
+            transversion: 2,
+            gap: 3,
+        }
+    }
+}
+
+/// A metric that can be selected by name.
+#[derive(Debug, Clone, Copy)]
+pub struct MetricRegistration<AlphabetType> {
+    /// The name used to select the metric, e.g. on the command line.
+    pub name: &'static str,
+    /// A short human-readable description of the metric.
+    pub description: &'static str,
+    /// True if the metric is constructed from [`MetricArguments::cost_table`].
+    ///
+    /// In a composite metric, the cost table is only given to such metrics.
+    pub uses_cost_table: bool,
+    /// Create the metric from the given arguments.
+    pub create: fn(&MetricArguments) -> Result<BoxedMetric<AlphabetType>>,
+}
+
+/// The metrics that can be selected by name.
+#[derive(Debug, Clone)]
+pub struct MetricRegistry<AlphabetType> {
+    registrations: Vec<MetricRegistration<AlphabetType>>,
+}
+
+impl<AlphabetType> MetricRegistry<AlphabetType> {
+    /// Create a registry without any metrics.
+    pub fn empty() -> Self {
+        Self {
+            registrations: Vec::new(),
+        }
+    }
+
+    /// Add a metric to the registry, replacing any metric with the same name.
+    pub fn register(&mut self, registration: MetricRegistration<AlphabetType>) {
+        self.registrations
+            .retain(|other| other.name != registration.name);
+        self.registrations.push(registration);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&MetricRegistration<AlphabetType>> {
+        self.registrations
+            .iter()
+            .find(|registration| registration.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &MetricRegistration<AlphabetType>> {
+        self.registrations.iter()
+    }
+
+    /// Create the metric with the given name, or return `None` if no metric with this name is registered.
+    pub fn metric(
+        &self,
+        name: &str,
+        arguments: &MetricArguments,
+    ) -> Option<Result<BoxedMetric<AlphabetType>>> {
+        self.get(name)
+            .map(|registration| (registration.create)(arguments))
+    }
+}
+
+impl<AlphabetType: Alphabet + Clone + Send + 'static> MetricRegistry<AlphabetType> {
+    /// Create the metric described by the given specification, see [`parse_metric_specification`].
+    ///
+    /// A single name without weight creates the registered metric itself, and everything else creates a [`CompositeMetric`].
+    /// In a composite metric, the cost table of the arguments is only given to the metrics that use it.
+    pub fn metric_from_specification(
+        &self,
+        specification: &str,
+        arguments: &MetricArguments,
+    ) -> Result<BoxedMetric<AlphabetType>> {
+        let registration = |name: &str| {
+            self.get(name).ok_or_else(|| {
+                MultialignError::InvalidMetricArguments(format!("Unknown metric {name}"))
+            })
+        };
+
+        let components = parse_metric_specification(specification)?;
+        if let [(name, None)] = components.as_slice() {
+            return (registration(name)?.create)(arguments);
+        }
+
+        let metrics = components
+            .into_iter()
+            .map(|(name, weight)| {
+                let registration = registration(name)?;
+                let metric = if registration.uses_cost_table {
+                    (registration.create)(arguments)?
+                } else {
+                    (registration.create)(&MetricArguments {
+                        cost_table: None,
+                        ..arguments.clone()
+                    })?
+                };
+                Ok((metric, weight.unwrap_or(1)))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Box::new(CompositeMetric::new(metrics)?))
+    }
+}
+
+/// Parse a metric specification of the form `name:weight+name:weight`, returning the name and weight of each metric.
+///
+/// Weights are non-negative integers and can be omitted together with their colon, in which case they are `None`.
+pub fn parse_metric_specification(specification: &str) -> Result<Vec<(&str, Option<u32>)>> {
+    specification
+        .split('+')
+        .map(|component| {
+            let component = component.trim();
+            let (name, weight) = match component.split_once(':') {
+                Some((name, weight)) => (
+                    name.trim(),
+                    Some(weight.trim().parse().map_err(|_| {
+                        MultialignError::InvalidMetricArguments(format!(
+                            "Invalid weight '{weight}' of metric {name}, expected a non-negative integer"
+                        ))
+                    })?),
+                ),
+                None => (component, None),
+            };
+            if name.is_empty() {
+                return Err(MultialignError::InvalidMetricArguments(format!(
+                    "Missing metric name in specification '{specification}'"
+                )));
+            }
+            Ok((name, weight))
+        })
+        .collect()
+}
+
+impl<AlphabetType: Alphabet + Clone + Send + 'static> Default for MetricRegistry<AlphabetType> {
+    /// Create a registry with all metrics of this crate that can be constructed from [`MetricArguments`].
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        for registration in [
+            MetricRegistration {
+                name: "pairwise-match",
+                description: "Sum of pairs, where each mismatching pair has a cost of one",
+                uses_cost_table: false,
+                create: |arguments| {
+                    if arguments.cost_table.is_some() {
+                        return Err(MultialignError::InvalidMetricArguments(
+                            "pairwise-match does not use a cost table".to_string(),
+                        ));
+                    }
+                    let metric = PairwiseMatchMetric::new(arguments.sequence_amount)?;
+                    Ok(Box::new(if arguments.nucleotide_ambiguity_codes {
+                        metric.with_nucleotide_ambiguity_codes()
+                    } else {
+                        metric
+                    }))
+                },
+            },
+            MetricRegistration {
+                name: "pairwise-cost",
+                description: "Sum of pairs, where each pair is scored with a cost table",
+                uses_cost_table: true,
+                create: |arguments| {
+                    let cost_table = arguments.cost_table.as_ref().ok_or_else(|| {
+                        MultialignError::InvalidMetricArguments(
+                            "pairwise-cost requires a cost table".to_string(),
+                        )
+                    })?;
+                    Ok(Box::new(PairwiseCostMetric::from_file(
+                        cost_table,
+                        &arguments.cost_table_options,
+                    )?))
+                },
+            },
+            MetricRegistration {
+                name: "consensus",
+                description: "Star cost, where each entry that differs from the most frequent entry of its column has a cost of one",
+                uses_cost_table: false,
+                create: |arguments| {
+                    if arguments.cost_table.is_some() {
+                        return Err(MultialignError::InvalidMetricArguments(
+                            "consensus does not use a cost table".to_string(),
+                        ));
+                    }
+                    Ok(Box::new(ConsensusMetric::new()))
+                },
+            },
+            MetricRegistration {
+                name: "gap-count",
+                description: "Each gap has a cost of one, regardless of the residues",
+                uses_cost_table: false,
+                create: |arguments| {
+                    if arguments.cost_table.is_some() {
+                        return Err(MultialignError::InvalidMetricArguments(
+                            "gap-count does not use a cost table".to_string(),
+                        ));
+                    }
+                    Ok(Box::new(GapCountMetric::new()))
+                },
+            },
+            MetricRegistration {
+                name: "gap-open-count",
+                description: "Each opened gap has a cost of one, regardless of the residues",
+                uses_cost_table: false,
+                create: |arguments| {
+                    if arguments.cost_table.is_some() {
+                        return Err(MultialignError::InvalidMetricArguments(
+                            "gap-open-count does not use a cost table".to_string(),
+                        ));
+                    }
+                    Ok(Box::new(GapCountMetric::gap_openings()))
+                },
+            },
+            MetricRegistration {
+                name: "transition-transversion",
+                description: "Sum of pairs, where transitions, transversions and gaps have separate costs",
+                uses_cost_table: false,
+                create: |arguments| {
+                    if arguments.cost_table.is_some() {
+                        return Err(MultialignError::InvalidMetricArguments(
+                            "transition-transversion does not use a cost table".to_string(),
+                        ));
+                    }
+                    let costs = arguments.transition_transversion_costs;
+                    Ok(Box::new(
+                        PairwiseCostMetric::from_transition_transversion_costs(
+                            costs.transition,
+                            costs.transversion,
+                            costs.gap,
+                        ),
+                    ))
+                },
+            },
+            MetricRegistration {
+                name: "profile",
+                description: "Each entry is scored by its frequency in the column of a profile alignment that the reference residue is aligned to",
+                uses_cost_table: false,
+                create: |arguments| {
+                    let profile = arguments.profile.as_ref().ok_or_else(|| {
+                        MultialignError::InvalidMetricArguments(
+                            "profile requires a profile alignment".to_string(),
+                        )
+                    })?;
+                    Ok(Box::new(ProfileMetric::from_file(
+                        profile,
+                        arguments.profile_scale,
+                        arguments.reference_length,
+                    )?))
+                },
+            },
+        ] {
+            registry.register(registration);
+        }
+
+        #[cfg(feature = "script")]
+        registry.register(MetricRegistration {
+            name: "script",
+            description: "Each column is scored by a user-defined script",
+            uses_cost_table: false,
+            create: |arguments| {
+                let script = arguments.script.as_ref().ok_or_else(|| {
+                    MultialignError::InvalidMetricArguments("script requires a script".to_string())
+                })?;
+                Ok(Box::new(ScriptMetric::from_file(script)?))
+            },
+        });
+
+        registry
+    }
+}
+
+/// Convert a cost computed by a metric into the given cost type, or return an error if it is out of range of the cost type.
+pub(crate) fn cost_from_i32<Cost: AStarCost>(cost: i32) -> Result<Cost>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    Cost::CostType::try_from(cost)
+        .map(Cost::from)
+        .map_err(|_| MultialignError::CostOutOfRange {
+            cost: cost.into(),
+            cost_type: cost_type_name::<Cost>(),
+        })
+}
+
+/// The name of the given cost type without its module path, e.g. `I32Cost`.
+pub(crate) fn cost_type_name<Cost>() -> &'static str {
+    let name = type_name::<Cost>();
+    name.rsplit("::").next().unwrap_or(name)
+}
@@ -0,0 +1,115 @@
+use std::marker::PhantomData;
+
+use compact_genome::interface::alphabet::Alphabet;
+use generic_a_star::cost::AStarCost;
+
+use super::{cost_from_i32, MultialignMetric};
+use crate::error::{MultialignError, Result};
+
+/// A metric that adds a cost for opening gaps to another metric, such that gaps become affine.
+///
+/// The wrapped metric scores every gap as usual, which is the cost of extending a gap.
+/// In addition, each gap that is opened in a column is charged the gap open cost once per residue in that column,
+/// like a sum-of-pairs metric charges a gap once per residue it is paired with.
+/// A gap is opened if its sequence had a residue in the previous column, or if it is in the first column.
+/// With a gap open cost of zero, gaps are linear and the aligner does not track which sequences are in a gap.
+#[derive(Clone)]
+pub struct AffineGapMetric<AlphabetType, Metric> {
+    metric: Metric,
+    gap_open_cost: i32,
+    residue_count: i32,
+    gap_open_count: i32,
+    phantom_data: PhantomData<AlphabetType>,
+}
+
+impl<AlphabetType: Alphabet, Metric: MultialignMetric<AlphabetType>>
+    AffineGapMetric<AlphabetType, Metric>
+{
+    /// Wrap the given metric, charging the given cost for each opened gap and residue in its column.
+    pub fn new(metric: Metric, gap_open_cost: i32) -> Self {
+        Self {
+            metric,
+            gap_open_cost,
+            residue_count: 0,
+            gap_open_count: 0,
+            phantom_data: PhantomData,
+        }
+    }
+}
+
+impl<AlphabetType: Alphabet, Metric: MultialignMetric<AlphabetType>> MultialignMetric<AlphabetType>
+    for AffineGapMetric<AlphabetType, Metric>
+{
+    fn reset_character_counts(&mut self) {
+        self.metric.reset_character_counts();
+        self.residue_count = 0;
+        self.gap_open_count = 0;
+    }
+
+    fn set_reference_offset(&mut self, offset: usize) {
+        self.metric.set_reference_offset(offset);
+    }
+
+    fn restrict_alphabet(&mut self, characters: &[AlphabetType::CharacterType]) -> Result<()> {
+        self.metric.restrict_alphabet(characters)
+    }
+
+    fn count_character(&mut self, character: &AlphabetType::CharacterType, sequence_index: usize) {
+        self.metric.count_character(character, sequence_index);
+        self.residue_count += 1;
+    }
+
+    fn count_wildcard(&mut self, character: &AlphabetType::CharacterType, sequence_index: usize) {
+        self.metric.count_wildcard(character, sequence_index);
+        self.residue_count += 1;
+    }
+
+    fn count_gap(&mut self, sequence_index: usize) {
+        self.metric.count_gap(sequence_index);
+    }
+
+    fn count_gap_open(&mut self, sequence_index: usize) {
+        self.metric.count_gap_open(sequence_index);
+        self.gap_open_count += 1;
+    }
+
+    fn has_affine_gaps(&self) -> bool {
+        self.gap_open_cost != 0 || self.metric.has_affine_gaps()
+    }
+
+    fn count_terminal_gap(&mut self, sequence_index: usize) {
+        self.metric.count_terminal_gap(sequence_index);
+    }
+
+    fn has_free_end_gaps(&self) -> bool {
+        self.metric.has_free_end_gaps()
+    }
+
+    fn uses_offsets(&self) -> bool {
+        self.metric.uses_offsets()
+    }
+
+    fn set_offset(&mut self, sequence_index: usize, offset: usize) {
+        self.metric.set_offset(sequence_index, offset);
+    }
+
+    fn is_sum_of_pairs(&self) -> bool {
+        self.gap_open_cost == 0 && self.metric.is_sum_of_pairs()
+    }
+
+    fn compute_cost_increment<Cost: AStarCost>(&mut self) -> Result<Cost>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        let cost = self.metric.compute_cost_increment::<Cost>()?;
+        let gap_open_cost = cost_from_i32::<Cost>(
+            self.gap_open_cost
+                .checked_mul(self.gap_open_count)
+                .and_then(|cost| cost.checked_mul(self.residue_count))
+                .ok_or(MultialignError::CostOverflow)?,
+        )?;
+
+        cost.checked_add(&gap_open_cost)
+            .ok_or(MultialignError::CostOverflow)
+    }
+}
@@ -0,0 +1,192 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use compact_genome::{
+    implementation::alphabets::famsa_amino_acid_alphabet::{
+        FamsaAminoAcidAlphabet, FamsaAminoAcidCharacter,
+    },
+    interface::alphabet::Alphabet,
+};
+use generic_a_star::cost::{AStarCost, I64Cost};
+
+use super::{cost_from_i32, MultialignMetric};
+use crate::error::{MultialignError, Result};
+
+/// A metric for coding nucleotide sequences that scores the translated codons with an amino acid metric.
+///
+/// Each sequence is read in frame from its first residue, and each of its residues has a codon position determined by its offset.
+/// The codon position of a column is the most frequent codon position of its residues, where ties are broken by the lower position.
+/// For each column, the amino acid metric counts
+///
+///  * the amino acid of the codon of each residue at the codon position of the column,
+///  * a gap for each sequence with a gap, and
+///  * a gap for each residue at another codon position, which is additionally charged the frameshift cost.
+///
+/// Since each codon spans three columns, aligned codons are scored three times, and a gap whose length is a multiple of three costs three amino acid gaps per codon.
+/// Other gaps shift the codon positions of their sequence against the others, which is charged for each residue until the frame is restored.
+/// Stop codons and codons containing other characters than nucleotides are counted as `X`.
+#[derive(Clone)]
+pub struct CodonMetric<AlphabetType, Metric> {
+    amino_acid_metric: Metric,
+    /// The amino acids of the codons of each sequence in input order, see [`crate::frame::translate`].
+    translations: Arc<Vec<Vec<char>>>,
+    frameshift_cost: i32,
+    /// The amount of residues of each sequence before the current column.
+    offsets: Vec<usize>,
+    /// The offset of the residue of each sequence in the current column, or `None` for a gap.
+    residues: Vec<Option<usize>>,
+    /// The index of a sequence in the current column that has no translation, if any.
+    unknown_sequence: Option<usize>,
+    phantom_data: PhantomData<AlphabetType>,
+}
+
+impl<AlphabetType: Alphabet, Metric: MultialignMetric<FamsaAminoAcidAlphabet>>
+    CodonMetric<AlphabetType, Metric>
+{
+    /// Score the translations of the aligned sequences in input order with the given amino acid metric.
+    ///
+    /// The amino acid metric is restricted to the amino acids of the translations.
+    /// Returns an [`MultialignError::InvalidMetricArguments`] if the frameshift cost is negative.
+    pub fn new(
+        mut amino_acid_metric: Metric,
+        translations: Arc<Vec<Vec<char>>>,
+        frameshift_cost: i32,
+    ) -> Result<Self> {
+        if frameshift_cost < 0 {
+            return Err(MultialignError::InvalidMetricArguments(format!(
+                "The frameshift cost must be non-negative, but is {frameshift_cost}"
+            )));
+        }
+
+        let mut amino_acids: Vec<_> = translations
+            .iter()
+            .flatten()
+            .map(|&amino_acid| amino_acid_character(amino_acid))
+            .collect();
+        amino_acids.sort_unstable();
+        amino_acids.dedup();
+        amino_acid_metric.restrict_alphabet(&amino_acids)?;
+
+        let sequence_amount = translations.len();
+        Ok(Self {
+            amino_acid_metric,
+            translations,
+            frameshift_cost,
+            offsets: vec![0; sequence_amount],
+            residues: vec![None; sequence_amount],
+            unknown_sequence: None,
+            phantom_data: PhantomData,
+        })
+    }
+
+    fn count_residue(&mut self, sequence_index: usize) {
+        match (
+            self.residues.get_mut(sequence_index),
+            self.offsets.get(sequence_index),
+        ) {
+            (Some(residue), Some(offset)) => *residue = Some(*offset),
+            _ => self.unknown_sequence = Some(sequence_index),
+        }
+    }
+
+    /// The amino acid of the codon of the residue at the given offset of the given sequence.
+    fn amino_acid(&self, sequence_index: usize, offset: usize) -> FamsaAminoAcidCharacter {
+        amino_acid_character(
+            self.translations[sequence_index]
+                .get(offset / 3)
+                .copied()
+                .unwrap_or('X'),
+        )
+    }
+}
+
+/// Convert a translated amino acid into a character of the amino acid alphabet.
+fn amino_acid_character(amino_acid: char) -> FamsaAminoAcidCharacter {
+    // Cost tables use `*` for gaps, so stop codons are counted as unknown amino acids.
+    let amino_acid = if amino_acid == '*' { 'X' } else { amino_acid };
+    FamsaAminoAcidCharacter::try_from(amino_acid)
+        .unwrap_or_else(|_| FamsaAminoAcidCharacter::try_from('X').unwrap())
+}
+
+impl<AlphabetType: Alphabet, Metric: MultialignMetric<FamsaAminoAcidAlphabet>>
+    MultialignMetric<AlphabetType> for CodonMetric<AlphabetType, Metric>
+{
+    fn reset_character_counts(&mut self) {
+        self.residues.fill(None);
+        self.unknown_sequence = None;
+    }
+
+    fn count_character(&mut self, _character: &AlphabetType::CharacterType, sequence_index: usize) {
+        self.count_residue(sequence_index);
+    }
+
+    fn count_wildcard(&mut self, _character: &AlphabetType::CharacterType, sequence_index: usize) {
+        self.count_residue(sequence_index);
+    }
+
+    fn count_gap(&mut self, sequence_index: usize) {
+        if sequence_index >= self.residues.len() {
+            self.unknown_sequence = Some(sequence_index);
+        }
+    }
+
+    fn uses_offsets(&self) -> bool {
+        true
+    }
+
+    fn set_offset(&mut self, sequence_index: usize, offset: usize) {
+        match self.offsets.get_mut(sequence_index) {
+            Some(sequence_offset) => *sequence_offset = offset,
+            // Reported as error when computing the cost.
+            None => self.unknown_sequence = Some(sequence_index),
+        }
+    }
+
+    fn compute_cost_increment<Cost: AStarCost>(&mut self) -> Result<Cost>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        if let Some(sequence_index) = self.unknown_sequence {
+            return Err(MultialignError::InvalidMetricArguments(format!(
+                "Translations of {} sequences are given, but sequence {sequence_index} is aligned",
+                self.residues.len(),
+            )));
+        }
+
+        let mut codon_position_counts = [0usize; 3];
+        for offset in self.residues.iter().flatten() {
+            codon_position_counts[offset % 3] += 1;
+        }
+        let codon_position = (0..3)
+            .rev()
+            .max_by_key(|&position| codon_position_counts[position])
+            .unwrap();
+
+        self.amino_acid_metric.reset_character_counts();
+        let mut frameshift_amount = 0;
+        for (sequence_index, residue) in self.residues.iter().enumerate() {
+            match residue {
+                Some(offset) if offset % 3 == codon_position => {
+                    let amino_acid = self.amino_acid(sequence_index, *offset);
+                    self.amino_acid_metric
+                        .count_character(&amino_acid, sequence_index);
+                }
+                Some(_) => {
+                    self.amino_acid_metric.count_gap(sequence_index);
+                    frameshift_amount += 1;
+                }
+                None => self.amino_acid_metric.count_gap(sequence_index),
+            }
+        }
+
+        let cost = self
+            .amino_acid_metric
+            .compute_cost_increment::<I64Cost>()?
+            .as_primitive();
+        let cost = self
+            .frameshift_cost
+            .checked_mul(frameshift_amount)
+            .and_then(|frameshift_cost| i32::try_from(cost).ok()?.checked_add(frameshift_cost))
+            .ok_or(MultialignError::CostOverflow)?;
+        cost_from_i32(cost)
+    }
+}
@@ -0,0 +1,138 @@
+use compact_genome::interface::alphabet::Alphabet;
+use generic_a_star::cost::{AStarCost, I64Cost};
+
+use super::{cost_from_i32, cost_type_name, BoxedMetric, MultialignMetric};
+use crate::error::{MultialignError, Result};
+
+/// A metric whose cost is the sum of the costs of multiple metrics, each multiplied by an integer weight.
+///
+/// This allows to trade off different aspects of an alignment, e.g. the substitution costs against the amount of distinct residues in a column.
+/// All metrics count the same columns.
+/// The composite distinguishes gap opens, terminal gaps or offsets if any of its metrics does, and the others count them like their defaults.
+#[derive(Clone)]
+pub struct CompositeMetric<AlphabetType: Alphabet> {
+    metrics: Vec<(BoxedMetric<AlphabetType>, u32)>,
+}
+
+impl<AlphabetType: Alphabet> CompositeMetric<AlphabetType> {
+    /// Combine the given metrics with their weights.
+    ///
+    /// Returns an [`MultialignError::InvalidMetricArguments`] if no metric is given.
+    pub fn new(metrics: Vec<(BoxedMetric<AlphabetType>, u32)>) -> Result<Self> {
+        if metrics.is_empty() {
+            return Err(MultialignError::InvalidMetricArguments(
+                "A composite metric requires at least one metric".to_string(),
+            ));
+        }
+
+        Ok(Self { metrics })
+    }
+}
+
+impl<AlphabetType: Alphabet> MultialignMetric<AlphabetType> for CompositeMetric<AlphabetType> {
+    fn reset_character_counts(&mut self) {
+        for (metric, _) in &mut self.metrics {
+            metric.reset_character_counts();
+        }
+    }
+
+    fn set_reference_offset(&mut self, offset: usize) {
+        for (metric, _) in &mut self.metrics {
+            metric.set_reference_offset(offset);
+        }
+    }
+
+    fn restrict_alphabet(&mut self, characters: &[AlphabetType::CharacterType]) -> Result<()> {
+        for (metric, _) in &mut self.metrics {
+            metric.restrict_alphabet(characters)?;
+        }
+        Ok(())
+    }
+
+    fn count_character(&mut self, character: &AlphabetType::CharacterType, sequence_index: usize) {
+        for (metric, _) in &mut self.metrics {
+            metric.count_character(character, sequence_index);
+        }
+    }
+
+    fn count_wildcard(&mut self, character: &AlphabetType::CharacterType, sequence_index: usize) {
+        for (metric, _) in &mut self.metrics {
+            metric.count_wildcard(character, sequence_index);
+        }
+    }
+
+    fn count_gap(&mut self, sequence_index: usize) {
+        for (metric, _) in &mut self.metrics {
+            metric.count_gap(sequence_index);
+        }
+    }
+
+    fn count_gap_open(&mut self, sequence_index: usize) {
+        for (metric, _) in &mut self.metrics {
+            if metric.has_affine_gaps() {
+                metric.count_gap_open(sequence_index);
+            } else {
+                metric.count_gap(sequence_index);
+            }
+        }
+    }
+
+    fn has_affine_gaps(&self) -> bool {
+        self.metrics
+            .iter()
+            .any(|(metric, _)| metric.has_affine_gaps())
+    }
+
+    fn count_terminal_gap(&mut self, sequence_index: usize) {
+        for (metric, _) in &mut self.metrics {
+            if metric.has_free_end_gaps() {
+                metric.count_terminal_gap(sequence_index);
+            } else {
+                metric.count_gap(sequence_index);
+            }
+        }
+    }
+
+    fn has_free_end_gaps(&self) -> bool {
+        self.metrics
+            .iter()
+            .any(|(metric, _)| metric.has_free_end_gaps())
+    }
+
+    fn uses_offsets(&self) -> bool {
+        self.metrics.iter().any(|(metric, _)| metric.uses_offsets())
+    }
+
+    fn set_offset(&mut self, sequence_index: usize, offset: usize) {
+        for (metric, _) in &mut self.metrics {
+            metric.set_offset(sequence_index, offset);
+        }
+    }
+
+    fn is_sum_of_pairs(&self) -> bool {
+        self.metrics
+            .iter()
+            .all(|(metric, _)| metric.is_sum_of_pairs())
+    }
+
+    fn compute_cost_increment<Cost: AStarCost>(&mut self) -> Result<Cost>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        let mut cost = 0i64;
+        for (metric, weight) in &mut self.metrics {
+            let metric_cost = metric.compute_cost_increment::<I64Cost>()?.as_primitive();
+            cost = metric_cost
+                .checked_mul(i64::from(*weight))
+                .and_then(|metric_cost| cost.checked_add(metric_cost))
+                .ok_or(MultialignError::CostOverflow)?;
+        }
+
+        i32::try_from(cost)
+            .map_err(|_| MultialignError::CostOutOfRange {
+                cost,
+                cost_type: cost_type_name::<Cost>(),
+            })
+            .and_then(cost_from_i32)
+    }
+}
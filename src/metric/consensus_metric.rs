@@ -0,0 +1,76 @@
+use std::marker::PhantomData;
+
+use compact_genome::interface::alphabet::{Alphabet, AlphabetCharacter};
+use generic_a_star::cost::AStarCost;
+
+use super::{cost_from_i32, MultialignMetric};
+use crate::error::Result;
+
+/// A star metric that scores each column with the amount of entries that differ from its most frequent entry.
+///
+/// Gaps are entries like residues, so a column in which most sequences have a gap costs the amount of its residues.
+/// Wildcards agree with every residue, but not with gaps.
+/// Compared to sum-of-pairs metrics, the cost grows only linearly with the amount of sequences.
+#[derive(Clone)]
+pub struct ConsensusMetric<AlphabetType: Alphabet> {
+    /// The count of each character, followed by the count of gaps.
+    character_counts: Vec<u8>,
+    wildcard_count: u8,
+    phantom_data: PhantomData<AlphabetType>,
+}
+
+impl<AlphabetType: Alphabet> ConsensusMetric<AlphabetType> {
+    pub fn new() -> Self {
+        Self {
+            character_counts: vec![0; usize::from(AlphabetType::SIZE) + 1],
+            wildcard_count: 0,
+            phantom_data: PhantomData,
+        }
+    }
+}
+
+impl<AlphabetType: Alphabet> Default for ConsensusMetric<AlphabetType> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<AlphabetType: Alphabet> MultialignMetric<AlphabetType> for ConsensusMetric<AlphabetType> {
+    fn reset_character_counts(&mut self) {
+        self.character_counts.fill(0);
+        self.wildcard_count = 0;
+    }
+
+    fn count_character(&mut self, character: &AlphabetType::CharacterType, _sequence_index: usize) {
+        self.character_counts[usize::from(character.index())] += 1;
+    }
+
+    fn count_wildcard(&mut self, _character: &AlphabetType::CharacterType, _sequence_index: usize) {
+        self.wildcard_count += 1;
+    }
+
+    fn count_gap(&mut self, _sequence_index: usize) {
+        self.character_counts[usize::from(AlphabetType::SIZE)] += 1;
+    }
+
+    fn count_terminal_gap(&mut self, _sequence_index: usize) {
+        // Terminal gaps are free, so they are not counted.
+    }
+
+    fn compute_cost_increment<Cost: AStarCost>(&mut self) -> Result<Cost>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        let (gap_count, residue_counts) = self.character_counts.split_last().unwrap();
+        let max_residue_count = residue_counts.iter().copied().max().unwrap_or(0);
+        let consensus_count = (max_residue_count + self.wildcard_count).max(*gap_count);
+        let total_count = residue_counts
+            .iter()
+            .map(|count| i32::from(*count))
+            .sum::<i32>()
+            + i32::from(self.wildcard_count)
+            + i32::from(*gap_count);
+
+        cost_from_i32(total_count - i32::from(consensus_count))
+    }
+}
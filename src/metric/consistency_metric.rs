@@ -0,0 +1,111 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use compact_genome::interface::alphabet::Alphabet;
+use generic_a_star::cost::AStarCost;
+
+use super::{cost_from_i32, MultialignMetric};
+use crate::{
+    consistency::ConsistencyLibrary,
+    error::{MultialignError, Result},
+};
+
+/// A sum-of-pairs metric that rewards aligning pairs of residues with a high weight in a [`ConsistencyLibrary`], like T-Coffee.
+///
+/// Each pair of residues in a column costs twice the difference of its library weight to the maximum weight of the library,
+/// and each pair of a residue and a gap costs the maximum weight.
+/// Then the cost of an alignment is a constant minus twice the sum of the library weights of its aligned pairs of residues,
+/// such that an optimal alignment maximises the T-Coffee score.
+/// The characters are ignored, so wildcards are counted like any other residue.
+#[derive(Clone)]
+pub struct ConsistencyMetric<AlphabetType> {
+    library: Arc<ConsistencyLibrary>,
+    /// The amount of residues of each sequence before the current column.
+    offsets: Vec<usize>,
+    /// True for each sequence that has a residue in the current column, and `None` for each sequence with a free terminal gap.
+    residues: Vec<Option<bool>>,
+    /// The index of a sequence in the current column that is not part of the library, if any.
+    unknown_sequence: Option<usize>,
+    phantom_data: PhantomData<AlphabetType>,
+}
+
+impl<AlphabetType: Alphabet> ConsistencyMetric<AlphabetType> {
+    /// Score columns with the given library, which must be built from the aligned sequences in input order.
+    pub fn new(library: Arc<ConsistencyLibrary>) -> Self {
+        let sequence_amount = library.sequence_amount();
+        Self {
+            library,
+            offsets: vec![0; sequence_amount],
+            residues: vec![Some(false); sequence_amount],
+            unknown_sequence: None,
+            phantom_data: PhantomData,
+        }
+    }
+}
+
+impl<AlphabetType: Alphabet> MultialignMetric<AlphabetType> for ConsistencyMetric<AlphabetType> {
+    fn reset_character_counts(&mut self) {
+        self.residues.fill(Some(false));
+        self.unknown_sequence = None;
+    }
+
+    fn uses_offsets(&self) -> bool {
+        true
+    }
+
+    fn set_offset(&mut self, sequence_index: usize, offset: usize) {
+        match self.offsets.get_mut(sequence_index) {
+            Some(sequence_offset) => *sequence_offset = offset,
+            // Reported as error when computing the cost.
+            None => self.unknown_sequence = Some(sequence_index),
+        }
+    }
+
+    fn count_character(&mut self, _character: &AlphabetType::CharacterType, sequence_index: usize) {
+        match self.residues.get_mut(sequence_index) {
+            Some(residue) => *residue = Some(true),
+            None => self.unknown_sequence = Some(sequence_index),
+        }
+    }
+
+    fn count_gap(&mut self, sequence_index: usize) {
+        if sequence_index >= self.residues.len() {
+            self.unknown_sequence = Some(sequence_index);
+        }
+    }
+
+    fn count_terminal_gap(&mut self, sequence_index: usize) {
+        match self.residues.get_mut(sequence_index) {
+            Some(residue) => *residue = None,
+            None => self.unknown_sequence = Some(sequence_index),
+        }
+    }
+
+    fn compute_cost_increment<Cost: AStarCost>(&mut self) -> Result<Cost>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        if let Some(sequence_index) = self.unknown_sequence {
+            return Err(MultialignError::InvalidMetricArguments(format!(
+                "The consistency library contains {} sequences, but sequence {sequence_index} is aligned",
+                self.residues.len(),
+            )));
+        }
+
+        let max_weight = i64::from(self.library.max_weight());
+        let mut cost = 0i64;
+        for i in 0..self.residues.len() {
+            for j in i + 1..self.residues.len() {
+                cost += match (self.residues[i], self.residues[j]) {
+                    (Some(true), Some(true)) => {
+                        let weight = self.library.weight(i, self.offsets[i], j, self.offsets[j]);
+                        2 * (max_weight - i64::from(weight))
+                    }
+                    (Some(true), Some(false)) | (Some(false), Some(true)) => max_weight,
+                    _ => 0,
+                };
+            }
+        }
+
+        cost_from_i32(i32::try_from(cost).map_err(|_| MultialignError::CostOverflow)?)
+    }
+}
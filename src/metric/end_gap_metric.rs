@@ -0,0 +1,95 @@
+use std::marker::PhantomData;
+
+use compact_genome::interface::alphabet::Alphabet;
+use generic_a_star::cost::AStarCost;
+
+use super::MultialignMetric;
+use crate::error::Result;
+
+/// A metric that makes gaps before the first and after the last residue of each sequence free, for semi-global alignment.
+///
+/// The aligner then counts these terminal gaps with [`MultialignMetric::count_terminal_gap`], which the wrapped metric should not charge.
+/// This is useful when aligning fragments against a full-length sequence, which is the longest sequence, whose end gaps are still penalised.
+/// With free end gaps disabled, all gaps are counted as usual.
+#[derive(Clone)]
+pub struct EndGapMetric<AlphabetType, Metric> {
+    metric: Metric,
+    free_end_gaps: bool,
+    phantom_data: PhantomData<AlphabetType>,
+}
+
+impl<AlphabetType: Alphabet, Metric: MultialignMetric<AlphabetType>>
+    EndGapMetric<AlphabetType, Metric>
+{
+    /// Wrap the given metric, making terminal gaps free if `free_end_gaps` is true.
+    pub fn new(metric: Metric, free_end_gaps: bool) -> Self {
+        Self {
+            metric,
+            free_end_gaps,
+            phantom_data: PhantomData,
+        }
+    }
+}
+
+impl<AlphabetType: Alphabet, Metric: MultialignMetric<AlphabetType>> MultialignMetric<AlphabetType>
+    for EndGapMetric<AlphabetType, Metric>
+{
+    fn reset_character_counts(&mut self) {
+        self.metric.reset_character_counts();
+    }
+
+    fn set_reference_offset(&mut self, offset: usize) {
+        self.metric.set_reference_offset(offset);
+    }
+
+    fn restrict_alphabet(&mut self, characters: &[AlphabetType::CharacterType]) -> Result<()> {
+        self.metric.restrict_alphabet(characters)
+    }
+
+    fn count_character(&mut self, character: &AlphabetType::CharacterType, sequence_index: usize) {
+        self.metric.count_character(character, sequence_index);
+    }
+
+    fn count_wildcard(&mut self, character: &AlphabetType::CharacterType, sequence_index: usize) {
+        self.metric.count_wildcard(character, sequence_index);
+    }
+
+    fn count_gap(&mut self, sequence_index: usize) {
+        self.metric.count_gap(sequence_index);
+    }
+
+    fn count_gap_open(&mut self, sequence_index: usize) {
+        self.metric.count_gap_open(sequence_index);
+    }
+
+    fn has_affine_gaps(&self) -> bool {
+        self.metric.has_affine_gaps()
+    }
+
+    fn count_terminal_gap(&mut self, sequence_index: usize) {
+        self.metric.count_terminal_gap(sequence_index);
+    }
+
+    fn has_free_end_gaps(&self) -> bool {
+        self.free_end_gaps || self.metric.has_free_end_gaps()
+    }
+
+    fn uses_offsets(&self) -> bool {
+        self.metric.uses_offsets()
+    }
+
+    fn set_offset(&mut self, sequence_index: usize, offset: usize) {
+        self.metric.set_offset(sequence_index, offset);
+    }
+
+    fn is_sum_of_pairs(&self) -> bool {
+        !self.free_end_gaps && self.metric.is_sum_of_pairs()
+    }
+
+    fn compute_cost_increment<Cost: AStarCost>(&mut self) -> Result<Cost>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        self.metric.compute_cost_increment()
+    }
+}
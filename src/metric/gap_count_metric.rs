@@ -0,0 +1,79 @@
+use std::marker::PhantomData;
+
+use compact_genome::interface::alphabet::Alphabet;
+use generic_a_star::cost::AStarCost;
+
+use super::{cost_from_i32, MultialignMetric};
+use crate::error::Result;
+
+/// A metric that scores each column with the amount of its gaps, or with the amount of gaps that are opened in it.
+///
+/// The characters are ignored, so an optimal alignment is one with the fewest gap characters or gap openings, i.e. the most compact one.
+/// Since it does not prefer any of the alignments with the fewest gaps, it is mostly useful as part of a composite metric.
+/// Counting gap openings makes the aligner track which sequences are in a gap, see [`MultialignMetric::has_affine_gaps`].
+#[derive(Clone)]
+pub struct GapCountMetric<AlphabetType> {
+    count_gap_openings: bool,
+    gap_count: i32,
+    phantom_data: PhantomData<AlphabetType>,
+}
+
+impl<AlphabetType: Alphabet> GapCountMetric<AlphabetType> {
+    /// Count the gaps of each column.
+    pub fn new() -> Self {
+        Self {
+            count_gap_openings: false,
+            gap_count: 0,
+            phantom_data: PhantomData,
+        }
+    }
+
+    /// Count only the gaps that are opened in each column.
+    pub fn gap_openings() -> Self {
+        Self {
+            count_gap_openings: true,
+            ..Self::new()
+        }
+    }
+}
+
+impl<AlphabetType: Alphabet> Default for GapCountMetric<AlphabetType> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<AlphabetType: Alphabet> MultialignMetric<AlphabetType> for GapCountMetric<AlphabetType> {
+    fn reset_character_counts(&mut self) {
+        self.gap_count = 0;
+    }
+
+    fn count_character(
+        &mut self,
+        _character: &AlphabetType::CharacterType,
+        _sequence_index: usize,
+    ) {
+        // Residues are free.
+    }
+
+    fn count_gap(&mut self, _sequence_index: usize) {
+        if !self.count_gap_openings {
+            self.gap_count += 1;
+        }
+    }
+
+    fn count_gap_open(&mut self, _sequence_index: usize) {
+        self.gap_count += 1;
+    }
+
+    fn has_affine_gaps(&self) -> bool {
+        self.count_gap_openings
+    }
+
+    fn compute_cost_increment<Cost: AStarCost>(&mut self) -> Result<Cost>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        cost_from_i32(self.gap_count)
+    }
+}
@@ -0,0 +1,176 @@
+use std::{fs::read_to_string, marker::PhantomData, path::Path};
+
+use compact_genome::interface::alphabet::Alphabet;
+use generic_a_star::cost::AStarCost;
+use log::info;
+
+use super::{cost_from_i32, MultialignMetric};
+use crate::error::{MultialignError, Result};
+
+/// A metric that adds a position-specific gap penalty to another metric.
+///
+/// The penalties are indexed by the offset of the reference sequence, which is the first sequence.
+/// Each gap in a column is charged the penalty of the reference position that the column is aligned to.
+/// If the reference itself has a gap in the column, then the penalty of the next reference position is used.
+/// Columns after the end of the reference are charged the extra entry at the end of the profile.
+/// Without a profile, no penalties are added.
+#[derive(Clone)]
+pub struct GapProfileMetric<AlphabetType, Metric> {
+    metric: Metric,
+    gap_penalties: Vec<i32>,
+    reference_offset: usize,
+    gap_count: i32,
+    phantom_data: PhantomData<AlphabetType>,
+}
+
+impl<AlphabetType: Alphabet, Metric: MultialignMetric<AlphabetType>>
+    GapProfileMetric<AlphabetType, Metric>
+{
+    /// Wrap the given metric without adding any gap penalties.
+    pub fn new(metric: Metric) -> Self {
+        Self {
+            metric,
+            gap_penalties: Vec::new(),
+            reference_offset: 0,
+            gap_count: 0,
+            phantom_data: PhantomData,
+        }
+    }
+
+    /// Read the gap penalties from a file containing one non-negative integer per reference position,
+    /// followed by the penalty for gaps after the end of the reference.
+    ///
+    /// The integers may be separated by any whitespace.
+    pub fn from_file(
+        metric: Metric,
+        path: impl AsRef<Path>,
+        reference_length: usize,
+    ) -> Result<Self> {
+        let path = path.as_ref();
+        info!("Reading gap profile {path:?}");
+
+        let gap_penalties = read_to_string(path)
+            .map_err(|source| MultialignError::Io {
+                path: path.to_owned(),
+                source,
+            })?
+            .split_whitespace()
+            .map(|penalty| match penalty.parse() {
+                Ok(penalty) if penalty >= 0 => Ok(penalty),
+                Ok(_) => Err(MultialignError::InvalidGapProfile(format!(
+                    "Gap penalty {penalty} is negative"
+                ))),
+                Err(_) => Err(MultialignError::InvalidGapProfile(format!(
+                    "Error parsing '{penalty}' as i32"
+                ))),
+            })
+            .collect::<Result<Vec<i32>>>()?;
+
+        if gap_penalties.len() != reference_length + 1 {
+            return Err(MultialignError::InvalidGapProfile(format!(
+                "Gap profile has {} entries, but the reference sequence has length {}, \
+                 so it requires {} entries including the one for gaps after its end",
+                gap_penalties.len(),
+                reference_length,
+                reference_length + 1
+            )));
+        }
+
+        Ok(Self {
+            metric,
+            gap_penalties,
+            reference_offset: 0,
+            gap_count: 0,
+            phantom_data: PhantomData,
+        })
+    }
+}
+
+impl<AlphabetType: Alphabet, Metric: MultialignMetric<AlphabetType>> MultialignMetric<AlphabetType>
+    for GapProfileMetric<AlphabetType, Metric>
+{
+    fn reset_character_counts(&mut self) {
+        self.metric.reset_character_counts();
+        self.gap_count = 0;
+    }
+
+    fn set_reference_offset(&mut self, offset: usize) {
+        self.metric.set_reference_offset(offset);
+        self.reference_offset = offset;
+    }
+
+    fn restrict_alphabet(&mut self, characters: &[AlphabetType::CharacterType]) -> Result<()> {
+        self.metric.restrict_alphabet(characters)
+    }
+
+    fn count_character(&mut self, character: &AlphabetType::CharacterType, sequence_index: usize) {
+        self.metric.count_character(character, sequence_index);
+    }
+
+    fn count_wildcard(&mut self, character: &AlphabetType::CharacterType, sequence_index: usize) {
+        self.metric.count_wildcard(character, sequence_index);
+    }
+
+    fn count_gap(&mut self, sequence_index: usize) {
+        self.metric.count_gap(sequence_index);
+        self.gap_count += 1;
+    }
+
+    fn count_gap_open(&mut self, sequence_index: usize) {
+        self.metric.count_gap_open(sequence_index);
+        self.gap_count += 1;
+    }
+
+    fn has_affine_gaps(&self) -> bool {
+        self.metric.has_affine_gaps()
+    }
+
+    fn count_terminal_gap(&mut self, sequence_index: usize) {
+        self.metric.count_terminal_gap(sequence_index);
+    }
+
+    fn has_free_end_gaps(&self) -> bool {
+        self.metric.has_free_end_gaps()
+    }
+
+    fn uses_offsets(&self) -> bool {
+        self.metric.uses_offsets()
+    }
+
+    fn set_offset(&mut self, sequence_index: usize, offset: usize) {
+        self.metric.set_offset(sequence_index, offset);
+    }
+
+    fn is_sum_of_pairs(&self) -> bool {
+        self.gap_penalties.is_empty() && self.metric.is_sum_of_pairs()
+    }
+
+    fn compute_cost_increment<Cost: AStarCost>(&mut self) -> Result<Cost>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        let cost = self.metric.compute_cost_increment::<Cost>()?;
+        let gap_penalty = if self.gap_penalties.is_empty() || self.gap_count == 0 {
+            0
+        } else {
+            *self
+                .gap_penalties
+                .get(self.reference_offset)
+                .ok_or_else(|| {
+                    MultialignError::InvalidGapProfile(format!(
+                        "Reference offset {} is past the end of the gap profile with {} entries",
+                        self.reference_offset,
+                        self.gap_penalties.len()
+                    ))
+                })?
+        };
+        let gap_cost = cost_from_i32::<Cost>(
+            gap_penalty
+                .checked_mul(self.gap_count)
+                .ok_or(MultialignError::CostOverflow)?,
+        )?;
+
+        cost.checked_add(&gap_cost)
+            .ok_or(MultialignError::CostOverflow)
+    }
+}
@@ -0,0 +1,147 @@
+use compact_genome::interface::alphabet::Alphabet;
+use generic_a_star::cost::AStarCost;
+
+use super::{
+    sequence_weighted_metric::{round_weighted_cost, PairCosts},
+    MultialignMetric,
+};
+use crate::error::{MultialignError, Result};
+
+/// A sum-of-pairs metric where the cost of each pair of sequences is multiplied by a weight of that pair.
+///
+/// With the distances between the leaves of a phylogenetic tree as weights (see [`GuideTree::leaf_distances`](crate::tree::GuideTree::leaf_distances)),
+/// pairs of closely related sequences contribute less, which makes the cost closer to the cost of the alignment along the tree.
+///
+/// Like with [`SequenceWeightedMetric`](super::sequence_weighted_metric::SequenceWeightedMetric), the wrapped metric scores a single pair
+/// and must not depend on the reference offset.
+/// The weights are scaled to an average of one over all pairs, and the weighted cost of each column is rounded to the nearest integer.
+#[derive(Clone)]
+pub struct PairWeightedMetric<AlphabetType, Metric> {
+    pair_costs: PairCosts<AlphabetType, Metric>,
+    /// The weight of each pair of sequences, indexed by the sequence indices.
+    weights: Vec<Vec<f64>>,
+    /// The index of a sequence in the current column that has no weight, if any.
+    unweighted_sequence: Option<usize>,
+    /// The entry of each sequence in the current column, see [`PairCosts`].
+    entries: Vec<Option<usize>>,
+}
+
+impl<AlphabetType: Alphabet, Metric: MultialignMetric<AlphabetType>>
+    PairWeightedMetric<AlphabetType, Metric>
+{
+    /// Wrap the given pairwise metric with a symmetric matrix of weights of all pairs of sequences in input order.
+    ///
+    /// The weights of sequences with themselves are ignored.
+    /// Returns an [`MultialignError::InvalidMetricArguments`] if the matrix is not square or not symmetric,
+    /// if a weight is negative or not finite, if all weights are zero, or if the metric depends on the offsets of the residues.
+    /// If there is only one sequence, then its cost is always zero.
+    pub fn new(metric: Metric, weights: &[Vec<f64>]) -> Result<Self> {
+        let sequence_amount = weights.len();
+        if let Some(row) = weights.iter().find(|row| row.len() != sequence_amount) {
+            return Err(MultialignError::InvalidMetricArguments(format!(
+                "Pair weights of {sequence_amount} sequences must be a square matrix, but found a row of length {}",
+                row.len()
+            )));
+        }
+
+        let mut sum = 0.0;
+        for i in 0..sequence_amount {
+            for j in i + 1..sequence_amount {
+                let weight = weights[i][j];
+                if !weight.is_finite() || weight < 0.0 {
+                    return Err(MultialignError::InvalidMetricArguments(format!(
+                        "Pair weights must be non-negative, but found {weight}"
+                    )));
+                }
+                if weight != weights[j][i] {
+                    return Err(MultialignError::InvalidMetricArguments(format!(
+                        "Pair weights must be symmetric, but sequences {i} and {j} have weights {weight} and {}",
+                        weights[j][i]
+                    )));
+                }
+                sum += weight;
+            }
+        }
+        if sum == 0.0 && sequence_amount > 1 {
+            return Err(MultialignError::InvalidMetricArguments(
+                "At least one pair weight must be positive".to_string(),
+            ));
+        }
+
+        let pair_amount = (sequence_amount * sequence_amount.saturating_sub(1) / 2) as f64;
+        let scale = if sum == 0.0 { 1.0 } else { pair_amount / sum };
+        Ok(Self {
+            pair_costs: PairCosts::new(metric)?,
+            weights: weights
+                .iter()
+                .map(|row| row.iter().map(|weight| weight * scale).collect())
+                .collect(),
+            unweighted_sequence: None,
+            entries: vec![None; sequence_amount],
+        })
+    }
+
+    fn count_entry(&mut self, entry: usize, sequence_index: usize) {
+        match self.entries.get_mut(sequence_index) {
+            Some(sequence_entry) => *sequence_entry = Some(entry),
+            // Reported as error when computing the cost.
+            None => self.unweighted_sequence = Some(sequence_index),
+        }
+    }
+}
+
+impl<AlphabetType: Alphabet, Metric: MultialignMetric<AlphabetType>> MultialignMetric<AlphabetType>
+    for PairWeightedMetric<AlphabetType, Metric>
+{
+    fn reset_character_counts(&mut self) {
+        self.unweighted_sequence = None;
+        self.entries.fill(None);
+    }
+
+    fn restrict_alphabet(&mut self, characters: &[AlphabetType::CharacterType]) -> Result<()> {
+        self.pair_costs.restrict_alphabet(characters)
+    }
+
+    fn count_character(&mut self, character: &AlphabetType::CharacterType, sequence_index: usize) {
+        self.count_entry(self.pair_costs.character_entry(character), sequence_index);
+    }
+
+    fn count_wildcard(&mut self, character: &AlphabetType::CharacterType, sequence_index: usize) {
+        self.count_entry(self.pair_costs.wildcard_entry(character), sequence_index);
+    }
+
+    fn count_gap(&mut self, sequence_index: usize) {
+        self.count_entry(self.pair_costs.gap_entry(), sequence_index);
+    }
+
+    fn count_terminal_gap(&mut self, _sequence_index: usize) {
+        // Terminal gaps are free, so they are not counted.
+    }
+
+    fn compute_cost_increment<Cost: AStarCost>(&mut self) -> Result<Cost>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        if let Some(sequence_index) = self.unweighted_sequence {
+            return Err(MultialignError::InvalidMetricArguments(format!(
+                "Got pair weights of {} sequences, but sequence {sequence_index} is aligned",
+                self.entries.len(),
+            )));
+        }
+
+        let mut cost = 0.0;
+        for i in 0..self.entries.len() {
+            let Some(first) = self.entries[i] else {
+                continue;
+            };
+            for j in i + 1..self.entries.len() {
+                let Some(second) = self.entries[j] else {
+                    continue;
+                };
+                cost += self.weights[i][j] * self.pair_costs.cost(first, second)? as f64;
+            }
+        }
+
+        round_weighted_cost(cost)
+    }
+}
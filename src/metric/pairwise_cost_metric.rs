@@ -0,0 +1,1191 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    fs::read_to_string,
+    marker::PhantomData,
+    path::Path,
+};
+
+use compact_genome::interface::alphabet::{Alphabet, AlphabetCharacter};
+use generic_a_star::cost::AStarCost;
+use log::{info, trace, warn};
+
+use super::{cost_from_i32, MultialignMetric};
+use crate::error::{MultialignError, Result};
+
+/// Return an [`MultialignError::InvalidCostTable`] if the condition is false.
+macro_rules! ensure_valid {
+    ($condition:expr, $($message:tt)+) => {
+        if !$condition {
+            return Err(MultialignError::InvalidCostTable(format!($($message)+)));
+        }
+    };
+}
+
+/// A pairwise metric with a pairwise scoring table.
+///
+/// Wildcards are scored with zero against residues and other wildcards, and against gaps with the cost of the wildcard character from the table.
+#[derive(Clone)]
+pub struct PairwiseCostMetric<AlphabetType> {
+    cost_table: PairwiseCostTable<AlphabetType>,
+    character_counts: Vec<u8>,
+    wildcard_counts: Vec<u8>,
+    non_zero_character_counts: Vec<usize>,
+    /// If the cost table is ordered, the sequence index, the character index (or [`Alphabet::SIZE`] for a gap) and whether it is a wildcard of each entry of the column.
+    ordered_column: Vec<(usize, usize, bool)>,
+    phantom_data: PhantomData<AlphabetType>,
+}
+
+/// A pair of characters, where `None` is a gap.
+type CharacterPair<AlphabetType> = (
+    Option<<AlphabetType as Alphabet>::CharacterType>,
+    Option<<AlphabetType as Alphabet>::CharacterType>,
+);
+
+/// Options for reading a cost table, see [`PairwiseCostMetric::from_file`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct CostTableOptions {
+    /// The maximum amount of decimal digits of fractional entries that are kept when scaling the table to integers.
+    pub precision: u32,
+    /// How entries are handled that differ from their mirrored entry.
+    pub asymmetric_costs: AsymmetricCosts,
+    /// The cost of missing entries between two different characters, in the units of the table before scaling.
+    ///
+    /// Missing entries are filled after making the table symmetric, and otherwise make aligning fail as soon as their pair occurs.
+    pub default_substitution_cost: Option<i32>,
+    /// The cost of missing entries between a character and a gap, in the units of the table before scaling.
+    pub default_gap_cost: Option<i32>,
+    /// The costs of the stop character `*` of alphabets that have it, e.g. the FAMSA amino acid alphabet.
+    ///
+    /// Cost tables use `*` for gaps, so they cannot contain costs for the stop character, and these are used instead.
+    pub stop_codon_costs: Option<StopCodonCosts>,
+}
+
+/// The costs of the stop character `*` in a cost table, in the units of the table before scaling.
+///
+/// Two stop characters are free.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct StopCodonCosts {
+    /// The cost of a stop character against any other character.
+    pub substitution: i32,
+    /// The cost of a stop character against a gap.
+    pub gap: i32,
+}
+
+impl Default for CostTableOptions {
+    fn default() -> Self {
+        Self {
+            precision: 3,
+            asymmetric_costs: AsymmetricCosts::Reject,
+            default_substitution_cost: None,
+            default_gap_cost: None,
+            stop_codon_costs: None,
+        }
+    }
+}
+
+/// How a cost table is handled whose entry for row `a` and column `b` differs from its entry for row `b` and column `a`.
+///
+/// Entries that exist only in one order are mirrored when symmetrizing.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum AsymmetricCosts {
+    /// Return an [`MultialignError::InvalidCostTable`].
+    #[default]
+    Reject,
+    /// Replace both entries with their average.
+    Average,
+    /// Replace both entries with the larger cost.
+    Max,
+    /// Keep the table, and score each pair of sequences with the row of the sequence with the lower index and the column of the other sequence.
+    ///
+    /// This is slower, since the column cannot be scored by character counts.
+    Ordered,
+}
+
+#[derive(Clone)]
+struct PairwiseCostTable<AlphabetType> {
+    table: Vec<Option<i32>>,
+    /// True if the rows are the characters of the sequence with the lower index, which allows asymmetric tables.
+    ordered: bool,
+    phantom_data: PhantomData<AlphabetType>,
+}
+
+impl<AlphabetType: Alphabet> PairwiseCostMetric<AlphabetType> {
+    /// Read the cost table from a file that is either a CSV file or an NCBI substitution matrix.
+    ///
+    /// The file is read as a CSV file if its first line that is neither empty nor a `#` comment contains a comma,
+    /// see [`Self::from_csv_file`] and [`Self::from_ncbi_file`].
+    ///
+    /// Entries may have fractional parts, in which case all entries are scaled to integers, keeping at most [`CostTableOptions::precision`] decimal digits.
+    /// For example, a table with entries `0.5` and `1.25` is scaled by 100 with a precision of two or more, and by 10 with a precision of one, rounding `12.5` to `13`.
+    ///
+    /// Tables whose entries differ from their mirrored entries are handled as selected by [`CostTableOptions::asymmetric_costs`].
+    pub fn from_file(path: impl AsRef<Path>, options: &CostTableOptions) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = read_to_string(path).map_err(|source| MultialignError::Io {
+            path: path.to_owned(),
+            source,
+        })?;
+        let is_csv = contents
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#'))
+            .is_some_and(|line| line.contains(','));
+
+        if is_csv {
+            Self::from_csv_file(path, options)
+        } else {
+            info!("Reading substitution matrix {path:?}");
+            Self::from_ncbi_matrix(&contents, options)
+        }
+    }
+
+    /// Read the cost table from a CSV file.
+    ///
+    /// Fractional entries are scaled like in [`Self::from_file`].
+    /// Without the `csv` feature, this returns an [`MultialignError::Io`] of kind [`std::io::ErrorKind::Unsupported`].
+    pub fn from_csv_file(path: impl AsRef<Path>, options: &CostTableOptions) -> Result<Self> {
+        Ok(Self::new(PairwiseCostTable::from_csv_file(path, options)?))
+    }
+
+    /// Create the metric from the rows of a cost table in the format of [`Self::from_csv_file`], i.e. the lines of the file split at commas.
+    pub fn from_rows(
+        rows: impl IntoIterator<Item = Vec<String>>,
+        options: &CostTableOptions,
+    ) -> Result<Self> {
+        Ok(Self::new(PairwiseCostTable::from_rows(
+            rows.into_iter().map(Ok),
+            options,
+        )?))
+    }
+
+    /// Read the cost table from a substitution matrix file in the format of NCBI and EMBOSS, e.g. `BLOSUM62`.
+    ///
+    /// The scores of the matrix are converted into costs by subtracting them from the maximum score between two residues,
+    /// and the `*` row and column are used as gap scores.
+    /// Fractional scores are scaled like in [`Self::from_file`].
+    pub fn from_ncbi_file(path: impl AsRef<Path>, options: &CostTableOptions) -> Result<Self> {
+        let path = path.as_ref();
+        info!("Reading substitution matrix {path:?}");
+        Self::from_ncbi_matrix(
+            &read_to_string(path).map_err(|source| MultialignError::Io {
+                path: path.to_owned(),
+                source,
+            })?,
+            options,
+        )
+    }
+
+    /// Create the metric from the contents of a substitution matrix file, see [`Self::from_ncbi_file`].
+    pub fn from_ncbi_matrix(matrix: &str, options: &CostTableOptions) -> Result<Self> {
+        Ok(Self::new(PairwiseCostTable::from_ncbi_matrix(
+            matrix, options,
+        )?))
+    }
+
+    /// Create a nucleotide metric with separate costs for transitions, which exchange two purines or two pyrimidines,
+    /// for transversions, which exchange a purine and a pyrimidine, and for gaps.
+    ///
+    /// Pairs of equal characters and pairs of gaps are free, and `U` is treated like `T`.
+    /// Characters that are not nucleotides, e.g. `N`, cost the transversion cost against all other characters.
+    pub fn from_transition_transversion_costs(
+        transition_cost: i32,
+        transversion_cost: i32,
+        gap_cost: i32,
+    ) -> Self {
+        let nucleotide_class = |character: &AlphabetType::CharacterType| {
+            let character: char = character.clone().into();
+            match character.to_ascii_uppercase() {
+                'A' | 'G' => Some(true),
+                'C' | 'T' | 'U' => Some(false),
+                _ => None,
+            }
+        };
+
+        let mut cost_map = BTreeMap::new();
+        for from in AlphabetType::iter().map(Some).chain([None]) {
+            for to in AlphabetType::iter().map(Some).chain([None]) {
+                let cost = match (&from, &to) {
+                    (None, None) => 0,
+                    (None, Some(_)) | (Some(_), None) => gap_cost,
+                    (Some(from), Some(to)) if from == to => 0,
+                    (Some(from), Some(to)) => {
+                        match (nucleotide_class(from), nucleotide_class(to)) {
+                            (Some(from_is_purine), Some(to_is_purine))
+                                if from_is_purine == to_is_purine =>
+                            {
+                                transition_cost
+                            }
+                            _ => transversion_cost,
+                        }
+                    }
+                };
+                cost_map.insert((from.clone(), to), cost);
+            }
+        }
+
+        Self::new(
+            PairwiseCostTable::from_cost_map(&cost_map, false)
+                .expect("The cost map is complete and symmetric"),
+        )
+    }
+
+    fn new(cost_table: PairwiseCostTable<AlphabetType>) -> Self {
+        Self {
+            cost_table,
+            character_counts: vec![0; usize::from(AlphabetType::SIZE) + 1],
+            wildcard_counts: vec![0; usize::from(AlphabetType::SIZE)],
+            non_zero_character_counts: Default::default(),
+            ordered_column: Default::default(),
+            phantom_data: PhantomData,
+        }
+    }
+
+    /// Score each pair of entries of the column with the row of the entry of the lower sequence index and the column of the other entry.
+    fn compute_ordered_cost_increment<Cost: AStarCost>(&mut self) -> Result<Cost>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        let gap_index = usize::from(AlphabetType::SIZE);
+        let character = |index: usize| {
+            (index != gap_index).then(|| {
+                AlphabetType::CharacterType::from_index(index.try_into().unwrap()).unwrap()
+            })
+        };
+
+        self.ordered_column
+            .sort_unstable_by_key(|(sequence_index, _, _)| *sequence_index);
+        let mut cost = 0i32;
+        for (offset, &(_, from, from_is_wildcard)) in self.ordered_column.iter().enumerate() {
+            for &(_, to, to_is_wildcard) in &self.ordered_column[offset + 1..] {
+                if (from_is_wildcard || to_is_wildcard) && from != gap_index && to != gap_index {
+                    // Wildcards are free against residues and other wildcards.
+                    continue;
+                }
+
+                cost = cost
+                    .checked_add(self.cost_table.cost(character(from), character(to))?)
+                    .ok_or(MultialignError::CostOverflow)?;
+            }
+        }
+
+        cost_from_i32(cost)
+    }
+
+    /// Check the cost table for properties that the aligner relies on, or whose violation likely indicates a mistake in the table.
+    ///
+    /// See [`CostTableIssue`] for the properties that are checked.
+    pub fn check(&self) -> CostTableCheck<AlphabetType> {
+        let entries: Vec<_> = AlphabetType::iter().map(Some).chain([None]).collect();
+        let cost = |from: &Option<AlphabetType::CharacterType>,
+                    to: &Option<AlphabetType::CharacterType>| {
+            self.cost_table.cost(from.clone(), to.clone()).ok()
+        };
+        let mut issues = Vec::new();
+
+        for (from_index, from) in entries.iter().enumerate() {
+            for (to_index, to) in entries.iter().enumerate() {
+                let Some(direct_cost) = cost(from, to) else {
+                    issues.push(CostTableIssue::Missing {
+                        from: from.clone(),
+                        to: to.clone(),
+                    });
+                    continue;
+                };
+                if from_index > to_index && cost(to, from) == Some(direct_cost) {
+                    // Symmetric entries are checked in the other order.
+                    continue;
+                }
+
+                if direct_cost < 0 {
+                    issues.push(CostTableIssue::Negative {
+                        from: from.clone(),
+                        to: to.clone(),
+                        cost: direct_cost,
+                    });
+                }
+
+                if from_index == to_index {
+                    // Two gaps do not pair any residues, so their cost is irrelevant for the diagonal.
+                    match from {
+                        Some(character) if direct_cost != 0 => {
+                            issues.push(CostTableIssue::NonZeroDiagonal {
+                                character: character.clone(),
+                                cost: direct_cost,
+                            })
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+
+                if let Some(reverse_cost) = cost(to, from) {
+                    if from_index < to_index && reverse_cost != direct_cost {
+                        issues.push(CostTableIssue::Asymmetric {
+                            from: from.clone(),
+                            to: to.clone(),
+                            cost: direct_cost,
+                            reverse_cost,
+                        });
+                    }
+                }
+
+                for (via_index, via) in entries.iter().enumerate() {
+                    if via_index == from_index || via_index == to_index {
+                        continue;
+                    }
+                    let (Some(first_cost), Some(second_cost)) = (cost(from, via), cost(via, to))
+                    else {
+                        continue;
+                    };
+                    let indirect_cost = i64::from(first_cost) + i64::from(second_cost);
+                    if i64::from(direct_cost) > indirect_cost {
+                        issues.push(CostTableIssue::TriangleInequality {
+                            from: from.clone(),
+                            via: via.clone(),
+                            to: to.clone(),
+                            direct_cost,
+                            indirect_cost,
+                        });
+                    }
+                }
+            }
+        }
+
+        let max_cost = entries
+            .iter()
+            .flat_map(|from| entries.iter().filter_map(move |to| cost(from, to)))
+            .max();
+
+        CostTableCheck { issues, max_cost }
+    }
+}
+
+/// The result of [`PairwiseCostMetric::check`].
+pub struct CostTableCheck<AlphabetType: Alphabet> {
+    /// The issues found in the cost table, errors and warnings mixed, see [`CostTableIssue::is_error`].
+    pub issues: Vec<CostTableIssue<AlphabetType>>,
+    /// The maximum cost of a pair of characters or gaps, or `None` if the table is empty.
+    pub max_cost: Option<i32>,
+}
+
+impl<AlphabetType: Alphabet> CostTableCheck<AlphabetType> {
+    /// Returns true if any issue is an error.
+    pub fn has_errors(&self) -> bool {
+        self.issues.iter().any(CostTableIssue::is_error)
+    }
+
+    /// An upper bound for the cost of a single column of an alignment of the given amount of sequences,
+    /// which is the maximum cost of a pair times the amount of pairs.
+    ///
+    /// Negative maximum costs are treated as zero.
+    pub fn max_column_cost(&self, sequence_amount: usize) -> Option<u128> {
+        let sequence_amount = sequence_amount as u128;
+        let pair_amount = sequence_amount * sequence_amount.saturating_sub(1) / 2;
+        let max_cost = u128::try_from(self.max_cost?.max(0)).unwrap();
+        pair_amount.checked_mul(max_cost)
+    }
+}
+
+/// An issue found by [`PairwiseCostMetric::check`], where `None` denotes a gap.
+pub enum CostTableIssue<AlphabetType: Alphabet> {
+    /// The table has no entry for a pair, which makes aligning fail as soon as the pair occurs.
+    Missing {
+        from: Option<AlphabetType::CharacterType>,
+        to: Option<AlphabetType::CharacterType>,
+    },
+    /// A negative cost, which the shortest-path search cannot handle.
+    Negative {
+        from: Option<AlphabetType::CharacterType>,
+        to: Option<AlphabetType::CharacterType>,
+        cost: i32,
+    },
+    /// The cost of a pair depends on the order of its characters, which is only supported with [`AsymmetricCosts::Ordered`].
+    Asymmetric {
+        from: Option<AlphabetType::CharacterType>,
+        to: Option<AlphabetType::CharacterType>,
+        cost: i32,
+        reverse_cost: i32,
+    },
+    /// Matching a character with itself is not free, which is allowed, but unusual.
+    NonZeroDiagonal {
+        character: AlphabetType::CharacterType,
+        cost: i32,
+    },
+    /// Replacing `from` by `to` directly is more expensive than replacing it via `via`.
+    /// This is allowed, but usually unintended, especially if `via` is a gap.
+    TriangleInequality {
+        from: Option<AlphabetType::CharacterType>,
+        via: Option<AlphabetType::CharacterType>,
+        to: Option<AlphabetType::CharacterType>,
+        direct_cost: i32,
+        indirect_cost: i64,
+    },
+}
+
+impl<AlphabetType: Alphabet> CostTableIssue<AlphabetType> {
+    /// Returns true if the issue makes the table unusable, and false if it is only a warning.
+    pub fn is_error(&self) -> bool {
+        matches!(
+            self,
+            Self::Missing { .. } | Self::Negative { .. } | Self::Asymmetric { .. }
+        )
+    }
+}
+
+impl<AlphabetType: Alphabet> Display for CostTableIssue<AlphabetType> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = |character: &Option<AlphabetType::CharacterType>| {
+            character
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_else(|| "gap".to_string())
+        };
+
+        match self {
+            Self::Missing { from, to } => {
+                write!(f, "Missing entry for row {}, column {}", name(from), name(to))
+            }
+            Self::Negative { from, to, cost } => write!(
+                f,
+                "Negative entry for row {}, column {}: {cost}",
+                name(from),
+                name(to)
+            ),
+            Self::Asymmetric {
+                from,
+                to,
+                cost,
+                reverse_cost,
+            } => write!(
+                f,
+                "Asymmetric entry for row {}, column {}: {cost} != {reverse_cost}",
+                name(from),
+                name(to)
+            ),
+            Self::NonZeroDiagonal { character, cost } => {
+                write!(f, "Non-zero diagonal entry for {character}: {cost}")
+            }
+            Self::TriangleInequality {
+                from,
+                via,
+                to,
+                direct_cost,
+                indirect_cost,
+            } => write!(
+                f,
+                "Triangle inequality violated: {} to {} costs {direct_cost}, but via {} only {indirect_cost}",
+                name(from),
+                name(to),
+                name(via)
+            ),
+        }
+    }
+}
+
+impl<AlphabetType: Alphabet> MultialignMetric<AlphabetType> for PairwiseCostMetric<AlphabetType> {
+    fn reset_character_counts(&mut self) {
+        self.character_counts.fill(0);
+        self.wildcard_counts.fill(0);
+        self.ordered_column.clear();
+    }
+
+    fn restrict_alphabet(&mut self, characters: &[AlphabetType::CharacterType]) -> Result<()> {
+        let characters: Vec<_> = characters.iter().cloned().map(Some).chain([None]).collect();
+
+        for from in &characters {
+            for to in &characters {
+                self.cost_table.cost(from.clone(), to.clone())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn count_character(
+        &mut self,
+        character: &<AlphabetType as Alphabet>::CharacterType,
+        sequence_index: usize,
+    ) {
+        if self.cost_table.ordered {
+            self.ordered_column
+                .push((sequence_index, usize::from(character.index()), false));
+        } else {
+            self.character_counts[usize::from(character.index())] += 1;
+        }
+    }
+
+    fn count_wildcard(
+        &mut self,
+        character: &<AlphabetType as Alphabet>::CharacterType,
+        sequence_index: usize,
+    ) {
+        if self.cost_table.ordered {
+            self.ordered_column
+                .push((sequence_index, usize::from(character.index()), true));
+        } else {
+            self.wildcard_counts[usize::from(character.index())] += 1;
+        }
+    }
+
+    fn count_gap(&mut self, sequence_index: usize) {
+        if self.cost_table.ordered {
+            self.ordered_column
+                .push((sequence_index, usize::from(AlphabetType::SIZE), false));
+        } else {
+            self.character_counts[usize::from(AlphabetType::SIZE)] += 1;
+        }
+    }
+
+    fn count_terminal_gap(&mut self, _sequence_index: usize) {
+        // Terminal gaps are free, so they are not counted.
+    }
+
+    fn is_sum_of_pairs(&self) -> bool {
+        // Unordered columns are scored by character counts, which charge the diagonal entry of a character also for a single occurrence.
+        self.cost_table.ordered || self.cost_table.has_zero_diagonal()
+    }
+
+    fn compute_cost_increment<Cost: AStarCost>(&mut self) -> Result<Cost>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        if self.cost_table.ordered {
+            return self.compute_ordered_cost_increment();
+        }
+
+        let mut cost = Cost::zero();
+
+        self.non_zero_character_counts.clear();
+        for (index, count) in self.character_counts.iter().copied().enumerate() {
+            if count == 0 {
+                continue;
+            }
+
+            self.non_zero_character_counts.push(index);
+
+            for other_index in self.non_zero_character_counts.iter().copied() {
+                let other_count = self.character_counts[other_index];
+                let count = i32::from(count);
+                let other_count = i32::from(other_count);
+
+                let multiplicity = count.checked_mul(other_count).unwrap();
+                let base_cost = self.cost_table.cost(
+                    if index == usize::from(AlphabetType::SIZE) {
+                        None
+                    } else {
+                        Some(
+                            AlphabetType::CharacterType::from_index(index.try_into().unwrap())
+                                .unwrap(),
+                        )
+                    },
+                    if other_index == usize::from(AlphabetType::SIZE) {
+                        None
+                    } else {
+                        Some(
+                            AlphabetType::CharacterType::from_index(
+                                other_index.try_into().unwrap(),
+                            )
+                            .unwrap(),
+                        )
+                    },
+                )?;
+                cost = cost
+                    .checked_add(&cost_from_i32(
+                        multiplicity.checked_mul(base_cost).unwrap(),
+                    )?)
+                    .ok_or(MultialignError::CostOverflow)?;
+            }
+        }
+
+        let gap_count = i32::from(self.character_counts[usize::from(AlphabetType::SIZE)]);
+        if gap_count > 0 {
+            for (index, count) in self.wildcard_counts.iter().copied().enumerate() {
+                if count == 0 {
+                    continue;
+                }
+
+                let base_cost = self.cost_table.cost(
+                    Some(
+                        AlphabetType::CharacterType::from_index(index.try_into().unwrap()).unwrap(),
+                    ),
+                    None,
+                )?;
+                cost = cost
+                    .checked_add(&cost_from_i32(
+                        i32::from(count)
+                            .checked_mul(gap_count)
+                            .unwrap()
+                            .checked_mul(base_cost)
+                            .unwrap(),
+                    )?)
+                    .ok_or(MultialignError::CostOverflow)?;
+            }
+        }
+
+        Ok(cost)
+    }
+}
+
+impl<AlphabetType: Alphabet> PairwiseCostTable<AlphabetType> {
+    #[cfg(feature = "csv")]
+    fn from_csv_file(path: impl AsRef<Path>, options: &CostTableOptions) -> Result<Self> {
+        let path = path.as_ref();
+        info!("Reading CSV file {path:?}");
+
+        let csv_error = |source| MultialignError::Csv {
+            path: path.to_owned(),
+            source,
+        };
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_path(path)
+            .map_err(csv_error)?;
+        Self::from_rows(
+            reader.records().map(|record| {
+                record
+                    .map(|record| record.iter().map(String::from).collect())
+                    .map_err(csv_error)
+            }),
+            options,
+        )
+    }
+
+    #[cfg(not(feature = "csv"))]
+    fn from_csv_file(path: impl AsRef<Path>, _options: &CostTableOptions) -> Result<Self> {
+        Err(MultialignError::Io {
+            path: path.as_ref().to_owned(),
+            source: std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "reading CSV files requires the csv feature",
+            ),
+        })
+    }
+
+    fn from_rows(
+        rows: impl IntoIterator<Item = Result<Vec<String>>>,
+        options: &CostTableOptions,
+    ) -> Result<Self> {
+        let mut lines = rows.into_iter().enumerate();
+        let mut cost_map = BTreeMap::new();
+        let mut decimal_digits = 0;
+
+        // Parse first line
+        let first_line = lines
+            .next()
+            .map(|(_, first_line)| first_line)
+            .ok_or_else(|| {
+                MultialignError::InvalidCostTable("CSV file contains no lines".to_string())
+            })??;
+        let mut character_to_column = vec![None; usize::from(AlphabetType::SIZE) + 1];
+        let mut column_to_character = Vec::new();
+        let gap_character_index = AlphabetType::SIZE;
+
+        for (column, character) in first_line.iter().enumerate() {
+            if column == 0 {
+                ensure_valid!(
+                    character.trim().is_empty(),
+                    "First column of first row must be empty, but was: {:?}",
+                    character.trim()
+                );
+                column_to_character.push(None);
+                continue;
+            }
+
+            let character = character.trim();
+            ensure_valid!(character.chars().count() == 1, "First row must contain a single character in each column, except for the first column which must be empty");
+            let character = character.chars().next().unwrap();
+
+            if character == '*' || character == '-' {
+                ensure_valid!(
+                    character_to_column[usize::from(gap_character_index)].is_none(),
+                    "First row contained a character twice: {character}"
+                );
+                character_to_column[usize::from(gap_character_index)] = Some(column);
+                column_to_character.push(Some(gap_character_index));
+                continue;
+            }
+
+            let character = character.try_into().map_err(|_| {
+                MultialignError::InvalidCostTable(format!(
+                    "Character must be a valid ASCII character, but is {character:?}"
+                ))
+            })?;
+            let character = AlphabetType::ascii_to_character(character).map_err(|_| {
+                MultialignError::InvalidCostTable(format!(
+                    "Character must be a valid alphabet character, but is {:?}",
+                    char::from(character)
+                ))
+            })?;
+            let index = usize::from(character.index());
+
+            ensure_valid!(
+                character_to_column[index].is_none(),
+                "First row contained a character twice: {character}"
+            );
+            character_to_column[index] = Some(column);
+            column_to_character.push(Some(character.index()));
+        }
+
+        // Parse further lines
+        let mut character_to_row = vec![None; usize::from(AlphabetType::SIZE) + 1];
+        let mut row_to_character = vec![None];
+        for (row, line) in lines {
+            let line = line?;
+
+            for (column, cost) in line.iter().enumerate() {
+                if column == 0 {
+                    let character = cost.trim();
+                    ensure_valid!(
+                        character.chars().count() == 1,
+                        "First column of rows after the first must contain a single character"
+                    );
+                    let character = character.chars().next().unwrap();
+
+                    if character == '*' || character == '-' {
+                        ensure_valid!(
+                            character_to_row[usize::from(gap_character_index)].is_none(),
+                            "First column contained a character twice: {character}"
+                        );
+                        character_to_row[usize::from(gap_character_index)] = Some(row);
+                        row_to_character.push(Some(gap_character_index));
+                        continue;
+                    }
+
+                    let character = character.try_into().map_err(|_| {
+                        MultialignError::InvalidCostTable(format!(
+                            "Character must be a valid ASCII character, but is {character:?}"
+                        ))
+                    })?;
+                    let character = AlphabetType::ascii_to_character(character).map_err(|_| {
+                        MultialignError::InvalidCostTable(format!(
+                            "Character must be a valid alphabet character, but is {:?}",
+                            char::from(character)
+                        ))
+                    })?;
+                    let index = usize::from(character.index());
+
+                    ensure_valid!(
+                        character_to_row[index].is_none(),
+                        "First column contained a character twice: {character}"
+                    );
+                    character_to_row[index] = Some(column);
+                    row_to_character.push(Some(character.index()));
+                    continue;
+                }
+
+                let (cost, cost_decimal_digits) = parse_decimal(cost)?;
+                decimal_digits = decimal_digits.max(cost_decimal_digits);
+
+                let from = row_to_character[row].unwrap();
+                let to = column_to_character[column].ok_or_else(|| {
+                    MultialignError::InvalidCostTable(
+                        "Subsequent row contains more columns than the first row".to_string(),
+                    )
+                })?;
+                let from = if from == gap_character_index {
+                    None
+                } else {
+                    Some(AlphabetType::CharacterType::from_index(from).unwrap())
+                };
+                let to = if to == gap_character_index {
+                    None
+                } else {
+                    Some(AlphabetType::CharacterType::from_index(to).unwrap())
+                };
+                let previous_cost = cost_map.insert((from, to), cost);
+                debug_assert!(previous_cost.is_none());
+            }
+        }
+
+        let decimal_digits = symmetrize::<AlphabetType>(
+            &mut cost_map,
+            decimal_digits,
+            options.asymmetric_costs,
+            false,
+        );
+        let scale = integer_scale(decimal_digits, options.precision);
+        let mut cost_map = scale_to_integers(cost_map, scale)?;
+        fill_missing_entries::<AlphabetType>(&mut cost_map, options, scale)?;
+        Self::from_cost_map(
+            &cost_map,
+            options.asymmetric_costs == AsymmetricCosts::Ordered,
+        )
+    }
+
+    /// Parse a substitution matrix in the whitespace-separated format of NCBI and EMBOSS, and convert its scores into costs.
+    ///
+    /// Lines starting with `#` are comments, the first other line contains the column characters,
+    /// and each further line contains a row character followed by one score per column.
+    /// Characters that are not part of the alphabet are skipped, and `*` or `-` denote the gap character.
+    ///
+    /// Each score is converted into the cost `max - score`, where `max` is the maximum score between two residues.
+    /// Two gaps always have cost zero, since they do not pair any residues.
+    fn from_ncbi_matrix(matrix: &str, options: &CostTableOptions) -> Result<Self> {
+        let gap_character_index = AlphabetType::SIZE;
+        let parse_character = |character: &str| {
+            ensure_valid!(
+                character.chars().count() == 1,
+                "Matrix must contain a single character in the first row and column, but contains {character:?}"
+            );
+            let character = character.chars().next().unwrap();
+            if character == '*' || character == '-' {
+                return Ok(Some(gap_character_index));
+            }
+
+            Ok(u8::try_from(character)
+                .ok()
+                .and_then(|character| AlphabetType::ascii_to_character(character).ok())
+                .map(|character| character.index()))
+        };
+
+        let mut lines = matrix
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        let column_to_character = lines
+            .next()
+            .ok_or_else(|| {
+                MultialignError::InvalidCostTable("Matrix contains no lines".to_string())
+            })?
+            .split_whitespace()
+            .map(parse_character)
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut scores = BTreeMap::new();
+        let mut decimal_digits = 0;
+        for line in lines {
+            let mut entries = line.split_whitespace();
+            let from = parse_character(entries.next().unwrap())?;
+            let entries: Vec<_> = entries.collect();
+            ensure_valid!(
+                entries.len() == column_to_character.len(),
+                "Matrix row has {} scores, but the first row has {} characters",
+                entries.len(),
+                column_to_character.len()
+            );
+            let Some(from) = from else {
+                continue;
+            };
+
+            for (score, to) in entries.into_iter().zip(&column_to_character) {
+                let Some(to) = *to else {
+                    continue;
+                };
+                let (score, score_decimal_digits) = parse_decimal(score)?;
+                decimal_digits = decimal_digits.max(score_decimal_digits);
+                let character = |index| {
+                    (index != gap_character_index)
+                        .then(|| AlphabetType::CharacterType::from_index(index).unwrap())
+                };
+                ensure_valid!(
+                    scores
+                        .insert((character(from), character(to)), score)
+                        .is_none(),
+                    "Matrix contains a character twice"
+                );
+            }
+        }
+
+        let decimal_digits =
+            symmetrize::<AlphabetType>(&mut scores, decimal_digits, options.asymmetric_costs, true);
+        let scale = integer_scale(decimal_digits, options.precision);
+        let scores = scale_to_integers(scores, scale)?;
+        let max_score = scores
+            .iter()
+            .filter(|((from, to), _)| from.is_some() && to.is_some())
+            .map(|(_, score)| *score)
+            .max()
+            .ok_or_else(|| {
+                MultialignError::InvalidCostTable(
+                    "Matrix contains no scores between characters of the alphabet".to_string(),
+                )
+            })?;
+        let mut cost_map = scores
+            .into_iter()
+            .map(|(key, score)| {
+                max_score
+                    .checked_sub(score)
+                    .map(|cost| (key, cost))
+                    .ok_or(MultialignError::CostOverflow)
+            })
+            .collect::<Result<BTreeMap<_, _>>>()?;
+        if cost_map
+            .keys()
+            .any(|(from, to)| from.is_none() || to.is_none())
+        {
+            cost_map.insert((None, None), 0);
+        }
+        fill_missing_entries::<AlphabetType>(&mut cost_map, options, scale)?;
+
+        Self::from_cost_map(
+            &cost_map,
+            options.asymmetric_costs == AsymmetricCosts::Ordered,
+        )
+    }
+
+    /// Transform a map from pairs of characters or gaps (`None`) to costs into a table, and check that it is symmetric unless it is `ordered`.
+    fn from_cost_map(
+        cost_map: &BTreeMap<CharacterPair<AlphabetType>, i32>,
+        ordered: bool,
+    ) -> Result<Self> {
+        let gap_character_index = AlphabetType::SIZE;
+        let mut table = Vec::with_capacity((usize::from(AlphabetType::SIZE) + 1) << 1);
+        for from in AlphabetType::iter().map(Some).chain([None]) {
+            for to in AlphabetType::iter().map(Some).chain([None]) {
+                trace!(
+                    "from_index: {:?}; to_index: {:?}; gap_character_index: {}",
+                    from.as_ref().map(AlphabetCharacter::index),
+                    to.as_ref().map(AlphabetCharacter::index),
+                    gap_character_index,
+                );
+                let cost = cost_map.get(&(from.clone(), to.clone())).copied();
+                table.push(cost);
+
+                let from_index = from
+                    .as_ref()
+                    .map(AlphabetCharacter::index)
+                    .unwrap_or(gap_character_index);
+                let to_index = to
+                    .as_ref()
+                    .map(AlphabetCharacter::index)
+                    .unwrap_or(gap_character_index);
+
+                // Ensure symmetry
+                if !ordered && to_index < from_index {
+                    let other_cost = table[usize::from(to_index)
+                        * (usize::from(AlphabetType::SIZE) + 1)
+                        + usize::from(from_index)];
+                    ensure_valid!(
+                        other_cost == cost,
+                        "Assymetric entry found for row {}, column {}: {:?} != {:?}",
+                        if let Some(from) = from {
+                            from.to_string()
+                        } else {
+                            "gap".to_string()
+                        },
+                        if let Some(to) = to {
+                            to.to_string()
+                        } else {
+                            "gap".to_string()
+                        },
+                        cost,
+                        other_cost,
+                    )
+                }
+            }
+        }
+
+        Ok(Self {
+            table,
+            ordered,
+            phantom_data: PhantomData,
+        })
+    }
+
+    fn cost(
+        &self,
+        from: Option<AlphabetType::CharacterType>,
+        to: Option<AlphabetType::CharacterType>,
+    ) -> Result<i32> {
+        let gap_character_index = usize::from(AlphabetType::SIZE);
+        let from = usize::from(
+            from.as_ref()
+                .map(AlphabetCharacter::index)
+                .unwrap_or(AlphabetType::SIZE),
+        );
+        let to = usize::from(
+            to.as_ref()
+                .map(AlphabetCharacter::index)
+                .unwrap_or(AlphabetType::SIZE),
+        );
+
+        self.table[from * (usize::from(AlphabetType::SIZE) + 1) + to].ok_or_else(|| {
+            MultialignError::IncompleteCostTable {
+                from: (from != gap_character_index).then(|| from.to_string()),
+                to: (to != gap_character_index).then(|| to.to_string()),
+            }
+        })
+    }
+
+    /// Returns true if no character, including the gap, has a non-zero cost against itself.
+    fn has_zero_diagonal(&self) -> bool {
+        let width = usize::from(AlphabetType::SIZE) + 1;
+        (0..width).all(|index| self.table[index * width + index].unwrap_or(0) == 0)
+    }
+}
+
+/// Parse an entry of a cost table, returning its value and the amount of its decimal digits after the decimal point, ignoring trailing zeros.
+fn parse_decimal(entry: &str) -> Result<(f64, u32)> {
+    let entry = entry.trim();
+    let value: f64 = entry
+        .parse()
+        .ok()
+        .filter(|value: &f64| value.is_finite() && !entry.contains(['e', 'E']))
+        .ok_or_else(|| {
+            MultialignError::InvalidCostTable(format!("Error parsing '{entry}' as decimal number"))
+        })?;
+    let decimal_digits = entry
+        .split_once('.')
+        .map(|(_, fraction)| fraction.trim_end_matches('0').len() as u32)
+        .unwrap_or(0);
+    Ok((value, decimal_digits))
+}
+
+/// Make the entries of a cost table symmetric as selected by `asymmetric_costs`, and return the amount of decimal digits of the result.
+///
+/// If an entry exists but its mirrored entry does not, then the mirrored entry is set to the entry.
+/// If `is_score` is true, then the entries are higher-is-better scores, so [`AsymmetricCosts::Max`] selects the lower score.
+fn symmetrize<AlphabetType: Alphabet>(
+    entries: &mut BTreeMap<CharacterPair<AlphabetType>, f64>,
+    decimal_digits: u32,
+    asymmetric_costs: AsymmetricCosts,
+    is_score: bool,
+) -> u32 {
+    if matches!(
+        asymmetric_costs,
+        AsymmetricCosts::Reject | AsymmetricCosts::Ordered
+    ) {
+        return decimal_digits;
+    }
+
+    let scale = 10f64.powi(decimal_digits as i32);
+    let mut symmetrized_amount = 0;
+    let mut has_halves = false;
+    let pairs: Vec<_> = entries.keys().cloned().collect();
+    for (from, to) in pairs {
+        let entry = entries[&(from.clone(), to.clone())];
+        let mirrored_key = (to, from);
+        let symmetric_entry = match entries.get(&mirrored_key) {
+            Some(&mirrored_entry) if mirrored_entry == entry => continue,
+            Some(&mirrored_entry) => match asymmetric_costs {
+                AsymmetricCosts::Average => {
+                    has_halves |= ((entry + mirrored_entry) * scale).round() as i64 % 2 != 0;
+                    (entry + mirrored_entry) / 2.0
+                }
+                _ if is_score => entry.min(mirrored_entry),
+                _ => entry.max(mirrored_entry),
+            },
+            None => entry,
+        };
+
+        symmetrized_amount += 1;
+        entries.insert(mirrored_key.clone(), symmetric_entry);
+        entries.insert((mirrored_key.1, mirrored_key.0), symmetric_entry);
+    }
+
+    if symmetrized_amount > 0 {
+        info!("Symmetrized {symmetrized_amount} asymmetric pairs of cost table entries");
+    }
+    decimal_digits + u32::from(has_halves)
+}
+
+/// The factor by which the entries of a cost table are scaled to integers, which keeps `min(decimal_digits, precision)` decimal digits.
+fn integer_scale(decimal_digits: u32, precision: u32) -> f64 {
+    if decimal_digits > precision {
+        warn!("Cost table has entries with {decimal_digits} decimal digits, but only {precision} are kept");
+    }
+    let scale = 10f64.powi(decimal_digits.min(precision) as i32);
+    if scale != 1.0 {
+        info!("Scaling cost table by {scale} to integers");
+    }
+    scale
+}
+
+/// Multiply an entry of a cost table by the scale and round it to an integer.
+///
+/// Returns an [`MultialignError::InvalidCostTable`] if the scaled entry is out of range of `i32`.
+fn scale_entry(entry: f64, scale: f64) -> Result<i32> {
+    let scaled_entry = (entry * scale).round();
+    if scaled_entry.abs() > f64::from(i32::MAX) {
+        return Err(MultialignError::InvalidCostTable(format!(
+            "Entry {entry} scaled by {scale} is out of range of i32"
+        )));
+    }
+    Ok(scaled_entry as i32)
+}
+
+/// Scale the entries of a cost table to integers, see [`scale_entry`].
+fn scale_to_integers<Key: Ord>(
+    entries: BTreeMap<Key, f64>,
+    scale: f64,
+) -> Result<BTreeMap<Key, i32>> {
+    entries
+        .into_iter()
+        .map(|(key, entry)| Ok((key, scale_entry(entry, scale)?)))
+        .collect()
+}
+
+/// Insert the stop codon costs and the default costs of the options scaled by `scale` for the pairs that are missing from the cost table.
+///
+/// Pairs of a character with itself are set to zero, since the substitution cost only applies to different characters,
+/// and the same holds for the pair of two gaps.
+fn fill_missing_entries<AlphabetType: Alphabet>(
+    cost_map: &mut BTreeMap<CharacterPair<AlphabetType>, i32>,
+    options: &CostTableOptions,
+    scale: f64,
+) -> Result<()> {
+    if let Some(stop_codon_costs) = options.stop_codon_costs {
+        let stop = AlphabetType::ascii_to_character(b'*').map_err(|_| {
+            MultialignError::InvalidCostTable(
+                "Stop codon costs require an alphabet with the stop character '*'".to_string(),
+            )
+        })?;
+        let substitution_cost = scale_entry(f64::from(stop_codon_costs.substitution), scale)?;
+        let gap_cost = scale_entry(f64::from(stop_codon_costs.gap), scale)?;
+
+        for other in AlphabetType::iter().map(Some).chain([None]) {
+            let cost = match &other {
+                Some(other) if *other == stop => 0,
+                Some(_) => substitution_cost,
+                None => gap_cost,
+            };
+            cost_map.insert((Some(stop.clone()), other.clone()), cost);
+            cost_map.insert((other, Some(stop.clone())), cost);
+        }
+    }
+
+    let default_substitution_cost = options
+        .default_substitution_cost
+        .map(|cost| scale_entry(f64::from(cost), scale))
+        .transpose()?;
+    let default_gap_cost = options
+        .default_gap_cost
+        .map(|cost| scale_entry(f64::from(cost), scale))
+        .transpose()?;
+
+    let mut substitution_amount = 0;
+    let mut gap_amount = 0;
+    for from in AlphabetType::iter().map(Some).chain([None]) {
+        for to in AlphabetType::iter().map(Some).chain([None]) {
+            let key = (from.clone(), to);
+            if cost_map.contains_key(&key) {
+                continue;
+            }
+
+            let cost = match &key {
+                (Some(from), Some(to)) if from != to => {
+                    substitution_amount += 1;
+                    default_substitution_cost
+                }
+                (Some(_), Some(_)) => default_substitution_cost.map(|_| 0),
+                (Some(_), None) | (None, Some(_)) => {
+                    gap_amount += 1;
+                    default_gap_cost
+                }
+                (None, None) => default_gap_cost.map(|_| 0),
+            };
+            if let Some(cost) = cost {
+                cost_map.insert(key, cost);
+            }
+        }
+    }
+
+    if default_substitution_cost.is_some() && substitution_amount > 0 {
+        info!("Set {substitution_amount} missing substitution entries of the cost table to the default substitution cost");
+    }
+    if default_gap_cost.is_some() && gap_amount > 0 {
+        info!("Set {gap_amount} missing gap entries of the cost table to the default gap cost");
+    }
+    Ok(())
+}
@@ -0,0 +1,163 @@
+use std::marker::PhantomData;
+
+use compact_genome::interface::alphabet::{Alphabet, AlphabetCharacter};
+
+use super::{cost_from_i32, MultialignMetric};
+use crate::{
+    consensus::iupac_nucleotides,
+    error::{MultialignError, Result},
+};
+
+/// A pairwise metric that scores matches with zero and everything else with one.
+///
+/// Specifically, pairs of gaps are scored with zero as well.
+/// Wildcards are scored with zero against residues and other wildcards, and with one against gaps.
+/// Optionally, IUPAC nucleotide codes that can stand for the same nucleotide are scored as matches, see [`Self::with_nucleotide_ambiguity_codes`].
+#[derive(Clone)]
+pub struct PairwiseMatchMetric<AlphabetType: Alphabet> {
+    character_counts: Vec<u8>,
+    /// The pairs of indices of different characters that are scored as matches.
+    compatible_characters: Vec<(usize, usize)>,
+    wildcard_count: i32,
+    /// The alphabet is only a marker, so it does not keep the metric from being sent to other threads.
+    phantom_data: PhantomData<fn() -> AlphabetType>,
+}
+
+impl<AlphabetType: Alphabet> PairwiseMatchMetric<AlphabetType> {
+    pub fn new(sequence_amount: usize) -> Result<Self> {
+        // We multiply the amount of sequences by itself later, so we restrict to i8 to make sure it does not overflow.
+        i8::try_from(sequence_amount).map_err(|_| MultialignError::TooManySequences {
+            amount: sequence_amount,
+            max: i8::MAX as usize,
+        })?;
+
+        Ok(Self {
+            character_counts: vec![0; usize::from(AlphabetType::SIZE) + 1],
+            compatible_characters: Vec::new(),
+            wildcard_count: 0,
+            phantom_data: PhantomData,
+        })
+    }
+
+    /// Score pairs of IUPAC nucleotide codes that can stand for the same nucleotide as matches, e.g. `N` with `A` or `R` with `G`.
+    ///
+    /// This must only be used with nucleotide alphabets, since amino acid alphabets use some of the same letters.
+    /// Characters that are not nucleotide codes still only match themselves.
+    pub fn with_nucleotide_ambiguity_codes(mut self) -> Self {
+        let nucleotides: Vec<_> = AlphabetType::iter()
+            .map(|character| iupac_nucleotides(character.into()))
+            .collect();
+        self.compatible_characters.clear();
+        for (first, first_nucleotides) in nucleotides.iter().enumerate() {
+            for (second, second_nucleotides) in nucleotides.iter().enumerate().skip(first + 1) {
+                if let (Some(first_nucleotides), Some(second_nucleotides)) =
+                    (first_nucleotides, second_nucleotides)
+                {
+                    if first_nucleotides & second_nucleotides != 0 {
+                        self.compatible_characters.push((first, second));
+                    }
+                }
+            }
+        }
+        self
+    }
+}
+
+impl<AlphabetType: Alphabet> MultialignMetric<AlphabetType> for PairwiseMatchMetric<AlphabetType> {
+    fn reset_character_counts(&mut self) {
+        self.character_counts.fill(0);
+        self.wildcard_count = 0;
+    }
+
+    fn count_character(
+        &mut self,
+        character: &<AlphabetType as Alphabet>::CharacterType,
+        _sequence_index: usize,
+    ) {
+        self.character_counts[usize::from(character.index())] += 1;
+    }
+
+    fn count_wildcard(
+        &mut self,
+        _character: &<AlphabetType as Alphabet>::CharacterType,
+        _sequence_index: usize,
+    ) {
+        self.wildcard_count += 1;
+    }
+
+    fn count_gap(&mut self, _sequence_index: usize) {
+        self.character_counts[usize::from(AlphabetType::SIZE)] += 1;
+    }
+
+    fn count_terminal_gap(&mut self, _sequence_index: usize) {
+        // Terminal gaps are free, so they are not counted.
+    }
+
+    fn is_sum_of_pairs(&self) -> bool {
+        true
+    }
+
+    fn compute_cost_increment<Cost: generic_a_star::cost::AStarCost>(&mut self) -> Result<Cost>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        let score_increment =
+            self.character_counts
+                .iter()
+                .try_fold(Cost::zero(), |score, character_count| {
+                    let character_count = i32::from(*character_count);
+                    let character_score = if character_count >= 2 {
+                        cost_from_i32::<Cost>(
+                            character_count
+                                .checked_mul(character_count.checked_sub(1).unwrap())
+                                .unwrap()
+                                .checked_div(2)
+                                .unwrap(),
+                        )?
+                    } else {
+                        Cost::zero()
+                    };
+
+                    score
+                        .checked_add(&character_score)
+                        .ok_or(MultialignError::CostOverflow)
+                })?;
+        let score_increment = self.compatible_characters.iter().try_fold(
+            score_increment,
+            |score, (first, second)| {
+                let pair_count = i32::from(self.character_counts[*first])
+                    * i32::from(self.character_counts[*second]);
+                score
+                    .checked_add(&cost_from_i32::<Cost>(pair_count)?)
+                    .ok_or(MultialignError::CostOverflow)
+            },
+        )?;
+        // Wildcards match everything except gaps, and terminal gaps are free, so only residues and gaps are paired.
+        // Counting them instead of using the amount of sequences also allows to score columns of only some sequences.
+        let sequence_amount = self
+            .character_counts
+            .iter()
+            .copied()
+            .map(i32::from)
+            .sum::<i32>();
+        let max_score = cost_from_i32::<Cost>(
+            sequence_amount
+                .checked_mul(sequence_amount.checked_sub(1).unwrap())
+                .unwrap()
+                .checked_div(2)
+                .unwrap(),
+        )?;
+        let wildcard_gap_cost = cost_from_i32::<Cost>(
+            self.wildcard_count
+                .checked_mul(i32::from(
+                    self.character_counts[usize::from(AlphabetType::SIZE)],
+                ))
+                .unwrap(),
+        )?;
+        max_score
+            .checked_sub(&score_increment)
+            .unwrap()
+            .checked_add(&wildcard_gap_cost)
+            .ok_or(MultialignError::CostOverflow)
+    }
+}
@@ -0,0 +1,308 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    marker::PhantomData,
+    path::Path,
+};
+
+use compact_genome::interface::alphabet::{Alphabet, AlphabetCharacter};
+use generic_a_star::cost::AStarCost;
+use log::{info, warn};
+
+use super::{cost_from_i32, MultialignMetric};
+use crate::error::{MultialignError, Result};
+
+/// Return an [`MultialignError::InvalidProfileHmm`] if the condition is false.
+macro_rules! ensure_valid {
+    ($condition:expr, $($message:tt)+) => {
+        if !$condition {
+            return Err(MultialignError::InvalidProfileHmm(format!($($message)+)));
+        }
+    };
+}
+
+/// Return an [`MultialignError::InvalidProfileHmm`].
+macro_rules! invalid {
+    ($($message:tt)+) => {
+        MultialignError::InvalidProfileHmm(format!($($message)+))
+    };
+}
+
+/// A metric derived from a Plan7 profile HMM as written by HMMER3.
+///
+/// The alignment lattice has no notion of model positions, so the emission and transition probabilities are averaged over all nodes of the model.
+/// Each column is then interpreted as a match column if at least half of its entries are residues, and as an insert column otherwise.
+///
+///  * In a match column, each residue costs its match emission plus the match-to-match transition, and each gap costs the match-to-delete transition.
+///  * In an insert column, each residue costs its insert emission plus the match-to-insert transition, and gaps are free.
+///
+/// All costs are negative natural logarithms of probabilities, multiplied by a scale factor and rounded to integers.
+/// Characters of the alphabet that do not occur in the model are scored with a uniform emission probability.
+/// Wildcards are emitted with probability one.
+#[derive(Clone)]
+pub struct ProfileHmmMetric<AlphabetType> {
+    match_emission_costs: Vec<i32>,
+    insert_emission_costs: Vec<i32>,
+    match_to_match_cost: i32,
+    match_to_insert_cost: i32,
+    match_to_delete_cost: i32,
+    character_counts: Vec<u8>,
+    wildcard_count: i32,
+    phantom_data: PhantomData<AlphabetType>,
+}
+
+/// The position-averaged probabilities of a profile HMM.
+struct AveragedProfileHmm {
+    symbols: Vec<u8>,
+    match_emissions: Vec<f64>,
+    insert_emissions: Vec<f64>,
+    match_to_match: f64,
+    match_to_insert: f64,
+    match_to_delete: f64,
+}
+
+impl<AlphabetType: Alphabet> ProfileHmmMetric<AlphabetType> {
+    /// Read a HMMER3 profile HMM file.
+    ///
+    /// If the file contains multiple models, only the first one is used.
+    pub fn from_hmm_file(path: impl AsRef<Path>, scale: f64) -> Result<Self> {
+        let path = path.as_ref();
+        info!("Reading profile HMM {path:?}");
+
+        let file = File::open(path).map_err(|source| MultialignError::Io {
+            path: path.to_owned(),
+            source,
+        })?;
+        let hmm = AveragedProfileHmm::read(BufReader::new(file), path)?;
+
+        let to_cost = |probability: f64| -> Result<i32> {
+            let cost = (-probability.ln() * scale).round();
+            ensure_valid!(
+                cost.is_finite() && cost <= f64::from(i32::MAX),
+                "Probability {probability} cannot be represented as cost with scale {scale}"
+            );
+            Ok(cost as i32)
+        };
+
+        let uniform_probability = 1.0 / hmm.symbols.len() as f64;
+        let mut match_emission_costs =
+            vec![to_cost(uniform_probability)?; AlphabetType::SIZE.into()];
+        let mut insert_emission_costs = match_emission_costs.clone();
+
+        for (index, symbol) in hmm.symbols.iter().copied().enumerate() {
+            let Ok(character) = AlphabetType::ascii_to_character(symbol) else {
+                warn!(
+                    "Profile HMM symbol {} is not part of the alphabet",
+                    char::from(symbol)
+                );
+                continue;
+            };
+
+            match_emission_costs[usize::from(character.index())] =
+                to_cost(hmm.match_emissions[index])?;
+            insert_emission_costs[usize::from(character.index())] =
+                to_cost(hmm.insert_emissions[index])?;
+        }
+
+        Ok(Self {
+            match_emission_costs,
+            insert_emission_costs,
+            match_to_match_cost: to_cost(hmm.match_to_match)?,
+            match_to_insert_cost: to_cost(hmm.match_to_insert)?,
+            match_to_delete_cost: to_cost(hmm.match_to_delete)?,
+            character_counts: vec![0; usize::from(AlphabetType::SIZE) + 1],
+            wildcard_count: 0,
+            phantom_data: PhantomData,
+        })
+    }
+}
+
+impl<AlphabetType: Alphabet> MultialignMetric<AlphabetType> for ProfileHmmMetric<AlphabetType> {
+    fn reset_character_counts(&mut self) {
+        self.character_counts.fill(0);
+        self.wildcard_count = 0;
+    }
+
+    fn count_character(&mut self, character: &AlphabetType::CharacterType, _sequence_index: usize) {
+        self.character_counts[usize::from(character.index())] += 1;
+    }
+
+    fn count_wildcard(&mut self, _character: &AlphabetType::CharacterType, _sequence_index: usize) {
+        self.wildcard_count += 1;
+    }
+
+    fn count_gap(&mut self, _sequence_index: usize) {
+        self.character_counts[usize::from(AlphabetType::SIZE)] += 1;
+    }
+
+    fn count_terminal_gap(&mut self, _sequence_index: usize) {
+        // Terminal gaps are free, so they are not counted.
+    }
+
+    fn compute_cost_increment<Cost: AStarCost>(&mut self) -> Result<Cost>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        let gap_count = i32::from(self.character_counts[usize::from(AlphabetType::SIZE)]);
+        let residue_count = self.character_counts[..usize::from(AlphabetType::SIZE)]
+            .iter()
+            .copied()
+            .map(i32::from)
+            .sum::<i32>()
+            + self.wildcard_count;
+        let is_match_column = residue_count >= gap_count;
+
+        let (emission_costs, transition_cost, gap_cost) = if is_match_column {
+            (
+                &self.match_emission_costs,
+                self.match_to_match_cost,
+                self.match_to_delete_cost,
+            )
+        } else {
+            (&self.insert_emission_costs, self.match_to_insert_cost, 0)
+        };
+
+        let mut cost = gap_cost
+            .checked_mul(gap_count)
+            .and_then(|cost| {
+                transition_cost
+                    .checked_mul(self.wildcard_count)
+                    .and_then(|wildcard_cost| wildcard_cost.checked_add(cost))
+            })
+            .ok_or(MultialignError::CostOverflow)?;
+        for (emission_cost, count) in emission_costs.iter().zip(&self.character_counts) {
+            cost = emission_cost
+                .checked_add(transition_cost)
+                .and_then(|residue_cost| residue_cost.checked_mul(i32::from(*count)))
+                .and_then(|residue_cost| residue_cost.checked_add(cost))
+                .ok_or(MultialignError::CostOverflow)?;
+        }
+
+        cost_from_i32(cost)
+    }
+}
+
+impl AveragedProfileHmm {
+    fn read(reader: impl BufRead, path: &Path) -> Result<Self> {
+        let io_error = |source| MultialignError::Io {
+            path: path.to_owned(),
+            source,
+        };
+        let mut lines = reader.lines();
+        let mut symbols = None;
+
+        // Parse header
+        for line in lines.by_ref() {
+            let line = line.map_err(io_error)?;
+            let mut columns = line.split_whitespace();
+
+            match columns.next() {
+                Some("HMM") => {
+                    symbols = Some(
+                        columns
+                            .map(|symbol| {
+                                ensure_valid!(
+                                    symbol.len() == 1,
+                                    "Profile HMM symbol is not a single character: {symbol:?}"
+                                );
+                                Ok(symbol.as_bytes()[0].to_ascii_uppercase())
+                            })
+                            .collect::<Result<Vec<_>>>()?,
+                    );
+                    break;
+                }
+                Some("//") => return Err(invalid!("Profile HMM ended before the HMM line")),
+                _ => {}
+            }
+        }
+
+        let Some(symbols) = symbols else {
+            return Err(invalid!("Profile HMM contains no HMM line"));
+        };
+        ensure_valid!(!symbols.is_empty(), "Profile HMM has no symbols");
+
+        // Skip the transition header line
+        lines
+            .next()
+            .ok_or_else(|| invalid!("Profile HMM ended after the HMM line"))?
+            .map_err(io_error)?;
+
+        let mut match_emissions = vec![0.0; symbols.len()];
+        let mut insert_emissions = vec![0.0; symbols.len()];
+        let mut transitions = [0.0; 3];
+        let mut node_amount = 0usize;
+        // The lines of node zero may be preceded by an optional COMPO line.
+        let mut current_node = 0;
+
+        // Parse model
+        for line in lines {
+            let line = line.map_err(io_error)?;
+            let columns: Vec<_> = line.split_whitespace().collect();
+
+            match columns.first().copied() {
+                Some("//") => break,
+                Some("COMPO") => current_node = 0,
+                Some(first) if first.parse::<usize>().is_ok() => {
+                    let node: usize = first.parse().unwrap();
+                    ensure_valid!(
+                        columns.len() > symbols.len(),
+                        "Match emission line of node {node} has too few columns"
+                    );
+                    for (sum, score) in match_emissions.iter_mut().zip(&columns[1..]) {
+                        *sum += parse_probability(score)?;
+                    }
+                    node_amount += 1;
+                    current_node = node;
+                }
+                Some(_) => {
+                    if columns.len() == symbols.len() {
+                        if current_node > 0 {
+                            for (sum, score) in insert_emissions.iter_mut().zip(&columns) {
+                                *sum += parse_probability(score)?;
+                            }
+                        }
+                    } else if columns.len() == 7 {
+                        if current_node > 0 {
+                            for (sum, score) in transitions.iter_mut().zip(&columns) {
+                                *sum += parse_probability(score)?;
+                            }
+                        }
+                    } else {
+                        return Err(invalid!("Unexpected line in profile HMM: {line:?}"));
+                    }
+                }
+                None => {}
+            }
+        }
+
+        ensure_valid!(node_amount > 0, "Profile HMM contains no nodes");
+        let node_amount = node_amount as f64;
+
+        Ok(Self {
+            symbols,
+            match_emissions: match_emissions
+                .into_iter()
+                .map(|sum| sum / node_amount)
+                .collect(),
+            insert_emissions: insert_emissions
+                .into_iter()
+                .map(|sum| sum / node_amount)
+                .collect(),
+            match_to_match: transitions[0] / node_amount,
+            match_to_insert: transitions[1] / node_amount,
+            match_to_delete: transitions[2] / node_amount,
+        })
+    }
+}
+
+/// Parse a HMMER3 score, which is a negative natural logarithm of a probability, or `*` for probability zero.
+fn parse_probability(score: &str) -> Result<f64> {
+    if score == "*" {
+        Ok(0.0)
+    } else {
+        let score: f64 = score
+            .parse()
+            .map_err(|_| invalid!("Error parsing '{score}' as f64"))?;
+        Ok((-score).exp())
+    }
+}
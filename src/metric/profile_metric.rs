@@ -0,0 +1,158 @@
+use std::{marker::PhantomData, path::Path};
+
+use compact_genome::interface::alphabet::{Alphabet, AlphabetCharacter};
+use generic_a_star::cost::AStarCost;
+use log::info;
+
+use super::{cost_from_i32, MultialignMetric};
+use crate::{
+    error::{MultialignError, Result},
+    profile::Profile,
+};
+
+/// A metric that scores columns against the [`Profile`] of an existing alignment of a sequence family.
+///
+/// The reference sequence, which is the first sequence, determines the position of the profile that a column is aligned to,
+/// so it should be the reference of the profile alignment.
+/// It is not scored itself.
+///
+///  * If the reference has a residue in the column, then each residue and gap of the other sequences costs its frequency at the position of the reference residue.
+///  * If the reference has a gap in the column, then the column is an insertion relative to the profile.
+///    Each residue of the other sequences costs its frequency in the whole profile alignment, and gaps are free.
+///
+/// All costs are negative natural logarithms of frequencies, multiplied by a scale factor and rounded to integers.
+/// Wildcards are free.
+#[derive(Clone)]
+pub struct ProfileMetric<AlphabetType> {
+    /// For each position, the cost of each character, followed by the cost of a gap.
+    position_costs: Vec<Vec<i32>>,
+    /// The cost of each character in an insertion.
+    insertion_costs: Vec<i32>,
+    reference_offset: usize,
+    reference_has_gap: bool,
+    /// The count of each character, followed by the count of gaps, excluding the reference.
+    character_counts: Vec<i32>,
+    phantom_data: PhantomData<AlphabetType>,
+}
+
+impl<AlphabetType: Alphabet> ProfileMetric<AlphabetType> {
+    /// Create the metric from the given profile.
+    ///
+    /// Returns an [`MultialignError::InvalidMetricArguments`] if the scale is not positive and finite.
+    pub fn new(profile: &Profile<AlphabetType>, scale: f64) -> Result<Self> {
+        if !scale.is_finite() || scale <= 0.0 {
+            return Err(MultialignError::InvalidMetricArguments(format!(
+                "The scale of the profile must be positive, but is {scale}"
+            )));
+        }
+
+        let to_cost = |frequency: f64| -> Result<i32> {
+            let cost = (-frequency.ln() * scale).round();
+            if cost > f64::from(i32::MAX) {
+                return Err(MultialignError::InvalidProfile(format!(
+                    "Frequency {frequency} cannot be represented as cost with scale {scale}"
+                )));
+            }
+            Ok(cost as i32)
+        };
+
+        let characters: Vec<_> = AlphabetType::iter().collect();
+        let position_costs = (0..profile.len())
+            .map(|position| {
+                characters
+                    .iter()
+                    .map(Some)
+                    .chain([None])
+                    .map(|character| to_cost(profile.frequency(position, character)))
+                    .collect()
+            })
+            .collect::<Result<_>>()?;
+        let insertion_costs = characters
+            .iter()
+            .map(|character| to_cost(profile.background_frequency(character)))
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            position_costs,
+            insertion_costs,
+            reference_offset: 0,
+            reference_has_gap: false,
+            character_counts: vec![0; usize::from(AlphabetType::SIZE) + 1],
+            phantom_data: PhantomData,
+        })
+    }
+
+    /// Read the profile from an aligned fasta file, see [`Profile::from_file`].
+    ///
+    /// Returns an [`MultialignError::InvalidProfile`] if the length of the profile differs from the length of the reference sequence.
+    pub fn from_file(path: impl AsRef<Path>, scale: f64, reference_length: usize) -> Result<Self> {
+        let path = path.as_ref();
+        info!("Building profile of {path:?}");
+
+        let profile = Profile::from_file(path, &[])?;
+        if profile.len() != reference_length {
+            return Err(MultialignError::InvalidProfile(format!(
+                "Profile has {} positions, but the reference sequence has length {}",
+                profile.len(),
+                reference_length
+            )));
+        }
+
+        Self::new(&profile, scale)
+    }
+}
+
+impl<AlphabetType: Alphabet> MultialignMetric<AlphabetType> for ProfileMetric<AlphabetType> {
+    fn reset_character_counts(&mut self) {
+        self.character_counts.fill(0);
+        self.reference_has_gap = false;
+    }
+
+    fn set_reference_offset(&mut self, offset: usize) {
+        self.reference_offset = offset;
+    }
+
+    fn count_character(&mut self, character: &AlphabetType::CharacterType, sequence_index: usize) {
+        if sequence_index != 0 {
+            self.character_counts[usize::from(character.index())] += 1;
+        }
+    }
+
+    fn count_wildcard(&mut self, _character: &AlphabetType::CharacterType, _sequence_index: usize) {
+        // Wildcards are free.
+    }
+
+    fn count_gap(&mut self, sequence_index: usize) {
+        if sequence_index == 0 {
+            self.reference_has_gap = true;
+        } else {
+            self.character_counts[usize::from(AlphabetType::SIZE)] += 1;
+        }
+    }
+
+    fn uses_offsets(&self) -> bool {
+        // The costs depend on the reference offset, so they must not be cached independently of the residues.
+        true
+    }
+
+    fn compute_cost_increment<Cost: AStarCost>(&mut self) -> Result<Cost>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        let costs = if self.reference_has_gap {
+            &self.insertion_costs
+        } else {
+            &self.position_costs[self.reference_offset]
+        };
+
+        let mut cost = 0i32;
+        for (entry_cost, count) in costs.iter().zip(&self.character_counts) {
+            cost = entry_cost
+                .checked_mul(*count)
+                .and_then(|entry_cost| entry_cost.checked_add(cost))
+                .ok_or(MultialignError::CostOverflow)?;
+        }
+
+        cost_from_i32(cost)
+    }
+}
@@ -0,0 +1,167 @@
+use std::{collections::HashMap, fs::read_to_string, marker::PhantomData, path::Path, sync::Arc};
+
+use compact_genome::interface::alphabet::{Alphabet, AlphabetCharacter};
+use generic_a_star::cost::AStarCost;
+use log::info;
+use rhai::{CallFnOptions, Dynamic, Engine, Map, Scope, AST, INT};
+
+use super::{cost_from_i32, MultialignMetric};
+use crate::error::{MultialignError, Result};
+
+/// Return an [`MultialignError::InvalidScript`].
+macro_rules! invalid {
+    ($($message:tt)+) => {
+        MultialignError::InvalidScript(format!($($message)+))
+    };
+}
+
+/// A metric that computes the cost of each column with a [Rhai](https://rhai.rs) script, such that arbitrary column objectives can be aligned without recompiling.
+///
+/// The script defines a function `cost(column)`, which returns the cost of a column as a non-negative integer.
+/// The column is an object map with the integer properties
+///
+///  * `entries` (the amount of sequences), `residues` (including wildcards), `gaps` and `wildcards`,
+///  * `distinct` (the amount of different characters) and `max_count` (the count of the most frequent character),
+///  * and `counts`, which maps each character of the alphabet, like `"A"`, to its count.
+///
+/// For example, the following script is a star cost, where each character that differs from the most frequent one costs one, and each gap costs two:
+///
+/// ```text
+/// fn cost(column) {
+///     let mismatches = column.residues - column.wildcards - column.max_count;
+///     mismatches + 2 * column.gaps
+/// }
+/// ```
+///
+/// The cost of a column only depends on the counts of its characters, wildcards and gaps, so the cost of each such signature is computed once and cached.
+#[derive(Clone)]
+pub struct ScriptMetric<AlphabetType> {
+    engine: Arc<Engine>,
+    script: Arc<AST>,
+    /// The count of each character, followed by the count of wildcards and the count of gaps.
+    counts: Vec<u32>,
+    /// The cost of each signature of a column, i.e. its counts, whose cost was computed before.
+    cache: HashMap<Vec<u32>, i32>,
+    phantom_data: PhantomData<AlphabetType>,
+}
+
+impl<AlphabetType: Alphabet> ScriptMetric<AlphabetType> {
+    /// Compile the given script, see [`ScriptMetric`].
+    ///
+    /// Returns an [`MultialignError::InvalidScript`] if the script is malformed or does not define `cost(column)`.
+    pub fn new(script: &str) -> Result<Self> {
+        let engine = Engine::new();
+        let script = engine
+            .compile(script)
+            .map_err(|error| invalid!("Error parsing the script: {error}"))?;
+        if !script
+            .iter_functions()
+            .any(|function| function.name == "cost" && function.params.len() == 1)
+        {
+            return Err(invalid!(
+                "The script does not define a function cost(column)"
+            ));
+        }
+
+        Ok(Self {
+            engine: Arc::new(engine),
+            script: Arc::new(script),
+            counts: vec![0; usize::from(AlphabetType::SIZE) + 2],
+            cache: HashMap::new(),
+            phantom_data: PhantomData,
+        })
+    }
+
+    /// Read and compile the script from the given file, see [`Self::new`].
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        info!("Reading script {path:?}");
+        Self::new(&read_to_string(path).map_err(|source| MultialignError::Io {
+            path: path.to_owned(),
+            source,
+        })?)
+    }
+
+    /// The object map that is passed to the `cost` function of the script for the current counts.
+    fn column(&self) -> Map {
+        let size = usize::from(AlphabetType::SIZE);
+        let character_counts = &self.counts[..size];
+        let counts: Map = AlphabetType::iter()
+            .map(|character| {
+                let count = character_counts[usize::from(character.index())];
+                let character: char = character.into();
+                (
+                    character.to_string().into(),
+                    Dynamic::from(INT::from(count)),
+                )
+            })
+            .collect();
+
+        let properties = [
+            ("entries", self.counts.iter().sum()),
+            ("residues", self.counts[..=size].iter().sum()),
+            ("gaps", self.counts[size + 1]),
+            ("wildcards", self.counts[size]),
+            (
+                "distinct",
+                character_counts.iter().filter(|&&count| count > 0).count() as u32,
+            ),
+            (
+                "max_count",
+                character_counts.iter().copied().max().unwrap_or(0),
+            ),
+        ];
+        let mut column: Map = properties
+            .into_iter()
+            .map(|(name, value)| (name.into(), Dynamic::from(INT::from(value))))
+            .collect();
+        column.insert("counts".into(), Dynamic::from_map(counts));
+        column
+    }
+}
+
+impl<AlphabetType: Alphabet> MultialignMetric<AlphabetType> for ScriptMetric<AlphabetType> {
+    fn reset_character_counts(&mut self) {
+        self.counts.fill(0);
+    }
+
+    fn count_character(&mut self, character: &AlphabetType::CharacterType, _sequence_index: usize) {
+        self.counts[usize::from(character.index())] += 1;
+    }
+
+    fn count_wildcard(&mut self, _character: &AlphabetType::CharacterType, _sequence_index: usize) {
+        self.counts[usize::from(AlphabetType::SIZE)] += 1;
+    }
+
+    fn count_gap(&mut self, _sequence_index: usize) {
+        self.counts[usize::from(AlphabetType::SIZE) + 1] += 1;
+    }
+
+    fn compute_cost_increment<Cost: AStarCost>(&mut self) -> Result<Cost>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        if let Some(&cost) = self.cache.get(&self.counts) {
+            return cost_from_i32(cost);
+        }
+
+        // Only the function is called, the top-level statements of the script are not evaluated.
+        let cost: INT = self
+            .engine
+            .call_fn_with_options(
+                CallFnOptions::new().eval_ast(false),
+                &mut Scope::new(),
+                &self.script,
+                "cost",
+                (Dynamic::from_map(self.column()),),
+            )
+            .map_err(|error| invalid!("Error evaluating the script: {error}"))?;
+        if cost < 0 {
+            return Err(invalid!("The script returned the negative cost {cost}"));
+        }
+        let cost = i32::try_from(cost).map_err(|_| MultialignError::CostOverflow)?;
+
+        self.cache.insert(self.counts.clone(), cost);
+        cost_from_i32(cost)
+    }
+}
@@ -0,0 +1,244 @@
+use std::{cmp::Ordering, marker::PhantomData};
+
+use compact_genome::interface::alphabet::{Alphabet, AlphabetCharacter};
+use generic_a_star::cost::{AStarCost, I64Cost};
+
+use super::{cost_from_i32, MultialignMetric};
+use crate::error::{MultialignError, Result};
+
+/// A sum-of-pairs metric where the cost of each pair of sequences is multiplied by the product of their weights, like in ClustalW.
+///
+/// The wrapped metric scores a single pair, i.e. it must be constructed for two sequences.
+/// Its costs for each pair of characters, wildcards or gaps are cached, so it must not depend on the reference offset.
+///
+/// The weights are scaled to an average of one, such that the costs are in the same range as without weights.
+/// The weighted cost of each column is rounded to the nearest integer.
+#[derive(Clone)]
+pub struct SequenceWeightedMetric<AlphabetType, Metric> {
+    pair_costs: PairCosts<AlphabetType, Metric>,
+    weights: Vec<f64>,
+    /// The index of a sequence in the current column that has no weight, if any.
+    unweighted_sequence: Option<usize>,
+    /// The sum of the weights and of the squared weights of the sequences of each entry in the current column.
+    entry_weights: Vec<(f64, f64)>,
+    /// The entries that were counted in the current column.
+    counted_entries: Vec<usize>,
+}
+
+impl<AlphabetType: Alphabet, Metric: MultialignMetric<AlphabetType>>
+    SequenceWeightedMetric<AlphabetType, Metric>
+{
+    /// Wrap the given pairwise metric with one weight per sequence in input order.
+    ///
+    /// Returns an [`MultialignError::InvalidMetricArguments`] if a weight is negative or not finite, if all weights are zero,
+    /// or if the metric depends on the offsets of the residues.
+    pub fn new(metric: Metric, weights: &[f64]) -> Result<Self> {
+        if let Some(weight) = weights
+            .iter()
+            .find(|weight| !weight.is_finite() || **weight < 0.0)
+        {
+            return Err(MultialignError::InvalidMetricArguments(format!(
+                "Sequence weights must be non-negative, but found {weight}"
+            )));
+        }
+        let sum: f64 = weights.iter().sum();
+        if sum == 0.0 {
+            return Err(MultialignError::InvalidMetricArguments(
+                "At least one sequence weight must be positive".to_string(),
+            ));
+        }
+
+        let pair_costs = PairCosts::new(metric)?;
+        Ok(Self {
+            weights: weights
+                .iter()
+                .map(|weight| weight * weights.len() as f64 / sum)
+                .collect(),
+            unweighted_sequence: None,
+            entry_weights: vec![(0.0, 0.0); pair_costs.entry_amount()],
+            counted_entries: Vec::new(),
+            pair_costs,
+        })
+    }
+
+    fn count_entry(&mut self, entry: usize, sequence_index: usize) {
+        let Some(weight) = self.weights.get(sequence_index).copied() else {
+            // Reported as error when computing the cost.
+            self.unweighted_sequence = Some(sequence_index);
+            return;
+        };
+
+        if !self.counted_entries.contains(&entry) {
+            self.counted_entries.push(entry);
+        }
+        let (weight_sum, squared_weight_sum) = &mut self.entry_weights[entry];
+        *weight_sum += weight;
+        *squared_weight_sum += weight * weight;
+    }
+}
+
+/// The costs of pairs of entries of a column under a metric for two sequences, which are computed on demand and cached.
+///
+/// The entries are the characters, followed by the gap, followed by the characters as wildcards.
+#[derive(Clone)]
+pub(super) struct PairCosts<AlphabetType, Metric> {
+    metric: Metric,
+    entry_amount: usize,
+    /// The cost of each pair of entries, if already computed.
+    costs: Vec<Option<i64>>,
+    phantom_data: PhantomData<AlphabetType>,
+}
+
+impl<AlphabetType: Alphabet, Metric: MultialignMetric<AlphabetType>>
+    PairCosts<AlphabetType, Metric>
+{
+    /// Returns an [`MultialignError::InvalidMetricArguments`] if the metric depends on the offsets of the residues,
+    /// since the costs of the entries are cached independently of the residues.
+    pub(super) fn new(metric: Metric) -> Result<Self> {
+        if metric.uses_offsets() {
+            return Err(MultialignError::InvalidMetricArguments(
+                "Weighted metrics cannot wrap a metric that depends on the offsets of the residues"
+                    .to_string(),
+            ));
+        }
+
+        let entry_amount = 2 * usize::from(AlphabetType::SIZE) + 1;
+        Ok(Self {
+            metric,
+            entry_amount,
+            costs: vec![None; entry_amount * entry_amount],
+            phantom_data: PhantomData,
+        })
+    }
+
+    pub(super) fn entry_amount(&self) -> usize {
+        self.entry_amount
+    }
+
+    pub(super) fn character_entry(&self, character: &AlphabetType::CharacterType) -> usize {
+        usize::from(character.index())
+    }
+
+    pub(super) fn gap_entry(&self) -> usize {
+        usize::from(AlphabetType::SIZE)
+    }
+
+    pub(super) fn wildcard_entry(&self, character: &AlphabetType::CharacterType) -> usize {
+        usize::from(AlphabetType::SIZE) + 1 + usize::from(character.index())
+    }
+
+    pub(super) fn restrict_alphabet(
+        &mut self,
+        characters: &[AlphabetType::CharacterType],
+    ) -> Result<()> {
+        self.metric.restrict_alphabet(characters)
+    }
+
+    /// The cost of a pair of entries under the wrapped metric.
+    pub(super) fn cost(&mut self, first: usize, second: usize) -> Result<i64> {
+        let index = first * self.entry_amount() + second;
+        if let Some(cost) = self.costs[index] {
+            return Ok(cost);
+        }
+
+        self.metric.reset_character_counts();
+        for (sequence_index, entry) in [first, second].into_iter().enumerate() {
+            let size = usize::from(AlphabetType::SIZE);
+            let character = |index: usize| {
+                AlphabetType::CharacterType::from_index(index.try_into().unwrap()).unwrap()
+            };
+
+            match entry.cmp(&size) {
+                Ordering::Less => self
+                    .metric
+                    .count_character(&character(entry), sequence_index),
+                Ordering::Equal => self.metric.count_gap(sequence_index),
+                Ordering::Greater => self
+                    .metric
+                    .count_wildcard(&character(entry - size - 1), sequence_index),
+            }
+        }
+        let cost = self
+            .metric
+            .compute_cost_increment::<I64Cost>()?
+            .as_primitive();
+
+        self.costs[index] = Some(cost);
+        Ok(cost)
+    }
+}
+
+/// Round a weighted column cost to the nearest integer.
+pub(super) fn round_weighted_cost<Cost: AStarCost>(cost: f64) -> Result<Cost>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    let cost = cost.round();
+    if cost.abs() > f64::from(i32::MAX) {
+        return Err(MultialignError::CostOverflow);
+    }
+    cost_from_i32(cost as i32)
+}
+
+impl<AlphabetType: Alphabet, Metric: MultialignMetric<AlphabetType>> MultialignMetric<AlphabetType>
+    for SequenceWeightedMetric<AlphabetType, Metric>
+{
+    fn reset_character_counts(&mut self) {
+        self.unweighted_sequence = None;
+        for entry in self.counted_entries.drain(..) {
+            self.entry_weights[entry] = (0.0, 0.0);
+        }
+    }
+
+    fn restrict_alphabet(&mut self, characters: &[AlphabetType::CharacterType]) -> Result<()> {
+        self.pair_costs.restrict_alphabet(characters)
+    }
+
+    fn count_character(&mut self, character: &AlphabetType::CharacterType, sequence_index: usize) {
+        self.count_entry(self.pair_costs.character_entry(character), sequence_index);
+    }
+
+    fn count_wildcard(&mut self, character: &AlphabetType::CharacterType, sequence_index: usize) {
+        self.count_entry(self.pair_costs.wildcard_entry(character), sequence_index);
+    }
+
+    fn count_gap(&mut self, sequence_index: usize) {
+        self.count_entry(self.pair_costs.gap_entry(), sequence_index);
+    }
+
+    fn count_terminal_gap(&mut self, _sequence_index: usize) {
+        // Terminal gaps are free, so they are not counted.
+    }
+
+    fn compute_cost_increment<Cost: AStarCost>(&mut self) -> Result<Cost>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        if let Some(sequence_index) = self.unweighted_sequence {
+            return Err(MultialignError::InvalidMetricArguments(format!(
+                "Got {} sequence weights, but sequence {sequence_index} is aligned",
+                self.weights.len(),
+            )));
+        }
+
+        let mut cost = 0.0;
+        for index in 0..self.counted_entries.len() {
+            let first = self.counted_entries[index];
+            let (first_weight, first_squared_weight) = self.entry_weights[first];
+
+            // Pairs of distinct sequences with the same entry.
+            let same_entry_weight = (first_weight * first_weight - first_squared_weight) / 2.0;
+            if same_entry_weight > 0.0 {
+                cost += same_entry_weight * self.pair_costs.cost(first, first)? as f64;
+            }
+
+            for second_index in index + 1..self.counted_entries.len() {
+                let second = self.counted_entries[second_index];
+                let second_weight = self.entry_weights[second].0;
+                cost += first_weight * second_weight * self.pair_costs.cost(first, second)? as f64;
+            }
+        }
+
+        round_weighted_cost(cost)
+    }
+}
@@ -0,0 +1,159 @@
+use std::marker::PhantomData;
+
+use compact_genome::interface::alphabet::Alphabet;
+use generic_a_star::cost::AStarCost;
+
+use super::{cost_from_i32, sequence_weighted_metric::round_weighted_cost, MultialignMetric};
+use crate::error::{MultialignError, Result};
+
+/// A metric that adjusts the cost of columns containing soft-masked residues, i.e. residues written in lower case in the input.
+///
+/// The cost of such a column under the wrapped metric is multiplied by a factor and rounded to the nearest integer, and then a fixed penalty is added.
+/// Since soft masking usually marks repeats and low-complexity regions, a factor below one makes the alignment of these regions less important.
+/// Without masks, the costs are not changed.
+#[derive(Clone)]
+pub struct SoftMaskMetric<AlphabetType, Metric> {
+    metric: Metric,
+    /// For each sequence in input order, true for each of its residues that is soft-masked.
+    masks: Vec<Vec<bool>>,
+    cost_factor: f64,
+    penalty: i32,
+    /// The amount of residues of each sequence before the current column.
+    offsets: Vec<usize>,
+    /// True if the current column contains a soft-masked residue.
+    has_masked_residue: bool,
+    phantom_data: PhantomData<AlphabetType>,
+}
+
+impl<AlphabetType: Alphabet, Metric: MultialignMetric<AlphabetType>>
+    SoftMaskMetric<AlphabetType, Metric>
+{
+    /// Wrap the given metric without any soft-masked residues.
+    pub fn new(metric: Metric) -> Self {
+        Self {
+            metric,
+            masks: Vec::new(),
+            cost_factor: 1.0,
+            penalty: 0,
+            offsets: Vec::new(),
+            has_masked_residue: false,
+            phantom_data: PhantomData,
+        }
+    }
+
+    /// Wrap the given metric with the soft masks of the aligned sequences in input order, see [`crate::soft_mask::soft_mask`].
+    ///
+    /// Returns an [`MultialignError::InvalidMetricArguments`] if the cost factor or the penalty is negative, or if the cost factor is not finite.
+    pub fn with_masks(
+        metric: Metric,
+        masks: Vec<Vec<bool>>,
+        cost_factor: f64,
+        penalty: i32,
+    ) -> Result<Self> {
+        if !cost_factor.is_finite() || cost_factor < 0.0 {
+            return Err(MultialignError::InvalidMetricArguments(format!(
+                "The cost factor of soft-masked columns must be non-negative, but is {cost_factor}"
+            )));
+        }
+        if penalty < 0 {
+            return Err(MultialignError::InvalidMetricArguments(format!(
+                "The penalty of soft-masked columns must be non-negative, but is {penalty}"
+            )));
+        }
+
+        Ok(Self {
+            metric,
+            offsets: vec![0; masks.len()],
+            masks,
+            cost_factor,
+            penalty,
+            has_masked_residue: false,
+            phantom_data: PhantomData,
+        })
+    }
+
+    fn count_residue(&mut self, sequence_index: usize) {
+        if let (Some(mask), Some(offset)) = (
+            self.masks.get(sequence_index),
+            self.offsets.get(sequence_index),
+        ) {
+            self.has_masked_residue |= mask.get(*offset).copied().unwrap_or(false);
+        }
+    }
+}
+
+impl<AlphabetType: Alphabet, Metric: MultialignMetric<AlphabetType>> MultialignMetric<AlphabetType>
+    for SoftMaskMetric<AlphabetType, Metric>
+{
+    fn reset_character_counts(&mut self) {
+        self.metric.reset_character_counts();
+        self.has_masked_residue = false;
+    }
+
+    fn set_reference_offset(&mut self, offset: usize) {
+        self.metric.set_reference_offset(offset);
+    }
+
+    fn restrict_alphabet(&mut self, characters: &[AlphabetType::CharacterType]) -> Result<()> {
+        self.metric.restrict_alphabet(characters)
+    }
+
+    fn count_character(&mut self, character: &AlphabetType::CharacterType, sequence_index: usize) {
+        self.metric.count_character(character, sequence_index);
+        self.count_residue(sequence_index);
+    }
+
+    fn count_wildcard(&mut self, character: &AlphabetType::CharacterType, sequence_index: usize) {
+        self.metric.count_wildcard(character, sequence_index);
+        self.count_residue(sequence_index);
+    }
+
+    fn count_gap(&mut self, sequence_index: usize) {
+        self.metric.count_gap(sequence_index);
+    }
+
+    fn count_gap_open(&mut self, sequence_index: usize) {
+        self.metric.count_gap_open(sequence_index);
+    }
+
+    fn has_affine_gaps(&self) -> bool {
+        self.metric.has_affine_gaps()
+    }
+
+    fn count_terminal_gap(&mut self, sequence_index: usize) {
+        self.metric.count_terminal_gap(sequence_index);
+    }
+
+    fn has_free_end_gaps(&self) -> bool {
+        self.metric.has_free_end_gaps()
+    }
+
+    fn uses_offsets(&self) -> bool {
+        !self.masks.is_empty() || self.metric.uses_offsets()
+    }
+
+    fn set_offset(&mut self, sequence_index: usize, offset: usize) {
+        self.metric.set_offset(sequence_index, offset);
+        if let Some(sequence_offset) = self.offsets.get_mut(sequence_index) {
+            *sequence_offset = offset;
+        }
+    }
+
+    fn is_sum_of_pairs(&self) -> bool {
+        self.masks.is_empty() && self.metric.is_sum_of_pairs()
+    }
+
+    fn compute_cost_increment<Cost: AStarCost>(&mut self) -> Result<Cost>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        let cost = self.metric.compute_cost_increment::<Cost>()?;
+        if !self.has_masked_residue {
+            return Ok(cost);
+        }
+
+        round_weighted_cost::<Cost>(cost.as_f64() * self.cost_factor)?
+            .checked_add(&cost_from_i32(self.penalty)?)
+            .ok_or(MultialignError::CostOverflow)
+    }
+}
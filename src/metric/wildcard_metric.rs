@@ -0,0 +1,103 @@
+use std::marker::PhantomData;
+
+use compact_genome::interface::alphabet::{Alphabet, AlphabetCharacter};
+use generic_a_star::cost::AStarCost;
+
+use super::MultialignMetric;
+use crate::error::Result;
+
+/// A metric that counts some characters of another metric as wildcards.
+///
+/// Wildcards match every residue, and how they are scored against gaps is up to the wrapped metric.
+/// Without wildcards, all characters are counted as usual.
+#[derive(Clone)]
+pub struct WildcardMetric<AlphabetType, Metric> {
+    metric: Metric,
+    is_wildcard: Vec<bool>,
+    phantom_data: PhantomData<AlphabetType>,
+}
+
+impl<AlphabetType: Alphabet, Metric: MultialignMetric<AlphabetType>>
+    WildcardMetric<AlphabetType, Metric>
+{
+    /// Wrap the given metric such that the given characters are counted as wildcards.
+    pub fn new(metric: Metric, wildcards: &[AlphabetType::CharacterType]) -> Self {
+        let mut is_wildcard = vec![false; AlphabetType::SIZE.into()];
+        for wildcard in wildcards {
+            is_wildcard[usize::from(wildcard.index())] = true;
+        }
+
+        Self {
+            metric,
+            is_wildcard,
+            phantom_data: PhantomData,
+        }
+    }
+}
+
+impl<AlphabetType: Alphabet, Metric: MultialignMetric<AlphabetType>> MultialignMetric<AlphabetType>
+    for WildcardMetric<AlphabetType, Metric>
+{
+    fn reset_character_counts(&mut self) {
+        self.metric.reset_character_counts();
+    }
+
+    fn set_reference_offset(&mut self, offset: usize) {
+        self.metric.set_reference_offset(offset);
+    }
+
+    fn restrict_alphabet(&mut self, characters: &[AlphabetType::CharacterType]) -> Result<()> {
+        self.metric.restrict_alphabet(characters)
+    }
+
+    fn count_character(&mut self, character: &AlphabetType::CharacterType, sequence_index: usize) {
+        if self.is_wildcard[usize::from(character.index())] {
+            self.metric.count_wildcard(character, sequence_index);
+        } else {
+            self.metric.count_character(character, sequence_index);
+        }
+    }
+
+    fn count_wildcard(&mut self, character: &AlphabetType::CharacterType, sequence_index: usize) {
+        self.metric.count_wildcard(character, sequence_index);
+    }
+
+    fn count_gap(&mut self, sequence_index: usize) {
+        self.metric.count_gap(sequence_index);
+    }
+
+    fn count_gap_open(&mut self, sequence_index: usize) {
+        self.metric.count_gap_open(sequence_index);
+    }
+
+    fn has_affine_gaps(&self) -> bool {
+        self.metric.has_affine_gaps()
+    }
+
+    fn count_terminal_gap(&mut self, sequence_index: usize) {
+        self.metric.count_terminal_gap(sequence_index);
+    }
+
+    fn has_free_end_gaps(&self) -> bool {
+        self.metric.has_free_end_gaps()
+    }
+
+    fn uses_offsets(&self) -> bool {
+        self.metric.uses_offsets()
+    }
+
+    fn set_offset(&mut self, sequence_index: usize, offset: usize) {
+        self.metric.set_offset(sequence_index, offset);
+    }
+
+    fn is_sum_of_pairs(&self) -> bool {
+        self.metric.is_sum_of_pairs()
+    }
+
+    fn compute_cost_increment<Cost: AStarCost>(&mut self) -> Result<Cost>
+    where
+        Cost::CostType: TryFrom<i32>,
+    {
+        self.metric.compute_cost_increment()
+    }
+}
@@ -0,0 +1,293 @@
+use std::{
+    fmt::Write as _,
+    fs::{read_to_string, write},
+    path::Path,
+};
+
+use log::info;
+
+use crate::{
+    error::{MultialignError, Result},
+    tree::GuideTree,
+};
+
+/// Read a phylogenetic tree of the given sequences from a file in Newick format, see [`parse_newick`].
+pub fn read_newick_file(path: impl AsRef<Path>, sequence_ids: &[&str]) -> Result<GuideTree> {
+    let path = path.as_ref();
+    let newick = read_to_string(path).map_err(|source| MultialignError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    parse_newick(&newick, sequence_ids)
+}
+
+/// Parse a phylogenetic tree of the given sequences in Newick format.
+///
+/// Each leaf must be labelled with the id of exactly one sequence, and each sequence must be a leaf.
+/// Labels can be quoted with `'`, and comments in square brackets are ignored, as are the labels of inner nodes.
+/// Missing branch lengths are taken to be one, such that distances in a tree without branch lengths count the branches.
+///
+/// Inner nodes with more than two children are resolved into binary nodes connected by branches of length zero,
+/// and inner nodes with a single child are merged with their child.
+pub fn parse_newick(newick: &str, sequence_ids: &[&str]) -> Result<GuideTree> {
+    let mut parser = NewickParser {
+        input: newick,
+        position: 0,
+    };
+    let root = parser.parse_node()?;
+    parser.skip_whitespace_and_comments()?;
+    if !parser.consume(';') {
+        return Err(parser.error("expected ';' after the root"));
+    }
+    parser.skip_whitespace_and_comments()?;
+    if parser.position < parser.input.len() {
+        return Err(parser.error("expected end of file after ';'"));
+    }
+
+    let mut leaves = vec![false; sequence_ids.len()];
+    let (tree, _) = root.into_guide_tree(sequence_ids, &mut leaves)?;
+    if let Some(sequence_index) = leaves.iter().position(|is_leaf| !is_leaf) {
+        return Err(MultialignError::InvalidTree(format!(
+            "Sequence {} is not a leaf",
+            sequence_ids[sequence_index]
+        )));
+    }
+
+    Ok(tree)
+}
+
+/// Write the given tree of the given sequences to a file in Newick format, see [`format_newick`].
+pub fn write_newick_file(
+    path: impl AsRef<Path>,
+    tree: &GuideTree,
+    sequence_ids: &[&str],
+) -> Result<()> {
+    let path = path.as_ref();
+    info!("Writing guide tree to {path:?}");
+    write(path, format_newick(tree, sequence_ids)).map_err(|source| MultialignError::Write {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Format the given tree of the given sequences in Newick format, followed by a newline.
+///
+/// Each leaf is labelled with the id of its sequence, which is quoted with `'` if it contains whitespace or characters with a meaning in Newick.
+/// Each node except the root has the length of the branch leading to it, such that the result can be read again with [`parse_newick`].
+///
+/// **Panics** if a leaf holds a sequence index of at least the amount of sequence ids.
+pub fn format_newick(tree: &GuideTree, sequence_ids: &[&str]) -> String {
+    let mut newick = String::new();
+    format_node(tree, sequence_ids, &mut newick);
+    newick.push_str(";\n");
+    newick
+}
+
+fn format_node(tree: &GuideTree, sequence_ids: &[&str], newick: &mut String) {
+    match tree {
+        GuideTree::Leaf { sequence_index } => {
+            let id = sequence_ids[*sequence_index];
+            if id.is_empty()
+                || id
+                    .chars()
+                    .any(|character| character.is_whitespace() || "()[]':;,".contains(character))
+            {
+                newick.push('\'');
+                newick.push_str(&id.replace('\'', "''"));
+                newick.push('\'');
+            } else {
+                newick.push_str(id);
+            }
+        }
+        GuideTree::Inner { children } => {
+            newick.push('(');
+            for (index, (child, length)) in children.iter().enumerate() {
+                if index > 0 {
+                    newick.push(',');
+                }
+                format_node(child, sequence_ids, newick);
+                write!(newick, ":{length}").unwrap();
+            }
+            newick.push(')');
+        }
+    }
+}
+
+/// A node of a Newick tree as written in the file.
+struct NewickNode {
+    label: Option<String>,
+    length: Option<f64>,
+    children: Vec<NewickNode>,
+}
+
+impl NewickNode {
+    /// Convert this node into a guide tree, and return it together with the length of the branch leading to it.
+    ///
+    /// `leaves` marks the sequences that were already found as leaves.
+    fn into_guide_tree(
+        self,
+        sequence_ids: &[&str],
+        leaves: &mut [bool],
+    ) -> Result<(GuideTree, f64)> {
+        let length = self.length.unwrap_or(1.0);
+
+        if self.children.is_empty() {
+            let label = self.label.ok_or_else(|| {
+                MultialignError::InvalidTree("Found a leaf without label".to_string())
+            })?;
+            let sequence_index =
+                sequence_ids
+                    .iter()
+                    .position(|id| *id == label)
+                    .ok_or_else(|| {
+                        MultialignError::InvalidTree(format!("Found no sequence with id {label}"))
+                    })?;
+            if leaves[sequence_index] {
+                return Err(MultialignError::InvalidTree(format!(
+                    "Sequence {label} is the label of multiple leaves"
+                )));
+            }
+            leaves[sequence_index] = true;
+
+            return Ok((GuideTree::Leaf { sequence_index }, length));
+        }
+
+        let mut children = self.children.into_iter();
+        let (mut tree, mut tree_length) = children
+            .next()
+            .unwrap()
+            .into_guide_tree(sequence_ids, leaves)?;
+        for child in children {
+            let child = child.into_guide_tree(sequence_ids, leaves)?;
+            tree = GuideTree::Inner {
+                children: Box::new([(tree, tree_length), child]),
+            };
+            tree_length = 0.0;
+        }
+
+        Ok((tree, length + tree_length))
+    }
+}
+
+struct NewickParser<'input> {
+    input: &'input str,
+    /// The byte offset of the next character.
+    position: usize,
+}
+
+impl NewickParser<'_> {
+    /// Parse a node with its descendants, its label and the length of the branch leading to it.
+    fn parse_node(&mut self) -> Result<NewickNode> {
+        self.skip_whitespace_and_comments()?;
+
+        let mut children = Vec::new();
+        if self.consume('(') {
+            loop {
+                children.push(self.parse_node()?);
+                self.skip_whitespace_and_comments()?;
+                if self.consume(')') {
+                    break;
+                }
+                if !self.consume(',') {
+                    return Err(self.error("expected ',' or ')'"));
+                }
+            }
+        }
+
+        self.skip_whitespace_and_comments()?;
+        let label = self.parse_label()?;
+
+        self.skip_whitespace_and_comments()?;
+        let length = if self.consume(':') {
+            self.skip_whitespace_and_comments()?;
+            let start = self.position;
+            while self.peek().is_some_and(|character| {
+                character.is_ascii_alphanumeric() || matches!(character, '.' | '-' | '+')
+            }) {
+                self.position += 1;
+            }
+            let length = &self.input[start..self.position];
+            let length: f64 = length
+                .parse()
+                .map_err(|_| self.error(&format!("invalid branch length '{length}'")))?;
+            if !length.is_finite() || length < 0.0 {
+                return Err(self.error(&format!(
+                    "branch lengths must be non-negative, but found {length}"
+                )));
+            }
+            Some(length)
+        } else {
+            None
+        };
+
+        Ok(NewickNode {
+            label,
+            length,
+            children,
+        })
+    }
+
+    fn parse_label(&mut self) -> Result<Option<String>> {
+        if self.consume('\'') {
+            let mut label = String::new();
+            loop {
+                let Some(character) = self.peek() else {
+                    return Err(self.error("unterminated quoted label"));
+                };
+                self.position += character.len_utf8();
+
+                if character == '\'' {
+                    // A doubled quote stands for a single quote.
+                    if !self.consume('\'') {
+                        return Ok(Some(label));
+                    }
+                }
+                label.push(character);
+            }
+        }
+
+        let start = self.position;
+        while let Some(character) = self.peek() {
+            if character.is_whitespace() || "()[]':;,".contains(character) {
+                break;
+            }
+            self.position += character.len_utf8();
+        }
+
+        Ok((self.position > start).then(|| self.input[start..self.position].to_string()))
+    }
+
+    fn skip_whitespace_and_comments(&mut self) -> Result<()> {
+        loop {
+            match self.peek() {
+                Some(character) if character.is_whitespace() => {
+                    self.position += character.len_utf8()
+                }
+                Some('[') => {
+                    let end = self.input[self.position..]
+                        .find(']')
+                        .ok_or_else(|| self.error("unterminated comment"))?;
+                    self.position += end + 1;
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    /// Skip the given character if it is next.
+    fn consume(&mut self, character: char) -> bool {
+        let is_next = self.peek() == Some(character);
+        if is_next {
+            self.position += character.len_utf8();
+        }
+        is_next
+    }
+
+    fn error(&self, message: &str) -> MultialignError {
+        MultialignError::InvalidTree(format!("{message} at byte {}", self.position))
+    }
+}
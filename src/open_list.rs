@@ -0,0 +1,417 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap},
+    fs::{self, File},
+    io::{Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+};
+
+use generic_a_star::cost::AStarCost;
+use log::{debug, warn};
+
+use super::{
+    error::{MultialignError, Result},
+    spill::{cost_from_i64, create_spill_file, SpillOptions},
+    NodeIdentifier, OpenListKind,
+};
+
+/// The open list of the searches implemented in this crate.
+///
+/// Items are popped in order of non-decreasing cost.
+/// The order of items with equal costs is unspecified and may differ between the kinds of open lists.
+pub enum OpenList<Cost, Item> {
+    Heap(BinaryHeap<Reverse<(Cost, Item)>>),
+    Bucket(BucketQueue<Cost, Item>),
+}
+
+/// A priority queue with one bucket per integer cost.
+///
+/// Pushing and popping take amortised constant time, as long as the costs of the pushed items are never much smaller than the cost of the last popped item.
+/// This is the case in a label-setting search with non-negative costs, where the costs of pushed items are never smaller than the cost of the last popped item.
+/// Negative costs and costs of at least [`MAX_BUCKETS`] are not supported, and the [`OpenList`] falls back to a heap when such a cost is pushed.
+pub struct BucketQueue<Cost, Item> {
+    buckets: Vec<Vec<(Cost, Item)>>,
+    /// All buckets before this index are empty.
+    minimum: usize,
+    len: usize,
+}
+
+impl<Cost: AStarCost, Item: Ord> OpenList<Cost, Item> {
+    pub fn new(kind: OpenListKind) -> Self {
+        match kind {
+            OpenListKind::Heap => Self::Heap(Default::default()),
+            OpenListKind::Bucket => Self::Bucket(BucketQueue {
+                buckets: Default::default(),
+                minimum: 0,
+                len: 0,
+            }),
+        }
+    }
+
+    pub fn push(&mut self, cost: Cost, item: Item) {
+        match self {
+            Self::Heap(heap) => heap.push(Reverse((cost, item))),
+            Self::Bucket(bucket_queue) => {
+                if let Some(index) = BucketQueue::<Cost, Item>::index(cost) {
+                    bucket_queue.push(index, cost, item);
+                } else {
+                    warn!(
+                        "The bucket queue does not support the cost {cost}, continuing with a heap"
+                    );
+                    let mut heap: BinaryHeap<_> = bucket_queue
+                        .buckets
+                        .drain(..)
+                        .flatten()
+                        .map(Reverse)
+                        .collect();
+                    heap.push(Reverse((cost, item)));
+                    *self = Self::Heap(heap);
+                }
+            }
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<(Cost, Item)> {
+        match self {
+            Self::Heap(heap) => heap.pop().map(|Reverse(entry)| entry),
+            Self::Bucket(bucket_queue) => bucket_queue.pop(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        match self {
+            Self::Heap(heap) => heap.clear(),
+            Self::Bucket(bucket_queue) => {
+                bucket_queue.buckets.iter_mut().for_each(Vec::clear);
+                bucket_queue.minimum = 0;
+                bucket_queue.len = 0;
+            }
+        }
+    }
+
+    /// Remove all items and return them in an unspecified order.
+    pub fn drain(&mut self) -> Vec<(Cost, Item)> {
+        match self {
+            Self::Heap(heap) => heap.drain().map(|Reverse(entry)| entry).collect(),
+            Self::Bucket(bucket_queue) => {
+                bucket_queue.minimum = 0;
+                bucket_queue.len = 0;
+                bucket_queue
+                    .buckets
+                    .iter_mut()
+                    .flat_map(|bucket| bucket.drain(..))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// The maximum amount of buckets of a [`BucketQueue`], which bounds the memory of its empty buckets.
+pub const MAX_BUCKETS: usize = 1 << 20;
+
+impl<Cost: AStarCost, Item> BucketQueue<Cost, Item> {
+    /// Returns the index of the bucket of the given cost, or `None` if the cost is negative or too large.
+    fn index(cost: Cost) -> Option<usize> {
+        if cost < Cost::zero() {
+            return None;
+        }
+        usize::try_from(cost.as_u64())
+            .ok()
+            .filter(|&index| index < MAX_BUCKETS)
+    }
+
+    fn push(&mut self, index: usize, cost: Cost, item: Item) {
+        if self.buckets.len() <= index {
+            self.buckets.resize_with(index + 1, Vec::new);
+        }
+        self.buckets[index].push((cost, item));
+        self.minimum = self.minimum.min(index);
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<(Cost, Item)> {
+        if self.len == 0 {
+            return None;
+        }
+
+        while self.buckets[self.minimum].is_empty() {
+            self.minimum += 1;
+        }
+        self.len -= 1;
+        self.buckets[self.minimum].pop()
+    }
+}
+
+/// The open list of a [`RederivingSearch`](crate::rederiving_search::RederivingSearch), which stores the cost of each open node by the priority of the node.
+pub(crate) enum NodeOpenList<Identifier, Cost> {
+    Memory(OpenList<Cost, (Cost, Identifier)>),
+    Spilling(SpillingOpenList<Identifier, Cost>),
+}
+
+impl<Identifier: NodeIdentifier, Cost: AStarCost> NodeOpenList<Identifier, Cost> {
+    /// Create an open list for nodes of the given amount of sequences,
+    /// which is written to disk if spill options are given, and kept in memory otherwise.
+    pub fn new(kind: OpenListKind, spill: Option<&SpillOptions>, sequence_amount: usize) -> Self {
+        match spill {
+            Some(spill) => Self::Spilling(SpillingOpenList {
+                options: spill.clone(),
+                sequence_amount,
+                memory: OpenList::new(kind),
+                len: 0,
+                runs: BTreeMap::new(),
+                file: None,
+            }),
+            None => Self::Memory(OpenList::new(kind)),
+        }
+    }
+
+    pub fn push(&mut self, priority: Cost, cost: Cost, identifier: Identifier) -> Result<()> {
+        match self {
+            Self::Memory(open_list) => {
+                open_list.push(priority, (cost, identifier));
+                Ok(())
+            }
+            Self::Spilling(open_list) => open_list.push(priority, cost, identifier),
+        }
+    }
+
+    /// Remove a node of the lowest priority and return its priority, cost and identifier.
+    pub fn pop(&mut self) -> Result<Option<(Cost, (Cost, Identifier))>> {
+        match self {
+            Self::Memory(open_list) => Ok(open_list.pop()),
+            Self::Spilling(open_list) => open_list.pop(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        match self {
+            Self::Memory(open_list) => open_list.clear(),
+            Self::Spilling(open_list) => {
+                open_list.memory.clear();
+                open_list.len = 0;
+                open_list.runs.clear();
+            }
+        }
+    }
+}
+
+/// An open list that keeps the nodes that are searched next in memory, and writes the others into a file.
+///
+/// When there are more nodes in memory than cached, the half of them with the highest priorities is appended to the file, in runs of equal priority.
+/// The runs of the lowest priority are read back into memory once all nodes in memory have a higher priority.
+/// The file is only appended to, so it grows with each node written to it until the search ends.
+pub(crate) struct SpillingOpenList<Identifier, Cost> {
+    options: SpillOptions,
+    sequence_amount: usize,
+    memory: OpenList<Cost, (Cost, Identifier)>,
+    /// The amount of nodes in memory.
+    len: usize,
+    /// The position in the file and the amount of nodes of each run, by the priority of its nodes.
+    runs: BTreeMap<Cost, Vec<(u64, usize)>>,
+    file: Option<RunFile>,
+}
+
+impl<Identifier: NodeIdentifier, Cost: AStarCost> SpillingOpenList<Identifier, Cost> {
+    /// The amount of words that store a node in the file, i.e. its cost, its offsets and its gaps.
+    fn node_words(&self) -> usize {
+        self.sequence_amount + 2
+    }
+
+    fn push(&mut self, priority: Cost, cost: Cost, identifier: Identifier) -> Result<()> {
+        self.memory.push(priority, (cost, identifier));
+        self.len += 1;
+        self.evict()
+    }
+
+    fn pop(&mut self) -> Result<Option<(Cost, (Cost, Identifier))>> {
+        loop {
+            let lowest_run = self.runs.keys().next().copied();
+            match (self.memory.pop(), lowest_run) {
+                (Some((priority, node)), Some(run_priority)) if run_priority < priority => {
+                    self.memory.push(priority, node);
+                    self.load(run_priority)?;
+                }
+                (None, Some(run_priority)) => self.load(run_priority)?,
+                (entry, _) => {
+                    self.len -= usize::from(entry.is_some());
+                    return Ok(entry);
+                }
+            }
+        }
+    }
+
+    /// Write the half of the nodes in memory with the highest priorities to the file if there are more nodes than cached.
+    fn evict(&mut self) -> Result<()> {
+        if self.len <= self.options.cached_nodes.max(1) {
+            return Ok(());
+        }
+
+        let mut nodes = self.memory.drain();
+        nodes.sort_unstable_by_key(|(priority, _)| *priority);
+        let evicted = nodes.split_off(nodes.len() / 2);
+        self.len = nodes.len();
+        for (priority, node) in nodes {
+            self.memory.push(priority, node);
+        }
+
+        if self.file.is_none() {
+            self.file = Some(RunFile::create(&self.options)?);
+        }
+        let file = self.file.as_mut().unwrap();
+        let mut words = Vec::new();
+        for run in evicted.chunk_by(|(a, _), (b, _)| a == b) {
+            words.clear();
+            for (_, (cost, identifier)) in run {
+                words.push(cost.as_u64());
+                words
+                    .extend((0..self.sequence_amount).map(|index| identifier.offset(index) as u64));
+                words.push(identifier.gaps() as u64);
+            }
+            let position = file.append(&words)?;
+            self.runs
+                .entry(run[0].0)
+                .or_default()
+                .push((position, run.len()));
+        }
+
+        debug!(
+            "Moved {} open nodes to disk, {} bytes were written to the file",
+            evicted.len(),
+            file.len
+        );
+        Ok(())
+    }
+
+    /// Read the runs of the given priority from the file into memory.
+    fn load(&mut self, priority: Cost) -> Result<()> {
+        let runs = self.runs.remove(&priority).unwrap();
+        let node_words = self.node_words();
+        let file = self.file.as_mut().unwrap();
+        let mut offsets = vec![0; self.sequence_amount];
+        for (position, amount) in runs {
+            let words = file.read(position, amount * node_words)?;
+            for node in words.chunks_exact(node_words) {
+                for (offset, word) in offsets.iter_mut().zip(&node[1..=self.sequence_amount]) {
+                    *offset = *word as usize;
+                }
+                let identifier =
+                    Identifier::from_offsets(&offsets, node[self.sequence_amount + 1] as usize);
+                self.memory
+                    .push(priority, (cost_from_i64(node[0] as i64), identifier));
+            }
+            self.len += amount;
+        }
+
+        self.evict()
+    }
+}
+
+/// A file to which runs of words are appended, and from which they are read by their position.
+struct RunFile {
+    path: PathBuf,
+    file: File,
+    /// The amount of bytes written to the file.
+    len: u64,
+}
+
+impl RunFile {
+    fn create(options: &SpillOptions) -> Result<Self> {
+        let (path, file) = create_spill_file(options, "open-list")?;
+        debug!("Writing open nodes to {path:?}");
+        Ok(Self { path, file, len: 0 })
+    }
+
+    /// Append the words to the file and return their position.
+    fn append(&mut self, words: &[u64]) -> Result<u64> {
+        let bytes: Vec<_> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+        let position = self.len;
+        self.file
+            .seek(SeekFrom::Start(position))
+            .and_then(|_| self.file.write_all(&bytes))
+            .map_err(|source| MultialignError::Write {
+                path: self.path.clone(),
+                source,
+            })?;
+        self.len += bytes.len() as u64;
+        Ok(position)
+    }
+
+    fn read(&mut self, position: u64, word_amount: usize) -> Result<Vec<u64>> {
+        let mut bytes = vec![0; word_amount * 8];
+        self.file
+            .seek(SeekFrom::Start(position))
+            .and_then(|_| self.file.read_exact(&mut bytes))
+            .map_err(|source| MultialignError::Io {
+                path: self.path.clone(),
+                source,
+            })?;
+        Ok(bytes
+            .chunks_exact(8)
+            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+            .collect())
+    }
+}
+
+impl Drop for RunFile {
+    fn drop(&mut self) {
+        // The file only serves the search, so it is removed even if the search failed.
+        fs::remove_file(&self.path).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use generic_a_star::cost::{AStarCost, I32Cost};
+
+    use super::{NodeOpenList, SpillOptions};
+    use crate::{NodeIdentifier, OpenListKind, VecIdentifier};
+
+    #[test]
+    fn spilling_open_list_pops_in_order() {
+        for kind in [OpenListKind::Heap, OpenListKind::Bucket] {
+            let spill = SpillOptions {
+                directory: env::temp_dir(),
+                cached_nodes: 8,
+            };
+            let mut open_list = NodeOpenList::new(kind, Some(&spill), 2);
+            let priorities: Vec<_> = (0..200)
+                .map(|index| (index * 37) % 23 + index / 50)
+                .collect();
+            for (index, priority) in priorities.iter().enumerate() {
+                open_list
+                    .push(
+                        I32Cost::from(*priority),
+                        I32Cost::from(-(index as i32)),
+                        VecIdentifier::from_offsets(&[index, index / 2], index % 5),
+                    )
+                    .unwrap();
+            }
+            let NodeOpenList::Spilling(spilling) = &open_list else {
+                panic!("the open list is not spilling");
+            };
+            assert!(spilling.len <= 8);
+            assert!(spilling.file.is_some());
+
+            let mut popped = Vec::new();
+            while let Some((priority, (cost, identifier))) = open_list.pop().unwrap() {
+                let index = -cost.as_primitive() as usize;
+                assert_eq!(priority.as_primitive(), priorities[index]);
+                assert_eq!(
+                    identifier,
+                    VecIdentifier::from_offsets(&[index, index / 2], index % 5)
+                );
+                popped.push((priority.as_primitive(), index));
+            }
+
+            assert_eq!(popped.len(), priorities.len());
+            assert!(popped.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+            popped.sort_unstable_by_key(|(_, index)| *index);
+            assert!(popped
+                .iter()
+                .enumerate()
+                .all(|(index, (_, popped))| index == *popped));
+        }
+    }
+}
@@ -0,0 +1,331 @@
+use std::{
+    fmt::Debug,
+    fs::File,
+    io::{self, Write},
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+use compression::{CompressedWriter, OutputCompression};
+
+use log::info;
+
+use super::{
+    error::{MultialignError, Result},
+    path::AlignmentColumn,
+    GapCharacters,
+};
+
+pub mod a2m_format;
+pub mod aligned_fasta_format;
+pub mod clustal_format;
+pub mod compression;
+pub mod maf_format;
+pub mod nexus_format;
+pub mod pairwise_cigar_format;
+pub mod phylip_format;
+pub mod sam_format;
+pub mod stockholm_format;
+pub mod svg_format;
+pub mod terminal_format;
+
+/// An alignment prepared for output, with its rows in output order.
+pub struct OutputAlignment {
+    /// The id of each row.
+    pub ids: Vec<String>,
+    /// The rows of the alignment, where gaps are `None`.
+    pub rows: Vec<Vec<Option<char>>>,
+    /// The columns of the alignment, with one operation per row.
+    ///
+    /// The offsets of the residues are counted from the start of each row, so they are relative to [`Self::starts`].
+    pub columns: Vec<AlignmentColumn>,
+    /// The zero-based offset of the first residue of each row in its source sequence.
+    pub starts: Vec<usize>,
+    /// The length of the source sequence of each row.
+    pub source_lengths: Vec<usize>,
+    /// The row of the reference sequence, for formats that project the alignment onto a reference.
+    ///
+    /// This is `None` if the reference sequence was removed from the alignment.
+    pub reference: Option<usize>,
+    /// The characters used to render gaps.
+    pub gap_characters: GapCharacters,
+    /// The type of the residues in the alignment.
+    pub residue_type: ResidueType,
+    /// The cost of the alignment under the metric used for aligning.
+    ///
+    /// It is converted to floating point, since the alignment may have been computed with any cost type.
+    pub cost: f64,
+    /// A human-readable name of the metric used for aligning.
+    pub metric_name: String,
+    /// The time it took to compute the alignment.
+    pub runtime: Duration,
+}
+
+/// The type of the residues of the sequences.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum ResidueType {
+    Dna,
+    Rna,
+    /// Proteins.
+    #[default]
+    AminoAcid,
+}
+
+/// A format in which alignments can be written.
+///
+/// Implement this to add a new output format, and register it in a [`WriterRegistry`] to make it selectable by name.
+pub trait AlignmentWriter {
+    fn write(&self, alignment: &OutputAlignment, writer: &mut dyn Write) -> io::Result<()>;
+}
+
+/// Options of the built-in writers that cannot be derived from the alignment.
+#[derive(Debug, Clone, Copy)]
+pub struct WriterOptions {
+    /// How long ids are handled in PHYLIP output.
+    pub phylip_ids: phylip_format::PhylipIds,
+    /// The amount of residues per row in SVG output.
+    pub svg_residues_per_row: usize,
+    /// The font size in pixels in SVG output.
+    pub svg_font_size: u32,
+}
+
+impl Default for WriterOptions {
+    fn default() -> Self {
+        Self {
+            phylip_ids: Default::default(),
+            svg_residues_per_row: 60,
+            svg_font_size: 14,
+        }
+    }
+}
+
+/// An [`AlignmentWriter`] that can be selected by name.
+#[derive(Debug, Clone, Copy)]
+pub struct WriterRegistration {
+    /// The name used to select the writer, e.g. on the command line.
+    pub name: &'static str,
+    /// A short human-readable description of the format.
+    pub description: &'static str,
+    /// Create the writer with the given options.
+    pub create: fn(&WriterOptions) -> Box<dyn AlignmentWriter + Send + Sync>,
+}
+
+/// The writers that can be selected by name.
+#[derive(Debug, Clone)]
+pub struct WriterRegistry {
+    registrations: Vec<WriterRegistration>,
+}
+
+impl WriterRegistry {
+    /// Create a registry without any writers.
+    pub fn empty() -> Self {
+        Self {
+            registrations: Vec::new(),
+        }
+    }
+
+    /// Add a writer to the registry, replacing any writer with the same name.
+    pub fn register(&mut self, registration: WriterRegistration) {
+        self.registrations
+            .retain(|other| other.name != registration.name);
+        self.registrations.push(registration);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&WriterRegistration> {
+        self.registrations
+            .iter()
+            .find(|registration| registration.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &WriterRegistration> {
+        self.registrations.iter()
+    }
+
+    /// Create the output format with the given name, or return `None` if no writer with this name is registered.
+    pub fn output_format(&self, name: &str, options: &WriterOptions) -> Option<OutputFormat> {
+        self.get(name).map(|registration| {
+            OutputFormat::new(registration.name, (registration.create)(options).into())
+        })
+    }
+}
+
+impl Default for WriterRegistry {
+    /// Create a registry with all writers of this crate.
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        for registration in [
+            WriterRegistration {
+                name: "fasta",
+                description: "Aligned fasta, with each sequence on a single line",
+                create: |_| Box::new(aligned_fasta_format::AlignedFastaWriter),
+            },
+            WriterRegistration {
+                name: "clustal",
+                description: "CLUSTAL W, with blocks of 60 columns and a conservation line",
+                create: |_| Box::new(clustal_format::ClustalWriter::default()),
+            },
+            WriterRegistration {
+                name: "stockholm",
+                description: "Stockholm, with the cost, metric and runtime as comments",
+                create: |_| Box::new(stockholm_format::StockholmWriter),
+            },
+            WriterRegistration {
+                name: "phylip",
+                description: "Sequential PHYLIP, with each sequence on a single line",
+                create: |options| {
+                    Box::new(phylip_format::PhylipWriter {
+                        interleaved: false,
+                        ids: options.phylip_ids,
+                        line_width: 60,
+                    })
+                },
+            },
+            WriterRegistration {
+                name: "phylip-interleaved",
+                description: "Interleaved PHYLIP, with blocks of 60 columns",
+                create: |options| {
+                    Box::new(phylip_format::PhylipWriter {
+                        interleaved: true,
+                        ids: options.phylip_ids,
+                        line_width: 60,
+                    })
+                },
+            },
+            WriterRegistration {
+                name: "nexus",
+                description: "NEXUS, with a data block whose data type is derived from the alphabet",
+                create: |_| Box::new(nexus_format::NexusWriter),
+            },
+            WriterRegistration {
+                name: "maf",
+                description: "Multiple Alignment Format, with source sizes and strands, as used by genome browsers",
+                create: |_| Box::new(maf_format::MafWriter),
+            },
+            WriterRegistration {
+                name: "sam",
+                description: "SAM, with each sequence projected onto the reference sequence",
+                create: |_| Box::new(sam_format::SamWriter),
+            },
+            WriterRegistration {
+                name: "a2m",
+                description: "A2M, with residues in columns where the reference sequence has a gap written in lower case",
+                create: |_| Box::new(a2m_format::A2mWriter),
+            },
+            WriterRegistration {
+                name: "svg",
+                description: "An SVG figure with highlighted consensus residues and a conservation bar chart",
+                create: |options| {
+                    Box::new(svg_format::SvgWriter {
+                        residues_per_row: options.svg_residues_per_row,
+                        font_size: options.svg_font_size,
+                    })
+                },
+            },
+            WriterRegistration {
+                name: "pairwise-cigar",
+                description: "Tab-separated extended CIGAR strings of each pair of sequences",
+                create: |_| Box::new(pairwise_cigar_format::PairwiseCigarWriter),
+            },
+        ] {
+            registry.register(registration);
+        }
+        registry
+    }
+}
+
+/// The format of an output file, i.e. a named [`AlignmentWriter`].
+#[derive(Clone)]
+pub struct OutputFormat {
+    name: String,
+    writer: Arc<dyn AlignmentWriter + Send + Sync>,
+}
+
+impl OutputFormat {
+    pub fn new(name: impl Into<String>, writer: Arc<dyn AlignmentWriter + Send + Sync>) -> Self {
+        Self {
+            name: name.into(),
+            writer,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn writer(&self) -> &(dyn AlignmentWriter + Send + Sync) {
+        self.writer.as_ref()
+    }
+}
+
+impl Default for OutputFormat {
+    /// Aligned fasta, see [`aligned_fasta_format::AlignedFastaWriter`].
+    fn default() -> Self {
+        Self::new("fasta", Arc::new(aligned_fasta_format::AlignedFastaWriter))
+    }
+}
+
+impl Debug for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl OutputAlignment {
+    /// Render the given row as string, with gaps rendered as gap characters.
+    pub fn render_row(&self, row: usize) -> String {
+        self.gap_characters.render_row(&self.rows[row])
+    }
+
+    /// Returns the amount of columns of the alignment.
+    pub fn len(&self) -> usize {
+        self.rows.first().map(Vec::len).unwrap_or(0)
+    }
+
+    /// Returns true if the alignment has no columns.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Write the alignment to the standard output in a human-readable format, see [`terminal_format::TerminalWriter`].
+pub fn write_alignment_to_terminal(alignment: &OutputAlignment) -> Result<()> {
+    let write_error = |source| MultialignError::Write {
+        path: "<stdout>".into(),
+        source,
+    };
+    let mut writer = io::stdout().lock();
+    terminal_format::TerminalWriter::for_stdout()
+        .write(alignment, &mut writer)
+        .map_err(write_error)?;
+    writer.flush().map_err(write_error)
+}
+
+/// Write the alignment to the given file in the given format.
+///
+/// If no compression is given, it is chosen from the extension of the path, see [`OutputCompression::from_path`].
+pub fn write_alignment_file(
+    path: impl AsRef<Path>,
+    alignment: &OutputAlignment,
+    format: &OutputFormat,
+    compression: Option<OutputCompression>,
+) -> Result<()> {
+    let path = path.as_ref();
+    let compression = compression.unwrap_or_else(|| OutputCompression::from_path(path));
+    info!(
+        "Writing alignment as {} with compression {compression:?} to {path:?}",
+        format.name()
+    );
+
+    let write_error = |source| MultialignError::Write {
+        path: path.to_owned(),
+        source,
+    };
+    let mut writer = CompressedWriter::new(File::create(path).map_err(write_error)?, compression)
+        .map_err(write_error)?;
+    format
+        .writer()
+        .write(alignment, &mut writer)
+        .map_err(write_error)?;
+    writer.finish().map_err(write_error)
+}
@@ -0,0 +1,44 @@
+use std::io::{self, Write};
+
+use super::{AlignmentWriter, OutputAlignment};
+
+/// A2M, which is aligned fasta where columns are classified by the reference row as master sequence.
+///
+/// Columns where the master has a residue are match columns, with residues in upper case and gaps as `-`.
+/// All other columns are insert columns, with residues in lower case and gaps as `.`.
+/// The gap characters of the alignment are ignored, since they are fixed by the format.
+pub struct A2mWriter;
+
+impl AlignmentWriter for A2mWriter {
+    fn write(&self, alignment: &OutputAlignment, writer: &mut dyn Write) -> io::Result<()> {
+        let reference = alignment.reference.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The master sequence is not part of the alignment",
+            )
+        })?;
+        let is_match_column: Vec<bool> = alignment.rows[reference]
+            .iter()
+            .map(Option::is_some)
+            .collect();
+
+        for (id, row) in alignment.ids.iter().zip(&alignment.rows) {
+            let row: String = row
+                .iter()
+                .zip(&is_match_column)
+                .map(
+                    |(character, is_match_column)| match (character, is_match_column) {
+                        (Some(residue), true) => residue.to_ascii_uppercase(),
+                        (None, true) => '-',
+                        (Some(residue), false) => residue.to_ascii_lowercase(),
+                        (None, false) => '.',
+                    },
+                )
+                .collect();
+            writeln!(writer, ">{id}")?;
+            writeln!(writer, "{row}")?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,17 @@
+use std::io::{self, Write};
+
+use super::{AlignmentWriter, OutputAlignment};
+
+/// Aligned fasta, where each record is written with its id as header and its aligned sequence on a single line.
+pub struct AlignedFastaWriter;
+
+impl AlignmentWriter for AlignedFastaWriter {
+    fn write(&self, alignment: &OutputAlignment, writer: &mut dyn Write) -> io::Result<()> {
+        for (row, id) in alignment.ids.iter().enumerate() {
+            writeln!(writer, ">{id}")?;
+            writeln!(writer, "{}", alignment.render_row(row))?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use std::io::{self, Write};
+
+use super::{AlignmentWriter, OutputAlignment, ResidueType};
+
+/// The strongly conserved amino acid groups of CLUSTAL W, marked with `:`.
+const STRONG_GROUPS: [&str; 9] = [
+    "STA", "NEQK", "NHQK", "NDEQ", "QHRK", "MILV", "MILF", "HY", "FYW",
+];
+
+/// The weakly conserved amino acid groups of CLUSTAL W, marked with `.`.
+const WEAK_GROUPS: [&str; 11] = [
+    "CSA", "ATV", "SAG", "STNK", "STPA", "SGND", "SNDEQK", "NDEQHK", "NEQHRK", "FVLIM", "HFY",
+];
+
+/// CLUSTAL W, where the alignment is wrapped into blocks of the given width.
+///
+/// Each block is followed by a conservation line, which marks columns with identical residues with `*`.
+/// For amino acids, columns whose residues are all in a strongly or weakly conserved group are marked with `:` or `.`, respectively.
+/// Columns with gaps are never marked.
+pub struct ClustalWriter {
+    /// The amount of columns per block.
+    pub line_width: usize,
+}
+
+impl Default for ClustalWriter {
+    fn default() -> Self {
+        Self { line_width: 60 }
+    }
+}
+
+impl AlignmentWriter for ClustalWriter {
+    fn write(&self, alignment: &OutputAlignment, writer: &mut dyn Write) -> io::Result<()> {
+        let rows: Vec<Vec<char>> = (0..alignment.rows.len())
+            .map(|row| alignment.render_row(row).chars().collect())
+            .collect();
+        let conservation = conservation_line(alignment);
+        let id_width = alignment
+            .ids
+            .iter()
+            .map(String::len)
+            .max()
+            .unwrap_or(0)
+            .max(10)
+            + 6;
+
+        writeln!(writer, "CLUSTAL W multiple sequence alignment")?;
+        writeln!(writer)?;
+
+        for block_start in (0..alignment.len()).step_by(self.line_width.max(1)) {
+            let block_end = (block_start + self.line_width.max(1)).min(alignment.len());
+            writeln!(writer)?;
+            for (id, row) in alignment.ids.iter().zip(&rows) {
+                writeln!(
+                    writer,
+                    "{id:<id_width$}{}",
+                    row[block_start..block_end].iter().collect::<String>()
+                )?;
+            }
+            writeln!(
+                writer,
+                "{:<id_width$}{}",
+                "",
+                conservation[block_start..block_end]
+                    .iter()
+                    .collect::<String>()
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Compute the conservation mark of each column.
+fn conservation_line(alignment: &OutputAlignment) -> Vec<char> {
+    (0..alignment.len())
+        .map(|column| {
+            let Some(residues) = alignment
+                .rows
+                .iter()
+                .map(|row| row[column].map(|residue| residue.to_ascii_uppercase()))
+                .collect::<Option<Vec<_>>>()
+            else {
+                return ' ';
+            };
+            let in_group = |group: &&str| residues.iter().all(|residue| group.contains(*residue));
+
+            if residues.iter().all(|residue| *residue == residues[0]) {
+                '*'
+            } else if alignment.residue_type != ResidueType::AminoAcid {
+                ' '
+            } else if STRONG_GROUPS.iter().any(in_group) {
+                ':'
+            } else if WEAK_GROUPS.iter().any(in_group) {
+                '.'
+            } else {
+                ' '
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,89 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use flate2::write::GzEncoder;
+
+/// The compression of an output file.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum OutputCompression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+/// The file name extensions of compressed files.
+pub const COMPRESSION_EXTENSIONS: [&str; 2] = ["gz", "zst"];
+
+impl OutputCompression {
+    /// Choose the compression from the extension of the given path, i.e. `.gz` for gzip and `.zst` for zstd.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("gz") => Self::Gzip,
+            Some("zst") => Self::Zstd,
+            _ => Self::None,
+        }
+    }
+}
+
+/// A writer that compresses its output according to an [`OutputCompression`].
+///
+/// Call [`Self::finish`] to flush the writer and write the trailer of the compressed file.
+pub enum CompressedWriter {
+    None(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::Encoder<'static, BufWriter<File>>),
+}
+
+impl CompressedWriter {
+    pub fn new(file: File, compression: OutputCompression) -> io::Result<Self> {
+        let writer = BufWriter::new(file);
+        Ok(match compression {
+            OutputCompression::None => Self::None(writer),
+            OutputCompression::Gzip => Self::Gzip(GzEncoder::new(writer, Default::default())),
+            #[cfg(feature = "zstd")]
+            OutputCompression::Zstd => Self::Zstd(zstd::Encoder::new(writer, 0)?),
+            #[cfg(not(feature = "zstd"))]
+            OutputCompression::Zstd => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "zstd compression requires the zstd feature",
+                ))
+            }
+        })
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        let mut writer = match self {
+            Self::None(writer) => writer,
+            Self::Gzip(encoder) => encoder.finish()?,
+            #[cfg(feature = "zstd")]
+            Self::Zstd(encoder) => encoder.finish()?,
+        };
+        writer.flush()
+    }
+}
+
+impl Write for CompressedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::None(writer) => writer.write(buf),
+            Self::Gzip(encoder) => encoder.write(buf),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::None(writer) => writer.flush(),
+            Self::Gzip(encoder) => encoder.flush(),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
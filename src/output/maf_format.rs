@@ -0,0 +1,52 @@
+use std::io::{self, Write};
+
+use log::warn;
+
+use super::{AlignmentWriter, OutputAlignment, ResidueType};
+
+/// Multiple Alignment Format (MAF), with the alignment as a single block.
+///
+/// Each row is written as `s` line with its start, aligned size, strand and source size.
+/// Since MAF scores are usually higher-is-better, the cost is declared as the scoring scheme in the header.
+pub struct MafWriter;
+
+impl AlignmentWriter for MafWriter {
+    fn write(&self, alignment: &OutputAlignment, writer: &mut dyn Write) -> io::Result<()> {
+        if alignment.residue_type == ResidueType::AminoAcid {
+            warn!("Writing amino acid alignment as MAF, which is meant for genomic alignments");
+        }
+
+        let id_width = alignment.ids.iter().map(String::len).max().unwrap_or(0);
+        let start_width = alignment
+            .starts
+            .iter()
+            .map(|start| start.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let source_length_width = alignment
+            .source_lengths
+            .iter()
+            .map(|length| length.to_string().len())
+            .max()
+            .unwrap_or(0);
+
+        writeln!(writer, "##maf version=1 scoring=multialign_cost")?;
+        writeln!(writer)?;
+        writeln!(writer, "a score={}", alignment.cost)?;
+        for (row, id) in alignment.ids.iter().enumerate() {
+            let size = alignment
+                .columns
+                .iter()
+                .filter(|column| !column.is_gap(row))
+                .count();
+            writeln!(
+                writer,
+                "s {id:<id_width$} {:>start_width$} {size:>source_length_width$} + {:>source_length_width$} {}",
+                alignment.starts[row],
+                alignment.source_lengths[row],
+                alignment.render_row(row),
+            )?;
+        }
+        writeln!(writer)
+    }
+}
@@ -0,0 +1,61 @@
+use std::io::{self, Write};
+
+use super::{AlignmentWriter, OutputAlignment, ResidueType};
+
+/// NEXUS, with a single `DATA` block whose `DATATYPE` is derived from the residue type.
+///
+/// If terminal and internal gaps are rendered with different characters, then terminal gaps are declared as missing data.
+/// Ids that contain whitespace or punctuation are quoted.
+pub struct NexusWriter;
+
+impl AlignmentWriter for NexusWriter {
+    fn write(&self, alignment: &OutputAlignment, writer: &mut dyn Write) -> io::Result<()> {
+        let ids: Vec<String> = alignment.ids.iter().map(|id| quote_id(id)).collect();
+        let id_width = ids.iter().map(String::len).max().unwrap_or(0) + 1;
+        let data_type = match alignment.residue_type {
+            ResidueType::Dna => "DNA",
+            ResidueType::Rna => "RNA",
+            ResidueType::AminoAcid => "PROTEIN",
+        };
+        let gap_characters = &alignment.gap_characters;
+        let missing = if gap_characters.terminal == gap_characters.internal {
+            String::new()
+        } else {
+            format!(" MISSING={}", gap_characters.terminal)
+        };
+
+        writeln!(writer, "#NEXUS")?;
+        writeln!(writer)?;
+        writeln!(writer, "BEGIN DATA;")?;
+        writeln!(
+            writer,
+            "    DIMENSIONS NTAX={} NCHAR={};",
+            alignment.rows.len(),
+            alignment.len()
+        )?;
+        writeln!(
+            writer,
+            "    FORMAT DATATYPE={data_type} GAP={}{missing};",
+            gap_characters.internal
+        )?;
+        writeln!(writer, "    MATRIX")?;
+        for (row, id) in ids.iter().enumerate() {
+            writeln!(writer, "    {id:<id_width$}{}", alignment.render_row(row))?;
+        }
+        writeln!(writer, "    ;")?;
+        writeln!(writer, "END;")
+    }
+}
+
+/// Quote the id if it contains characters that are not allowed in unquoted NEXUS tokens.
+fn quote_id(id: &str) -> String {
+    if !id.is_empty()
+        && id
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric() || "_.|".contains(character))
+    {
+        id.to_string()
+    } else {
+        format!("'{}'", id.replace('\'', "''"))
+    }
+}
@@ -0,0 +1,66 @@
+use std::io::{self, Write};
+
+use super::{AlignmentWriter, OutputAlignment};
+
+/// Extended CIGAR strings of each pair of rows, with one tab-separated line per pair.
+///
+/// Each line contains the id of the first row, the id of the second row, and the CIGAR string of the second row projected onto the first row.
+/// Columns where both rows have the same residue become `=`, columns where they have different residues become `X`,
+/// columns where only the first row has a residue become `D`, and columns where only the second row has a residue become `I`.
+/// Columns where both have gaps are skipped.
+/// Residues are compared case-insensitively.
+pub struct PairwiseCigarWriter;
+
+impl AlignmentWriter for PairwiseCigarWriter {
+    fn write(&self, alignment: &OutputAlignment, writer: &mut dyn Write) -> io::Result<()> {
+        for first in 0..alignment.rows.len() {
+            for second in first + 1..alignment.rows.len() {
+                writeln!(
+                    writer,
+                    "{}\t{}\t{}",
+                    alignment.ids[first],
+                    alignment.ids[second],
+                    extended_cigar(alignment, first, second)
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Project the second row onto the first row and return the extended CIGAR string.
+fn extended_cigar(alignment: &OutputAlignment, first: usize, second: usize) -> String {
+    let mut operations: Vec<(char, usize)> = Vec::new();
+
+    for (index, column) in alignment.columns.iter().enumerate() {
+        let operation = match (column.is_gap(first), column.is_gap(second)) {
+            (false, false) => {
+                let residue = |row: usize| alignment.rows[row][index].unwrap();
+                if residue(first).eq_ignore_ascii_case(&residue(second)) {
+                    '='
+                } else {
+                    'X'
+                }
+            }
+            (false, true) => 'D',
+            (true, false) => 'I',
+            (true, true) => continue,
+        };
+
+        match operations.last_mut() {
+            Some((last_operation, length)) if *last_operation == operation => *length += 1,
+            _ => operations.push((operation, 1)),
+        }
+    }
+
+    if operations.is_empty() {
+        return "*".to_string();
+    }
+
+    let mut cigar = String::new();
+    for (operation, length) in operations {
+        cigar.push_str(&format!("{length}{operation}"));
+    }
+    cigar
+}
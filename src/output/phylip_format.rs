@@ -0,0 +1,112 @@
+use std::{
+    collections::BTreeSet,
+    io::{self, Write},
+};
+
+use log::info;
+
+use super::{AlignmentWriter, OutputAlignment};
+
+/// The maximum length of an id in strict PHYLIP.
+const STRICT_ID_LENGTH: usize = 10;
+
+/// PHYLIP, either sequential with each sequence on a single line, or interleaved in blocks of the given width.
+pub struct PhylipWriter {
+    pub interleaved: bool,
+    pub ids: PhylipIds,
+    /// The amount of columns per block of the interleaved format.
+    pub line_width: usize,
+}
+
+/// How ids are written in PHYLIP, which allows only ten characters per id in its strict form.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum PhylipIds {
+    /// Truncate ids to ten characters.
+    ///
+    /// If truncated ids collide, their ends are replaced by the one-based index of the sequence.
+    #[default]
+    Truncate,
+    /// Replace all ids with `S` followed by the one-based index of the sequence, and log the mapping.
+    Rename,
+    /// Write the full ids, separated from the sequence by a space, as supported by e.g. RAxML and PhyML.
+    Relaxed,
+}
+
+impl AlignmentWriter for PhylipWriter {
+    fn write(&self, alignment: &OutputAlignment, writer: &mut dyn Write) -> io::Result<()> {
+        let ids = self.ids.apply(&alignment.ids);
+        let id_width = match self.ids {
+            PhylipIds::Relaxed => ids.iter().map(String::len).max().unwrap_or(0) + 1,
+            PhylipIds::Truncate | PhylipIds::Rename => STRICT_ID_LENGTH,
+        };
+        let rows: Vec<Vec<char>> = (0..alignment.rows.len())
+            .map(|row| alignment.render_row(row).chars().collect())
+            .collect();
+
+        writeln!(writer, " {} {}", alignment.rows.len(), alignment.len())?;
+
+        if !self.interleaved {
+            for (id, row) in ids.iter().zip(&rows) {
+                writeln!(writer, "{id:<id_width$}{}", row.iter().collect::<String>())?;
+            }
+            return Ok(());
+        }
+
+        for block_start in (0..alignment.len()).step_by(self.line_width.max(1)) {
+            let block_end = (block_start + self.line_width.max(1)).min(alignment.len());
+            if block_start > 0 {
+                writeln!(writer)?;
+            }
+            for (id, row) in ids.iter().zip(&rows) {
+                // Only the first block is labelled with the ids.
+                let id = if block_start == 0 { id.as_str() } else { "" };
+                writeln!(
+                    writer,
+                    "{id:<id_width$}{}",
+                    row[block_start..block_end].iter().collect::<String>()
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl PhylipIds {
+    fn apply(&self, ids: &[String]) -> Vec<String> {
+        match self {
+            Self::Truncate => {
+                let mut used_ids = BTreeSet::new();
+                ids.iter()
+                    .enumerate()
+                    .map(|(index, id)| {
+                        let mut truncated: String = id.chars().take(STRICT_ID_LENGTH).collect();
+                        if !used_ids.insert(truncated.clone()) {
+                            let suffix = (index + 1).to_string();
+                            truncated = truncated
+                                .chars()
+                                .take(STRICT_ID_LENGTH.saturating_sub(suffix.len()))
+                                .chain(suffix.chars())
+                                .collect();
+                            used_ids.insert(truncated.clone());
+                        }
+                        if truncated != *id {
+                            info!("Writing id {id} as {truncated}");
+                        }
+                        truncated
+                    })
+                    .collect()
+            }
+            Self::Rename => ids
+                .iter()
+                .enumerate()
+                .map(|(index, id)| {
+                    let renamed = format!("S{}", index + 1);
+                    info!("Writing id {id} as {renamed}");
+                    renamed
+                })
+                .collect(),
+            Self::Relaxed => ids.to_vec(),
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::io::{self, Write};
+
+use super::{AlignmentWriter, OutputAlignment};
+use crate::path::{AlignmentColumn, AlignmentOperation};
+
+/// SAM, with each row projected onto the reference row.
+///
+/// Columns where both the reference and the row have residues become `M`, columns where only the reference has a residue become `D`,
+/// and columns where only the row has a residue become `I`, or `S` if they are before or after all `M` columns.
+/// Columns where both have gaps are skipped.
+/// Rows without any `M` column are written as unmapped.
+/// The reference itself is only written into the header.
+pub struct SamWriter;
+
+impl AlignmentWriter for SamWriter {
+    fn write(&self, alignment: &OutputAlignment, writer: &mut dyn Write) -> io::Result<()> {
+        let reference = alignment.reference.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The reference sequence is not part of the alignment",
+            )
+        })?;
+        let reference_id = &alignment.ids[reference];
+
+        writeln!(writer, "@HD\tVN:1.6\tSO:unsorted")?;
+        writeln!(
+            writer,
+            "@SQ\tSN:{reference_id}\tLN:{}",
+            alignment.starts[reference] + alignment.source_lengths[reference]
+        )?;
+        writeln!(writer, "@PG\tID:multialign\tPN:multialign")?;
+
+        for (row, id) in alignment.ids.iter().enumerate() {
+            if row == reference {
+                continue;
+            }
+
+            let sequence: String = alignment.rows[row].iter().flatten().collect();
+            let sequence = if sequence.is_empty() {
+                "*".to_string()
+            } else {
+                sequence
+            };
+
+            match project(
+                &alignment.columns,
+                reference,
+                row,
+                alignment.source_lengths[row],
+            ) {
+                Some((position, cigar)) => writeln!(
+                    writer,
+                    "{id}\t0\t{reference_id}\t{}\t255\t{cigar}\t*\t0\t0\t{sequence}\t*",
+                    alignment.starts[reference] + position + 1
+                )?,
+                None => writeln!(writer, "{id}\t4\t*\t0\t0\t*\t*\t0\t0\t{sequence}\t*")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Project the row onto the reference row.
+///
+/// Returns the zero-based reference position of the first `M` column and the CIGAR string, or `None` if there is no `M` column.
+fn project(
+    columns: &[AlignmentColumn],
+    reference: usize,
+    row: usize,
+    row_length: usize,
+) -> Option<(usize, String)> {
+    let residues = |column: &AlignmentColumn| {
+        (
+            column.operations[reference].residue(),
+            column.operations[row].residue(),
+        )
+    };
+    let is_match = |column: &AlignmentColumn| matches!(residues(column), (Some(_), Some(_)));
+    let first_match = columns.iter().position(is_match)?;
+    let last_match = columns.iter().rposition(is_match)?;
+
+    // The offsets of the residues in the first and last `M` column determine the reference position and the clipped residues.
+    let (Some(position), Some(leading_clip)) = residues(&columns[first_match]) else {
+        unreachable!()
+    };
+    let AlignmentOperation::Residue(last_residue) = columns[last_match].operations[row] else {
+        unreachable!()
+    };
+
+    let mut operations = Vec::new();
+    if leading_clip > 0 {
+        operations.push(('S', leading_clip));
+    }
+
+    for column in &columns[first_match..=last_match] {
+        let operation = match residues(column) {
+            (Some(_), Some(_)) => 'M',
+            (Some(_), None) => 'D',
+            (None, Some(_)) => 'I',
+            (None, None) => continue,
+        };
+
+        match operations.last_mut() {
+            Some((last_operation, length)) if *last_operation == operation => *length += 1,
+            _ => operations.push((operation, 1)),
+        }
+    }
+
+    let trailing_clip = row_length - last_residue - 1;
+    if trailing_clip > 0 {
+        operations.push(('S', trailing_clip));
+    }
+
+    let mut cigar = String::new();
+    for (operation, length) in operations {
+        cigar.push_str(&format!("{length}{operation}"));
+    }
+    Some((position, cigar))
+}
@@ -0,0 +1,30 @@
+use std::io::{self, Write};
+
+use super::{AlignmentWriter, OutputAlignment};
+
+/// Stockholm, where the alignment is written as a single block.
+///
+/// The cost, the metric and the runtime are written as `#=GF CC` comment lines.
+pub struct StockholmWriter;
+
+impl AlignmentWriter for StockholmWriter {
+    fn write(&self, alignment: &OutputAlignment, writer: &mut dyn Write) -> io::Result<()> {
+        let id_width = alignment.ids.iter().map(String::len).max().unwrap_or(0) + 1;
+
+        writeln!(writer, "# STOCKHOLM 1.0")?;
+        writeln!(writer, "#=GF AU multialign")?;
+        writeln!(writer, "#=GF CC cost {}", alignment.cost)?;
+        writeln!(writer, "#=GF CC metric {}", alignment.metric_name)?;
+        writeln!(
+            writer,
+            "#=GF CC runtime {:.2}s",
+            alignment.runtime.as_secs_f64()
+        )?;
+        writeln!(writer)?;
+
+        for (row, id) in alignment.ids.iter().enumerate() {
+            writeln!(writer, "{id:<id_width$}{}", alignment.render_row(row))?;
+        }
+        writeln!(writer, "//")
+    }
+}
@@ -0,0 +1,141 @@
+use std::io::{self, Write};
+
+use super::{AlignmentWriter, OutputAlignment};
+use crate::curation::consensus;
+
+/// The margin around the figure, relative to the font size.
+const MARGIN: f64 = 1.0;
+/// The width of a residue, relative to the font size.
+const CELL_WIDTH: f64 = 0.75;
+/// The height of a row, relative to the font size.
+const LINE_HEIGHT: f64 = 1.3;
+/// The maximum height of a conservation bar, relative to the font size.
+const BAR_HEIGHT: f64 = 2.0;
+/// The approximate width of a character of a monospace font, relative to the font size.
+const CHARACTER_WIDTH: f64 = 0.6;
+
+/// An SVG figure of the alignment, wrapped into blocks of the given amount of residues per row.
+///
+/// Residues that are equal to the consensus of their column are highlighted.
+/// Under each block, a bar chart shows the conservation of each column, which is the fraction of rows that contain the consensus residue.
+pub struct SvgWriter {
+    pub residues_per_row: usize,
+    /// The font size in pixels, which determines the size of the whole figure.
+    pub font_size: u32,
+}
+
+impl AlignmentWriter for SvgWriter {
+    fn write(&self, alignment: &OutputAlignment, writer: &mut dyn Write) -> io::Result<()> {
+        let font_size = f64::from(self.font_size);
+        let residues_per_row = self.residues_per_row.max(1);
+        let rows: Vec<Vec<char>> = (0..alignment.rows.len())
+            .map(|row| alignment.render_row(row).chars().collect())
+            .collect();
+        let columns: Vec<Vec<Option<char>>> = (0..alignment.len())
+            .map(|column| alignment.rows.iter().map(|row| row[column]).collect())
+            .collect();
+        let consensus = consensus(&columns);
+        let conservation: Vec<f64> = columns
+            .iter()
+            .zip(&consensus)
+            .map(|(column, consensus)| {
+                column
+                    .iter()
+                    .filter(|character| consensus.is_some() && *character == consensus)
+                    .count() as f64
+                    / column.len().max(1) as f64
+            })
+            .collect();
+
+        let margin = MARGIN * font_size;
+        let cell_width = CELL_WIDTH * font_size;
+        let line_height = LINE_HEIGHT * font_size;
+        let bar_height = BAR_HEIGHT * font_size;
+        let id_width = (alignment
+            .ids
+            .iter()
+            .map(|id| id.chars().count())
+            .max()
+            .unwrap_or(0)
+            + 2) as f64
+            * CHARACTER_WIDTH
+            * font_size;
+        let block_amount = alignment.len().div_ceil(residues_per_row);
+        let block_height = rows.len() as f64 * line_height + bar_height + line_height;
+        let width =
+            2.0 * margin + id_width + alignment.len().min(residues_per_row) as f64 * cell_width;
+        let height = 2.0 * margin + block_amount as f64 * block_height;
+
+        writeln!(
+            writer,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width:.1}" height="{height:.1}" viewBox="0 0 {width:.1} {height:.1}" font-family="monospace" font-size="{font_size}">"#
+        )?;
+        writeln!(
+            writer,
+            r##"<rect width="100%" height="100%" fill="#ffffff"/>"##
+        )?;
+
+        for block in 0..block_amount {
+            let block_start = block * residues_per_row;
+            let block_end = (block_start + residues_per_row).min(alignment.len());
+            let block_top = margin + block as f64 * block_height;
+            let residues_left = margin + id_width;
+
+            for (row_index, (id, row)) in alignment.ids.iter().zip(&rows).enumerate() {
+                let row_top = block_top + row_index as f64 * line_height;
+                let baseline = row_top + 0.75 * line_height;
+                writeln!(
+                    writer,
+                    r#"<text x="{margin:.1}" y="{baseline:.1}">{}</text>"#,
+                    escape(id)
+                )?;
+
+                for column in block_start..block_end {
+                    let x = residues_left + (column - block_start) as f64 * cell_width;
+                    let character = alignment.rows[row_index][column];
+                    if character.is_some() && character == consensus[column] {
+                        writeln!(
+                            writer,
+                            r##"<rect x="{x:.1}" y="{row_top:.1}" width="{cell_width:.1}" height="{line_height:.1}" fill="#a6cee3"/>"##
+                        )?;
+                    }
+                    // Gaps are dimmed.
+                    let fill = if character.is_some() {
+                        "#000000"
+                    } else {
+                        "#999999"
+                    };
+                    writeln!(
+                        writer,
+                        r#"<text x="{:.1}" y="{baseline:.1}" text-anchor="middle" fill="{fill}">{}</text>"#,
+                        x + cell_width / 2.0,
+                        escape(&row[column].to_string())
+                    )?;
+                }
+            }
+
+            let bar_bottom = block_top + rows.len() as f64 * line_height + bar_height;
+            for (offset, conservation) in conservation[block_start..block_end].iter().enumerate() {
+                let x = residues_left + offset as f64 * cell_width;
+                let height = conservation * bar_height;
+                writeln!(
+                    writer,
+                    r##"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{height:.1}" fill="#555555"/>"##,
+                    x + 0.1 * cell_width,
+                    bar_bottom - height,
+                    0.8 * cell_width
+                )?;
+            }
+        }
+
+        writeln!(writer, "</svg>")
+    }
+}
+
+/// Escape the characters that have a special meaning in XML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,93 @@
+use std::{
+    env,
+    io::{self, IsTerminal, Write},
+};
+
+use super::{AlignmentWriter, OutputAlignment};
+use crate::curation::consensus;
+
+const RESET: &str = "\x1b[0m";
+/// Residues of columns in which all sequences have the same residue.
+const CONSERVED: &str = "\x1b[30;42m";
+/// Residues that are equal to the consensus of their column.
+const CONSENSUS: &str = "\x1b[30;46m";
+/// Gaps.
+const DIM: &str = "\x1b[2m";
+
+/// A human-readable view of the alignment for the terminal, wrapped into blocks that fit the terminal width.
+///
+/// If colored, residues are highlighted by their identity to the consensus of their column, and gaps are dimmed.
+pub struct TerminalWriter {
+    /// The maximum width of a line, including the ids.
+    pub width: usize,
+    pub colored: bool,
+}
+
+impl TerminalWriter {
+    /// Create a writer for the standard output.
+    ///
+    /// The width is taken from the `COLUMNS` environment variable, and defaults to 80.
+    /// Colors are used if the standard output is a terminal and the `NO_COLOR` environment variable is not set.
+    pub fn for_stdout() -> Self {
+        Self {
+            width: env::var("COLUMNS")
+                .ok()
+                .and_then(|columns| columns.parse().ok())
+                .unwrap_or(80),
+            colored: io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none(),
+        }
+    }
+}
+
+impl AlignmentWriter for TerminalWriter {
+    fn write(&self, alignment: &OutputAlignment, writer: &mut dyn Write) -> io::Result<()> {
+        let rows: Vec<Vec<char>> = (0..alignment.rows.len())
+            .map(|row| alignment.render_row(row).chars().collect())
+            .collect();
+        let columns: Vec<Vec<Option<char>>> = (0..alignment.len())
+            .map(|column| alignment.rows.iter().map(|row| row[column]).collect())
+            .collect();
+        let consensus = consensus(&columns);
+        let id_width = alignment
+            .ids
+            .iter()
+            .map(|id| id.chars().count())
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let block_width = self.width.saturating_sub(id_width).max(10);
+
+        for block_start in (0..alignment.len()).step_by(block_width) {
+            let block_end = (block_start + block_width).min(alignment.len());
+            if block_start > 0 {
+                writeln!(writer)?;
+            }
+
+            for (row_index, (id, row)) in alignment.ids.iter().zip(&rows).enumerate() {
+                write!(writer, "{id:<id_width$}")?;
+                for column in block_start..block_end {
+                    let character = alignment.rows[row_index][column];
+                    let style = if !self.colored {
+                        None
+                    } else if character.is_none() {
+                        Some(DIM)
+                    } else if columns[column].iter().all(|other| *other == character) {
+                        Some(CONSERVED)
+                    } else if character == consensus[column] {
+                        Some(CONSENSUS)
+                    } else {
+                        None
+                    };
+
+                    match style {
+                        Some(style) => write!(writer, "{style}{}{RESET}", row[column])?,
+                        None => write!(writer, "{}", row[column])?,
+                    }
+                }
+                writeln!(writer)?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,492 @@
+use compact_genome::interface::{alphabet::Alphabet, sequence::GenomeSequence};
+use generic_a_star::{cost::AStarCost, AStarPerformanceCounters};
+
+use super::{
+    cancellation::CancellationToken,
+    error::{MultialignError, Result},
+    metric::MultialignMetric,
+    path::AlignmentPath,
+    MemoryLimit, SearchOutput, Segment,
+};
+
+/// The edge through which a cell of the dynamic programming matrix was reached.
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Direction {
+    Root,
+    Match,
+    GapInSecond,
+    GapInFirst,
+}
+
+impl Direction {
+    /// The edges into a cell, in the order in which they are preferred among edges of equal cost.
+    ///
+    /// With affine gaps, the index of an edge is also the state of the cell it leads to.
+    const EDGES: [Self; 3] = [Self::Match, Self::GapInSecond, Self::GapInFirst];
+}
+
+/// The edge through which a state of a cell was reached, and the state of the predecessor cell.
+///
+/// A cell other than the root whose direction is [`Direction::Root`] is not reachable in this state.
+#[derive(Clone, Copy)]
+struct Step {
+    direction: Direction,
+    predecessor_state: u8,
+}
+
+/// Returns the amount of states of each cell, which is one for each kind of the last column if gaps are affine.
+fn state_amount(affine_gaps: bool) -> usize {
+    if affine_gaps {
+        Direction::EDGES.len()
+    } else {
+        1
+    }
+}
+
+/// Align two sequences with a dynamic programming algorithm in the style of Needleman-Wunsch, or of Gotoh if the metric has affine gaps.
+///
+/// This computes an alignment of the same cost as the general search,
+/// but without enumerating gap patterns and without the overhead of the open and closed lists.
+/// Each column is scored by the metric, just like in the general search.
+/// With affine gaps, each cell has a state for each kind of its last column, since whether a gap is opened depends on the previous column.
+/// Among multiple optimal alignments, a different one than in the general search may be returned.
+pub(super) fn align_two_sequences<
+    AlphabetType: Alphabet,
+    Cost: AStarCost,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized,
+    Metric: MultialignMetric<AlphabetType>,
+>(
+    sequences: &[&SequenceType],
+    mut metric: Metric,
+    segment: Segment<'_>,
+    cancellation: &CancellationToken,
+) -> Result<SearchOutput<Cost>>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    debug_assert_eq!(sequences.len(), 2);
+    let (first, second) = (sequences[0], sequences[1]);
+    let affine_gaps = metric.has_affine_gaps();
+    let states = state_amount(affine_gaps);
+    let width = second.len() + 1;
+    let cell_amount = (first.len() + 1)
+        .checked_mul(width)
+        .ok_or(MultialignError::MemoryLimit { bytes: usize::MAX })?;
+    let state_cell_amount = cell_amount
+        .checked_mul(states)
+        .ok_or(MultialignError::MemoryLimit { bytes: usize::MAX })?;
+
+    let mut costs = allocate_cells(Cost::zero(), state_cell_amount)?;
+    let mut steps = allocate_cells(
+        Step {
+            direction: Direction::Root,
+            predecessor_state: 0,
+        },
+        state_cell_amount,
+    )?;
+    let mut performance_counters = AStarPerformanceCounters::default();
+
+    let uses_offsets = metric.uses_offsets();
+    let free_leading_gaps = metric.has_free_end_gaps() && segment.is_start;
+    let free_trailing_gaps = metric.has_free_end_gaps() && segment.is_end;
+    let first_is_fragment = segment.full_length_sequence != 0;
+    let second_is_fragment = segment.full_length_sequence != 1;
+    let column_cost = |metric: &mut Metric,
+                       i: usize,
+                       j: usize,
+                       a: Option<usize>,
+                       b: Option<usize>,
+                       opens_gap: bool| {
+        metric.reset_character_counts();
+        metric.set_reference_offset(segment.start(0) + i);
+        if uses_offsets {
+            metric.set_offset(0, segment.start(0) + i);
+            metric.set_offset(1, segment.start(1) + j);
+        }
+        match a {
+            Some(a) => metric.count_character(&first[a], 0),
+            None if first_is_fragment
+                && ((free_leading_gaps && i == 0) || (free_trailing_gaps && i == first.len())) =>
+            {
+                metric.count_terminal_gap(0)
+            }
+            None if opens_gap => metric.count_gap_open(0),
+            None => metric.count_gap(0),
+        }
+        match b {
+            Some(b) => metric.count_character(&second[b], 1),
+            None if second_is_fragment
+                && ((free_leading_gaps && j == 0) || (free_trailing_gaps && j == second.len())) =>
+            {
+                metric.count_terminal_gap(1)
+            }
+            None if opens_gap => metric.count_gap_open(1),
+            None => metric.count_gap(1),
+        }
+        metric.compute_cost_increment::<Cost>()
+    };
+    // Only the state of the root is reachable in the root cell.
+    let is_reachable =
+        |steps: &[Step], index: usize| index == 0 || steps[index].direction != Direction::Root;
+
+    for i in 0..=first.len() {
+        if cancellation.is_cancelled() {
+            // The cells are not computed in order of cost, so there is no lower bound.
+            return Err(MultialignError::Cancelled {
+                closed_nodes: performance_counters.closed_nodes,
+                cost_lower_bound: None,
+            });
+        }
+
+        for j in 0..=second.len() {
+            if i == 0 && j == 0 {
+                continue;
+            }
+
+            for (edge, direction) in Direction::EDGES.into_iter().enumerate() {
+                let (predecessor_i, predecessor_j, a, b) = match direction {
+                    Direction::Match if i > 0 && j > 0 => (i - 1, j - 1, Some(i - 1), Some(j - 1)),
+                    Direction::GapInSecond if i > 0 => (i - 1, j, Some(i - 1), None),
+                    Direction::GapInFirst if j > 0 => (i, j - 1, None, Some(j - 1)),
+                    _ => continue,
+                };
+                let extension =
+                    column_cost(&mut metric, predecessor_i, predecessor_j, a, b, false)?;
+                // A gap is opened if the previous column has no gap in the same sequence, i.e. if its kind differs.
+                let opening = if affine_gaps && direction != Direction::Match {
+                    column_cost(&mut metric, predecessor_i, predecessor_j, a, b, true)?
+                } else {
+                    extension
+                };
+
+                let state = if affine_gaps { edge } else { 0 };
+                let index = (i * width + j) * states + state;
+                for predecessor_state in 0..states {
+                    let predecessor =
+                        (predecessor_i * width + predecessor_j) * states + predecessor_state;
+                    if !is_reachable(&steps, predecessor) {
+                        continue;
+                    }
+
+                    let increment = if predecessor_state == edge {
+                        extension
+                    } else {
+                        opening
+                    };
+                    let cost = costs[predecessor]
+                        .checked_add(&increment)
+                        .ok_or(MultialignError::CostOverflow)?;
+                    performance_counters.opened_nodes += 1;
+                    if !is_reachable(&steps, index) || cost < costs[index] {
+                        costs[index] = cost;
+                        steps[index] = Step {
+                            direction,
+                            predecessor_state: predecessor_state as u8,
+                        };
+                    }
+                }
+            }
+
+            performance_counters.closed_nodes += (0..states)
+                .filter(|state| is_reachable(&steps, (i * width + j) * states + state))
+                .count();
+        }
+    }
+
+    let last_cell = (cell_amount - 1) * states;
+    let (mut state, cost) = (0..states)
+        .filter(|state| is_reachable(&steps, last_cell + state))
+        .map(|state| (state, costs[last_cell + state]))
+        .min_by_key(|(_, cost)| *cost)
+        .expect("the last cell is reachable");
+
+    let mut path = AlignmentPath::new(2);
+    let (mut i, mut j) = (first.len(), second.len());
+    loop {
+        let step = steps[(i * width + j) * states + state];
+        match step.direction {
+            Direction::Root => break,
+            Direction::Match => {
+                i -= 1;
+                j -= 1;
+                path.push(0b11);
+            }
+            Direction::GapInSecond => {
+                i -= 1;
+                path.push(0b01);
+            }
+            Direction::GapInFirst => {
+                j -= 1;
+                path.push(0b10);
+            }
+        }
+        state = usize::from(step.predecessor_state);
+    }
+    path.reverse();
+
+    Ok(SearchOutput {
+        alignments: vec![(path, cost)],
+        cost_lower_bound: cost,
+        performance_counters,
+        search_graph: None,
+        details: Default::default(),
+    })
+}
+
+/// Returns true if the dynamic programming matrix of two sequences of the given lengths fits into the memory limit.
+///
+/// A limit of nodes is compared to the amount of states of the cells, and a limit of bytes to the memory of the cells.
+pub(super) fn fits_memory_limit<Cost>(
+    first_length: usize,
+    second_length: usize,
+    affine_gaps: bool,
+    memory_limit: Option<MemoryLimit>,
+) -> bool {
+    let Some(state_cell_amount) = first_length
+        .checked_add(1)
+        .zip(second_length.checked_add(1))
+        .and_then(|(height, width)| height.checked_mul(width))
+        .and_then(|cell_amount| cell_amount.checked_mul(state_amount(affine_gaps)))
+    else {
+        return false;
+    };
+    match memory_limit {
+        None => true,
+        Some(MemoryLimit::Nodes(nodes)) => state_cell_amount <= nodes,
+        Some(MemoryLimit::Bytes(bytes)) => state_cell_amount
+            .checked_mul(size_of::<Cost>() + size_of::<Step>())
+            .is_some_and(|cell_bytes| cell_bytes <= bytes),
+    }
+}
+
+/// Allocate a DP matrix, returning an error instead of aborting if the memory is not available.
+pub(super) fn allocate_cells<T: Clone>(value: T, cell_amount: usize) -> Result<Vec<T>> {
+    let mut cells = Vec::new();
+    cells
+        .try_reserve_exact(cell_amount)
+        .map_err(|_| MultialignError::MemoryLimit {
+            bytes: cell_amount.saturating_mul(size_of::<T>()),
+        })?;
+    cells.resize(cell_amount, value);
+    Ok(cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use compact_genome::{
+        implementation::{alphabets::dna_alphabet::DnaAlphabet, vec_sequence::VectorGenome},
+        interface::sequence::{GenomeSequence, OwnedGenomeSequence},
+    };
+    use generic_a_star::cost::I32Cost;
+
+    use super::{align_two_sequences, fits_memory_limit};
+    use crate::{
+        cancellation::CancellationToken,
+        full_length_sequence,
+        metric::{
+            affine_gap_metric::AffineGapMetric, end_gap_metric::EndGapMetric,
+            gap_profile_metric::GapProfileMetric, pairwise_match_metric::PairwiseMatchMetric,
+            soft_mask_metric::SoftMaskMetric, MultialignMetric,
+        },
+        multialign_astar_with_identifier,
+        path::verify_columns,
+        residue_columns, score_alignment,
+        spill::SpillOptions,
+        HeuristicKind, MemoryLimit, OpenListKind, SearchControl, SearchHeuristic, SearchMode,
+        Segment, VecIdentifier,
+    };
+
+    const PAIRS: [[&str; 2]; 6] = [
+        ["ACGT", "ACGT"],
+        ["ACGTACGT", "ACGACGTT"],
+        ["AAAACCCC", "CCCC"],
+        ["GATTACA", "TACAGATTACAGG"],
+        ["A", "TTTTTT"],
+        ["", "ACG"],
+    ];
+
+    /// Align the sequences with the dynamic programming and with the general A* search, and assert that both find the same cost.
+    ///
+    /// For whole sequences, the alignment of the dynamic programming is also rescored, to assert that its columns have the reported cost.
+    fn assert_same_cost<Metric: MultialignMetric<DnaAlphabet> + Clone + Send>(
+        sequences: [&str; 2],
+        metric: Metric,
+        starts: &[usize],
+        is_start: bool,
+        is_end: bool,
+    ) {
+        let genomes: Vec<_> = sequences
+            .iter()
+            .map(|sequence| {
+                VectorGenome::<DnaAlphabet>::from_slice_u8(sequence.as_bytes()).unwrap()
+            })
+            .collect();
+        let genomes: Vec<_> = genomes
+            .iter()
+            .map(|genome| genome.as_genome_subsequence())
+            .collect();
+        let segment = Segment {
+            starts,
+            is_start,
+            is_end,
+            full_length_sequence: full_length_sequence(
+                sequences.iter().map(|sequence| sequence.len()),
+            ),
+        };
+        let cancellation = CancellationToken::default();
+
+        let dynamic_programming = align_two_sequences::<_, I32Cost, _, _>(
+            &genomes,
+            metric.clone(),
+            segment,
+            &cancellation,
+        )
+        .unwrap();
+        let search = |search_mode, spill| {
+            multialign_astar_with_identifier::<_, _, VecIdentifier, _, I32Cost>(
+                &genomes,
+                metric.clone(),
+                segment,
+                search_mode,
+                SearchHeuristic {
+                    kind: HeuristicKind::Pairwise,
+                    weight: 1.0,
+                    gpu: false,
+                },
+                false,
+                SearchControl {
+                    cancellation: &cancellation,
+                    on_improvement: &mut |_, _, _, _| Ok(()),
+                    memory_limit: None,
+                    memory_limit_fallback: false,
+                    upper_bound_pruning: None,
+                    spill,
+                    band: None,
+                    tie_break: Default::default(),
+                    progress_interval: None,
+                    dominance_pruning: false,
+                },
+            )
+        };
+        let a_star = search(SearchMode::AStar(OpenListKind::Heap), None).unwrap();
+
+        let (path, cost) = &dynamic_programming.alignments[0];
+        assert_eq!(*cost, a_star.alignments[0].1, "{sequences:?}");
+        let spill = SpillOptions {
+            directory: env::temp_dir(),
+            cached_nodes: 2,
+        };
+        let spilling = search(
+            SearchMode::RederivePredecessors(OpenListKind::Heap),
+            Some(&spill),
+        )
+        .unwrap();
+        assert_eq!(*cost, spilling.alignments[0].1, "{sequences:?}");
+        verify_columns(
+            &path.columns().collect::<Vec<_>>(),
+            &[sequences[0].len(), sequences[1].len()],
+        )
+        .unwrap();
+        if starts.is_empty() && is_start && is_end {
+            let columns = residue_columns(path, &genomes);
+            let rescored: I32Cost = score_alignment(&columns, &mut metric.clone()).unwrap();
+            assert_eq!(rescored, *cost, "{sequences:?}");
+        }
+    }
+
+    #[test]
+    fn same_cost_as_a_star() {
+        for pair in PAIRS {
+            assert_same_cost(pair, PairwiseMatchMetric::new(2).unwrap(), &[], true, true);
+        }
+    }
+
+    #[test]
+    fn same_cost_as_a_star_with_free_end_gaps() {
+        for pair in PAIRS {
+            let metric = EndGapMetric::new(PairwiseMatchMetric::new(2).unwrap(), true);
+            assert_same_cost(pair, metric.clone(), &[], true, true);
+            // Between anchors, only the gaps at the ends of the input sequences are free.
+            assert_same_cost(pair, metric.clone(), &[3, 5], false, true);
+            assert_same_cost(pair, metric, &[3, 5], true, false);
+        }
+    }
+
+    #[test]
+    fn same_cost_as_a_star_with_affine_gaps() {
+        for pair in PAIRS {
+            let metric = AffineGapMetric::new(PairwiseMatchMetric::new(2).unwrap(), 3);
+            assert_same_cost(pair, metric.clone(), &[], true, true);
+            assert_same_cost(pair, metric.clone(), &[3, 5], false, false);
+            // Free end gaps never open an affine gap.
+            let metric = EndGapMetric::new(metric, true);
+            assert_same_cost(pair, metric.clone(), &[], true, true);
+            assert_same_cost(pair, metric.clone(), &[3, 5], false, true);
+            assert_same_cost(pair, metric, &[3, 5], true, false);
+        }
+    }
+
+    #[test]
+    fn same_cost_as_a_star_with_offsets() {
+        for pair in PAIRS {
+            // The masks cover the sequences of the pair also when they start at an offset within them.
+            let masks: Vec<_> = pair
+                .iter()
+                .map(|sequence| {
+                    (0..sequence.len() + 8)
+                        .map(|offset| offset % 3 == 0)
+                        .collect()
+                })
+                .collect();
+            let metric =
+                SoftMaskMetric::with_masks(PairwiseMatchMetric::new(2).unwrap(), masks, 0.5, 1)
+                    .unwrap();
+            assert_same_cost(pair, metric.clone(), &[], true, true);
+            assert_same_cost(pair, metric, &[2, 7], false, false);
+        }
+    }
+
+    #[test]
+    fn same_cost_as_a_star_with_gap_profile() {
+        for (index, pair) in PAIRS.into_iter().enumerate() {
+            let path = env::temp_dir().join(format!(
+                "multialign-test-gap-profile-{}-{index}.txt",
+                std::process::id()
+            ));
+            let penalties: Vec<_> = (0..=pair[0].len())
+                .map(|offset| (offset % 4).to_string())
+                .collect();
+            fs::write(&path, penalties.join(" ")).unwrap();
+            let metric = GapProfileMetric::from_file(
+                PairwiseMatchMetric::new(2).unwrap(),
+                &path,
+                pair[0].len(),
+            );
+            fs::remove_file(&path).unwrap();
+            assert_same_cost(pair, metric.unwrap(), &[], true, true);
+        }
+    }
+
+    #[test]
+    fn memory_limit_of_the_matrix() {
+        let cell_bytes = size_of::<I32Cost>() + size_of::<super::Step>();
+        for (affine_gaps, states) in [(false, 1), (true, 3)] {
+            let fits = |memory_limit| fits_memory_limit::<I32Cost>(3, 4, affine_gaps, memory_limit);
+            assert!(fits(None));
+            assert!(fits(Some(MemoryLimit::Nodes(20 * states))));
+            assert!(!fits(Some(MemoryLimit::Nodes(20 * states - 1))));
+            assert!(fits(Some(MemoryLimit::Bytes(20 * states * cell_bytes))));
+            assert!(!fits(Some(MemoryLimit::Bytes(
+                20 * states * cell_bytes - 1
+            ))));
+            assert!(!fits_memory_limit::<I32Cost>(
+                usize::MAX,
+                1,
+                affine_gaps,
+                None
+            ));
+        }
+    }
+}
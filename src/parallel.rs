@@ -0,0 +1,336 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Mutex,
+    },
+    thread,
+};
+
+use compact_genome::interface::{alphabet::Alphabet, sequence::GenomeSequence};
+use generic_a_star::{cost::AStarCost, AStarContext, AStarPerformanceCounters};
+use log::debug;
+
+use super::{
+    cancellation::CancellationToken,
+    error::{MultialignError, Result},
+    metric::MultialignMetric,
+    open_list::OpenList,
+    Context, MultialignCost, Node, NodeIdentifier, OpenListKind,
+};
+
+/// A hash-distributed A* search (HDA*), which searches with multiple threads.
+///
+/// Each node is owned by the thread selected by the hash of its identifier, which stores its cost and predecessor and closes it.
+/// The successors of a closed node are sent to the threads owning them, so the threads share no open or closed lists.
+/// Since the threads do not close nodes in a global order of priority, a node may be closed with a cost that is not optimal,
+/// in which case it is opened again once a cheaper path to it arrives.
+/// The search ends once no thread has an open node whose priority is lower than the cost of the cheapest target found, and no nodes are sent anymore.
+pub struct ParallelSearch<Identifier, Cost> {
+    thread_amount: usize,
+    open_list_kind: OpenListKind,
+    /// The cheapest known cost of each node reached by any thread, and the predecessor it was reached from.
+    costs: HashMap<Identifier, (Cost, Option<Identifier>)>,
+    performance_counters: AStarPerformanceCounters,
+}
+
+/// A message sent between the threads of a [`ParallelSearch`].
+enum Message<Identifier: NodeIdentifier, Cost> {
+    /// A node reached by the sending thread, which is owned by the receiving thread.
+    Node(Node<Identifier, Cost>),
+    /// The search has ended.
+    Stop,
+}
+
+/// The state shared by the threads of a [`ParallelSearch`].
+struct SharedState<'cancellation, Identifier: NodeIdentifier, Cost> {
+    senders: Vec<Sender<Message<Identifier, Cost>>>,
+    /// The amount of threads that are not idle plus the amount of nodes that were sent but not yet received.
+    ///
+    /// A thread that receives a node while idle counts itself before the node is uncounted, so this only reaches zero once the search has ended.
+    work: AtomicUsize,
+    /// The cheapest target closed by any thread, with its cost.
+    best_target: Mutex<Option<(Cost, Identifier)>>,
+    cancellation: &'cancellation CancellationToken,
+    cancelled: AtomicBool,
+}
+
+/// The result of a single thread of a [`ParallelSearch`].
+struct ThreadOutput<Identifier, Cost> {
+    costs: HashMap<Identifier, (Cost, Option<Identifier>)>,
+    performance_counters: AStarPerformanceCounters,
+    error: Option<MultialignError>,
+}
+
+impl<Identifier: NodeIdentifier, Cost: MultialignCost> ParallelSearch<Identifier, Cost>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    pub fn new(thread_amount: usize, open_list_kind: OpenListKind) -> Self {
+        Self {
+            thread_amount,
+            open_list_kind,
+            costs: Default::default(),
+            performance_counters: Default::default(),
+        }
+    }
+
+    pub fn into_performance_counters(self) -> AStarPerformanceCounters {
+        self.performance_counters
+    }
+
+    /// Search for a target node and return the edges of the path to it.
+    ///
+    /// The edges are ordered from the target to the root, like [`generic_a_star::AStar::backtrack`].
+    /// Returns [`MultialignError::Cancelled`] if the cancellation token is set, without a lower bound of the optimal cost,
+    /// since the threads do not know the priorities of the nodes that are sent to them.
+    pub fn search<
+        AlphabetType: Alphabet,
+        SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized + Sync,
+        Metric: MultialignMetric<AlphabetType> + Clone + Send,
+    >(
+        &mut self,
+        context: &mut Context<'_, AlphabetType, Cost, SequenceType, Identifier, Metric>,
+        cancellation: &CancellationToken,
+    ) -> Result<Option<Vec<Node<Identifier, Cost>>>> {
+        let (senders, receivers): (Vec<_>, Vec<_>) =
+            (0..self.thread_amount).map(|_| mpsc::channel()).unzip();
+        let shared = SharedState {
+            senders,
+            // The threads start as not idle, and sending the root counts it as well.
+            work: AtomicUsize::new(self.thread_amount),
+            best_target: Mutex::new(None),
+            cancellation,
+            cancelled: AtomicBool::new(false),
+        };
+        let root = context.create_root();
+        shared.send(owner(&root.identifier, self.thread_amount), root);
+
+        let outputs: Vec<_> = thread::scope(|scope| {
+            let handles: Vec<_> = receivers
+                .into_iter()
+                .enumerate()
+                .map(|(index, receiver)| {
+                    let context = context.clone();
+                    let shared = &shared;
+                    let open_list_kind = self.open_list_kind;
+                    scope.spawn(move || {
+                        search_thread(index, context, receiver, shared, open_list_kind)
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("Search thread panicked"))
+                .collect()
+        });
+
+        for output in outputs {
+            if let Some(error) = output.error {
+                return Err(error);
+            }
+            self.performance_counters.opened_nodes += output.performance_counters.opened_nodes;
+            self.performance_counters.suboptimal_opened_nodes +=
+                output.performance_counters.suboptimal_opened_nodes;
+            self.performance_counters.closed_nodes += output.performance_counters.closed_nodes;
+            self.costs.extend(output.costs);
+        }
+        if shared.cancelled.load(Ordering::Relaxed) {
+            return Err(MultialignError::Cancelled {
+                closed_nodes: self.performance_counters.closed_nodes,
+                cost_lower_bound: None,
+            });
+        }
+
+        let Some((_, target)) = shared.best_target.into_inner().unwrap() else {
+            return Ok(None);
+        };
+        let mut identifiers = vec![target];
+        while let Some(predecessor) = &self.costs[identifiers.last().unwrap()].1 {
+            identifiers.push(predecessor.clone());
+        }
+        // The root is not an edge.
+        identifiers.pop();
+
+        Ok(Some(context.edges_along(identifiers.into_iter().rev())))
+    }
+}
+
+impl<Identifier: NodeIdentifier, Cost: AStarCost> SharedState<'_, Identifier, Cost> {
+    fn send(&self, thread: usize, node: Node<Identifier, Cost>) {
+        self.work.fetch_add(1, Ordering::SeqCst);
+        // The receiving thread may have stopped already if the search was cancelled.
+        self.senders[thread].send(Message::Node(node)).ok();
+    }
+
+    /// Uncount a unit of work, and end the search if no work is left.
+    fn finish_work(&self) {
+        if self.work.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.stop();
+        }
+    }
+
+    fn stop(&self) {
+        for sender in &self.senders {
+            // A thread that stopped already may have dropped its receiver.
+            sender.send(Message::Stop).ok();
+        }
+    }
+
+    fn best_cost(&self) -> Option<Cost> {
+        self.best_target
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|(cost, _)| *cost)
+    }
+
+    /// Store the given target if it is cheaper than the best target.
+    fn offer_target(&self, cost: Cost, identifier: Identifier) {
+        let mut best_target = self.best_target.lock().unwrap();
+        if best_target
+            .as_ref()
+            .is_none_or(|(best_cost, _)| cost < *best_cost)
+        {
+            debug!("Found a target of cost {cost}");
+            *best_target = Some((cost, identifier));
+        }
+    }
+}
+
+/// Returns the index of the thread that owns the node with the given identifier.
+fn owner<Identifier: Hash>(identifier: &Identifier, thread_amount: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    identifier.hash(&mut hasher);
+    (hasher.finish() % thread_amount as u64) as usize
+}
+
+/// Open the given node if it is cheaper than any known path to it.
+fn open<Identifier: NodeIdentifier, Cost: AStarCost>(
+    costs: &mut HashMap<Identifier, (Cost, Option<Identifier>)>,
+    open_list: &mut OpenList<Cost, (Cost, Identifier)>,
+    node: Node<Identifier, Cost>,
+) {
+    if costs
+        .get(&node.identifier)
+        .is_none_or(|(cost, _)| node.cost < *cost)
+    {
+        open_list.push(node.priority(), (node.cost, node.identifier.clone()));
+        costs.insert(node.identifier, (node.cost, node.predecessor));
+    }
+}
+
+/// Close the nodes owned by the thread with the given index until the search ends.
+fn search_thread<
+    AlphabetType: Alphabet,
+    Cost: MultialignCost,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized,
+    Identifier: NodeIdentifier,
+    Metric: MultialignMetric<AlphabetType>,
+>(
+    index: usize,
+    mut context: Context<'_, AlphabetType, Cost, SequenceType, Identifier, Metric>,
+    receiver: Receiver<Message<Identifier, Cost>>,
+    shared: &SharedState<'_, Identifier, Cost>,
+    open_list_kind: OpenListKind,
+) -> ThreadOutput<Identifier, Cost>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    let thread_amount = shared.senders.len();
+    let mut costs = HashMap::new();
+    let mut open_list = OpenList::new(open_list_kind);
+    let mut performance_counters = AStarPerformanceCounters::default();
+    let mut successors = Vec::new();
+    let mut idle = false;
+
+    loop {
+        // Open the received nodes, waiting for them if there is nothing else to do.
+        loop {
+            let message = if idle {
+                receiver.recv().unwrap()
+            } else {
+                match receiver.try_recv() {
+                    Ok(message) => message,
+                    Err(_) => break,
+                }
+            };
+
+            match message {
+                Message::Node(node) => {
+                    if idle {
+                        shared.work.fetch_add(1, Ordering::SeqCst);
+                        idle = false;
+                    }
+                    open(&mut costs, &mut open_list, node);
+                    shared.finish_work();
+                }
+                Message::Stop => {
+                    return ThreadOutput {
+                        costs,
+                        performance_counters,
+                        error: context.error.take(),
+                    }
+                }
+            }
+        }
+
+        let Some((priority, (cost, identifier))) = open_list.pop() else {
+            idle = true;
+            shared.finish_work();
+            continue;
+        };
+        if costs[&identifier].0 != cost {
+            performance_counters.suboptimal_opened_nodes += 1;
+            continue;
+        }
+        if shared
+            .best_cost()
+            .is_some_and(|best_cost| best_cost <= priority)
+        {
+            // The cost of the best target only decreases, so none of the open nodes can lead to a cheaper one.
+            open_list.clear();
+            idle = true;
+            shared.finish_work();
+            continue;
+        }
+        if shared.cancellation.is_cancelled() {
+            shared.cancelled.store(true, Ordering::Relaxed);
+            shared.stop();
+            idle = true;
+            continue;
+        }
+        performance_counters.closed_nodes += 1;
+
+        let node = Node {
+            cost,
+            lower_bound: Cost::zero(),
+            identifier,
+            predecessor: None,
+            tie_breaker: 0,
+        };
+        if context.is_target(&node) {
+            shared.offer_target(node.cost, node.identifier);
+            continue;
+        }
+
+        successors.clear();
+        context.generate_successors(&node, &mut successors);
+        if context.error.is_some() {
+            shared.stop();
+            idle = true;
+            continue;
+        }
+        performance_counters.opened_nodes += successors.len();
+        for successor in successors.drain(..) {
+            let successor_owner = owner(&successor.identifier, thread_amount);
+            if successor_owner == index {
+                open(&mut costs, &mut open_list, successor);
+            } else {
+                shared.send(successor_owner, successor);
+            }
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use std::{iter::FusedIterator, slice};
+
+use compact_genome::interface::{alphabet::Alphabet, sequence::GenomeSequence};
+use generic_a_star::cost::I32Cost;
+
+use crate::{
+    error::{MultialignError, Result},
+    statistics::SearchStatistics,
+};
+
+/// The columns of an alignment, stored compactly as the set of sequences that have a residue in each column.
+///
+/// Each column takes a single word, independently of the amount of sequences.
+/// The columns themselves are reconstructed on demand with [`Self::columns`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AlignmentPath {
+    sequence_amount: usize,
+    /// For each column, bit `i` is set if sequence `i` has a residue in that column.
+    columns: Vec<usize>,
+}
+
+impl AlignmentPath {
+    /// Create a path without columns through the alignment graph of the given amount of sequences.
+    pub(crate) fn new(sequence_amount: usize) -> Self {
+        Self {
+            sequence_amount,
+            columns: Vec::new(),
+        }
+    }
+
+    /// The mask of a column in which all of the given amount of sequences have a residue.
+    pub(crate) fn full_column(sequence_amount: usize) -> usize {
+        debug_assert!(sequence_amount < usize::BITS.try_into().unwrap());
+        (1 << sequence_amount) - 1
+    }
+
+    pub(crate) fn push(&mut self, column: usize) {
+        self.columns.push(column);
+    }
+
+    pub(crate) fn reverse(&mut self) {
+        self.columns.reverse();
+    }
+
+    pub(crate) fn extend(&mut self, other: &Self) {
+        debug_assert_eq!(self.sequence_amount, other.sequence_amount);
+        self.columns.extend_from_slice(&other.columns);
+    }
+
+    /// Returns the amount of aligned sequences.
+    pub fn sequence_amount(&self) -> usize {
+        self.sequence_amount
+    }
+
+    /// Returns the amount of columns.
+    pub fn len(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// Returns true if the path has no columns.
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    /// Returns true if the given sequence has a residue in the given column.
+    pub fn has_residue(&self, column: usize, sequence: usize) -> bool {
+        self.columns[column] & (1 << sequence) != 0
+    }
+
+    /// Iterate over the columns of the alignment.
+    pub fn columns(&self) -> AlignmentColumnIter<'_> {
+        AlignmentColumnIter {
+            columns: self.columns.iter(),
+            offsets: vec![0; self.sequence_amount],
+        }
+    }
+}
+
+/// What a sequence contributes to a column of an alignment.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum AlignmentOperation {
+    /// The residue at the given offset of the sequence.
+    Residue(usize),
+    Gap,
+}
+
+impl AlignmentOperation {
+    /// Returns the offset of the residue, or `None` for a gap.
+    pub fn residue(self) -> Option<usize> {
+        match self {
+            Self::Residue(offset) => Some(offset),
+            Self::Gap => None,
+        }
+    }
+
+    pub fn is_gap(self) -> bool {
+        self == Self::Gap
+    }
+}
+
+/// A column of an alignment.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct AlignmentColumn {
+    /// One operation per sequence in input order.
+    pub operations: Vec<AlignmentOperation>,
+}
+
+impl AlignmentColumn {
+    /// Returns true if the given sequence has a gap in this column.
+    pub fn is_gap(&self, sequence: usize) -> bool {
+        self.operations[sequence].is_gap()
+    }
+
+    /// Look up the residues of this column in the given sequences, which must be the sequences that were aligned.
+    ///
+    /// Returns one entry per sequence, which is `None` if the sequence has a gap in this column.
+    pub fn residues<
+        AlphabetType: Alphabet,
+        SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized,
+    >(
+        &self,
+        sequences: &[&SequenceType],
+    ) -> Vec<Option<AlphabetType::CharacterType>> {
+        self.operations
+            .iter()
+            .zip(sequences)
+            .map(|(operation, sequence)| operation.residue().map(|offset| sequence[offset].clone()))
+            .collect()
+    }
+}
+
+/// Convert columns of residues, where gaps are `None`, into columns of operations.
+///
+/// The offsets of the residues are counted from the start of each row.
+pub fn operation_columns<CharacterType>(
+    columns: &[Vec<Option<CharacterType>>],
+) -> Vec<AlignmentColumn> {
+    let mut offsets = vec![0; columns.first().map(Vec::len).unwrap_or(0)];
+    columns
+        .iter()
+        .map(|column| AlignmentColumn {
+            operations: column
+                .iter()
+                .zip(&mut offsets)
+                .map(|(residue, offset)| match residue {
+                    Some(_) => {
+                        *offset += 1;
+                        AlignmentOperation::Residue(*offset - 1)
+                    }
+                    None => AlignmentOperation::Gap,
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Check that the columns are an alignment of sequences with the given lengths.
+///
+/// Each column must have one operation per sequence, and the residues of each sequence must appear exactly once and in order.
+/// Returns an [`MultialignError::InvalidAlignment`] otherwise.
+pub fn verify_columns(columns: &[AlignmentColumn], sequence_lengths: &[usize]) -> Result<()> {
+    let mut offsets = vec![0; sequence_lengths.len()];
+
+    for (index, column) in columns.iter().enumerate() {
+        if column.operations.len() != sequence_lengths.len() {
+            return Err(MultialignError::InvalidAlignment(format!(
+                "Column {index} has {} entries, but {} sequences are aligned",
+                column.operations.len(),
+                sequence_lengths.len()
+            )));
+        }
+
+        for (sequence, (operation, offset)) in
+            column.operations.iter().zip(&mut offsets).enumerate()
+        {
+            if let Some(residue) = operation.residue() {
+                if residue != *offset {
+                    return Err(MultialignError::InvalidAlignment(format!(
+                        "Column {index} contains residue {residue} of sequence {sequence}, but residue {offset} was expected"
+                    )));
+                }
+                *offset += 1;
+            }
+        }
+    }
+
+    for (sequence, (offset, length)) in offsets.iter().zip(sequence_lengths).enumerate() {
+        if offset != length {
+            return Err(MultialignError::InvalidAlignment(format!(
+                "The alignment contains {offset} residues of sequence {sequence}, but it has length {length}"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// An iterator over the columns of an [`AlignmentPath`].
+#[derive(Debug)]
+pub struct AlignmentColumnIter<'path> {
+    columns: slice::Iter<'path, usize>,
+    /// The offset of the next residue of each sequence.
+    offsets: Vec<usize>,
+}
+
+impl Iterator for AlignmentColumnIter<'_> {
+    type Item = AlignmentColumn;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let column = *self.columns.next()?;
+        let operations = self
+            .offsets
+            .iter_mut()
+            .enumerate()
+            .map(|(index, offset)| {
+                if column & (1 << index) != 0 {
+                    *offset += 1;
+                    AlignmentOperation::Residue(*offset - 1)
+                } else {
+                    AlignmentOperation::Gap
+                }
+            })
+            .collect();
+
+        Some(AlignmentColumn { operations })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.columns.size_hint()
+    }
+}
+
+impl ExactSizeIterator for AlignmentColumnIter<'_> {}
+
+impl FusedIterator for AlignmentColumnIter<'_> {}
+
+/// An alignment computed by [`align_streaming`](crate::align_streaming), whose columns are reconstructed on demand.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct StreamingAlignment<Cost = I32Cost> {
+    pub path: AlignmentPath,
+    /// The cost of the alignment under the metric used for aligning.
+    pub cost: Cost,
+    /// A lower bound of the cost of an optimal alignment, which is only lower than the cost if the search was weighted.
+    pub cost_lower_bound: Cost,
+    /// The statistics of the search that found the alignment.
+    pub search_statistics: SearchStatistics,
+}
+
+impl<Cost> StreamingAlignment<Cost> {
+    /// Iterate over the columns of the alignment, see [`AlignmentPath::columns`].
+    ///
+    /// The residues of each column can be looked up in the aligned sequences with [`AlignmentColumn::residues`].
+    pub fn columns(&self) -> AlignmentColumnIter<'_> {
+        self.path.columns()
+    }
+}
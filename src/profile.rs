@@ -0,0 +1,116 @@
+use std::{marker::PhantomData, path::Path};
+
+use compact_genome::interface::alphabet::{Alphabet, AlphabetCharacter};
+
+use super::{
+    aligned_fasta::{read_aligned_fasta_file, AlignedFastaRecord},
+    error::{MultialignError, Result},
+};
+
+/// A position-specific profile of an existing alignment of a sequence family.
+///
+/// The positions of the profile are the residues of the first record of the alignment, which is called the reference.
+/// For each position, the profile counts the characters and gaps of all records in the column of the reference residue.
+/// Columns where the reference has a gap are insertions relative to the reference, and only contribute to the background composition.
+#[derive(Debug, Clone)]
+pub struct Profile<AlphabetType> {
+    /// For each position, the count of each character, followed by the count of gaps.
+    counts: Vec<Vec<u32>>,
+    /// The count of each character over the whole alignment.
+    background_counts: Vec<u32>,
+    record_amount: u32,
+    phantom_data: PhantomData<AlphabetType>,
+}
+
+impl<AlphabetType: Alphabet> Profile<AlphabetType> {
+    /// Build the profile of the given aligned records, see [`Profile`].
+    ///
+    /// Returns an [`MultialignError::InvalidProfile`] if there are no records.
+    pub fn from_records(
+        records: &[AlignedFastaRecord<AlphabetType::CharacterType>],
+    ) -> Result<Self> {
+        let Some(reference) = records.first() else {
+            return Err(MultialignError::InvalidProfile(
+                "The profile alignment contains no records".to_string(),
+            ));
+        };
+        let record_amount = u32::try_from(records.len()).map_err(|_| {
+            MultialignError::InvalidProfile(format!(
+                "The profile alignment contains too many records: {}",
+                records.len()
+            ))
+        })?;
+
+        let size = usize::from(AlphabetType::SIZE);
+        let mut counts = Vec::new();
+        let mut background_counts = vec![0; size];
+        for (column, reference_entry) in reference.row.iter().enumerate() {
+            let mut column_counts = vec![0; size + 1];
+            for record in records {
+                let entry = record.row[column]
+                    .as_ref()
+                    .map(|character| usize::from(character.index()))
+                    .unwrap_or(size);
+                column_counts[entry] += 1;
+            }
+
+            for (background_count, count) in background_counts.iter_mut().zip(&column_counts) {
+                *background_count += count;
+            }
+            if reference_entry.is_some() {
+                counts.push(column_counts);
+            }
+        }
+
+        Ok(Self {
+            counts,
+            background_counts,
+            record_amount,
+            phantom_data: PhantomData,
+        })
+    }
+
+    /// Read an aligned fasta file and build its profile, see [`Profile`].
+    ///
+    /// If an ASCII index in `skip_characters` contains true, then that character is skipped (after capitalisation).
+    pub fn from_file(path: impl AsRef<Path>, skip_characters: &[bool]) -> Result<Self> {
+        Self::from_records(&read_aligned_fasta_file::<AlphabetType>(
+            path,
+            skip_characters,
+        )?)
+    }
+
+    /// The amount of positions, which is the length of the reference.
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// The frequency of the given character at the given position, or of gaps if the character is `None`.
+    ///
+    /// One pseudocount is added for each character and for gaps, such that no frequency is zero.
+    pub fn frequency(
+        &self,
+        position: usize,
+        character: Option<&AlphabetType::CharacterType>,
+    ) -> f64 {
+        let size = usize::from(AlphabetType::SIZE);
+        let entry = character
+            .map(|character| usize::from(character.index()))
+            .unwrap_or(size);
+        f64::from(self.counts[position][entry] + 1)
+            / (f64::from(self.record_amount) + 1.0 + f64::from(AlphabetType::SIZE))
+    }
+
+    /// The frequency of the given character over all residues of the alignment.
+    ///
+    /// One pseudocount is added for each character, such that no frequency is zero.
+    pub fn background_frequency(&self, character: &AlphabetType::CharacterType) -> f64 {
+        let residue_amount: u32 = self.background_counts.iter().sum();
+        f64::from(self.background_counts[usize::from(character.index())] + 1)
+            / (f64::from(residue_amount) + f64::from(AlphabetType::SIZE))
+    }
+}
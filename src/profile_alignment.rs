@@ -0,0 +1,289 @@
+use compact_genome::interface::alphabet::{Alphabet, AlphabetCharacter};
+use generic_a_star::{cost::AStarCost, AStarPerformanceCounters};
+
+use super::{
+    cancellation::CancellationToken,
+    error::{MultialignError, Result},
+    metric::MultialignMetric,
+    pairwise::allocate_cells,
+    AlignmentColumns,
+};
+
+/// An alignment of some of the rows of a larger alignment, which is aligned to other partial alignments as a whole.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PartialAlignment<CharacterType> {
+    /// The indices of the aligned rows in the larger alignment, in the order of the entries of each column.
+    ///
+    /// These are the sequence indices passed to the metric.
+    pub rows: Vec<usize>,
+    /// The columns of the alignment, with one entry per row, which is `None` for a gap.
+    pub columns: AlignmentColumns<CharacterType>,
+}
+
+impl<CharacterType: Clone> PartialAlignment<CharacterType> {
+    /// The alignment of a single sequence with itself, i.e. one column per residue, as the given row.
+    pub fn sequence(row: usize, residues: impl IntoIterator<Item = CharacterType>) -> Self {
+        Self {
+            rows: vec![row],
+            columns: residues
+                .into_iter()
+                .map(|residue| vec![Some(residue)])
+                .collect(),
+        }
+    }
+
+    /// The alignment of the given rows within the given columns of the larger alignment.
+    ///
+    /// Columns in which all of the given rows have a gap are removed.
+    pub fn project(columns: &[Vec<Option<CharacterType>>], rows: Vec<usize>) -> Self {
+        let columns = columns
+            .iter()
+            .map(|column| rows.iter().map(|row| column[*row].clone()).collect())
+            .filter(|column: &Vec<_>| column.iter().any(Option::is_some))
+            .collect();
+        Self { rows, columns }
+    }
+
+    /// The columns of this alignment with one entry per row of the larger alignment with the given amount of rows,
+    /// where the rows that are not part of this alignment have gaps.
+    pub fn into_columns(self, row_amount: usize) -> AlignmentColumns<CharacterType> {
+        self.columns
+            .into_iter()
+            .map(|column| {
+                let mut entries = vec![None; row_amount];
+                for (row, entry) in self.rows.iter().zip(column) {
+                    entries[*row] = entry;
+                }
+                entries
+            })
+            .collect()
+    }
+
+    /// For each column and after the last column, the amount of residues of each row before it.
+    fn offsets(&self) -> Vec<Vec<usize>> {
+        let mut offsets = vec![0; self.rows.len()];
+        let mut result = Vec::with_capacity(self.columns.len() + 1);
+        result.push(offsets.clone());
+        for column in &self.columns {
+            for (offset, entry) in offsets.iter_mut().zip(column) {
+                *offset += usize::from(entry.is_some());
+            }
+            result.push(offsets.clone());
+        }
+        result
+    }
+}
+
+/// The edge through which a cell of the dynamic programming matrix was reached.
+#[derive(Clone, Copy)]
+enum Direction {
+    Root,
+    /// A column of each partial alignment.
+    Both,
+    /// A column of the first partial alignment, with gaps in the rows of the second.
+    First,
+    /// A column of the second partial alignment, with gaps in the rows of the first.
+    Second,
+}
+
+/// Align two partial alignments of disjoint rows of an alignment with the given amount of rows to each other, keeping the columns of each.
+///
+/// Each column of the result consists of a column of either partial alignment or of both, which is scored by the metric,
+/// where the rows that are in neither partial alignment are counted as gaps, and columns with gaps only cost nothing.
+/// A dynamic program over the columns of both partial alignments then finds a merge of minimum cost.
+/// The offsets passed to the metric are counted from the start of each row of the partial alignments, and the reference is row zero.
+/// Since each column is scored on its own, gaps are always counted with [`MultialignMetric::count_gap`],
+/// so the cost of the merge only approximates the cost under metrics with affine gaps or free end gaps.
+///
+/// If the second partial alignment is a single row without gaps other than the reference and the metric does not use offsets,
+/// then the cost of each column only depends on the column of the first and the character of the second, and is computed only once.
+/// This makes aligning a single sequence to a large alignment much faster.
+///
+/// Returns the merged alignment of the rows of the first followed by the rows of the second, and its cost as computed by the dynamic program.
+pub fn align_profiles<
+    AlphabetType: Alphabet,
+    Cost: AStarCost,
+    Metric: MultialignMetric<AlphabetType>,
+>(
+    first: &PartialAlignment<AlphabetType::CharacterType>,
+    second: &PartialAlignment<AlphabetType::CharacterType>,
+    row_amount: usize,
+    metric: &mut Metric,
+    cancellation: &CancellationToken,
+    performance_counters: &mut AStarPerformanceCounters,
+) -> Result<(PartialAlignment<AlphabetType::CharacterType>, Cost)>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    let width = second.columns.len() + 1;
+    let cell_amount = (first.columns.len() + 1)
+        .checked_mul(width)
+        .ok_or(MultialignError::MemoryLimit { bytes: usize::MAX })?;
+    let mut costs = allocate_cells(Cost::zero(), cell_amount)?;
+    let mut directions = allocate_cells(Direction::Root, cell_amount)?;
+
+    let first_offsets = first.offsets();
+    let second_offsets = second.offsets();
+    let mut is_member = vec![false; row_amount];
+    for row in first.rows.iter().chain(&second.rows) {
+        is_member[*row] = true;
+    }
+    let uses_offsets = metric.uses_offsets();
+    let first_reference = first.rows.iter().position(|row| *row == 0);
+    let second_reference = second.rows.iter().position(|row| *row == 0);
+
+    // Counts the entries of the given partial alignment in the given column, or gaps if there is no column.
+    let count_entries =
+        |metric: &mut Metric,
+         alignment: &PartialAlignment<AlphabetType::CharacterType>,
+         offsets: &[usize],
+         column: Option<&Vec<Option<AlphabetType::CharacterType>>>| {
+            for (index, row) in alignment.rows.iter().copied().enumerate() {
+                if uses_offsets {
+                    metric.set_offset(row, offsets[index]);
+                }
+                match column.and_then(|column| column[index].as_ref()) {
+                    Some(character) => metric.count_character(character, row),
+                    None => metric.count_gap(row),
+                }
+            }
+        };
+    // The cost of the column of the given direction that leaves the cell of the given columns.
+    let mut compute_column_cost = |i: usize, j: usize, direction: Direction| -> Result<Cost> {
+        let (first_column, second_column) = match direction {
+            Direction::Both => (Some(&first.columns[i]), Some(&second.columns[j])),
+            Direction::First => (Some(&first.columns[i]), None),
+            Direction::Second => (None, Some(&second.columns[j])),
+            Direction::Root => unreachable!("The root has no column"),
+        };
+        if first_column
+            .into_iter()
+            .chain(second_column)
+            .flatten()
+            .all(Option::is_none)
+        {
+            return Ok(Cost::zero());
+        }
+
+        metric.reset_character_counts();
+        let reference_offset = first_reference
+            .map(|index| first_offsets[i][index])
+            .or_else(|| second_reference.map(|index| second_offsets[j][index]))
+            .unwrap_or(0);
+        metric.set_reference_offset(reference_offset);
+        count_entries(metric, first, &first_offsets[i], first_column);
+        count_entries(metric, second, &second_offsets[j], second_column);
+        for (row, is_member) in is_member.iter().enumerate() {
+            if !is_member {
+                metric.count_gap(row);
+            }
+        }
+        metric.compute_cost_increment()
+    };
+
+    // With a single row in the second alignment, the cost only depends on the column of the first and the character of the second.
+    let alphabet_size = usize::from(AlphabetType::SIZE);
+    let cache_by_character = second.rows.len() == 1
+        && second_reference.is_none()
+        && !uses_offsets
+        && second.columns.iter().all(|column| column[0].is_some());
+    let mut cached_costs: Vec<Option<Cost>> = if cache_by_character {
+        vec![None; (first.columns.len() + 1) * (2 * alphabet_size + 1)]
+    } else {
+        Vec::new()
+    };
+    let mut column_cost = |i: usize, j: usize, direction: Direction| -> Result<Cost> {
+        if !cache_by_character {
+            return compute_column_cost(i, j, direction);
+        }
+
+        let character = || usize::from(second.columns[j][0].as_ref().unwrap().index());
+        let cache_index = i * (2 * alphabet_size + 1)
+            + match direction {
+                Direction::Both => character(),
+                Direction::Second => alphabet_size + character(),
+                Direction::First => 2 * alphabet_size,
+                Direction::Root => unreachable!("The root has no column"),
+            };
+        match cached_costs[cache_index] {
+            Some(cost) => Ok(cost),
+            None => {
+                let cost = compute_column_cost(i, j, direction)?;
+                cached_costs[cache_index] = Some(cost);
+                Ok(cost)
+            }
+        }
+    };
+
+    for i in 0..=first.columns.len() {
+        if cancellation.is_cancelled() {
+            // The cells are not computed in order of cost, so there is no lower bound.
+            return Err(MultialignError::Cancelled {
+                closed_nodes: performance_counters.closed_nodes,
+                cost_lower_bound: None,
+            });
+        }
+
+        for j in 0..=second.columns.len() {
+            if i == 0 && j == 0 {
+                continue;
+            }
+
+            let mut best: Option<(Cost, Direction)> = None;
+            for (predecessor_i, predecessor_j, direction) in [
+                (i > 0 && j > 0).then(|| (i - 1, j - 1, Direction::Both)),
+                (i > 0).then(|| (i - 1, j, Direction::First)),
+                (j > 0).then(|| (i, j - 1, Direction::Second)),
+            ]
+            .into_iter()
+            .flatten()
+            {
+                let cost = costs[predecessor_i * width + predecessor_j]
+                    .checked_add(&column_cost(predecessor_i, predecessor_j, direction)?)
+                    .ok_or(MultialignError::CostOverflow)?;
+                if best.is_none_or(|(best_cost, _)| cost < best_cost) {
+                    best = Some((cost, direction));
+                }
+                performance_counters.opened_nodes += 1;
+            }
+
+            let (cost, direction) = best.unwrap();
+            costs[i * width + j] = cost;
+            directions[i * width + j] = direction;
+            performance_counters.closed_nodes += 1;
+        }
+    }
+
+    let mut columns = Vec::new();
+    let (mut i, mut j) = (first.columns.len(), second.columns.len());
+    loop {
+        let (first_column, second_column) = match directions[i * width + j] {
+            Direction::Root => break,
+            Direction::Both => {
+                i -= 1;
+                j -= 1;
+                (first.columns[i].clone(), second.columns[j].clone())
+            }
+            Direction::First => {
+                i -= 1;
+                (first.columns[i].clone(), vec![None; second.rows.len()])
+            }
+            Direction::Second => {
+                j -= 1;
+                (vec![None; first.rows.len()], second.columns[j].clone())
+            }
+        };
+        let mut column = first_column;
+        column.extend(second_column);
+        columns.push(column);
+    }
+    columns.reverse();
+
+    Ok((
+        PartialAlignment {
+            rows: first.rows.iter().chain(&second.rows).copied().collect(),
+            columns,
+        },
+        costs[cell_amount - 1],
+    ))
+}
@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+// `std::time::Instant` is not implemented on `wasm32-unknown-unknown`.
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+use log::info;
+
+/// Logs the progress of a search periodically, see [`MultialignOptions::progress_interval`](crate::MultialignOptions::progress_interval).
+///
+/// The progress is recorded whenever the successors of a node are generated, which all searches do once per closed node.
+/// Compared to generating the successors, checking the time is cheap, so it is checked for each node.
+pub(crate) struct Progress {
+    interval: Duration,
+    start: Instant,
+    last_report: Instant,
+    /// The closed nodes at the time of the last report.
+    last_closed_nodes: usize,
+    closed_nodes: usize,
+    opened_nodes: usize,
+    /// The priority of the root, where the search starts.
+    root_priority: f64,
+    /// The cost of a known alignment, where the search ends at the latest.
+    cost_upper_bound: Option<f64>,
+}
+
+impl Progress {
+    pub(crate) fn new(
+        interval: Duration,
+        root_priority: f64,
+        cost_upper_bound: Option<f64>,
+    ) -> Self {
+        let start = Instant::now();
+        Self {
+            interval,
+            start,
+            last_report: start,
+            last_closed_nodes: 0,
+            closed_nodes: 0,
+            opened_nodes: 0,
+            root_priority,
+            cost_upper_bound,
+        }
+    }
+
+    pub(crate) fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Record that a node of the given priority was closed and the given amount of successors was opened,
+    /// and log the progress if the interval has passed since the last report.
+    pub(crate) fn record(&mut self, priority: f64, successor_amount: usize) {
+        self.closed_nodes += 1;
+        self.opened_nodes += successor_amount;
+        let now = Instant::now();
+        if now - self.last_report < self.interval {
+            return;
+        }
+
+        let elapsed = now - self.start;
+        let rate = (self.closed_nodes - self.last_closed_nodes) as f64
+            / (now - self.last_report).as_secs_f64();
+        // Assuming that the priority keeps increasing at the same speed, which underestimates the time if the search space widens.
+        let eta = self.cost_upper_bound.and_then(|cost_upper_bound| {
+            let progress = priority - self.root_priority;
+            (progress > 0.0)
+                .then(|| elapsed.mul_f64(((cost_upper_bound - priority) / progress).max(0.0)))
+        });
+
+        info!(
+            "Closed {} nodes ({rate:.0}/s), opened {} nodes, {} of them not closed, at priority {priority} (root {}){} after {:.0}s",
+            self.closed_nodes,
+            self.opened_nodes,
+            self.opened_nodes.saturating_sub(self.closed_nodes),
+            self.root_priority,
+            eta.map(|eta| format!(", ETA {:.0}s", eta.as_secs_f64()))
+                .unwrap_or_default(),
+            elapsed.as_secs_f64(),
+        );
+        self.last_report = now;
+        self.last_closed_nodes = self.closed_nodes;
+    }
+}
@@ -0,0 +1,96 @@
+use compact_genome::interface::{alphabet::Alphabet, sequence::GenomeSequence};
+use generic_a_star::{cost::AStarCost, AStarPerformanceCounters};
+
+use super::{
+    cancellation::CancellationToken,
+    error::Result,
+    metric::MultialignMetric,
+    profile_alignment::{align_profiles, PartialAlignment},
+    tree::GuideTree,
+    AlignmentColumns,
+};
+
+/// Align the sequences progressively along the guide tree, whose leaves must contain each sequence exactly once.
+///
+/// From the leaves to the root, the alignments of the two subtrees of each inner node are aligned to each other with [`align_profiles`].
+///
+/// Returns the columns of the alignment with one entry per sequence in input order, and the amount of computed cells as performance counters.
+pub(super) fn progressive_alignment<
+    AlphabetType: Alphabet,
+    Cost: AStarCost,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized,
+    Metric: MultialignMetric<AlphabetType>,
+>(
+    sequences: &[&SequenceType],
+    guide_tree: &GuideTree,
+    metric: &mut Metric,
+    cancellation: &CancellationToken,
+) -> Result<(
+    AlignmentColumns<AlphabetType::CharacterType>,
+    AStarPerformanceCounters,
+)>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    let mut performance_counters = AStarPerformanceCounters::default();
+    let alignment = align_subtree::<_, Cost, _, _>(
+        sequences,
+        guide_tree,
+        metric,
+        cancellation,
+        &mut performance_counters,
+    )?;
+
+    Ok((
+        alignment.into_columns(sequences.len()),
+        performance_counters,
+    ))
+}
+
+fn align_subtree<
+    AlphabetType: Alphabet,
+    Cost: AStarCost,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized,
+    Metric: MultialignMetric<AlphabetType>,
+>(
+    sequences: &[&SequenceType],
+    tree: &GuideTree,
+    metric: &mut Metric,
+    cancellation: &CancellationToken,
+    performance_counters: &mut AStarPerformanceCounters,
+) -> Result<PartialAlignment<AlphabetType::CharacterType>>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    match tree {
+        GuideTree::Leaf { sequence_index } => Ok(PartialAlignment::sequence(
+            *sequence_index,
+            sequences[*sequence_index].iter().cloned(),
+        )),
+        GuideTree::Inner { children } => {
+            let first = align_subtree::<_, Cost, _, _>(
+                sequences,
+                &children[0].0,
+                metric,
+                cancellation,
+                performance_counters,
+            )?;
+            let second = align_subtree::<_, Cost, _, _>(
+                sequences,
+                &children[1].0,
+                metric,
+                cancellation,
+                performance_counters,
+            )?;
+            let (alignment, _) = align_profiles::<_, Cost, _>(
+                &first,
+                &second,
+                sequences.len(),
+                metric,
+                cancellation,
+                performance_counters,
+            )?;
+            Ok(alignment)
+        }
+    }
+}
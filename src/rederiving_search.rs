@@ -0,0 +1,192 @@
+use generic_a_star::{cost::AStarCost, AStarContext, AStarPerformanceCounters};
+
+use super::{
+    cancellation::CancellationToken,
+    closed_list::ClosedList,
+    error::{MultialignError, Result},
+    open_list::NodeOpenList,
+    Node, NodeIdentifier,
+};
+
+/// A best-first search that stores only the cost of closed nodes, but not their predecessors.
+///
+/// Compared to [`generic_a_star::AStar`], the closed list stores roughly half the amount of data per node,
+/// and the open list does not store predecessors at all.
+/// The predecessors of the nodes on the optimal path are re-derived during backtracking,
+/// by regenerating the successors of all closed nodes that may be a predecessor.
+/// This trades additional runtime for less memory per node, but every closed node is still kept,
+/// so the memory consumption grows like that of [`generic_a_star::AStar`].
+/// The open list has no index of its nodes, so a node reached on several paths before it is closed is queued once per path,
+/// but successors that are already closed are not queued again.
+/// The open and closed list may additionally be written to disk, see [`NodeOpenList`] and [`ClosedList`].
+/// Like the other searches, nodes are closed in order of their cost plus their lower bound, see [`Node::priority`].
+pub struct RederivingSearch<Identifier, Cost> {
+    closed_list: ClosedList<Identifier, Cost>,
+    /// The open nodes with their costs, ordered by their priorities.
+    open_list: NodeOpenList<Identifier, Cost>,
+    performance_counters: AStarPerformanceCounters,
+}
+
+impl<Identifier: NodeIdentifier, Cost: AStarCost> RederivingSearch<Identifier, Cost> {
+    pub fn new(
+        open_list: NodeOpenList<Identifier, Cost>,
+        closed_list: ClosedList<Identifier, Cost>,
+    ) -> Self {
+        Self {
+            closed_list,
+            open_list,
+            performance_counters: Default::default(),
+        }
+    }
+
+    pub fn into_performance_counters(self) -> AStarPerformanceCounters {
+        self.performance_counters
+    }
+
+    /// Search for a target node and return its identifier and cost.
+    ///
+    /// Like [`generic_a_star::AStar`], a node that has already been closed is never closed again.
+    /// Returns [`MultialignError::Cancelled`] if the cancellation token is set.
+    pub fn search<Context: AStarContext<Node = Node<Identifier, Cost>>>(
+        &mut self,
+        context: &mut Context,
+        cancellation: &CancellationToken,
+    ) -> Result<Option<(Identifier, Cost)>> {
+        let root = context.create_root();
+        self.open_list
+            .push(root.priority(), root.cost, root.identifier)?;
+        let mut successors = Vec::new();
+
+        while let Some((priority, (cost, identifier))) = self.open_list.pop()? {
+            if self.closed_list.contains(&identifier)? {
+                self.performance_counters.suboptimal_opened_nodes += 1;
+                continue;
+            }
+            if cancellation.is_cancelled() {
+                self.open_list.clear();
+                return Err(MultialignError::Cancelled {
+                    closed_nodes: self.performance_counters.closed_nodes,
+                    cost_lower_bound: Some(priority.as_u64()),
+                });
+            }
+
+            let node = Node {
+                cost,
+                lower_bound: Cost::zero(),
+                identifier,
+                predecessor: None,
+                tie_breaker: 0,
+            };
+
+            successors.clear();
+            context.generate_successors(&node, &mut successors);
+            self.performance_counters.opened_nodes += successors.len();
+            for successor in successors.drain(..) {
+                if self.closed_list.contains(&successor.identifier)? {
+                    continue;
+                }
+                self.open_list
+                    .push(successor.priority(), successor.cost, successor.identifier)?;
+            }
+
+            self.closed_list
+                .insert(node.identifier.clone(), node.cost)?;
+            self.performance_counters.closed_nodes += 1;
+
+            if context.is_target(&node) {
+                self.open_list.clear();
+                return Ok(Some((node.identifier, node.cost)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Backtrack from the given closed node to the root.
+    ///
+    /// The returned edges are ordered from the given node to the root, like [`generic_a_star::AStar::backtrack`].
+    /// With `affine_gaps`, the sequences that advanced are known from the gaps of each node,
+    /// but the predecessor may have any gaps, so all of them are tried.
+    pub fn backtrack<Context: AStarContext<Node = Node<Identifier, Cost>>>(
+        &mut self,
+        context: &mut Context,
+        target: Identifier,
+        sequence_amount: usize,
+        affine_gaps: bool,
+    ) -> Result<Vec<Node<Identifier, Cost>>> {
+        let root = context.create_root().identifier;
+        let mut edges = Vec::new();
+        let mut current = Node {
+            cost: self.closed_list.get(&target)?.unwrap(),
+            lower_bound: Cost::zero(),
+            identifier: target,
+            predecessor: None,
+            tie_breaker: 0,
+        };
+        let mut successors = Vec::new();
+
+        while current.identifier != root {
+            let all_sequences = (1usize << sequence_amount) - 1;
+            let (residue_masks, gap_masks) = if affine_gaps {
+                let residues = all_sequences & !current.identifier.gaps();
+                (residues..=residues, 0..=all_sequences)
+            } else {
+                (1..=all_sequences, 0..=0)
+            };
+
+            let mut found = None;
+            // The closed list may need to read from disk, so it is queried in a loop rather than in an iterator chain.
+            'masks: for residues in residue_masks {
+                for gaps in gap_masks.clone() {
+                    let mut predecessor = current.identifier.clone();
+                    predecessor.set_gaps(gaps);
+                    if (0..sequence_amount)
+                        .any(|index| residues & (1 << index) != 0 && predecessor.offset(index) == 0)
+                    {
+                        continue;
+                    }
+                    for index in 0..sequence_amount {
+                        if residues & (1 << index) != 0 {
+                            predecessor.decrement(index);
+                        }
+                    }
+
+                    let Some(cost) = self.closed_list.get(&predecessor)? else {
+                        continue;
+                    };
+                    successors.clear();
+                    context.generate_successors(
+                        &Node {
+                            cost,
+                            lower_bound: Cost::zero(),
+                            identifier: predecessor.clone(),
+                            predecessor: None,
+                            tie_breaker: 0,
+                        },
+                        &mut successors,
+                    );
+                    if successors.iter().any(|successor| {
+                        successor.identifier == current.identifier && successor.cost == current.cost
+                    }) {
+                        found = Some((predecessor, cost));
+                        break 'masks;
+                    }
+                }
+            }
+            let predecessor =
+                found.expect("Every closed node except the root has a closed predecessor");
+
+            current.predecessor = Some(predecessor.0.clone());
+            edges.push(current);
+            current = Node {
+                cost: predecessor.1,
+                lower_bound: Cost::zero(),
+                identifier: predecessor.0,
+                predecessor: None,
+                tie_breaker: 0,
+            };
+        }
+
+        Ok(edges)
+    }
+}
@@ -0,0 +1,117 @@
+use compact_genome::interface::alphabet::Alphabet;
+use generic_a_star::{cost::AStarCost, AStarPerformanceCounters};
+use log::{debug, info};
+
+use super::{
+    cancellation::CancellationToken,
+    error::{MultialignError, Result},
+    metric::MultialignMetric,
+    profile_alignment::{align_profiles, PartialAlignment},
+    score_alignment,
+    tree::GuideTree,
+    AlignmentColumns,
+};
+
+/// Refine the alignment with the given columns and cost by realigning groups of sequences to each other, see [`MultialignOptions::refine`](crate::MultialignOptions::refine).
+///
+/// Each branch of the guide tree partitions the sequences into the leaves below it and all others.
+/// In each of the given amount of rounds, the alignment is projected onto both groups of each partition,
+/// where columns with gaps only are removed, and the two projections are realigned to each other like in a progressive alignment.
+/// A realignment is kept if it costs less than the current alignment under the metric.
+/// The refinement stops early after a round without improvement or when the cancellation token is set.
+///
+/// Returns the refined columns, which are the given columns if no realignment was cheaper, together with their cost.
+pub(super) fn refine_alignment<
+    AlphabetType: Alphabet,
+    Cost: AStarCost,
+    Metric: MultialignMetric<AlphabetType>,
+>(
+    mut columns: AlignmentColumns<AlphabetType::CharacterType>,
+    mut cost: Cost,
+    guide_tree: &GuideTree,
+    rounds: usize,
+    metric: &mut Metric,
+    cancellation: &CancellationToken,
+) -> Result<(AlignmentColumns<AlphabetType::CharacterType>, Cost)>
+where
+    Cost::CostType: TryFrom<i32>,
+{
+    let sequence_amount = columns.first().map(Vec::len).unwrap_or(0);
+    let partitions = partitions(guide_tree, sequence_amount);
+    let mut performance_counters = AStarPerformanceCounters::default();
+
+    'rounds: for round in 0..rounds {
+        let mut improvements = 0;
+
+        for (first, second) in &partitions {
+            if cancellation.is_cancelled() {
+                info!("Stopping the refinement, since it was cancelled");
+                break 'rounds;
+            }
+
+            let realigned = match align_profiles::<_, Cost, _>(
+                &PartialAlignment::project(&columns, first.clone()),
+                &PartialAlignment::project(&columns, second.clone()),
+                sequence_amount,
+                metric,
+                cancellation,
+                &mut performance_counters,
+            ) {
+                Err(MultialignError::Cancelled { .. }) => {
+                    info!("Stopping the refinement, since it was cancelled");
+                    break 'rounds;
+                }
+                realigned => realigned?.0.into_columns(sequence_amount),
+            };
+            // The dynamic program only approximates the cost under some metrics.
+            let realigned_cost: Cost = score_alignment(&realigned, metric)?;
+
+            if realigned_cost < cost {
+                columns = realigned;
+                cost = realigned_cost;
+                improvements += 1;
+            }
+        }
+
+        info!(
+            "Refinement round {} improved the alignment {improvements} times to cost {cost}",
+            round + 1
+        );
+        if improvements == 0 {
+            break;
+        }
+    }
+
+    debug!("Refinement performance: {performance_counters:?}");
+    Ok((columns, cost))
+}
+
+/// The partitions of the given amount of sequences into two non-empty groups induced by the branches of the guide tree.
+///
+/// The two branches below the root induce the same partition, so it is only returned once.
+fn partitions(guide_tree: &GuideTree, sequence_amount: usize) -> Vec<(Vec<usize>, Vec<usize>)> {
+    let mut partitions = Vec::new();
+    let mut stack = match guide_tree {
+        GuideTree::Leaf { .. } => Vec::new(),
+        GuideTree::Inner { children } => vec![&children[0].0, &children[1].0],
+    };
+
+    while let Some(node) = stack.pop() {
+        let mut is_below = vec![false; sequence_amount];
+        for sequence_index in node.leaf_order() {
+            is_below[sequence_index] = true;
+        }
+        let (below, above): (Vec<_>, Vec<_>) =
+            (0..sequence_amount).partition(|sequence_index| is_below[*sequence_index]);
+        if !above.is_empty() && !partitions.contains(&(above.clone(), below.clone())) {
+            partitions.push((below, above));
+        }
+
+        if let GuideTree::Inner { children } = node {
+            stack.push(&children[0].0);
+            stack.push(&children[1].0);
+        }
+    }
+
+    partitions
+}
@@ -0,0 +1,158 @@
+use std::time::Duration;
+
+/// A hasher whose output is stable across platforms, compiler versions and runs.
+///
+/// This implements the 64-bit FNV-1a hash.
+/// Values are hashed through [`StableHash`], which defines their bytes explicitly,
+/// instead of through [`Hash`](std::hash::Hash), whose output may change between compiler versions.
+pub struct StableHasher {
+    state: u64,
+}
+
+impl StableHasher {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    pub fn new() -> Self {
+        Self {
+            state: Self::OFFSET_BASIS,
+        }
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.state
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.state ^= u64::from(*byte);
+            self.state = self.state.wrapping_mul(Self::PRIME);
+        }
+    }
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A value with an explicit byte encoding for a [`StableHasher`].
+///
+/// Integers are written in little-endian byte order, with `usize` widened to 64 bits, and floats as their bits.
+/// Strings and slices are prefixed with their length, and options with a tag byte, such that consecutive values cannot be confused.
+pub trait StableHash {
+    fn stable_hash(&self, hasher: &mut StableHasher);
+}
+
+macro_rules! impl_stable_hash_for_integers {
+    ($($integer:ty),*) => {
+        $(
+            impl StableHash for $integer {
+                fn stable_hash(&self, hasher: &mut StableHasher) {
+                    hasher.write(&self.to_le_bytes());
+                }
+            }
+        )*
+    };
+}
+
+impl_stable_hash_for_integers!(u8, u16, u32, u64, i32, i64);
+
+impl StableHash for usize {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        (*self as u64).stable_hash(hasher);
+    }
+}
+
+impl StableHash for bool {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        u8::from(*self).stable_hash(hasher);
+    }
+}
+
+impl StableHash for char {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        u32::from(*self).stable_hash(hasher);
+    }
+}
+
+impl StableHash for f64 {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        self.to_bits().stable_hash(hasher);
+    }
+}
+
+impl StableHash for Duration {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        self.as_secs().stable_hash(hasher);
+        self.subsec_nanos().stable_hash(hasher);
+    }
+}
+
+impl StableHash for str {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        self.as_bytes().stable_hash(hasher);
+    }
+}
+
+impl StableHash for String {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        self.as_str().stable_hash(hasher);
+    }
+}
+
+impl<T: StableHash> StableHash for [T] {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        self.len().stable_hash(hasher);
+        for value in self {
+            value.stable_hash(hasher);
+        }
+    }
+}
+
+impl<T: StableHash> StableHash for Vec<T> {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        self.as_slice().stable_hash(hasher);
+    }
+}
+
+impl<T: StableHash> StableHash for Option<T> {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        match self {
+            None => 0u8.stable_hash(hasher),
+            Some(value) => {
+                1u8.stable_hash(hasher);
+                value.stable_hash(hasher);
+            }
+        }
+    }
+}
+
+impl<T: StableHash + ?Sized> StableHash for &T {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        (**self).stable_hash(hasher);
+    }
+}
+
+/// Compute a stable hash of the given alignment columns.
+///
+/// Gaps are hashed independently of the characters used to render them.
+pub fn alignment_hash<CharacterType: Clone + Into<char>>(
+    columns: &[Vec<Option<CharacterType>>],
+) -> u64 {
+    let mut hasher = StableHasher::new();
+    columns.len().stable_hash(&mut hasher);
+
+    for column in columns {
+        column.len().stable_hash(&mut hasher);
+        for character in column {
+            character
+                .clone()
+                .map(Into::<char>::into)
+                .stable_hash(&mut hasher);
+        }
+    }
+
+    hasher.finish()
+}
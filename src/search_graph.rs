@@ -0,0 +1,150 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use generic_a_star::cost::{AStarCost, I32Cost};
+use log::info;
+
+use super::error::{MultialignError, Result};
+
+/// Where and how to dump the search graph explored by the A* search.
+#[derive(Debug, Clone)]
+pub struct SearchGraphDump {
+    /// The DOT file the search graph is written to.
+    pub path: PathBuf,
+    /// If set, only nodes whose offsets differ by at most this amount in each sequence from a node on the optimal path are written.
+    pub max_distance_to_optimal_path: Option<usize>,
+}
+
+/// A node closed by the A* search.
+#[derive(Debug, Clone)]
+pub struct SearchGraphNode<Cost = I32Cost> {
+    /// The offset into each sequence.
+    pub offsets: Vec<usize>,
+    /// The cost from the root, i.e. the g value.
+    pub cost: Cost,
+    /// The A* lower bound of the cost to a target, i.e. the h value.
+    pub lower_bound: Cost,
+    /// The offsets of the predecessor on the cheapest known path from the root.
+    pub predecessor: Option<Vec<usize>>,
+    pub on_optimal_path: bool,
+}
+
+/// The nodes closed by the A* search, in the order they were closed.
+#[derive(Debug, Clone, Default)]
+pub struct SearchGraph<Cost = I32Cost> {
+    pub nodes: Vec<SearchGraphNode<Cost>>,
+}
+
+/// Write the search graph in DOT format.
+///
+/// Each node is labelled with its offsets and its g, h and f values, and each edge leads from the predecessor of a node to the node and is labelled with its cost.
+/// The nodes and edges of the optimal path are highlighted.
+pub fn write_search_graph<Cost: AStarCost>(
+    dump: &SearchGraphDump,
+    graph: &SearchGraph<Cost>,
+) -> Result<()> {
+    let path = &dump.path;
+    let write_error = |source| MultialignError::Write {
+        path: path.to_owned(),
+        source,
+    };
+
+    let optimal_path: Vec<_> = graph
+        .nodes
+        .iter()
+        .filter(|node| node.on_optimal_path)
+        .collect();
+    let nodes: Vec<_> = graph
+        .nodes
+        .iter()
+        .filter(|node| {
+            dump.max_distance_to_optimal_path
+                .is_none_or(|max_distance| {
+                    optimal_path
+                        .iter()
+                        .any(|other| distance(&node.offsets, &other.offsets) <= max_distance)
+                })
+        })
+        .collect();
+    info!(
+        "Writing {} of {} closed nodes of the search graph to {path:?}",
+        nodes.len(),
+        graph.nodes.len()
+    );
+
+    write_dot(path, &nodes).map_err(write_error)
+}
+
+fn write_dot<Cost: AStarCost>(
+    path: &Path,
+    nodes: &[&SearchGraphNode<Cost>],
+) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    let name = |offsets: &[usize]| {
+        offsets
+            .iter()
+            .map(usize::to_string)
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    let highlight = |on_optimal_path: bool| {
+        if on_optimal_path {
+            ", color=red, penwidth=2"
+        } else {
+            ""
+        }
+    };
+
+    writeln!(writer, "digraph search_graph {{")?;
+    writeln!(writer, "    node [shape=box, fontname=monospace];")?;
+
+    for node in nodes {
+        writeln!(
+            writer,
+            "    \"{}\" [label=\"({})\\ng={} h={} f={}\"{}];",
+            name(&node.offsets),
+            name(&node.offsets),
+            node.cost,
+            node.lower_bound,
+            node.cost + node.lower_bound,
+            highlight(node.on_optimal_path),
+        )?;
+    }
+
+    let written_nodes: HashMap<_, _> = nodes.iter().map(|node| (&node.offsets, node)).collect();
+    for node in nodes {
+        let Some(predecessor) = node
+            .predecessor
+            .as_ref()
+            .and_then(|predecessor| written_nodes.get(predecessor))
+        else {
+            continue;
+        };
+
+        writeln!(
+            writer,
+            "    \"{}\" -> \"{}\" [label=\"{}\"{}];",
+            name(&predecessor.offsets),
+            name(&node.offsets),
+            node.cost - predecessor.cost,
+            highlight(node.on_optimal_path),
+        )?;
+    }
+
+    writeln!(writer, "}}")?;
+    writer.flush()
+}
+
+/// The maximum difference of the offsets in any sequence.
+fn distance(first: &[usize], second: &[usize]) -> usize {
+    first
+        .iter()
+        .zip(second)
+        .map(|(first, second)| first.abs_diff(*second))
+        .max()
+        .unwrap_or(0)
+}
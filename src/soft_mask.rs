@@ -0,0 +1,70 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use flate2::read::MultiGzDecoder;
+use log::info;
+
+use super::error::{MultialignError, Result};
+
+/// Returns which residues of the given raw sequence are soft-masked, i.e. written in lower case.
+///
+/// Whitespace is ignored, and if an ASCII index in `skip_characters` contains true, then that character is skipped (after capitalisation),
+/// such that the mask has one entry per residue of the loaded sequence.
+pub fn soft_mask(sequence: &[u8], skip_characters: &[bool]) -> Vec<bool> {
+    sequence
+        .iter()
+        .filter(|character| {
+            !character.is_ascii_whitespace()
+                && !skip_characters
+                    .get(usize::from(character.to_ascii_uppercase()))
+                    .copied()
+                    .unwrap_or(false)
+        })
+        .map(u8::is_ascii_lowercase)
+        .collect()
+}
+
+/// Read the soft masks of all records of a fasta file in the order of the file, see [`soft_mask`].
+///
+/// The file may be compressed with gzip.
+pub fn read_soft_masks(path: impl AsRef<Path>, skip_characters: &[bool]) -> Result<Vec<Vec<bool>>> {
+    let path = path.as_ref();
+    info!("Loading soft masks of fasta file {path:?}");
+
+    let io_error = |source| MultialignError::Io {
+        path: path.to_owned(),
+        source,
+    };
+    let mut reader = BufReader::new(File::open(path).map_err(io_error)?);
+    let is_gzip = reader
+        .fill_buf()
+        .map_err(io_error)?
+        .starts_with(&[0x1f, 0x8b]);
+    let reader: Box<dyn BufRead> = if is_gzip {
+        Box::new(BufReader::new(MultiGzDecoder::new(reader)))
+    } else {
+        Box::new(reader)
+    };
+
+    let mut masks = Vec::new();
+    // The sequence of the current record, or `None` before the first header.
+    let mut sequence: Option<Vec<u8>> = None;
+    for line in reader.split(b'\n') {
+        let line = line.map_err(io_error)?;
+        if line.starts_with(b">") {
+            if let Some(sequence) = sequence.replace(Vec::new()) {
+                masks.push(soft_mask(&sequence, skip_characters));
+            }
+        } else if let Some(sequence) = &mut sequence {
+            sequence.extend_from_slice(&line);
+        }
+    }
+    if let Some(sequence) = sequence {
+        masks.push(soft_mask(&sequence, skip_characters));
+    }
+
+    Ok(masks)
+}
@@ -0,0 +1,102 @@
+use std::{
+    fs::{File, OpenOptions},
+    path::PathBuf,
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use generic_a_star::cost::AStarCost;
+
+use super::error::{MultialignError, Result};
+
+/// Where and how many of the nodes of the search with re-derived predecessors are written to disk, see [`MultialignOptions::spill`](crate::MultialignOptions::spill).
+#[derive(Debug, Clone)]
+pub struct SpillOptions {
+    /// The directory in which the files of the open and closed list are created.
+    ///
+    /// The files are removed after the search.
+    pub directory: PathBuf,
+    /// The amount of nodes of each of the open and closed list kept in memory.
+    ///
+    /// When more nodes are closed, the least recently used half of them is written to disk,
+    /// and when more nodes are open, the half of them that is searched last.
+    pub cached_nodes: usize,
+}
+
+/// Distinguishes the files of searches at the same time, e.g. for the segments between anchors.
+static FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Create a new file for the given list in the directory of the options, which is named after the list and is unique within the directory.
+pub(crate) fn create_spill_file(options: &SpillOptions, list: &str) -> Result<(PathBuf, File)> {
+    let path = options.directory.join(format!(
+        "multialign-{list}-{}-{}.bin",
+        process::id(),
+        FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let file = create_file(&path)?;
+    Ok((path, file))
+}
+
+pub(crate) fn create_file(path: &PathBuf) -> Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .map_err(|source| MultialignError::Write {
+            path: path.clone(),
+            source,
+        })
+}
+
+/// Convert a cost that was stored with [`AStarCost::as_u64`], which is reinterpreted as signed to keep negative costs.
+pub(crate) fn cost_from_i64<Cost: AStarCost>(cost: i64) -> Cost {
+    let mut result = Cost::zero();
+    for byte in cost.unsigned_abs().to_be_bytes() {
+        for _ in 0..8 {
+            result += result;
+        }
+        result += Cost::from(byte);
+    }
+
+    if cost < 0 {
+        Cost::zero() - result
+    } else {
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use generic_a_star::cost::{AStarCost, I32Cost, I64Cost, U32Cost};
+
+    use super::cost_from_i64;
+
+    #[test]
+    fn costs_survive_storing() {
+        for cost in [
+            0,
+            1,
+            255,
+            256,
+            1 << 20,
+            i32::MAX,
+            -1,
+            -256,
+            -(1 << 20),
+            i32::MIN + 1,
+        ] {
+            let stored = I32Cost::from(cost).as_u64() as i64;
+            assert_eq!(cost_from_i64::<I32Cost>(stored).as_primitive(), cost);
+        }
+        for cost in [0, 1 << 40, i64::MAX, -(1 << 40), i64::MIN + 1] {
+            let stored = I64Cost::from(cost).as_u64() as i64;
+            assert_eq!(cost_from_i64::<I64Cost>(stored).as_primitive(), cost);
+        }
+        for cost in [0, 1, u32::MAX] {
+            let stored = U32Cost::from(cost).as_u64() as i64;
+            assert_eq!(cost_from_i64::<U32Cost>(stored).as_primitive(), cost);
+        }
+    }
+}
@@ -0,0 +1,446 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use generic_a_star::{cost::AStarCost, AStarPerformanceCounters};
+use log::info;
+
+use super::{
+    curation::consensus,
+    effective_branching_factor,
+    error::{MultialignError, Result},
+    reproducibility::alignment_hash,
+    total_column_score, Alignment,
+};
+
+/// Where to append the statistics of an alignment run.
+#[derive(Debug, Clone)]
+pub struct StatisticsOutput {
+    /// The TSV file the statistics are appended to.
+    pub path: PathBuf,
+    /// The name of the dataset, which is written into the first column.
+    pub dataset: String,
+}
+
+const HEADER: [&str; 9] = [
+    "dataset",
+    "n_sequences",
+    "total_residues",
+    "alignment_length",
+    "cost",
+    "normalized_cost",
+    "conserved_columns",
+    "runtime",
+    "nodes_expanded",
+];
+
+/// Append a row with the statistics of an alignment run to a TSV file, and write a header first if the file is new or empty.
+///
+/// The normalized cost is the cost per residue, the conserved columns are the columns in which all sequences have the same residue,
+/// and the runtime is given in seconds.
+pub fn append_statistics<CharacterType: Eq, Cost: AStarCost>(
+    output: &StatisticsOutput,
+    columns: &[Vec<Option<CharacterType>>],
+    cost: Cost,
+    runtime: Duration,
+    performance_counters: &AStarPerformanceCounters,
+) -> Result<()> {
+    info!("Appending statistics to {:?}", output.path);
+    let write_error = |source| MultialignError::Write {
+        path: output.path.clone(),
+        source,
+    };
+
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&output.path)
+        .map_err(write_error)?;
+    let is_new = file.metadata().map_err(write_error)?.len() == 0;
+
+    let sequence_amount = columns.first().map(Vec::len).unwrap_or(0);
+    let total_residues = columns.iter().flatten().flatten().count();
+    let normalized_cost = if total_residues > 0 {
+        cost.as_f64() / total_residues as f64
+    } else {
+        0.0
+    };
+
+    let header = is_new.then(|| HEADER.map(String::from));
+    let record = [
+        output.dataset.clone(),
+        sequence_amount.to_string(),
+        total_residues.to_string(),
+        columns.len().to_string(),
+        cost.to_string(),
+        format!("{normalized_cost:.6}"),
+        total_column_score(columns).to_string(),
+        format!("{:.3}", runtime.as_secs_f64()),
+        performance_counters.closed_nodes.to_string(),
+    ];
+    write_tsv(file, header.into_iter().chain([record])).map_err(write_error)
+}
+
+/// The performance counters and runtime of a search.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchStatistics {
+    /// The amount of nodes inserted into the open list.
+    pub opened_nodes: usize,
+    /// The amount of nodes popped from the open list that had already been closed with a lower cost.
+    pub suboptimal_opened_nodes: usize,
+    /// The amount of nodes expanded.
+    pub closed_nodes: usize,
+    /// The time it took to search.
+    pub runtime: Duration,
+}
+
+impl SearchStatistics {
+    pub fn new(performance_counters: &AStarPerformanceCounters, runtime: Duration) -> Self {
+        Self {
+            opened_nodes: performance_counters.opened_nodes,
+            suboptimal_opened_nodes: performance_counters.suboptimal_opened_nodes,
+            closed_nodes: performance_counters.closed_nodes,
+            runtime,
+        }
+    }
+}
+
+/// Where to write the statistics of an alignment run as JSON.
+///
+/// The file contains the hashes of the configuration and of the alignment as 16 hexadecimal digits, the sizes of the alignment, the node counts of the search, measures of the quality of the heuristic,
+/// the runtime of each phase in seconds, and the peak resident set size of the process in bytes.
+/// Values that are not known are `null`, e.g. the peak resident set size on other systems than Linux.
+/// The search runtime excludes the preprocessing and backtracking, and when aligning between anchors,
+/// the runtimes of the phases are summed over the segments, which are aligned in parallel.
+#[derive(Debug, Clone)]
+pub struct StatisticsJsonOutput {
+    /// The JSON file, which is overwritten.
+    pub path: PathBuf,
+    /// The time it took to read the input, which happens before calling the aligner.
+    pub input_runtime: Duration,
+    /// A hash of everything that influences the alignment, which is only known to the caller, e.g. the command line interface.
+    pub configuration_hash: Option<u64>,
+}
+
+/// The details of a search that are only written to the statistics JSON file.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SearchDetails<Cost> {
+    /// The lower bound of the cost of the root, i.e. the estimate of the heuristic for the whole alignment.
+    pub root_lower_bound: Option<Cost>,
+    /// An upper bound of the maximum size of the open list, if the search kept track of it.
+    pub peak_open_nodes: Option<usize>,
+    /// The amount of nodes pruned as dominated by closed nodes, if [`MultialignOptions::dominance_pruning`](crate::MultialignOptions::dominance_pruning) was used.
+    pub dominated_nodes: Option<usize>,
+    /// The time it took to compute the lower bounds of the heuristic.
+    pub preprocessing_runtime: Duration,
+    /// The time it took to reconstruct the alignment after the search, which is zero for searches that reconstruct it while searching.
+    pub backtrack_runtime: Duration,
+}
+
+impl<Cost> Default for SearchDetails<Cost> {
+    fn default() -> Self {
+        Self {
+            root_lower_bound: None,
+            peak_open_nodes: None,
+            dominated_nodes: None,
+            preprocessing_runtime: Duration::ZERO,
+            backtrack_runtime: Duration::ZERO,
+        }
+    }
+}
+
+impl<Cost: AStarCost> SearchDetails<Cost> {
+    /// Combine the details of the searches of consecutive segments, which are only known if they are known for both segments.
+    pub fn combine(&self, other: &Self) -> Self {
+        Self {
+            root_lower_bound: self
+                .root_lower_bound
+                .zip(other.root_lower_bound)
+                .and_then(|(a, b)| a.checked_add(&b)),
+            peak_open_nodes: self
+                .peak_open_nodes
+                .zip(other.peak_open_nodes)
+                .map(|(a, b)| a + b),
+            dominated_nodes: self
+                .dominated_nodes
+                .zip(other.dominated_nodes)
+                .map(|(a, b)| a + b),
+            preprocessing_runtime: self.preprocessing_runtime + other.preprocessing_runtime,
+            backtrack_runtime: self.backtrack_runtime + other.backtrack_runtime,
+        }
+    }
+}
+
+/// Write the statistics of an alignment run to a JSON file, see [`StatisticsJsonOutput`] for its contents.
+pub(crate) fn write_statistics_json<CharacterType: Clone + Into<char>, Cost: AStarCost>(
+    output: &StatisticsJsonOutput,
+    alignment: &Alignment<CharacterType, Cost>,
+    details: &SearchDetails<Cost>,
+    output_runtime: Duration,
+) -> Result<()> {
+    info!("Writing statistics to {:?}", output.path);
+    let statistics = &alignment.search_statistics;
+    let number = |value: f64| {
+        if value.is_finite() {
+            value.to_string()
+        } else {
+            "null".to_string()
+        }
+    };
+    let optional = |value: Option<String>| value.unwrap_or_else(|| "null".to_string());
+    let hash = |hash: u64| format!("\"{hash:016x}\"");
+
+    let successors_per_expansion = statistics.opened_nodes as f64 / statistics.closed_nodes as f64;
+    let depth = alignment.len();
+    let root_lower_bound_ratio = details
+        .root_lower_bound
+        .map(|root_lower_bound| root_lower_bound.as_f64() / alignment.cost.as_f64());
+    let search_runtime = statistics
+        .runtime
+        .saturating_sub(details.preprocessing_runtime)
+        .saturating_sub(details.backtrack_runtime);
+    let total_runtime = output.input_runtime + statistics.runtime + output_runtime;
+
+    let runtimes = [
+        ("input", output.input_runtime),
+        ("preprocessing", details.preprocessing_runtime),
+        ("search", search_runtime),
+        ("backtrack", details.backtrack_runtime),
+        ("output", output_runtime),
+        ("total", total_runtime),
+    ]
+    .map(|(phase, runtime)| format!("\"{phase}\": {}", runtime.as_secs_f64()))
+    .join(",\n    ");
+    let effective_branching_factor = (depth > 0 && statistics.closed_nodes > 0)
+        .then(|| number(effective_branching_factor(statistics.opened_nodes, depth)));
+
+    let json = format!(
+        r#"{{
+  "configuration_hash": {},
+  "alignment_hash": {},
+  "sequences": {},
+  "alignment_length": {depth},
+  "cost": {},
+  "cost_lower_bound": {},
+  "nodes": {{
+    "expanded": {},
+    "generated": {},
+    "suboptimal": {},
+    "dominated": {},
+    "peak_open": {},
+    "peak_closed": {}
+  }},
+  "heuristic": {{
+    "root_lower_bound": {},
+    "root_lower_bound_ratio": {},
+    "successors_per_expansion": {},
+    "effective_branching_factor": {}
+  }},
+  "runtime": {{
+    {runtimes}
+  }},
+  "peak_rss_bytes": {}
+}}
+"#,
+        optional(output.configuration_hash.map(hash)),
+        hash(alignment_hash(&alignment.columns)),
+        alignment.sequence_amount(),
+        alignment.cost,
+        alignment.cost_lower_bound,
+        statistics.closed_nodes,
+        statistics.opened_nodes,
+        statistics.suboptimal_opened_nodes,
+        optional(details.dominated_nodes.map(|nodes| nodes.to_string())),
+        optional(details.peak_open_nodes.map(|nodes| nodes.to_string())),
+        // The closed list only grows.
+        statistics.closed_nodes,
+        optional(details.root_lower_bound.map(|cost| cost.to_string())),
+        optional(root_lower_bound_ratio.map(number)),
+        number(successors_per_expansion),
+        optional(effective_branching_factor),
+        optional(peak_resident_set_size().map(|bytes| bytes.to_string())),
+    );
+
+    fs::write(&output.path, json).map_err(|source| MultialignError::Write {
+        path: output.path.clone(),
+        source,
+    })
+}
+
+/// Returns the maximum amount of bytes of memory that the process has held, which is only known on Linux.
+fn peak_resident_set_size() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let kilobytes = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// The statistics of a single column of an alignment.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ColumnStatistics {
+    /// The number of pairs of entries that are not equal, where pairs of gaps are equal.
+    pub sum_of_pairs_cost: usize,
+    /// The Shannon entropy in bits of the residues of the column, ignoring gaps.
+    pub entropy: f64,
+    /// The fraction of entries that are gaps.
+    pub gap_fraction: f64,
+    /// The most frequent residue, chosen like in [`consensus`], or `None` if the column has no residues.
+    pub most_frequent_residue: Option<char>,
+}
+
+/// Compute the statistics of each column of an alignment.
+pub fn column_statistics<CharacterType: Clone + Eq + Into<char>>(
+    columns: &[Vec<Option<CharacterType>>],
+) -> Vec<ColumnStatistics> {
+    columns
+        .iter()
+        .zip(consensus(columns))
+        .map(|(column, most_frequent)| {
+            let mut counts: Vec<(&Option<CharacterType>, usize)> = Vec::new();
+            for entry in column {
+                match counts.iter_mut().find(|(other, _)| *other == entry) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((entry, 1)),
+                }
+            }
+
+            let unequal_pairs = (column.len() * column.len().saturating_sub(1)
+                - counts
+                    .iter()
+                    .map(|(_, count)| count * (count - 1))
+                    .sum::<usize>())
+                / 2;
+            let gaps = column.iter().filter(|entry| entry.is_none()).count();
+            let residues = column.len() - gaps;
+            let entropy: f64 = counts
+                .iter()
+                .filter(|(entry, _)| entry.is_some())
+                .map(|(_, count)| {
+                    let frequency = *count as f64 / residues as f64;
+                    -frequency * frequency.log2()
+                })
+                .sum();
+
+            ColumnStatistics {
+                sum_of_pairs_cost: unequal_pairs,
+                entropy: entropy.abs(),
+                gap_fraction: if column.is_empty() {
+                    0.0
+                } else {
+                    gaps as f64 / column.len() as f64
+                },
+                most_frequent_residue: most_frequent.map(Into::into),
+            }
+        })
+        .collect()
+}
+
+const COLUMN_HEADER: [&str; 5] = [
+    "column",
+    "sum_of_pairs_cost",
+    "entropy",
+    "gap_fraction",
+    "most_frequent_residue",
+];
+
+/// Write a TSV file with the statistics of each column of an alignment, see [`column_statistics`].
+///
+/// Columns are numbered from one, and columns without residues have `-` as most frequent residue.
+pub fn write_column_statistics<CharacterType: Clone + Eq + Into<char>>(
+    path: impl AsRef<Path>,
+    columns: &[Vec<Option<CharacterType>>],
+) -> Result<()> {
+    let path = path.as_ref();
+    info!("Writing column statistics to {path:?}");
+    let write_error = |source| MultialignError::Write {
+        path: path.to_owned(),
+        source,
+    };
+
+    let file = File::create(path).map_err(write_error)?;
+    let records = column_statistics(columns)
+        .into_iter()
+        .enumerate()
+        .map(|(index, statistics)| {
+            [
+                (index + 1).to_string(),
+                statistics.sum_of_pairs_cost.to_string(),
+                format!("{:.6}", statistics.entropy),
+                format!("{:.6}", statistics.gap_fraction),
+                statistics.most_frequent_residue.unwrap_or('-').to_string(),
+            ]
+        });
+    write_tsv(
+        file,
+        [COLUMN_HEADER.map(String::from)].into_iter().chain(records),
+    )
+    .map_err(write_error)
+}
+
+/// Write a TSV file with the cost increment of each column of an alignment, and the cost accumulated up to and including the column.
+///
+/// Columns are numbered from one, and columns without cost increment, i.e. columns consisting only of gaps, have an empty cost increment.
+pub fn write_column_costs<Cost: AStarCost>(
+    path: impl AsRef<Path>,
+    costs: &[Option<Cost>],
+) -> Result<()> {
+    let path = path.as_ref();
+    info!("Writing column costs to {path:?}");
+    let write_error = |source| MultialignError::Write {
+        path: path.to_owned(),
+        source,
+    };
+
+    let file = File::create(path).map_err(write_error)?;
+    let header = ["column", "cost_increment", "accumulated_cost"].map(String::from);
+    let mut accumulated_cost = Cost::zero();
+    let records = costs.iter().enumerate().map(|(index, cost)| {
+        if let Some(cost) = cost {
+            accumulated_cost += *cost;
+        }
+
+        [
+            (index + 1).to_string(),
+            cost.map(|cost| cost.to_string()).unwrap_or_default(),
+            accumulated_cost.to_string(),
+        ]
+    });
+    write_tsv(file, [header].into_iter().chain(records)).map_err(write_error)
+}
+
+/// Write the records into the file as tab-separated values.
+///
+/// Without the `csv` feature, this returns an error of kind [`io::ErrorKind::Unsupported`].
+fn write_tsv<const FIELDS: usize>(
+    file: File,
+    records: impl IntoIterator<Item = [String; FIELDS]>,
+) -> io::Result<()> {
+    #[cfg(feature = "csv")]
+    {
+        let mut writer = csv::WriterBuilder::new().delimiter(b'\t').from_writer(file);
+        for record in records {
+            writer.write_record(record)?;
+        }
+        writer.flush()
+    }
+
+    #[cfg(not(feature = "csv"))]
+    {
+        let _ = (file, records);
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "writing TSV files requires the csv feature",
+        ))
+    }
+}
@@ -0,0 +1,527 @@
+use std::{collections::BTreeMap, fmt::Debug};
+
+use compact_genome::interface::{alphabet::Alphabet, sequence::GenomeSequence};
+use generic_a_star::cost::I32Cost;
+
+use crate::{
+    align,
+    error::{MultialignError, Result},
+    metric::pairwise_match_metric::PairwiseMatchMetric,
+    GuideTreeMethod, GuideTreeOptions, MultialignOptions,
+};
+
+/// A rooted binary tree over the input sequences.
+#[derive(Debug, Clone)]
+pub enum GuideTree {
+    /// A leaf holding the index of a sequence.
+    Leaf { sequence_index: usize },
+    /// An inner node with two children and the lengths of the branches leading to them.
+    Inner {
+        children: Box<[(GuideTree, f64); 2]>,
+    },
+}
+
+impl GuideTree {
+    /// Returns the sequence indices in the order in which the leaves are visited by a depth-first traversal.
+    pub fn leaf_order(&self) -> Vec<usize> {
+        let mut order = Vec::new();
+        let mut stack = vec![self];
+
+        while let Some(node) = stack.pop() {
+            match node {
+                GuideTree::Leaf { sequence_index } => order.push(*sequence_index),
+                GuideTree::Inner { children } => {
+                    stack.push(&children[1].0);
+                    stack.push(&children[0].0);
+                }
+            }
+        }
+
+        order
+    }
+
+    /// Compute a weight for each of the given amount of sequences like ClustalW, which is higher for sequences that are more divergent.
+    ///
+    /// Like ClustalW, the tree is first rerooted at the midpoint of its longest path between two leaves.
+    /// Then the weight of a sequence is the sum of the lengths of the branches from the root to its leaf,
+    /// where each branch length is divided by the amount of leaves below the branch.
+    /// If all branches have length zero, then all sequences get weight one.
+    ///
+    /// **Panics** if a leaf holds a sequence index of at least `sequence_amount`.
+    pub fn sequence_weights(&self, sequence_amount: usize) -> Vec<f64> {
+        let tree = UnrootedTree::new(self);
+        let mut weights = vec![0.0; sequence_amount];
+
+        if let Some(((first, second), first_length)) = tree.midpoint() {
+            let second_length = tree.branch_length(first, second) - first_length;
+            for (node, parent, length) in [
+                (first, second, first_length),
+                (second, first, second_length),
+            ] {
+                tree.accumulate_weights(node, parent, length, 0.0, &mut weights);
+            }
+        }
+
+        if weights.iter().all(|weight| *weight == 0.0) {
+            weights.fill(1.0);
+        }
+        weights
+    }
+
+    /// Compute the distances between the leaves for each pair of the given amount of sequences.
+    ///
+    /// The distance of two leaves is the sum of the lengths of the branches on the path between them.
+    /// Sequences without a leaf have distance zero to all other sequences.
+    ///
+    /// **Panics** if a leaf holds a sequence index of at least `sequence_amount`.
+    pub fn leaf_distances(&self, sequence_amount: usize) -> Vec<Vec<f64>> {
+        let tree = UnrootedTree::new(self);
+        let mut distances = vec![vec![0.0; sequence_amount]; sequence_amount];
+
+        for (node, sequence_index) in tree.sequence_indices.iter().enumerate() {
+            if let Some(sequence_index) = *sequence_index {
+                let node_distances = tree.distances(node);
+                for (other_node, other_sequence_index) in tree.sequence_indices.iter().enumerate() {
+                    if let Some(other_sequence_index) = *other_sequence_index {
+                        distances[sequence_index][other_sequence_index] =
+                            node_distances[other_node].0;
+                    }
+                }
+            }
+        }
+
+        distances
+    }
+}
+
+/// A [`GuideTree`] as undirected graph, used to reroot it.
+struct UnrootedTree {
+    /// The neighbours of each node together with the length of the branch leading to them.
+    neighbours: Vec<Vec<(usize, f64)>>,
+    /// The sequence index of each node that is a leaf.
+    sequence_indices: Vec<Option<usize>>,
+}
+
+impl UnrootedTree {
+    fn new(tree: &GuideTree) -> Self {
+        let mut result = Self {
+            neighbours: Vec::new(),
+            sequence_indices: Vec::new(),
+        };
+        let mut stack: Vec<(_, Option<(usize, f64)>)> = vec![(tree, None)];
+
+        while let Some((node, parent)) = stack.pop() {
+            let index = result.neighbours.len();
+            result.neighbours.push(Vec::new());
+            result.sequence_indices.push(None);
+            if let Some((parent, length)) = parent {
+                result.neighbours[parent].push((index, length));
+                result.neighbours[index].push((parent, length));
+            }
+
+            match node {
+                GuideTree::Leaf { sequence_index } => {
+                    result.sequence_indices[index] = Some(*sequence_index)
+                }
+                GuideTree::Inner { children } => {
+                    for (child, length) in children.iter() {
+                        stack.push((child, Some((index, *length))));
+                    }
+                }
+            }
+        }
+
+        result
+    }
+
+    fn branch_length(&self, first: usize, second: usize) -> f64 {
+        self.neighbours[first]
+            .iter()
+            .find(|(neighbour, _)| *neighbour == second)
+            .unwrap()
+            .1
+    }
+
+    /// The distance from the given node to all nodes, and the predecessor of each node on the path from the given node.
+    fn distances(&self, from: usize) -> Vec<(f64, Option<usize>)> {
+        let mut distances = vec![(0.0, None); self.neighbours.len()];
+        let mut stack = vec![(from, None)];
+
+        while let Some((node, parent)) = stack.pop() {
+            for (neighbour, length) in self.neighbours[node].iter().copied() {
+                if Some(neighbour) != parent {
+                    distances[neighbour] = (distances[node].0 + length, Some(node));
+                    stack.push((neighbour, Some(node)));
+                }
+            }
+        }
+
+        distances
+    }
+
+    /// The leaf that is farthest from the given node.
+    fn farthest_leaf(&self, distances: &[(f64, Option<usize>)]) -> usize {
+        (0..self.neighbours.len())
+            .filter(|node| self.sequence_indices[*node].is_some())
+            .max_by(|a, b| distances[*a].0.total_cmp(&distances[*b].0))
+            .unwrap()
+    }
+
+    /// Find the midpoint of the longest path between two leaves.
+    ///
+    /// Returns the branch containing the midpoint and the distance of the midpoint from the first node of the branch,
+    /// or `None` if the tree has a single node.
+    fn midpoint(&self) -> Option<((usize, usize), f64)> {
+        // In a tree, the farthest leaf from any leaf is an end of a longest path.
+        let leaf = self.sequence_indices.iter().position(Option::is_some)?;
+        let start = self.farthest_leaf(&self.distances(leaf));
+        let distances = self.distances(start);
+        let end = self.farthest_leaf(&distances);
+        let half = distances[end].0 / 2.0;
+
+        // Walk from the end towards the start until passing the midpoint.
+        let mut node = end;
+        while let Some(predecessor) = distances[node].1 {
+            if distances[predecessor].0 <= half {
+                return Some(((predecessor, node), half - distances[predecessor].0));
+            }
+            node = predecessor;
+        }
+        None
+    }
+
+    fn leaf_count(&self, node: usize, parent: usize) -> usize {
+        if self.sequence_indices[node].is_some() {
+            return 1;
+        }
+        self.neighbours[node]
+            .iter()
+            .filter(|(neighbour, _)| *neighbour != parent)
+            .map(|(neighbour, _)| self.leaf_count(*neighbour, node))
+            .sum()
+    }
+
+    /// Add the weights of the leaves below the given node, which is reached from its parent via a branch of the given length.
+    fn accumulate_weights(
+        &self,
+        node: usize,
+        parent: usize,
+        length: f64,
+        weight: f64,
+        weights: &mut [f64],
+    ) {
+        let weight = weight + length / self.leaf_count(node, parent) as f64;
+        if let Some(sequence_index) = self.sequence_indices[node] {
+            weights[sequence_index] = weight;
+            return;
+        }
+
+        for (neighbour, length) in self.neighbours[node].iter().copied() {
+            if neighbour != parent {
+                self.accumulate_weights(neighbour, node, length, weight, weights);
+            }
+        }
+    }
+}
+
+/// Construct a guide tree of the given sequences using the neighbor-joining algorithm.
+///
+/// The distances are computed with [`alignment_distance_matrix`] from optimal pairwise alignments of all pairs of sequences under [`PairwiseMatchMetric`].
+///
+/// **Panics** if no sequences are given.
+pub fn sequence_guide_tree<
+    AlphabetType: Alphabet + Debug + Clone + Eq + Send + 'static,
+    SequenceType: GenomeSequence<AlphabetType, SequenceType> + ?Sized + Sync,
+>(
+    sequences: &[&SequenceType],
+) -> Result<GuideTree> {
+    let mut distances = vec![vec![0.0; sequences.len()]; sequences.len()];
+
+    for i in 0..sequences.len() {
+        for j in i + 1..sequences.len() {
+            let alignment = align::<_, _, _, I32Cost>(
+                &[sequences[i], sequences[j]],
+                PairwiseMatchMetric::new(2)?,
+                &MultialignOptions::default(),
+            )?;
+            let distance = alignment_distance_matrix(&alignment.columns)[0][1];
+            distances[i][j] = distance;
+            distances[j][i] = distance;
+        }
+    }
+
+    Ok(neighbor_joining(&distances))
+}
+
+/// Compute the pairwise distances between the rows of the given alignment columns.
+///
+/// The distance of two rows is the fraction of mismatching characters among all columns where neither row has a gap.
+/// If two rows share no such column, their distance is one.
+pub fn alignment_distance_matrix<CharacterType: Eq>(
+    columns: &[Vec<Option<CharacterType>>],
+) -> Vec<Vec<f64>> {
+    let sequence_amount = columns.first().map(Vec::len).unwrap_or(0);
+    let mut distances = vec![vec![0.0; sequence_amount]; sequence_amount];
+
+    for i in 0..sequence_amount {
+        for j in i + 1..sequence_amount {
+            let mut compared = 0usize;
+            let mut mismatches = 0usize;
+
+            for column in columns {
+                if let (Some(a), Some(b)) = (&column[i], &column[j]) {
+                    compared += 1;
+                    if a != b {
+                        mismatches += 1;
+                    }
+                }
+            }
+
+            let distance = if compared == 0 {
+                1.0
+            } else {
+                mismatches as f64 / compared as f64
+            };
+            distances[i][j] = distance;
+            distances[j][i] = distance;
+        }
+    }
+
+    distances
+}
+
+/// Compute the pairwise distances between the given sequences from the k-mers of the given length that they share.
+///
+/// The distance of two sequences is one minus the fraction of the k-mers of the shorter sequence that also occur in the longer one,
+/// where each k-mer is counted at most as often as it occurs in both sequences.
+/// This is much faster than aligning each pair of sequences, like [`sequence_guide_tree`] does.
+/// If one of two sequences is shorter than the k-mers, their distance is one.
+///
+/// Returns a [`MultialignError::InvalidGuideTree`] if the length of the k-mers is zero.
+pub fn kmer_distance_matrix<CharacterType: Ord>(
+    sequences: &[Vec<CharacterType>],
+    k: usize,
+) -> Result<Vec<Vec<f64>>> {
+    if k == 0 {
+        return Err(MultialignError::InvalidGuideTree(
+            "The length of the k-mers must be at least one".to_owned(),
+        ));
+    }
+
+    let kmer_counts: Vec<BTreeMap<&[CharacterType], usize>> = sequences
+        .iter()
+        .map(|sequence| {
+            let mut counts = BTreeMap::new();
+            for kmer in sequence.windows(k) {
+                *counts.entry(kmer).or_insert(0) += 1;
+            }
+            counts
+        })
+        .collect();
+    let mut distances = vec![vec![0.0; sequences.len()]; sequences.len()];
+
+    for i in 0..sequences.len() {
+        for j in i + 1..sequences.len() {
+            let kmer_amount = (sequences[i].len().min(sequences[j].len()) + 1).saturating_sub(k);
+            let shared: usize = kmer_counts[i]
+                .iter()
+                .filter_map(|(kmer, count)| {
+                    kmer_counts[j]
+                        .get(kmer)
+                        .map(|other_count| (*count).min(*other_count))
+                })
+                .sum();
+
+            let distance = if kmer_amount == 0 {
+                1.0
+            } else {
+                1.0 - shared as f64 / kmer_amount as f64
+            };
+            distances[i][j] = distance;
+            distances[j][i] = distance;
+        }
+    }
+
+    Ok(distances)
+}
+
+/// Apply Kimura's correction for multiple substitutions to the given distances, which are fractions of mismatching characters.
+///
+/// Each distance `d` becomes `-ln(1 - d - 0.2 d²)`, which estimates the amount of substitutions per character that led to the observed mismatches.
+/// Since the correction diverges for distances of about `0.85` and above, the corrected distances are capped at [`KIMURA_MAX_DISTANCE`].
+pub fn kimura_correction(distances: &mut [Vec<f64>]) {
+    for distance in distances.iter_mut().flatten() {
+        let argument = 1.0 - *distance - 0.2 * *distance * *distance;
+        *distance = if argument > (-KIMURA_MAX_DISTANCE).exp() {
+            -argument.ln()
+        } else {
+            KIMURA_MAX_DISTANCE
+        };
+    }
+}
+
+/// The maximum distance after [`kimura_correction`].
+pub const KIMURA_MAX_DISTANCE: f64 = 10.0;
+
+/// Construct a guide tree of the given sequences from the k-mers they share, as configured by the given options.
+///
+/// The distances are computed with [`kmer_distance_matrix`], optionally corrected with [`kimura_correction`],
+/// and the tree is constructed with [`neighbor_joining`] or [`upgma`].
+///
+/// **Panics** if no sequences are given.
+pub fn kmer_guide_tree<CharacterType: Ord>(
+    sequences: &[Vec<CharacterType>],
+    options: &GuideTreeOptions,
+) -> Result<GuideTree> {
+    let mut distances = kmer_distance_matrix(sequences, options.kmer_length)?;
+    if options.kimura_correction {
+        kimura_correction(&mut distances);
+    }
+
+    Ok(match options.method {
+        GuideTreeMethod::NeighborJoining => neighbor_joining(&distances),
+        GuideTreeMethod::Upgma => upgma(&distances),
+    })
+}
+
+/// Construct a tree from the given distance matrix using the UPGMA algorithm.
+///
+/// The two closest clusters are joined repeatedly, and the distance of the joined cluster to each other cluster is the average distance of their sequences.
+/// The resulting tree is ultrametric, i.e. each inner node has the same distance to all leaves below it, which is half the distance of the clusters it joins.
+///
+/// **Panics** if the distance matrix is empty.
+pub fn upgma(distances: &[Vec<f64>]) -> GuideTree {
+    assert!(!distances.is_empty());
+
+    // Each cluster with the amount of its sequences and the distance of its root to its leaves.
+    let mut clusters: Vec<_> = (0..distances.len())
+        .map(|sequence_index| (GuideTree::Leaf { sequence_index }, 1usize, 0.0))
+        .collect();
+    let mut distances = distances.to_vec();
+
+    while clusters.len() > 1 {
+        let n = clusters.len();
+
+        let mut best = (0, 1);
+        for i in 0..n {
+            for j in i + 1..n {
+                if distances[i][j] < distances[best.0][best.1] {
+                    best = (i, j);
+                }
+            }
+        }
+
+        let (i, j) = best;
+        let height = distances[i][j] / 2.0;
+        let (size_i, size_j) = (clusters[i].1, clusters[j].1);
+        let new_distances: Vec<f64> = (0..n)
+            .filter(|&k| k != i && k != j)
+            .map(|k| {
+                (size_i as f64 * distances[i][k] + size_j as f64 * distances[j][k])
+                    / (size_i + size_j) as f64
+            })
+            .collect();
+
+        // Remove j first, since j > i.
+        let (cluster_j, _, height_j) = clusters.remove(j);
+        let (cluster_i, _, height_i) = clusters.remove(i);
+        distances.remove(j);
+        distances.remove(i);
+        for row in &mut distances {
+            row.remove(j);
+            row.remove(i);
+        }
+
+        for (row, new_distance) in distances.iter_mut().zip(&new_distances) {
+            row.push(*new_distance);
+        }
+        let mut new_row = new_distances;
+        new_row.push(0.0);
+        distances.push(new_row);
+        clusters.push((
+            GuideTree::Inner {
+                children: Box::new([
+                    (cluster_i, (height - height_i).max(0.0)),
+                    (cluster_j, (height - height_j).max(0.0)),
+                ]),
+            },
+            size_i + size_j,
+            height,
+        ));
+    }
+
+    clusters.pop().unwrap().0
+}
+
+/// Construct a tree from the given distance matrix using the neighbor-joining algorithm.
+///
+/// The resulting unrooted tree is rooted at the last join.
+///
+/// **Panics** if the distance matrix is empty.
+pub fn neighbor_joining(distances: &[Vec<f64>]) -> GuideTree {
+    assert!(!distances.is_empty());
+
+    let mut clusters: Vec<_> = (0..distances.len())
+        .map(|sequence_index| GuideTree::Leaf { sequence_index })
+        .collect();
+    let mut distances = distances.to_vec();
+
+    while clusters.len() > 2 {
+        let n = clusters.len();
+        let sums: Vec<f64> = distances.iter().map(|row| row.iter().sum()).collect();
+
+        // Find the pair minimising the Q-criterion.
+        let mut best = (0, 1);
+        let mut best_q = f64::INFINITY;
+        for i in 0..n {
+            for j in i + 1..n {
+                let q = (n - 2) as f64 * distances[i][j] - sums[i] - sums[j];
+                if q < best_q {
+                    best_q = q;
+                    best = (i, j);
+                }
+            }
+        }
+
+        let (i, j) = best;
+        let distance = distances[i][j];
+        let branch_i = (0.5 * distance + (sums[i] - sums[j]) / (2.0 * (n - 2) as f64)).max(0.0);
+        let branch_j = (distance - branch_i).max(0.0);
+
+        let new_distances: Vec<f64> = (0..n)
+            .filter(|&k| k != i && k != j)
+            .map(|k| 0.5 * (distances[i][k] + distances[j][k] - distance))
+            .collect();
+
+        // Remove j first, since j > i.
+        let cluster_j = clusters.remove(j);
+        let cluster_i = clusters.remove(i);
+        distances.remove(j);
+        distances.remove(i);
+        for row in &mut distances {
+            row.remove(j);
+            row.remove(i);
+        }
+
+        for (row, new_distance) in distances.iter_mut().zip(&new_distances) {
+            row.push(*new_distance);
+        }
+        let mut new_row = new_distances;
+        new_row.push(0.0);
+        distances.push(new_row);
+        clusters.push(GuideTree::Inner {
+            children: Box::new([(cluster_i, branch_i), (cluster_j, branch_j)]),
+        });
+    }
+
+    if clusters.len() == 1 {
+        return clusters.pop().unwrap();
+    }
+
+    let distance = distances[0][1];
+    let cluster_1 = clusters.pop().unwrap();
+    let cluster_0 = clusters.pop().unwrap();
+    GuideTree::Inner {
+        children: Box::new([(cluster_0, distance / 2.0), (cluster_1, distance / 2.0)]),
+    }
+}
@@ -0,0 +1,92 @@
+//! A JavaScript API for the aligner.
+//!
+//! Build it with `wasm-pack build --target web --no-default-features --features wasm`.
+//! The search runs on the calling thread and keeps all nodes in memory, so it is only suitable for small instances.
+//!
+//! ```js
+//! import init, { alignSequences } from "./pkg/multialign.js";
+//!
+//! await init();
+//! const alignment = alignSequences(["ACGT", "AGT"], "dna");
+//! console.log(alignment.cost, alignment.rows);
+//! ```
+
+use compact_genome::{
+    implementation::{
+        alphabets::{
+            dna_iupac_nucleic_acid_alphabet::DnaIupacNucleicAcidAlphabet,
+            iupac_amino_acid_alphabet::IupacAminoAcidAlphabet,
+            rna_iupac_nucleic_acid_alphabet::RnaIupacNucleicAcidAlphabet,
+        },
+        vec_sequence::VectorGenome,
+    },
+    interface::{
+        alphabet::Alphabet,
+        sequence::{GenomeSequence, OwnedGenomeSequence},
+    },
+};
+use generic_a_star::cost::{AStarCost, I32Cost};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    align, metric::pairwise_match_metric::PairwiseMatchMetric, GapCharacters, MultialignOptions,
+};
+
+/// An optimal alignment returned by [`align_sequences`].
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Debug, Clone)]
+pub struct JsAlignment {
+    /// The gapped rows of the alignment, in the order of the input sequences.
+    pub rows: Vec<String>,
+    pub cost: i32,
+}
+
+/// Align the given sequences with the default metric.
+///
+/// The alphabet is one of `dna`, `rna` or `amino-acid`, using the IUPAC codes in each case.
+#[wasm_bindgen(js_name = alignSequences)]
+pub fn align_sequences(sequences: Vec<String>, alphabet: &str) -> Result<JsAlignment, JsError> {
+    match alphabet {
+        "dna" => align_rows::<DnaIupacNucleicAcidAlphabet>(&sequences),
+        "rna" => align_rows::<RnaIupacNucleicAcidAlphabet>(&sequences),
+        "amino-acid" => align_rows::<IupacAminoAcidAlphabet>(&sequences),
+        alphabet => Err(JsError::new(&format!("Unknown alphabet {alphabet}"))),
+    }
+}
+
+fn align_rows<AlphabetType: Alphabet + std::fmt::Debug + Clone + Eq + 'static>(
+    sequences: &[String],
+) -> Result<JsAlignment, JsError>
+where
+    AlphabetType::CharacterType: Sync,
+{
+    let genomes = sequences
+        .iter()
+        .enumerate()
+        .map(|(index, sequence)| {
+            VectorGenome::<AlphabetType>::from_iter_u8(
+                sequence
+                    .bytes()
+                    .map(|character| character.to_ascii_uppercase()),
+            )
+            .map_err(|error| JsError::new(&format!("Invalid sequence {index}: {error}")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let genomes: Vec<_> = genomes
+        .iter()
+        .map(|genome| genome.as_genome_subsequence())
+        .collect();
+
+    let alignment = align::<_, _, _, I32Cost>(
+        &genomes,
+        PairwiseMatchMetric::new(genomes.len())?,
+        &MultialignOptions::default(),
+    )?;
+
+    Ok(JsAlignment {
+        rows: (0..alignment.sequence_amount())
+            .map(|row| alignment.render_row(row, &GapCharacters::default()))
+            .collect(),
+        cost: alignment.cost.as_primitive(),
+    })
+}